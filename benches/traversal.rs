@@ -0,0 +1,146 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Measures how fast git-tree traverses a large history, both from `git
+//! rev-list --parents` output (compared to the time git itself takes to
+//! produce it) and from a commit-graph. Run with
+//! `cargo bench --bench traversal`.
+//!
+//! The repository's main branch merges in a long history that shares no
+//! commits with the merge base, so git-tree traverses all of it but displays
+//! almost none of it.
+
+use core::iter::repeat_with;
+use core::time::Duration;
+use git_tree_test_support::Repo;
+use std::fs;
+use std::io::{stdout, Write as _};
+use std::time::Instant;
+
+/// The number of commits in the merged-in history.
+const COMMITS: u32 = 200_000;
+
+/// The fast-import mark of the merged-in history's first commit.
+const HISTORY_ROOT: u32 = 4;
+
+/// Every this many commits, the merged-in history has a merge.
+const MERGE_INTERVAL: u32 = 50;
+
+/// Each command is timed this many times, keeping the fastest run.
+const RUNS: usize = 5;
+
+/// The first commit date in the fast-import stream.
+const EPOCH: u32 = 1_700_000_000;
+
+/// Appends a commit on `branch` with the given mark and parents.
+fn commit(stream: &mut Vec<u8>, branch: &str, mark: u32, parents: &[u32]) {
+    let date = EPOCH.saturating_add(mark);
+    writeln!(stream, "commit {branch}\nmark :{mark}").unwrap();
+    writeln!(stream, "committer Test <test@example.com> {date} +0000\ndata 0").unwrap();
+    let mut parents = parents.iter();
+    if let Some(first) = parents.next() {
+        writeln!(stream, "from :{first}").unwrap();
+    }
+    for parent in parents {
+        writeln!(stream, "merge :{parent}").unwrap();
+    }
+}
+
+/// Returns the parents of the merged-in history's commit `mark`: a linear
+/// chain, with a side commit and a merge every `MERGE_INTERVAL` commits.
+fn history_parents(mark: u32) -> Vec<u32> {
+    let (previous, before) = (mark.saturating_sub(1), mark.saturating_sub(2));
+    if mark == HISTORY_ROOT {
+        vec![]
+    } else if before <= HISTORY_ROOT {
+        vec![previous]
+    } else if mark.checked_rem(MERGE_INTERVAL) == Some(MERGE_INTERVAL.saturating_sub(1)) {
+        // A side commit, forking from the commit before the previous one.
+        vec![before]
+    } else if mark.checked_rem(MERGE_INTERVAL) == Some(0) {
+        // Merge the side commit into the chain.
+        vec![before, previous]
+    } else {
+        vec![previous]
+    }
+}
+
+/// Builds the repository: `feature` and `main` fork from a base commit, and
+/// `main` merges in a history of `COMMITS` commits.
+fn repo() -> Repo {
+    let mut stream = vec![];
+    commit(&mut stream, "refs/heads/main", 1, &[]);
+    commit(&mut stream, "refs/heads/feature", 2, &[1]);
+    commit(&mut stream, "refs/heads/main", 3, &[1]);
+    let merge = HISTORY_ROOT.saturating_add(COMMITS);
+    for mark in HISTORY_ROOT..merge {
+        commit(&mut stream, "refs/imported/history", mark, &history_parents(mark));
+    }
+    commit(&mut stream, "refs/heads/main", merge, &[3, merge.saturating_sub(1)]);
+    let repo = Repo::new();
+    repo.fast_import(&stream);
+    repo.git(&["reset", "--quiet", "--hard", "main"]);
+    repo
+}
+
+/// Returns the fastest of `RUNS` runs of `run`.
+fn fastest<F: FnMut()>(mut run: F) -> Duration {
+    repeat_with(|| {
+        let start = Instant::now();
+        run();
+        start.elapsed()
+    })
+    .take(RUNS)
+    .min()
+    .unwrap_or_default()
+}
+
+/// Runs git-tree in `repo` without its cache, as on a first run.
+fn git_tree(repo: &Repo) {
+    drop(fs::remove_file(repo.path().join(".git/gitxl/cache")));
+    let output = repo
+        .command(env!("CARGO_BIN_EXE_git-tree"))
+        .arg("--porcelain")
+        .output()
+        .expect("failed to run git-tree");
+    assert!(output.status.success(), "git-tree failed");
+}
+
+fn main() {
+    let repo = repo();
+    let base = repo.rev_parse("main~1~1");
+    let rev_list = fastest(|| {
+        let output = repo
+            .command("git")
+            .args(["rev-list", "--parents", "--reverse", "--topo-order", "main", "feature"])
+            .args(["--not", &base])
+            .output()
+            .expect("failed to run git rev-list");
+        assert!(output.status.success(), "git rev-list failed");
+    });
+    let git_tree_rev_list = fastest(|| git_tree(&repo));
+    repo.git(&["commit-graph", "write", "--reachable", "--no-progress"]);
+    let git_tree_commit_graph = fastest(|| git_tree(&repo));
+    let per_commit = |duration: Duration| duration.as_nanos().checked_div(COMMITS.into());
+    let mut out = stdout().lock();
+    for (name, duration) in [
+        ("git rev-list", rev_list),
+        ("git-tree (rev-list)", git_tree_rev_list),
+        ("git-tree (commit-graph)", git_tree_commit_graph),
+    ] {
+        let nanos = per_commit(duration).unwrap_or_default();
+        let millis = duration.as_millis();
+        writeln!(out, "{name:>23}: {millis:>6} ms ({nanos} ns per commit)").unwrap();
+    }
+}
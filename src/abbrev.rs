@@ -0,0 +1,87 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Abbreviated commit IDs in git-tree's own output: summaries, annotations,
+//! explanations, and logs. Machine-readable output (porcelain, exports' IDs,
+//! JSON-RPC `id` fields) keeps the full IDs.
+//!
+//! An abbreviation is as long as git's own (`core.abbrev`, which may be `auto`
+//! or `no`), and longer if needed to tell apart the commits git-tree has
+//! loaded. `--full-hashes` turns abbreviation off.
+
+use crate::log::Logged as _;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+/// The length of git's abbreviations when it cannot be asked, e.g. in a
+/// repository without commits.
+const DEFAULT_LENGTH: usize = 7;
+
+/// Whether `--full-hashes` was given.
+static FULL: AtomicBool = AtomicBool::new(false);
+
+/// The length of git's abbreviations.
+static GIT_LENGTH: OnceLock<usize> = OnceLock::new();
+
+/// The shortest length that tells apart the commits loaded so far.
+static UNIQUE_LENGTH: AtomicUsize = AtomicUsize::new(0);
+
+/// Turns abbreviation off.
+pub fn full_hashes() {
+    FULL.store(true, Ordering::Relaxed);
+}
+
+/// Returns the length of git's own abbreviations, e.g. for `%h`.
+pub fn git_length() -> usize {
+    *GIT_LENGTH.get_or_init(|| {
+        let output = Command::new("git")
+            .args(["rev-parse", "--short", "HEAD"])
+            .stderr(Stdio::null())
+            .logged()
+            .output();
+        output
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim_end().len())
+            .filter(|&length| length > 0)
+            .unwrap_or(DEFAULT_LENGTH)
+    })
+}
+
+/// Makes the abbreviations long enough to tell apart the commits `ids`.
+pub fn disambiguate<'id, I: IntoIterator<Item = &'id str>>(ids: I) {
+    let mut ids: Vec<_> = ids.into_iter().collect();
+    ids.sort_unstable();
+    ids.dedup();
+    let length = ids
+        .windows(2)
+        .filter_map(|pair| {
+            let (first, second) = (pair.first()?, pair.get(1)?);
+            let common = first.bytes().zip(second.bytes()).take_while(|&(a, b)| a == b).count();
+            Some(common.saturating_add(1))
+        })
+        .max()
+        .unwrap_or_default();
+    UNIQUE_LENGTH.fetch_max(length, Ordering::Relaxed);
+}
+
+/// Returns the abbreviation of commit `id`.
+pub fn short(id: &str) -> &str {
+    if FULL.load(Ordering::Relaxed) {
+        return id;
+    }
+    let length = git_length().max(UNIQUE_LENGTH.load(Ordering::Relaxed));
+    id.get(..length).unwrap_or(id)
+}
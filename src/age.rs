@@ -0,0 +1,115 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--color-by-age`: commits colored by how recent they are, so that fresh
+//! work stands out in a dense graph.
+//!
+//! Each commit falls in a bucket by its committer date (which rebasing and
+//! amending update): today (the last 24 hours), this week, this month (the
+//! last 30 days), or older. The colors come from the
+//! `gitxl.ageColor.<bucket>` settings, in git's color syntax (e.g. `bold
+//! green`), read with `git config --get-color`.
+
+use crate::log::Logged as _;
+use std::process::Command;
+
+/// Seconds in a day.
+const DAY: i64 = 24 * 60 * 60;
+
+/// How recent a commit is.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Bucket {
+    Today,
+    Week,
+    Month,
+    Older,
+}
+
+impl Bucket {
+    /// The buckets, newest first.
+    const ALL: [Self; 4] = [Self::Today, Self::Week, Self::Month, Self::Older];
+
+    /// Returns the bucket of a commit with committer date `time`, `now` being
+    /// the current time (see `date::now`). Commits from the future are today's.
+    pub const fn of(time: i64, now: i64) -> Self {
+        let age = now.saturating_sub(time);
+        if age < DAY {
+            Self::Today
+        } else if age < DAY.saturating_mul(7) {
+            Self::Week
+        } else if age < DAY.saturating_mul(30) {
+            Self::Month
+        } else {
+            Self::Older
+        }
+    }
+
+    /// Returns the bucket's name, as in JSON output and config keys.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Today => "today",
+            Self::Week => "week",
+            Self::Month => "month",
+            Self::Older => "older",
+        }
+    }
+
+    /// Returns the bucket's default color, in git's color syntax.
+    const fn default_color(self) -> &'static str {
+        match self {
+            Self::Today => "bold green",
+            Self::Week => "green",
+            Self::Month => "normal",
+            Self::Older => "dim",
+        }
+    }
+}
+
+/// The escape sequences that start each bucket's color, newest first (empty
+/// for no color).
+pub struct Palette([String; 4]);
+
+impl Palette {
+    /// Reads the palette from the `gitxl.ageColor.<bucket>` settings.
+    pub fn load() -> Self {
+        Self(Bucket::ALL.map(|bucket| {
+            Command::new("git")
+                .args(["config", "--get-color"])
+                .arg(format!("gitxl.ageColor.{}", bucket.name()))
+                .arg(bucket.default_color())
+                .logged()
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+                .unwrap_or_default()
+        }))
+    }
+
+    /// Colors each line of `text` with `bucket`'s color.
+    pub fn paint(&self, bucket: Bucket, text: &mut String) {
+        let [today, week, month, older] = &self.0;
+        let start = match bucket {
+            Bucket::Today => today,
+            Bucket::Week => week,
+            Bucket::Month => month,
+            Bucket::Older => older,
+        };
+        if start.is_empty() {
+            return;
+        }
+        let lines: Vec<_> = text.split('\n').map(|line| format!("{start}{line}\x1b[m")).collect();
+        *text = lines.join("\n");
+    }
+}
@@ -0,0 +1,101 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Allocation accounting for `--timing`. With the `count-allocations` feature,
+//! the global allocator is the system's, wrapped to count the allocations
+//! git-tree makes and the bytes they request (a reallocation counts as an
+//! allocation of its new size). Without it, nothing is counted, and
+//! allocating costs nothing more.
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// The allocations made so far, or during a stage.
+#[derive(Clone, Copy)]
+pub struct Allocations {
+    pub count: u64,
+    pub bytes: u64,
+}
+
+impl Allocations {
+    /// Returns the allocations made since `start`.
+    pub const fn since(self, start: Self) -> Self {
+        Self {
+            count: self.count.saturating_sub(start.count),
+            bytes: self.bytes.saturating_sub(start.bytes),
+        }
+    }
+}
+
+/// Whether allocations are counted.
+const COUNTED: bool = cfg!(feature = "count-allocations");
+
+static COUNT: AtomicU64 = AtomicU64::new(0);
+static BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the allocations made so far, if they are counted.
+pub fn so_far() -> Option<Allocations> {
+    COUNTED.then(|| Allocations {
+        count: COUNT.load(Ordering::Relaxed),
+        bytes: BYTES.load(Ordering::Relaxed),
+    })
+}
+
+#[cfg(feature = "count-allocations")]
+mod counting {
+    use super::{BYTES, COUNT};
+    use core::alloc::{GlobalAlloc, Layout};
+    use core::sync::atomic::Ordering;
+    use std::alloc::System;
+
+    /// The system allocator, counting allocations.
+    struct Counting;
+
+    /// Counts an allocation of `size` bytes.
+    fn count(size: usize) {
+        COUNT.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(u64::try_from(size).unwrap_or(u64::MAX), Ordering::Relaxed);
+    }
+
+    // SAFETY: every method forwards to `System`, which implements
+    // `GlobalAlloc` correctly, and counting neither allocates nor unwinds.
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            count(layout.size());
+            // SAFETY: the caller upholds `alloc`'s contract.
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            count(layout.size());
+            // SAFETY: the caller upholds `alloc_zeroed`'s contract.
+            unsafe { System.alloc_zeroed(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            // SAFETY: the caller upholds `dealloc`'s contract, and `ptr` was
+            // allocated by `System`, as all of git-tree's memory is.
+            unsafe { System.dealloc(ptr, layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            count(new_size);
+            // SAFETY: the caller upholds `realloc`'s contract, and `ptr` was
+            // allocated by `System`, as all of git-tree's memory is.
+            unsafe { System.realloc(ptr, layout, new_size) }
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: Counting = Counting;
+}
@@ -0,0 +1,74 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `git-tree authors`: who is working on which interesting branch, for an
+//! overview of the work in flight in a shared repository.
+//!
+//! A branch is attributed to the authors of its own commits: the displayed
+//! commits above the merge bases that it reaches without going through the tip
+//! of another interesting branch, and that no other branch reaches that way.
+//! In a stack, the commits of the lower branch are its own, not those of the
+//! branches above it. The branches owning each commit are worked out by
+//! pushing the tips from children to parents, as `matrix` does, except that a
+//! tip owns itself alone.
+
+use crate::matrix::Reach;
+use crate::timing::Timing;
+use crate::{graph, interesting_branches, View};
+use alloc::collections::BTreeMap;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Writes, for each author, the interesting branches with commits of theirs
+/// that are on no other branch, and how many, e.g. `alice: feature-x (4),
+/// fix-y (1)`.
+pub fn write<W: Write>(mut out: W) -> io::Result<()> {
+    let mut buffer = Vec::with_capacity(256);
+    let (branches, head) = interesting_branches(&mut buffer)?;
+    if branches.is_empty() && head.is_none() {
+        return Ok(());
+    }
+    let view = View::compute(buffer, branches, head, None, false, 0, &mut Timing::new(false))?;
+    let by_id = view.branches_by_id();
+    let mut tips: Vec<_> = by_id.keys().copied().collect();
+    tips.sort_unstable();
+    let commits = graph::load(&view.revisions(), None, false)?;
+    // The number of commits of each author on each tip alone.
+    let mut counts: BTreeMap<&str, HashMap<usize, usize>> = BTreeMap::new();
+    let mut walk = Reach::owners(&tips, &view.merge_bases);
+    for commit in &commits {
+        let Some(owners) = walk.visit(&commit.id, commit.parents.iter().map(String::as_str)) else {
+            continue;
+        };
+        let mut owning = owners.indices();
+        if let (Some(tip), None) = (owning.next(), owning.next()) {
+            let count = counts.entry(&commit.author).or_default().entry(tip).or_default();
+            *count = count.saturating_add(1);
+        }
+    }
+    for (author, by_tip) in counts {
+        let mut owned: Vec<_> = by_tip
+            .into_iter()
+            .map(|(tip, count)| {
+                let names = tips.get(tip).and_then(|tip| by_id.get(tip));
+                (count, names.map(|names| names.join(", ")).unwrap_or_default())
+            })
+            .collect();
+        owned.sort_unstable_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        let owned: Vec<_> =
+            owned.iter().map(|(count, names)| format!("{names} ({count})")).collect();
+        writeln!(out, "{author}: {}", owned.join(", "))?;
+    }
+    out.flush()
+}
@@ -0,0 +1,88 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--warn-behind=<n>` and `gitxl.warnBehind`: after the log, a warning about
+//! the local branches that are more than `n` commits behind the trunk
+//! (`origin/HEAD`), i.e. that forked from it that long ago, as a nudge to
+//! rebase stale work. With `--warn-behind`, such branches also make git-tree
+//! fail, as a gate.
+//!
+//! The counts come from `matrix::Counts` over the view's tips. If the trunk is
+//! one of them (it usually is, as the upstream of a local branch), the view's
+//! merge bases are reused rather than computed again.
+
+use crate::cli::DEFAULT_TRUNK;
+use crate::matrix::Counts;
+use crate::{config, quote, rev_parse, warn, View};
+use core::fmt::Write as _;
+use std::io;
+
+/// Returns the threshold set by `gitxl.warnBehind`, if any.
+pub fn configured() -> Option<usize> {
+    let value = config::get("gitxl.warnBehind")?;
+    let threshold = value.parse().ok();
+    if threshold.is_none() {
+        warn(&tr!("warning: ignoring invalid gitxl.warnBehind value {$value}", value = value));
+    }
+    threshold
+}
+
+/// Returns the local branches of `view` more than `threshold` commits behind
+/// the trunk, with how far behind, or none if there is no trunk.
+pub fn stale(view: &View, threshold: usize) -> io::Result<Vec<(&str, usize)>> {
+    let Some(trunk) = rev_parse(DEFAULT_TRUNK) else { return Ok(vec![]) };
+    let tips = View::tips(&view.branches, view.head.as_ref(), &view.reflog);
+    let mut buffer = Vec::with_capacity(256);
+    // The first-parent merge bases are not those of the whole history.
+    let counts = if tips.binary_search(&trunk).is_ok() && !view.first_parent {
+        Counts::with_merge_bases(&mut buffer, tips, &view.merge_bases)?
+    } else {
+        Counts::new(&mut buffer, tips.into_iter().chain([trunk.clone()]))?
+    };
+    Ok(view
+        .branches
+        .iter()
+        .filter(|branch| branch.refname.starts_with("refs/heads/"))
+        .map(|branch| (branch.name.as_str(), counts.ahead(&trunk, &branch.id)))
+        .filter(|&(_, behind)| behind > threshold)
+        .collect())
+}
+
+/// Warns about the `stale` branches, more than `threshold` commits behind the
+/// trunk.
+pub fn warn_stale(stale: &[(&str, usize)], threshold: usize) {
+    if stale.is_empty() {
+        return;
+    }
+    let mut message = if let [_] = stale {
+        tr!(
+            "warning: 1 branch is more than {$threshold} commits behind {$trunk}; consider rebasing:",
+            threshold = threshold,
+            trunk = DEFAULT_TRUNK,
+        )
+    } else {
+        tr!(
+            "warning: {$count} branches are more than {$threshold} commits behind {$trunk}; consider rebasing:",
+            count = stale.len(),
+            threshold = threshold,
+            trunk = DEFAULT_TRUNK,
+        )
+    };
+    for &(name, behind) in stale {
+        let line = tr!("{$name} ({$behind} behind)", name = quote::name(name), behind = behind);
+        #[allow(clippy::unwrap_used, reason = "writing to a String cannot fail")]
+        write!(message, "\n  {line}").unwrap();
+    }
+    warn(&message);
+}
@@ -0,0 +1,94 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `git-tree bench`: runs the stages of computing the graph on the current
+//! repository `--iterations` times and prints the minimum, median, and maximum
+//! wall-clock time of each stage and of the total, for comparable numbers in
+//! performance reports.
+//!
+//! Every iteration computes the view from scratch, without the cache, then
+//! loads the displayed commits and lays them out as the native renderer does.
+//! Nothing is written, so output and pager times are left out.
+
+use crate::timing::{millis, Timing};
+use crate::{graph, interesting_branches, layout, replace, shallow, View};
+use core::time::Duration;
+use std::io::{self, Write};
+
+/// The number of iterations by default.
+pub const DEFAULT_ITERATIONS: usize = 10;
+
+/// Runs the stages once, recording them in `timing`.
+fn iteration(timing: &mut Timing) -> io::Result<()> {
+    let mut buffer = Vec::with_capacity(256);
+    let (branches, head) = interesting_branches(&mut buffer)?;
+    timing.record("refs", Some((branches.len(), "branches")));
+    let tips = View::tips(&branches, head.as_ref(), &[]);
+    let (entry, boundaries) =
+        View::traverse(buffer, &tips, false, 0, replace::rewritten(), timing)?;
+    let view = View {
+        branches,
+        head,
+        merge_bases: entry.merge_bases,
+        boundaries,
+        includes: entry.includes,
+        excludes: entry.excludes,
+        shallow: shallow::boundary(),
+        reflog: vec![],
+        first_parent: false,
+    };
+    let commits = graph::load(&view.revisions(), None, false)?;
+    timing.record("load", Some((commits.len(), "commits")));
+    drop(layout::layout(&commits));
+    timing.record("layout", None);
+    Ok(())
+}
+
+/// Runs the stages `iterations` times and writes their statistics.
+pub fn run<W: Write>(mut out: W, iterations: usize) -> io::Result<()> {
+    // The stages in the order they ran, with their times.
+    let mut stages: Vec<(&'static str, Vec<Duration>)> = vec![];
+    let mut totals = vec![];
+    for _ in 0..iterations {
+        let mut timing = Timing::new(true);
+        iteration(&mut timing)?;
+        let mut total = Duration::ZERO;
+        for (name, wall) in timing.walls() {
+            let position = stages.iter().position(|stage| stage.0 == name).unwrap_or_else(|| {
+                stages.push((name, vec![]));
+                stages.len().saturating_sub(1)
+            });
+            #[allow(clippy::unwrap_used, reason = "position was just found or pushed")]
+            stages.get_mut(position).unwrap().1.push(wall);
+            total = total.saturating_add(wall);
+        }
+        totals.push(total);
+    }
+    writeln!(out, "{iterations} iterations:")?;
+    writeln!(out, "  {:<12} {:>12} {:>12} {:>12}", "stage", "min", "median", "max")?;
+    for (name, mut walls) in stages.into_iter().chain([("total", totals)]) {
+        walls.sort_unstable();
+        #[allow(clippy::unwrap_used, reason = "the divisor is not zero")]
+        let median = walls.get(walls.len().checked_div(2).unwrap()).copied().unwrap_or_default();
+        let (min, max) = (walls.first().copied(), walls.last().copied());
+        writeln!(
+            out,
+            "  {name:<12} {:>12} {:>12} {:>12}",
+            millis(min.unwrap_or_default()),
+            millis(median),
+            millis(max.unwrap_or_default())
+        )?;
+    }
+    out.flush()
+}
@@ -0,0 +1,147 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The state of a `git bisect` in progress, overlaid on the graph: the commits
+//! marked bad, good, or skipped, and the candidates that may still be the
+//! first bad commit.
+//!
+//! A bisection is in progress while `BISECT_START` exists in the git
+//! directory. The terms come from `BISECT_TERMS` (`git bisect start --term-new
+//! ...`), and the marks from the refs git keeps under `refs/bisect`: the bad
+//! commit is `refs/bisect/<bad term>`, and the good and skipped ones are
+//! `refs/bisect/<good term>-<ID>` and `refs/bisect/skip-<ID>`.
+
+use crate::json::Value;
+use crate::report::git;
+use crate::{cache, Branch};
+use std::collections::HashMap;
+use std::fs;
+
+/// What the bisection says about a commit.
+#[derive(Clone, Copy)]
+pub enum Mark {
+    Bad,
+    Good,
+    Skip,
+    /// Neither marked nor ruled out yet: reachable from the bad commit but not
+    /// from a good one.
+    Candidate,
+}
+
+impl Mark {
+    /// Returns the mark's name in the JSON output.
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Bad => "bad",
+            Self::Good => "good",
+            Self::Skip => "skip",
+            Self::Candidate => "candidate",
+        }
+    }
+}
+
+/// A bisection in progress.
+pub struct Bisect {
+    /// The term for bad commits, `bad` unless `git bisect start --term-new`
+    /// chose another.
+    bad: String,
+    /// The term for good commits.
+    good: String,
+    /// The refs under `refs/bisect`.
+    refs: Vec<Branch>,
+    /// The marks, by commit ID.
+    marks: HashMap<String, Mark>,
+}
+
+impl Bisect {
+    /// Returns the bisection in progress, or `None` if there is none.
+    pub fn load() -> Option<Self> {
+        if !cache::git_path("BISECT_START")?.exists() {
+            return None;
+        }
+        let terms = cache::git_path("BISECT_TERMS")
+            .and_then(|path| fs::read_to_string(path).ok())
+            .unwrap_or_default();
+        let mut terms = terms.lines();
+        let bad = terms.next().unwrap_or("bad").to_owned();
+        let good = terms.next().unwrap_or("good").to_owned();
+        let output = git(&[
+            "for-each-ref",
+            "--format=%(refname)%00%(objectname)%00%(committerdate:unix)",
+            "refs/bisect",
+        ])
+        .unwrap_or_default();
+        let refs: Vec<Branch> = output
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split('\0');
+                let (refname, id, time) = (fields.next()?, fields.next()?, fields.next()?);
+                Some(Branch {
+                    name: refname.strip_prefix("refs/")?.to_owned(),
+                    refname: refname.to_owned(),
+                    id: id.to_owned(),
+                    time: time.parse().unwrap_or_default(),
+                })
+            })
+            .collect();
+        let mut marks = HashMap::new();
+        let mut goods = vec![];
+        for branch in &refs {
+            let name = branch.refname.strip_prefix("refs/bisect/").unwrap_or_default();
+            let mark = if name == bad {
+                Mark::Bad
+            } else if name.strip_prefix(good.as_str()).is_some_and(|rest| rest.starts_with('-')) {
+                goods.push(branch.id.as_str());
+                Mark::Good
+            } else if name.starts_with("skip-") {
+                Mark::Skip
+            } else {
+                continue;
+            };
+            marks.insert(branch.id.clone(), mark);
+        }
+        let bad_ref = format!("refs/bisect/{bad}");
+        if marks.values().any(|&mark| matches!(mark, Mark::Bad)) {
+            let mut args = vec!["rev-list", bad_ref.as_str(), "--not"];
+            args.extend(goods);
+            for id in git(&args).unwrap_or_default().lines() {
+                marks.entry(id.to_owned()).or_insert(Mark::Candidate);
+            }
+        }
+        Some(Self { bad, good, refs, marks })
+    }
+
+    /// Returns the refs under `refs/bisect`, to add to the interesting
+    /// branches (`--bisect`).
+    pub fn refs(&self) -> &[Branch] {
+        &self.refs
+    }
+
+    /// Returns the annotation of the commit `id`, e.g. `bisect bad`, or `None`
+    /// if the bisection says nothing about it.
+    pub fn label(&self, id: &str) -> Option<String> {
+        let term = match self.marks.get(id)? {
+            Mark::Bad => &self.bad,
+            Mark::Good => &self.good,
+            Mark::Skip => "skip",
+            Mark::Candidate => "candidate",
+        };
+        Some(format!("bisect {term}"))
+    }
+
+    /// Returns the mark of the commit `id` as JSON: its name, or null.
+    pub fn json(&self, id: &str) -> Value {
+        self.marks.get(id).map(|mark| mark.name()).into()
+    }
+}
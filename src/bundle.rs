@@ -0,0 +1,67 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `git-tree bundle`: a `git bundle` of exactly the displayed commits, to move
+//! the branches in flight to another machine or attach them to a bug report.
+//!
+//! The bundle's refs are HEAD and the interesting branches, which point to
+//! every commit the view includes. The view's exclusions are passed as
+//! negative revisions, so the commits below the merge bases become the
+//! bundle's prerequisites.
+
+use crate::log::Logged as _;
+use crate::timing::Timing;
+use crate::{graph, interesting_branches, namespace_prefix, View};
+use std::io::{self, Write as _};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Writes the bundle of the displayed commits to `output`.
+pub fn create(output: &Path) -> io::Result<()> {
+    let mut buffer = Vec::with_capacity(256);
+    let (branches, head) = interesting_branches(&mut buffer)?;
+    if branches.is_empty() && head.is_none() {
+        return Err(io::Error::other(tr!("repository has no commits to bundle")));
+    }
+    let view = View::compute(buffer, branches, head, None, false, 0, &mut Timing::new(false))?;
+    let prefix = namespace_prefix().unwrap_or_default();
+    let mut revisions = String::new();
+    for branch in &view.branches {
+        revisions.extend([prefix.as_str(), &branch.refname, "\n"]);
+    }
+    if view.head.is_some() {
+        revisions.extend([prefix.as_str(), "HEAD\n"]);
+    }
+    // Options such as --not cannot be given on stdin.
+    for negative in view.negatives() {
+        revisions.extend(["^", &negative, "\n"]);
+    }
+    let mut git = Command::new("git")
+        .args(["bundle", "create", "--quiet"])
+        .arg(output)
+        .arg("--stdin")
+        .env(graph::NO_LAZY_FETCH, "1")
+        .stdin(Stdio::piped())
+        .logged()
+        .spawn()?;
+    // git reads all the revisions before writing anything, so this cannot
+    // block on it.
+    #[allow(clippy::unwrap_used, reason = "stdin is piped")]
+    git.stdin.take().unwrap().write_all(revisions.as_bytes())?;
+    let status = git.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(tr!("git bundle failed with {$status}", status = status)));
+    }
+    Ok(())
+}
@@ -0,0 +1,172 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Persistent cache of the computed merge bases and include/exclude lists.
+//!
+//! The merge bases, includes, and excludes are fully determined by the set of
+//! commits pointed to by HEAD and the interesting branches, so the cache is
+//! keyed by those commit IDs (sorted and deduplicated). Entries are stored in a
+//! text file under `.git/gitxl/cache` (in the repository's common git
+//! directory, shared by its linked worktrees), most recently stored first:
+//!
+//! ```text
+//! gitxl cache v1
+//! tips <id> <id> ...
+//! bases <id> ...
+//! includes <id> ...
+//! excludes <id> ...
+//! ```
+//!
+//! The cache is best-effort: any failure to read or write it is ignored, as is
+//! another run holding its lock for long.
+
+use crate::lock::Lock;
+use crate::log::Logged as _;
+use core::fmt::Write as _;
+use core::time::Duration;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+const HEADER: &str = "gitxl cache v1";
+
+/// Maximum number of entries kept in the cache file. Keeping a few entries
+/// lets switching back and forth between branches hit the cache.
+const MAX_ENTRIES: usize = 8;
+
+/// How long to wait for another run to release the cache's lock, short as a
+/// result is not worth waiting for.
+const LOCK_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// A cached result.
+pub struct Entry {
+    pub merge_bases: Vec<String>,
+    pub includes: Vec<String>,
+    pub excludes: Vec<String>,
+}
+
+/// Returns the location of the cache file, or `None` if it cannot be
+/// determined.
+pub fn path() -> Option<PathBuf> {
+    common_path("gitxl/cache")
+}
+
+/// Returns the location of `name` in the git directory, or `None` if it cannot
+/// be determined.
+pub fn git_path(name: &str) -> Option<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", name])
+        .stderr(Stdio::null())
+        .logged()
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8(output.stdout).ok()?.trim_end_matches(['\r', '\n']).into())
+}
+
+/// Returns the location of `name` in the common git directory, shared by the
+/// linked worktrees (whose own git directories `git_path` gives for names git
+/// does not know), or `None` if it cannot be determined.
+pub fn common_path(name: &str) -> Option<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-common-dir"])
+        .stderr(Stdio::null())
+        .logged()
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let dir = String::from_utf8(output.stdout).ok()?;
+    Some(Path::new(dir.trim_end_matches(['\r', '\n'])).join(name))
+}
+
+/// Parses the cache file, returning its entries as (key, entry) pairs.
+fn read_entries(path: &Path) -> Vec<(String, Entry)> {
+    let Ok(contents) = fs::read_to_string(path) else { return vec![] };
+    let mut lines = contents.lines();
+    if lines.next() != Some(HEADER) {
+        return vec![];
+    }
+    let mut entries = vec![];
+    // Entries are groups of four lines; stop at the first malformed group.
+    while let (Some(tips), Some(bases), Some(includes), Some(excludes)) =
+        (lines.next(), lines.next(), lines.next(), lines.next())
+    {
+        let (Some(key), Some(merge_bases), Some(includes), Some(excludes)) = (
+            tips.strip_prefix("tips "),
+            ids(bases, "bases"),
+            ids(includes, "includes"),
+            ids(excludes, "excludes"),
+        ) else {
+            break;
+        };
+        entries.push((key.into(), Entry { merge_bases, includes, excludes }));
+    }
+    entries
+}
+
+/// Parses a line consisting of `name` followed by space-separated IDs.
+fn ids(line: &str, name: &str) -> Option<Vec<String>> {
+    Some(
+        line.strip_prefix(name)?.split(' ').filter(|id| !id.is_empty()).map(String::from).collect(),
+    )
+}
+
+/// Appends an entry to the cache contents.
+fn write_entry(contents: &mut String, key: &str, entry: &Entry) {
+    #[allow(clippy::unwrap_used, reason = "writing to a String cannot fail")]
+    writeln!(contents, "tips {key}").unwrap();
+    for (name, ids) in [
+        ("bases", &entry.merge_bases),
+        ("includes", &entry.includes),
+        ("excludes", &entry.excludes),
+    ] {
+        contents.push_str(name);
+        for id in ids {
+            contents.push(' ');
+            contents.push_str(id);
+        }
+        contents.push('\n');
+    }
+}
+
+/// Looks up the cached result for `tips`, which must be sorted and
+/// deduplicated.
+pub fn load(path: &Path, tips: &[String]) -> Option<Entry> {
+    let key = tips.join(" ");
+    read_entries(path).into_iter().find(|entry| entry.0 == key).map(|(_, entry)| entry)
+}
+
+/// Stores the result for `tips` (which must be sorted and deduplicated) in the
+/// cache, evicting the least recently stored entries if necessary.
+pub fn store(path: &Path, tips: &[String], entry: &Entry) {
+    // Under the lock, so that a concurrent run's entry is not lost.
+    let Ok(lock) = Lock::acquire(path, LOCK_TIMEOUT) else { return };
+    let key = tips.join(" ");
+    let mut contents = String::new();
+    contents.push_str(HEADER);
+    contents.push('\n');
+    write_entry(&mut contents, &key, entry);
+    for (other_key, other) in read_entries(path)
+        .into_iter()
+        .filter(|other| other.0 != key)
+        .take(MAX_ENTRIES.saturating_sub(1))
+    {
+        write_entry(&mut contents, &other_key, &other);
+    }
+    drop(lock.commit(&contents));
+}
@@ -0,0 +1,138 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `git-tree check`: a branch hygiene gate for CI, which fails when a branch
+//! has diverged too far from the trunk.
+//!
+//! The branches checked are the local branches, or those matching a pattern,
+//! local or remote. How far each is ahead of and behind the trunk comes from a
+//! single walk of the history (see `matrix::Counts`). The result is written to
+//! stdout as a JSON object, and each branch over a threshold to stderr.
+
+use crate::json::Value;
+use crate::matrix::Counts;
+use crate::report::git;
+use crate::{namespace_prefix, peel_to_commit, quote, rev_parse};
+use std::io::{self, stderr, Write};
+
+/// The divergence limits of `check`, each unlimited if `None`.
+pub struct Thresholds {
+    /// The most commits a branch may have that the trunk does not.
+    pub max_ahead: Option<usize>,
+    /// The most commits the trunk may have that a branch does not.
+    pub max_behind: Option<usize>,
+}
+
+/// Returns the branches matching `pattern` (a `git for-each-ref` pattern of
+/// the branch name, without the remote for remote branches), or the local
+/// branches if there is none, as (short name, commit ID) pairs sorted by ref
+/// name. Symbolic refs, such as `origin/HEAD`, are left out.
+fn branches(pattern: Option<&str>) -> io::Result<Vec<(String, String)>> {
+    let prefix = namespace_prefix().unwrap_or_default();
+    let mut args = vec![
+        "for-each-ref".to_owned(),
+        "--format=%(refname)%00%(objectname)%00%(objecttype)%00%(symref)".to_owned(),
+    ];
+    match pattern {
+        Some(pattern) => args.extend([
+            format!("{prefix}refs/heads/{pattern}"),
+            format!("{prefix}refs/remotes/*/{pattern}"),
+        ]),
+        None => args.push(format!("{prefix}refs/heads")),
+    }
+    let output = git(&args.iter().map(String::as_str).collect::<Vec<_>>())?;
+    let mut branches = vec![];
+    for line in output.lines() {
+        let mut fields = line.split('\0');
+        let mut field = || fields.next().unwrap_or_default();
+        let (refname, id, kind, target) = (field(), field(), field(), field());
+        if !target.is_empty() {
+            continue;
+        }
+        let refname = refname.strip_prefix(prefix.as_str()).unwrap_or(refname);
+        let name = refname
+            .strip_prefix("refs/heads/")
+            .or_else(|| refname.strip_prefix("refs/remotes/"))
+            .unwrap_or(refname);
+        let id = if kind == "commit" {
+            Some(id.to_owned())
+        } else {
+            peel_to_commit(id).map(|(commit, _)| commit)
+        };
+        match id {
+            Some(id) => branches.push((name.to_owned(), id)),
+            None => crate::warn(&tr!(
+                "warning: ignoring {$name}, which does not point to a commit",
+                name = quote::name(refname)
+            )),
+        }
+    }
+    Ok(branches)
+}
+
+/// Checks how far the branches matching `pattern` (see `branches`) have
+/// diverged from `trunk`, and writes the JSON report. Returns whether every
+/// branch is within the `thresholds`.
+pub fn check<W: Write>(
+    mut out: W,
+    trunk: &str,
+    thresholds: &Thresholds,
+    pattern: Option<&str>,
+) -> io::Result<bool> {
+    let trunk_id = rev_parse(trunk)
+        .ok_or_else(|| io::Error::other(tr!("unknown trunk {$trunk}", trunk = trunk)))?;
+    let branches = branches(pattern)?;
+    let tips = branches.iter().map(|(_, id)| id.clone()).chain([trunk_id.clone()]);
+    let counts = Counts::new(&mut Vec::with_capacity(256), tips)?;
+    let within = |count: usize, max: Option<usize>| max.is_none_or(|max| count <= max);
+    let mut passed = true;
+    let mut reports = vec![];
+    for (name, id) in &branches {
+        let (branch_ahead, branch_behind) =
+            (counts.ahead(id, &trunk_id), counts.ahead(&trunk_id, id));
+        let ok = within(branch_ahead, thresholds.max_ahead)
+            && within(branch_behind, thresholds.max_behind);
+        if !ok {
+            passed = false;
+            // Best-effort, like the rest of stderr.
+            let message = tr!(
+                "{$name} is {$ahead} ahead of and {$behind} behind {$trunk}",
+                name = quote::name(name),
+                ahead = branch_ahead,
+                behind = branch_behind,
+                trunk = trunk,
+            );
+            drop(writeln!(stderr(), "git-tree: {message}"));
+        }
+        reports.push(Value::object([
+            ("name", name.as_str().into()),
+            ("ahead", branch_ahead.into()),
+            ("behind", branch_behind.into()),
+            ("ok", ok.into()),
+        ]));
+    }
+    let limit = |max: Option<usize>| max.map_or(Value::Null, Value::from);
+    let mut json = String::new();
+    Value::object([
+        ("trunk", trunk.into()),
+        ("maxAhead", limit(thresholds.max_ahead)),
+        ("maxBehind", limit(thresholds.max_behind)),
+        ("ok", passed.into()),
+        ("branches", Value::Array(reports)),
+    ])
+    .write(&mut json);
+    writeln!(out, "{json}")?;
+    out.flush()?;
+    Ok(passed)
+}
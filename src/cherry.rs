@@ -0,0 +1,99 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--cherry-mark`: the displayed commits that make the same change as another
+//! displayed commit, e.g. after a branch was rebased or a commit cherry-picked
+//! while the original is still around.
+//!
+//! Commits are equivalent if their patch IDs are equal: `git diff-tree -p`
+//! lists each commit's diff and `git patch-id --stable` hashes it, ignoring
+//! whitespace and line numbers, as `git cherry` does. Merges have no patch ID,
+//! so they are never equivalent to anything.
+
+use crate::abbrev;
+use crate::graph::{self, Commit};
+use crate::log::Logged as _;
+use std::collections::HashMap;
+use std::io::{self, Write as _};
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// Returns the patch ID of each of `commits` that has one, by commit ID.
+fn patch_ids(commits: &[Commit]) -> io::Result<HashMap<String, String>> {
+    let mut input = String::new();
+    for commit in commits {
+        input.extend([commit.id.as_str(), "\n"]);
+    }
+    let mut diff_tree = Command::new("git")
+        .args(["diff-tree", "--stdin", "--root", "-p", "--no-color", "--no-ext-diff"])
+        .env(graph::NO_LAZY_FETCH, "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .logged()
+        .spawn()?;
+    #[allow(clippy::unwrap_used, reason = "stdout is piped")]
+    let hasher = Command::new("git")
+        .args(["patch-id", "--stable"])
+        .stdin(diff_tree.stdout.take().unwrap())
+        .stdout(Stdio::piped())
+        .logged()
+        .spawn()?;
+    #[allow(clippy::unwrap_used, reason = "stdin is piped")]
+    let mut stdin = diff_tree.stdin.take().unwrap();
+    // Write from another thread, so that neither side blocks on a full pipe.
+    let output = thread::scope(|scope| {
+        let writer = scope.spawn(move || stdin.write_all(input.as_bytes()));
+        let output = hasher.wait_with_output()?;
+        #[allow(clippy::unwrap_used, reason = "the writer does not panic")]
+        writer.join().unwrap()?;
+        io::Result::Ok(output)
+    })?;
+    let status = diff_tree.wait()?;
+    if !status.success() || !output.status.success() {
+        return Err(io::Error::other(tr!("git patch-id failed")));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(patch_id, commit)| (commit.to_owned(), patch_id.to_owned()))
+        .collect())
+}
+
+/// Returns, for each of `commits` that makes the same change as others of
+/// them, the IDs of those others, in the order of `commits`.
+pub fn equivalents(commits: &[Commit]) -> io::Result<HashMap<String, Vec<String>>> {
+    let patch_ids = patch_ids(commits)?;
+    let mut by_patch_id: HashMap<&str, Vec<&str>> = HashMap::new();
+    for commit in commits {
+        if let Some(patch_id) = patch_ids.get(&commit.id) {
+            by_patch_id.entry(patch_id).or_default().push(&commit.id);
+        }
+    }
+    let mut equivalents = HashMap::new();
+    for group in by_patch_id.values().filter(|group| group.len() > 1) {
+        for &id in group {
+            let others = group.iter().filter(|&&other| other != id).map(|&other| other.to_owned());
+            equivalents.insert(id.to_owned(), others.collect());
+        }
+    }
+    Ok(equivalents)
+}
+
+/// Returns the annotation for a commit equivalent to `others`, e.g. "same
+/// change as 0123abc".
+pub fn label(others: &[String]) -> String {
+    let short: Vec<_> = others.iter().map(|other| abbrev::short(other)).collect();
+    format!("same change as {}", short.join(", "))
+}
@@ -0,0 +1,136 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--ci` and `--status-command`: the CI status of each branch tip.
+//!
+//! `--ci` sums up the GitHub check runs of each tip, as reported by the GitHub
+//! CLI (`gh api`). `--status-command=<command>` instead runs `<command>` in the
+//! shell with the tip's commit ID as its argument; the first word it prints is
+//! the status (`pass`, `fail`, or `pending`, or a synonym such as `success`).
+
+use crate::json::Value;
+use crate::log::Logged as _;
+use crate::Branch;
+use core::str;
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+
+/// A commit's CI status.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Pass,
+    Fail,
+    Pending,
+}
+
+impl Status {
+    /// Parses a status word.
+    fn parse(word: &str) -> Option<Self> {
+        let word = word.trim_end_matches(|c: char| !c.is_ascii_alphanumeric());
+        match word.to_ascii_lowercase().as_str() {
+            "pass" | "passed" | "success" | "ok" | "green" => Some(Self::Pass),
+            "fail" | "failed" | "failure" | "error" | "red" => Some(Self::Fail),
+            "pending" | "running" | "queued" | "in_progress" | "yellow" => Some(Self::Pending),
+            _ => None,
+        }
+    }
+
+    /// Returns the status word.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Pass => "pass",
+            Self::Fail => "fail",
+            Self::Pending => "pending",
+        }
+    }
+
+    /// Returns the ANSI color code for the status.
+    pub const fn color(self) -> &'static str {
+        match self {
+            Self::Pass => "32",
+            Self::Fail => "31",
+            Self::Pending => "33",
+        }
+    }
+}
+
+/// Where the statuses come from.
+pub enum Source<'command> {
+    /// GitHub check runs, via `gh`.
+    GitHub,
+    /// A `--status-command`.
+    Command(&'command str),
+}
+
+/// Returns the status reported by `command` for `id`, if any.
+fn from_command(command: &str, id: &str) -> Option<Status> {
+    let output = Command::new("sh")
+        .args(["-c", &format!("{command} \"$1\""), "sh", id])
+        .stdin(Stdio::null())
+        .logged()
+        .output()
+        .ok()?;
+    Status::parse(str::from_utf8(&output.stdout).ok()?.split_whitespace().next()?)
+}
+
+/// Returns the status summed up from `id`'s GitHub check runs: failed if any
+/// failed, pending if any has not completed, and passed otherwise. Returns
+/// `None` if there are no check runs or `gh` fails.
+fn from_github(id: &str) -> Option<Status> {
+    let output = Command::new("gh")
+        .args(["api", &format!("repos/{{owner}}/{{repo}}/commits/{id}/check-runs?per_page=100")])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .logged()
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let response = Value::parse(str::from_utf8(&output.stdout).ok()?)?;
+    let Some(Value::Array(runs)) = response.get("check_runs") else { return None };
+    let mut status = None;
+    for run in runs {
+        let run_status = match run.get("conclusion").and_then(Value::as_str) {
+            Some("success" | "neutral" | "skipped") => Status::Pass,
+            Some(_) => Status::Fail,
+            None => Status::Pending,
+        };
+        status = match (status, run_status) {
+            (Some(Status::Fail), _) | (_, Status::Fail) => Some(Status::Fail),
+            (Some(Status::Pending), _) | (_, Status::Pending) => Some(Status::Pending),
+            _ => Some(Status::Pass),
+        };
+    }
+    status
+}
+
+/// Returns the statuses of the tips of `branches` that have one, keyed by
+/// commit ID.
+pub fn statuses(source: &Source<'_>, branches: &[Branch]) -> HashMap<String, Status> {
+    let mut statuses = HashMap::new();
+    for branch in branches {
+        if statuses.contains_key(&branch.id) {
+            continue;
+        }
+        let status = match *source {
+            Source::GitHub => from_github(&branch.id),
+            Source::Command(command) => from_command(command, &branch.id),
+        };
+        if let Some(status) = status {
+            statuses.insert(branch.id.clone(), status);
+        }
+    }
+    statuses
+}
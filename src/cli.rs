@@ -0,0 +1,1239 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Command-line parsing.
+//!
+//! Without a subcommand, git-tree displays the log. In that mode git-tree's
+//! own options are recognized anywhere before a `--` argument, and every other
+//! argument is passed through to `git log`, except for revisions: those replace
+//! the interesting branches, and options that select revisions of their own are
+//! rejected. Arguments after `--` are paths, passed to `git log` after its
+//! revisions. Subcommands only accept their own options.
+//! The log mode's default arguments (see `default_args`) come before the
+//! command line's.
+
+use crate::bench;
+use crate::check::Thresholds;
+use crate::ci;
+use crate::default_args::NO_DEFAULT_ARGS;
+use crate::extra_refs;
+use crate::files;
+use crate::graph::{DatePriority, MergeFilter};
+use crate::help;
+use crate::icons::Icons;
+use crate::pins::Pin;
+use crate::reflog;
+use crate::style::ColorChoice;
+use crate::template;
+use core::iter::once;
+use core::time::Duration;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// What git-tree should do.
+pub enum Subcommand {
+    /// Display the log (the default).
+    Log,
+    /// Run the JSON-RPC server, on the given Unix socket or on stdin/stdout.
+    Serve { socket: Option<PathBuf> },
+    /// Write a Markdown report on the local branches to the given file or to
+    /// stdout.
+    Report { output: Option<PathBuf>, pager: bool, link_template: Option<String> },
+    /// Print how far the interesting branches are ahead of and behind each
+    /// other, as a table or (if `list` is true) a list of pairs.
+    Matrix { list: bool },
+    /// Print a line per local branch with its upstream and last commit, dated
+    /// in the `date` format.
+    Status { date: Option<String> },
+    /// Print, per author, the interesting branches with commits of theirs,
+    /// mapping the authors through `.mailmap` if `mailmap` is true.
+    Authors { mailmap: bool },
+    /// Pin the `branches` as always or never interesting, or list the pins if
+    /// there are none.
+    Pin { branches: Vec<String>, pin: Pin },
+    /// Remove the pins of the `branches`.
+    Unpin { branches: Vec<String> },
+    /// Print which local branches would conflict with `with` (or the trunk).
+    Conflicts { with: Option<String> },
+    /// Print a one-line summary for a shell prompt, within `budget` (or the
+    /// configured one).
+    Prompt { budget: Option<Duration> },
+    /// Time the stages of computing the graph over `iterations` runs.
+    Bench { iterations: usize },
+    /// Print the `limit` paths the displayed commits touch most often, overall
+    /// and per branch.
+    Files { limit: usize },
+    /// Write a bundle of the displayed commits to the given file.
+    Bundle { output: PathBuf },
+    /// Write the patch series of `branch` since it forked from `trunk` (or its
+    /// upstream), passing `args` to `git format-patch`.
+    FormatPatch { branch: String, trunk: Option<String>, args: Vec<OsString> },
+    /// Show the help on `topic` (or the overview), or with `man`, the whole
+    /// manual as a man page.
+    Help { topic: Option<String>, man: bool, pager: bool },
+    /// Check that the branches matching `branches` (or the local branches)
+    /// have not diverged from `trunk` beyond the `thresholds`.
+    Check { trunk: String, thresholds: Thresholds, branches: Option<String> },
+    /// Print (or, if `exec` is true, run) the commands that rebase the stack
+    /// of branches HEAD is on (or every stack, if `all` is true) onto `onto`.
+    Restack { onto: String, all: bool, exec: bool },
+}
+
+impl Subcommand {
+    /// Returns the subcommand's name, or `log` for the log mode.
+    pub const fn name(&self) -> &'static str {
+        match *self {
+            Self::Log => "log",
+            Self::Serve { .. } => "serve",
+            Self::Report { .. } => "report",
+            Self::Matrix { .. } => "matrix",
+            Self::Status { .. } => "status",
+            Self::Authors { .. } => "authors",
+            Self::Pin { .. } => "pin",
+            Self::Unpin { .. } => "unpin",
+            Self::Conflicts { .. } => "conflicts",
+            Self::Prompt { .. } => "prompt",
+            Self::Bench { .. } => "bench",
+            Self::Files { .. } => "files",
+            Self::Bundle { .. } => "bundle",
+            Self::FormatPatch { .. } => "format-patch",
+            Self::Help { .. } => "help",
+            Self::Check { .. } => "check",
+            Self::Restack { .. } => "restack",
+        }
+    }
+}
+
+/// How to draw the graph.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Render {
+    /// Run `git log` (the default).
+    Git,
+    /// Use git-tree's own renderer.
+    Native,
+}
+
+/// A file format to export the graph in, instead of displaying it.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Export {
+    /// A self-contained HTML report (`--format=html`).
+    Html,
+    /// A picture of the graph (`--format=svg`).
+    Svg,
+    /// A GraphML document (`--format=graphml`).
+    GraphMl,
+    /// A JSON node/edge list (`--format=json-edges`).
+    JsonEdges,
+    /// The stable line-oriented format (`--porcelain=v1`).
+    Porcelain,
+}
+
+/// Parsed command-line options.
+#[allow(clippy::struct_excessive_bools, reason = "they are independent flags")]
+pub struct Options {
+    pub subcommand: Subcommand,
+    /// Re-render the log whenever a ref changes.
+    pub watch: bool,
+    /// Pick commits with the fuzzy picker and print their IDs (`--pick`).
+    pub pick: bool,
+    /// List the interesting branches touching each issue instead of the graph
+    /// (`--group-by-issue`).
+    pub group_by_issue: bool,
+    /// Also treat commits from the last this many reflog entries of HEAD and
+    /// the local branches as interesting (`--reflog[=<n>]`).
+    pub reflog: Option<usize>,
+    /// Also treat this many of the refs matching `gitxl.extraRefs` with the
+    /// most recent commits as interesting (`--extra-refs[=<n>]`).
+    pub extra_refs: Option<usize>,
+    /// Show what changed since the previous `--since-last` run.
+    pub since_last: bool,
+    /// Read the interesting set from stdin (`--stdin-refs`).
+    pub stdin_refs: bool,
+    /// The revisions that make up the interesting set, if given.
+    pub revisions: Vec<String>,
+    /// Page the output (unless `--no-pager` is given).
+    pub pager: bool,
+    pub render: Render,
+    /// Draw the native graph with ASCII rather than Unicode characters.
+    pub ascii: bool,
+    /// `--color`/`--no-color`, which are also passed through to `git log`.
+    pub color: ColorChoice,
+    /// Collapse runs of more than this many linear commits in the native
+    /// graph.
+    pub collapse: Option<usize>,
+    /// The commits to hide with the native renderer (`--no-merges` or
+    /// `--merges-only`).
+    pub merges: Option<MergeFilter>,
+    /// Show only the branch skeleton (`--decorations-only`).
+    pub decorations_only: bool,
+    pub export: Option<Export>,
+    /// Where to write the export (`-o`); stdout if unset.
+    pub output: Option<PathBuf>,
+    /// URL template for the commits (`--link-template`); see `link`.
+    pub link_template: Option<String>,
+    /// End porcelain records with NUL rather than newline (`-z`, which is also
+    /// passed through to `git log`).
+    pub nul: bool,
+    /// Annotate the branch tips with their GitHub pull requests.
+    pub github: bool,
+    /// Show the CI status of the branch tips (`--ci`).
+    pub ci: bool,
+    /// The command that reports a commit's CI status (`--status-command`).
+    pub status_command: Option<String>,
+    /// Group the commits by Gerrit change.
+    pub gerrit: bool,
+    /// The remote to fetch the Gerrit changes' patchsets from, if any.
+    pub gerrit_fetch: Option<String>,
+    /// Turn replace refs off for every git command (`--no-replace-objects`).
+    pub no_replace_objects: bool,
+    /// The ref namespace to show (`--namespace`), overriding `GIT_NAMESPACE`.
+    pub namespace: Option<OsString>,
+    /// Print how long each stage took (`--timing`).
+    pub timing: bool,
+    /// How many times `-v` was given (`-vv` counts twice).
+    pub verbosity: u8,
+    /// Decorate HEAD and the interesting branches, whatever git's decoration
+    /// settings (`--decorate-interesting`).
+    pub decorate_interesting: bool,
+    /// Do not print warnings or notices (`-q`, also passed to `git log`).
+    pub quiet: bool,
+    /// Follow only first parents (`--first-parent`, also passed to `git log`).
+    pub first_parent: bool,
+    /// The `--notes[=<ref>]` options choosing the notes to show (see `notes`),
+    /// also passed to `git log`. `--no-notes` clears them.
+    pub notes: Vec<String>,
+    /// How many first parents below the merge bases to display too
+    /// (`--context`).
+    pub context: usize,
+    /// Mark the commits that make the same change as another displayed commit
+    /// (`--cherry-mark`).
+    pub cherry_mark: bool,
+    /// Mark where each local branch forked from its upstream or the trunk
+    /// (`--fork-points`).
+    pub fork_points: bool,
+    /// List the size of each local branch's work (`--diffstat`).
+    pub diffstat: bool,
+    /// Color the commits by how recent they are (`--color-by-age`).
+    pub color_by_age: bool,
+    /// Mark the kinds of the commits (`--icons` or `--no-icons`).
+    pub icons: Option<Icons>,
+    /// The pattern of the commit messages to highlight (`--find`).
+    pub find: Option<String>,
+    /// Show only the commits matching `find` besides the landmarks
+    /// (`--find-only`).
+    pub find_only: bool,
+    /// The keys of the trailers to show in the native graph and list in the
+    /// JSON and porcelain output (`--trailer`).
+    pub trailers: Vec<String>,
+    /// Leave out the remote branches that are the upstream of a local branch
+    /// (`--mine-only`).
+    pub mine_only: bool,
+    /// Add the refs of the bisection in progress to the interesting branches
+    /// (`--bisect`).
+    pub bisect: bool,
+    /// The branches to preview merging into the trunk (`--preview-merge`).
+    pub preview_merge: Vec<String>,
+    /// The profile defining the interesting branches (`--profile`).
+    pub profile: Option<String>,
+    /// The remotes whose branches can be interesting, with patterns
+    /// (`--remote`).
+    pub remotes: Vec<String>,
+    /// Fail if a local branch is more than this many commits behind the trunk
+    /// (`--warn-behind`).
+    pub warn_behind: Option<usize>,
+    /// Print full commit IDs rather than abbreviations (`--full-hashes`).
+    pub full_hashes: bool,
+    /// Map authors through `.mailmap` (unless `--no-mailmap`).
+    pub mailmap: bool,
+    /// Which date orders the commits, instead of the topology alone
+    /// (`--priority-date`).
+    pub priority_date: Option<DatePriority>,
+    /// The format of dates, for git-tree as for `git log` (`--date`).
+    pub date: Option<String>,
+    /// Options to pass through to `git log`.
+    pub log_args: Vec<OsString>,
+    /// Paths to limit `git log` to (the arguments after `--`).
+    pub pathspecs: Vec<OsString>,
+}
+
+impl Options {
+    /// Parses the command-line arguments (excluding the program name), after
+    /// the log mode's default arguments, returned by `default_args` (given the
+    /// `--profile`, if any) unless `--no-default-args` is given. Returns an
+    /// error message if they are invalid.
+    pub fn parse<I, D>(args: I, default_args: D) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = OsString>,
+        D: FnOnce(Option<&str>) -> Vec<OsString>,
+    {
+        let mut options = Self {
+            subcommand: Subcommand::Log,
+            watch: false,
+            pick: false,
+            group_by_issue: false,
+            since_last: false,
+            stdin_refs: false,
+            revisions: vec![],
+            reflog: None,
+            extra_refs: None,
+            pager: true,
+            render: Render::Git,
+            ascii: false,
+            color: ColorChoice::Auto,
+            collapse: None,
+            merges: None,
+            decorations_only: false,
+            export: None,
+            output: None,
+            link_template: None,
+            nul: false,
+            github: false,
+            ci: false,
+            status_command: None,
+            gerrit: false,
+            gerrit_fetch: None,
+            no_replace_objects: false,
+            namespace: None,
+            timing: false,
+            verbosity: 0,
+            quiet: false,
+            first_parent: false,
+            notes: vec![],
+            context: 0,
+            mine_only: false,
+            bisect: false,
+            preview_merge: vec![],
+            profile: None,
+            remotes: vec![],
+            warn_behind: None,
+            full_hashes: false,
+            mailmap: true,
+            cherry_mark: false,
+            fork_points: false,
+            diffstat: false,
+            color_by_age: false,
+            icons: None,
+            find: None,
+            find_only: false,
+            trailers: vec![],
+            priority_date: None,
+            date: None,
+            decorate_interesting: false,
+            log_args: vec![],
+            pathspecs: vec![],
+        };
+        let mut args = args.into_iter().peekable();
+        if let Some(name) = args.peek().and_then(|arg| arg.to_str()).map(str::to_owned) {
+            // The name is only consumed if it is a subcommand's.
+            if let Some(subcommand) = parse_subcommand(&name, args.by_ref().skip(1)) {
+                options.subcommand = subcommand?;
+                return Ok(options);
+            }
+        }
+        let mut args: Vec<_> = args.collect();
+        let options_end = args.iter().position(|arg| arg == "--").unwrap_or(args.len());
+        if let Some(index) = args.iter().take(options_end).position(|arg| arg == NO_DEFAULT_ARGS) {
+            args.remove(index);
+        } else {
+            let profile = profile_name(args.get(..options_end).unwrap_or_default());
+            args.splice(..0, default_args(profile.as_deref()));
+        }
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            options.log_arg(arg, &mut args)?;
+        }
+        options.take_revisions()?;
+        options.check()?;
+        Ok(options)
+    }
+
+    /// Parses an argument `arg` in the log mode, taking its value from `args`
+    /// if needed.
+    fn log_arg<I: Iterator<Item = OsString>>(
+        &mut self,
+        arg: OsString,
+        args: &mut I,
+    ) -> Result<(), String> {
+        let Some(arg_str) = arg.to_str() else {
+            self.log_args.push(arg);
+            return Ok(());
+        };
+        if self.value_arg(arg_str, args)? {
+            return Ok(());
+        }
+        if let Some(date) = arg_str.strip_prefix("--priority-date=") {
+            self.priority_date = Some(DatePriority::parse(date).ok_or_else(|| {
+                tr!("invalid {$option} value {$value}", option = "--priority-date", value = date)
+            })?);
+            return Ok(());
+        }
+        if let Some(remote) = arg_str.strip_prefix("--gerrit-fetch=") {
+            self.gerrit = true;
+            self.gerrit_fetch = Some(remote.to_owned());
+            return Ok(());
+        }
+        if let Some(entries) = arg_str.strip_prefix("--reflog=") {
+            self.reflog = Some(count("--reflog", entries)?);
+            return Ok(());
+        }
+        if let Some(refs) = arg_str.strip_prefix("--extra-refs=") {
+            self.extra_refs = Some(count("--extra-refs", refs)?);
+            return Ok(());
+        }
+        if let Some(threshold) = arg_str.strip_prefix("--warn-behind=") {
+            self.warn_behind = Some(count("--warn-behind", threshold)?);
+            return Ok(());
+        }
+        if let Some(max_run) = arg_str.strip_prefix("--collapse=") {
+            self.collapse = Some(count("--collapse", max_run)?);
+            return Ok(());
+        }
+        // The git log options are passed on below, whether or not git-tree acts
+        // on them too.
+        self.git_log_arg(arg_str)?;
+        match arg_str {
+            "--watch" => self.watch = true,
+            "--pick" => self.pick = true,
+            "--group-by-issue" => self.group_by_issue = true,
+            "--since-last" => self.since_last = true,
+            "--stdin-refs" => self.stdin_refs = true,
+            "--mine-only" => self.mine_only = true,
+            "--bisect" => self.bisect = true,
+            "--cherry-mark" => self.cherry_mark = true,
+            "--fork-points" => self.fork_points = true,
+            "--diffstat" => self.diffstat = true,
+            "--color-by-age" => self.color_by_age = true,
+            "--icons" => self.icons = Some(Icons::Glyphs),
+            "--no-icons" => self.icons = Some(Icons::Ascii),
+            "--find-only" => self.find_only = true,
+            "--full-hashes" => self.full_hashes = true,
+            "--no-mailmap" => self.mailmap = false,
+            "--reflog" => self.reflog = Some(reflog::DEFAULT_ENTRIES),
+            "--extra-refs" => self.extra_refs = Some(extra_refs::DEFAULT_COUNT),
+            "--no-replace-objects" => self.no_replace_objects = true,
+            "--timing" => self.timing = true,
+            "--decorate-interesting" => self.decorate_interesting = true,
+            "-v" => self.verbosity = self.verbosity.saturating_add(1),
+            "-vv" => self.verbosity = self.verbosity.saturating_add(2),
+            "--no-pager" => self.pager = false,
+            "--collapse" => self.collapse = Some(DEFAULT_COLLAPSE),
+            "--merges-only" => self.merges = Some(MergeFilter::MergesOnly),
+            "--decorations-only" => self.decorations_only = true,
+            "--format=html" => self.export = Some(Export::Html),
+            "--format=svg" => self.export = Some(Export::Svg),
+            "--format=graphml" => self.export = Some(Export::GraphMl),
+            "--format=json-edges" => self.export = Some(Export::JsonEdges),
+            "--porcelain" | "--porcelain=v1" => self.export = Some(Export::Porcelain),
+            _ if arg_str.starts_with("--porcelain=") => {
+                return Err(tr!("unsupported porcelain version {$option}", option = arg_str));
+            }
+            "--ascii" => self.ascii = true,
+            "--github" => self.github = true,
+            "--ci" => self.ci = true,
+            "--gerrit" => self.gerrit = true,
+            "--gerrit-fetch" => {
+                self.gerrit = true;
+                self.gerrit_fetch = Some("origin".into());
+            }
+            "--" => self.pathspecs.extend(args.by_ref()),
+            // Everything up to `--` is a revision, even if it looks like an
+            // option.
+            "--end-of-options" => {
+                self.log_args.push(arg);
+                while let Some(revision) = args.next() {
+                    if revision == "--" {
+                        self.pathspecs.extend(args.by_ref());
+                        break;
+                    }
+                    self.log_args.push(revision);
+                }
+            }
+            _ => self.log_args.push(arg),
+        }
+        Ok(())
+    }
+
+    /// Handles the `git log` options that git-tree acts on too.
+    fn git_log_arg(&mut self, arg: &str) -> Result<(), String> {
+        if let Some(format) = arg.strip_prefix("--date=") {
+            self.date = Some(format.to_owned());
+        } else if arg.starts_with("--notes=") {
+            self.notes.push(arg.to_owned());
+        } else if let Some(when) = arg.strip_prefix("--color=") {
+            self.color = ColorChoice::parse(when).ok_or_else(|| {
+                tr!("invalid {$option} value {$value}", option = "--color", value = when)
+            })?;
+        }
+        match arg {
+            "-z" => self.nul = true,
+            "-q" | "--quiet" => self.quiet = true,
+            "--first-parent" => self.first_parent = true,
+            "--notes" => self.notes.push(arg.to_owned()),
+            "--no-notes" => self.notes.clear(),
+            "--no-merges" => self.merges = Some(MergeFilter::NoMerges),
+            "--color" => self.color = ColorChoice::Always,
+            "--no-color" => self.color = ColorChoice::Never,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Handles the git-tree options that take a value, either in the same
+    /// argument or the next one. Returns whether `arg` was one of them.
+    fn value_arg<I: Iterator<Item = OsString>>(
+        &mut self,
+        arg: &str,
+        args: &mut I,
+    ) -> Result<bool, String> {
+        if let Some(render) = option_value("--render", arg, args)? {
+            self.render = match render.to_str() {
+                Some("git") => Render::Git,
+                Some("native") => Render::Native,
+                _ => return Err(tr!("unknown renderer {$renderer}", renderer = render.display())),
+            };
+            return Ok(true);
+        }
+        if let Some(namespace) = option_value("--namespace", arg, args)? {
+            self.namespace = Some(namespace);
+            return Ok(true);
+        }
+        if let Some(output) = option_value("-o", arg, args)? {
+            self.output = Some(output.into());
+            return Ok(true);
+        }
+        if let Some(template) = option_value("--link-template", arg, args)? {
+            self.link_template = Some(unicode("--link-template", template)?);
+            return Ok(true);
+        }
+        if let Some(command) = option_value("--status-command", arg, args)? {
+            self.status_command = Some(unicode("--status-command", command)?);
+            return Ok(true);
+        }
+        if let Some(profile) = option_value("--profile", arg, args)? {
+            self.profile = Some(unicode("--profile", profile)?);
+            return Ok(true);
+        }
+        if let Some(remote) = option_value("--remote", arg, args)? {
+            self.remotes.push(unicode("--remote", remote)?);
+            return Ok(true);
+        }
+        if let Some(branch) = option_value("--preview-merge", arg, args)? {
+            self.preview_merge.push(unicode("--preview-merge", branch)?);
+            return Ok(true);
+        }
+        if let Some(key) = option_value("--trailer", arg, args)? {
+            self.trailers.push(unicode("--trailer", key)?);
+            return Ok(true);
+        }
+        if let Some(pattern) = option_value("--find", arg, args)? {
+            self.find = Some(unicode("--find", pattern)?);
+            return Ok(true);
+        }
+        if let Some(lines) = option_value("--context", arg, args)? {
+            self.context = count("--context", &unicode("--context", lines)?)?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Checks that the log mode options are consistent.
+    fn check(&self) -> Result<(), String> {
+        if self.pick && (self.watch || self.export.is_some()) {
+            return Err(tr!("--pick cannot be combined with --watch or an export"));
+        }
+        if self.group_by_issue && (self.watch || self.pick || self.export.is_some()) {
+            return Err(tr!(
+                "--group-by-issue cannot be combined with --watch, --pick, or an export"
+            ));
+        }
+        if self.stdin_refs && (self.pick || self.profile.is_some()) {
+            return Err(tr!("--stdin-refs cannot be combined with --pick or --profile"));
+        }
+        if !self.revisions.is_empty() && (self.stdin_refs || self.profile.is_some()) {
+            return Err(tr!("revisions cannot be combined with --stdin-refs or --profile"));
+        }
+        if self.since_last && (self.watch || self.pick || self.export.is_some()) {
+            return Err(tr!("--since-last cannot be combined with --watch, --pick, or an export"));
+        }
+        if self.collapse.is_some() && self.render != Render::Native && self.export.is_none() {
+            return Err(tr!("--collapse requires --render=native or an export --format"));
+        }
+        if self.decorations_only && self.render != Render::Native && self.export.is_none() {
+            return Err(tr!("--decorations-only requires --render=native or an export --format"));
+        }
+        let merges_only = self.merges == Some(MergeFilter::MergesOnly);
+        if merges_only && self.render != Render::Native && self.export.is_none() {
+            return Err(tr!("--merges-only requires --render=native or an export --format"));
+        }
+        if self.render == Render::Native {
+            self.text_format()?;
+        }
+        if self.export.is_none() && self.output.is_some() {
+            return Err(tr!("-o requires an export --format"));
+        }
+        if self.render != Render::Native && self.export.is_none() && self.link_template.is_some() {
+            return Err(tr!("--link-template requires --render=native or an export --format"));
+        }
+        let git_order = self.priority_date.is_none_or(|date| date.git_option().is_some());
+        if !git_order && self.render != Render::Native && self.export.is_none() {
+            return Err(tr!("--priority-date=max requires --render=native or an export --format"));
+        }
+        if (self.collapse.is_some() || self.decorations_only)
+            && self.export == Some(Export::Porcelain)
+        {
+            return Err(tr!(
+                "--collapse and --decorations-only are not supported with --porcelain"
+            ));
+        }
+        if (self.merges.is_some() || self.find_only) && self.export == Some(Export::Porcelain) {
+            return Err(tr!(
+                "--no-merges, --merges-only, and --find-only are not supported with --porcelain"
+            ));
+        }
+        let annotated = self.render == Render::Native || self.export == Some(Export::JsonEdges);
+        if self.github && !annotated {
+            return Err(tr!("--github requires --render=native or --format=json-edges"));
+        }
+        if (self.ci || self.status_command.is_some()) && !annotated {
+            return Err(tr!(
+                "--ci and --status-command require --render=native or --format=json-edges"
+            ));
+        }
+        if self.gerrit && !annotated {
+            return Err(tr!("--gerrit requires --render=native or --format=json-edges"));
+        }
+        if self.cherry_mark && !annotated {
+            return Err(tr!("--cherry-mark requires --render=native or --format=json-edges"));
+        }
+        if self.fork_points && !annotated {
+            return Err(tr!("--fork-points requires --render=native or --format=json-edges"));
+        }
+        if self.diffstat && self.export != Some(Export::JsonEdges) {
+            return Err(tr!("--diffstat requires --format=json-edges"));
+        }
+        if !self.preview_merge.is_empty() && !annotated {
+            return Err(tr!("--preview-merge requires --render=native or --format=json-edges"));
+        }
+        if self.find.is_some() && self.render != Render::Native && self.export.is_none() {
+            return Err(tr!("--find requires --render=native or an export --format"));
+        }
+        if self.find_only && self.find.is_none() {
+            return Err(tr!("--find-only requires --find"));
+        }
+        let listed = matches!(self.export, Some(Export::JsonEdges | Export::Porcelain));
+        if !self.trailers.is_empty() && self.render != Render::Native && !listed {
+            return Err(tr!(
+                "--trailer requires --render=native, --format=json-edges, or --porcelain"
+            ));
+        }
+        if self.icons.is_some() && self.render != Render::Native {
+            return Err(tr!("--icons and --no-icons require --render=native"));
+        }
+        if self.color_by_age && !annotated {
+            return Err(tr!("--color-by-age requires --render=native or --format=json-edges"));
+        }
+        Ok(())
+    }
+
+    /// Takes the revisions out of the arguments passed through to `git log`:
+    /// bare words before `--`, unless they are the value of the preceding
+    /// option, and everything after `--end-of-options`. Fails on options that
+    /// select revisions of their own, which would change the commits displayed.
+    fn take_revisions(&mut self) -> Result<(), String> {
+        let mut log_args = vec![];
+        let mut args = self.log_args.drain(..);
+        while let Some(arg) = args.next() {
+            if arg == "--end-of-options" {
+                for revision in args.by_ref() {
+                    self.revisions.push(unicode("revision", revision)?);
+                }
+                break;
+            }
+            let arg_str = arg.to_string_lossy();
+            let name = arg_str.split_once('=').map_or(&*arg_str, |(name, _)| name);
+            if REVISION_OPTIONS.contains(&name) {
+                return Err(tr!(
+                    "{$option} is not supported, as git-tree chooses the revisions",
+                    option = name
+                ));
+            }
+            if SEPARATE_VALUE_OPTIONS.contains(&&*arg_str) {
+                let value = args.next();
+                if arg_str == "--date" {
+                    self.date = value.as_ref().map(|value| value.to_string_lossy().into_owned());
+                }
+                log_args.extend([Some(arg), value].into_iter().flatten());
+            } else if !arg_str.starts_with('-') || arg_str == "-" {
+                self.revisions.push(unicode("revision", arg)?);
+            } else {
+                log_args.push(arg);
+            }
+        }
+        drop(args);
+        self.log_args = log_args;
+        Ok(())
+    }
+
+    /// Returns where to get the CI statuses from, if they are requested.
+    pub fn ci_source(&self) -> Option<ci::Source<'_>> {
+        match (self.status_command.as_deref(), self.ci) {
+            (Some(command), _) => Some(ci::Source::Command(command)),
+            (None, true) => Some(ci::Source::GitHub),
+            (None, false) => None,
+        }
+    }
+
+    /// Returns the format for each commit's text in the native renderer,
+    /// derived from the `--format`, `--pretty`, and `--oneline` options in the
+    /// `git log` arguments and compiled by `template::compile`.
+    pub fn text_format(&self) -> Result<String, String> {
+        let mut format = DEFAULT_TEXT_FORMAT.to_owned();
+        for arg in self.log_args.iter().map_while(|arg| arg.to_str()) {
+            if arg == "--oneline" {
+                DEFAULT_TEXT_FORMAT.clone_into(&mut format);
+            } else if let Some(value) =
+                arg.strip_prefix("--format=").or_else(|| arg.strip_prefix("--pretty="))
+            {
+                value
+                    .strip_prefix("format:")
+                    .or_else(|| value.strip_prefix("tformat:"))
+                    .unwrap_or(match value {
+                        "oneline" => DEFAULT_TEXT_FORMAT,
+                        _ => value,
+                    })
+                    .clone_into(&mut format);
+            }
+        }
+        template::compile(&format, self.decorate_interesting)
+    }
+}
+
+/// Parses the value of `option`, a count.
+fn count(option: &str, value: &str) -> Result<usize, String> {
+    value.parse().map_err(|error| {
+        tr!(
+            "invalid {$option} value {$value}: {$error}",
+            option = option,
+            value = value,
+            error = error
+        )
+    })
+}
+
+/// The default `--collapse` run length.
+const DEFAULT_COLLAPSE: usize = 5;
+
+/// The native renderer's default format for commit text.
+const DEFAULT_TEXT_FORMAT: &str = "%C(auto)%h%d %s";
+
+/// `git log` options that select revisions. `--bisect` and `--reflog` are
+/// git-tree's own options, so they never reach `take_revisions`.
+const REVISION_OPTIONS: [&str; 12] = [
+    "--all",
+    "--alternate-refs",
+    "--branches",
+    "--exclude",
+    "--exclude-hidden",
+    "--glob",
+    "--not",
+    "--remotes",
+    "--stdin",
+    "--tags",
+    "--walk-reflogs",
+    "-g",
+];
+
+/// `git log` options whose value can be the next argument (`-n 5`): those
+/// that git's revision.c, diff.c, and builtin/log.c parse with a required
+/// value.
+const SEPARATE_VALUE_OPTIONS: [&str; 48] = [
+    "--after",
+    "--anchored",
+    "--author",
+    "--before",
+    "--committer",
+    "--date",
+    "--decorate-refs",
+    "--decorate-refs-exclude",
+    "--diff-algorithm",
+    "--diff-filter",
+    "--diff-merges",
+    "--dst-prefix",
+    "--encoding",
+    "--find-object",
+    "--grep",
+    "--grep-reflog",
+    "--ignore-matching-lines",
+    "--inter-hunk-context",
+    "--line-prefix",
+    "--max-age",
+    "--max-count",
+    "--min-age",
+    "--output",
+    "--output-indicator-context",
+    "--output-indicator-new",
+    "--output-indicator-old",
+    "--rotate-to",
+    "--since",
+    "--since-as-filter",
+    "--skip",
+    "--skip-to",
+    "--src-prefix",
+    "--stat-count",
+    "--stat-graph-width",
+    "--stat-name-width",
+    "--stat-width",
+    "--unified",
+    "--until",
+    "--word-diff-regex",
+    "--ws-error-highlight",
+    "-G",
+    "-I",
+    "-L",
+    "-O",
+    "-S",
+    "-U",
+    "-l",
+    "-n",
+];
+
+/// Returns the value of an option that takes a value, either from the rest of
+/// the argument (`--name=value`) or from the next argument (`--name value`).
+fn option_value<I: Iterator<Item = OsString>>(
+    name: &str,
+    arg: &str,
+    args: &mut I,
+) -> Result<Option<OsString>, String> {
+    let Some(rest) = arg.strip_prefix(name) else { return Ok(None) };
+    if let Some(value) = rest.strip_prefix('=') {
+        return Ok(Some(value.into()));
+    }
+    if !rest.is_empty() {
+        return Ok(None);
+    }
+    args.next().map(Some).ok_or_else(|| tr!("{$option} requires a value", option = name))
+}
+
+/// Returns the `--profile` among the log mode's arguments `args`, if any, to
+/// pick its default arguments before parsing them all.
+fn profile_name(args: &[OsString]) -> Option<String> {
+    let mut args = args.iter().filter_map(|arg| arg.to_str());
+    let mut profile = None;
+    while let Some(arg) = args.next() {
+        if let Some(name) = arg.strip_prefix("--profile=") {
+            profile = Some(name.to_owned());
+        } else if arg == "--profile" {
+            profile = args.next().map(str::to_owned);
+        }
+    }
+    profile
+}
+
+/// Returns the value of option `name`, which must be Unicode.
+fn unicode(name: &str, value: OsString) -> Result<String, String> {
+    value
+        .into_string()
+        .map_err(|value| tr!("invalid {$option} {$value}", option = name, value = value.display()))
+}
+
+/// Parses the options of the subcommand `name`, or returns `None` if there is
+/// no such subcommand.
+fn parse_subcommand<I: Iterator<Item = OsString>>(
+    name: &str,
+    args: I,
+) -> Option<Result<Subcommand, String>> {
+    Some(match name {
+        "help" | "--help" | "-h" => parse_help(args),
+        "--man" => parse_help(once("--man".into()).chain(args)),
+        "serve" => parse_serve(args),
+        "report" => parse_report(args),
+        "restack" => parse_restack(args),
+        "status" => parse_status(args),
+        "authors" => parse_authors(args),
+        "files" => parse_files(args),
+        "pin" => parse_pin(args),
+        "unpin" => parse_unpin(args),
+        "conflicts" => parse_conflicts(args),
+        "prompt" => parse_prompt(args),
+        "bench" => parse_bench(args),
+        "format-patch" => parse_format_patch(args),
+        "bundle" => parse_bundle(args),
+        "check" => parse_check(args),
+        "matrix" => parse_matrix(args),
+        _ => return None,
+    })
+}
+
+/// Parses the options of the `authors` subcommand: `--no-mailmap`.
+fn parse_authors<I: Iterator<Item = OsString>>(args: I) -> Result<Subcommand, String> {
+    let mut mailmap = true;
+    for arg in args {
+        if arg != "--no-mailmap" {
+            return Err(tr!(
+                "unknown {$subcommand} option {$option}",
+                subcommand = "authors",
+                option = arg.display()
+            ));
+        }
+        mailmap = false;
+    }
+    Ok(Subcommand::Authors { mailmap })
+}
+
+/// Parses the options of the `status` subcommand.
+fn parse_status<I: Iterator<Item = OsString>>(mut args: I) -> Result<Subcommand, String> {
+    let mut date = None;
+    while let Some(arg) = args.next() {
+        let arg = arg
+            .into_string()
+            .map_err(|arg| tr!("invalid argument {$argument}", argument = arg.display()))?;
+        if let Some(format) = option_value("--date", &arg, &mut args)? {
+            date = Some(unicode("--date", format)?);
+        } else {
+            return Err(tr!(
+                "unknown {$subcommand} option {$option}",
+                subcommand = "status",
+                option = arg
+            ));
+        }
+    }
+    Ok(Subcommand::Status { date })
+}
+
+/// Parses the options of the `serve` subcommand.
+fn parse_serve<I: Iterator<Item = OsString>>(mut args: I) -> Result<Subcommand, String> {
+    let mut socket = None;
+    while let Some(arg) = args.next() {
+        let arg = arg
+            .into_string()
+            .map_err(|arg| tr!("invalid argument {$argument}", argument = arg.display()))?;
+        if let Some(value) = option_value("--socket", &arg, &mut args)? {
+            socket = Some(value.into());
+        } else {
+            return Err(tr!(
+                "unknown {$subcommand} option {$option}",
+                subcommand = "serve",
+                option = arg
+            ));
+        }
+    }
+    Ok(Subcommand::Serve { socket })
+}
+
+/// Parses the options of the `report` subcommand.
+fn parse_report<I: Iterator<Item = OsString>>(mut args: I) -> Result<Subcommand, String> {
+    let mut output = None;
+    let mut pager = true;
+    let mut link_template = None;
+    while let Some(arg) = args.next() {
+        let arg = arg
+            .into_string()
+            .map_err(|arg| tr!("invalid argument {$argument}", argument = arg.display()))?;
+        if arg == "--no-pager" {
+            pager = false;
+        } else if let Some(format) = option_value("--format", &arg, &mut args)? {
+            if format != "markdown" {
+                return Err(tr!("unknown report format {$format}", format = format.display()));
+            }
+        } else if let Some(value) = option_value("-o", &arg, &mut args)? {
+            output = Some(value.into());
+        } else if let Some(template) = option_value("--link-template", &arg, &mut args)? {
+            link_template = Some(unicode("--link-template", template)?);
+        } else {
+            return Err(tr!(
+                "unknown {$subcommand} option {$option}",
+                subcommand = "report",
+                option = arg
+            ));
+        }
+    }
+    Ok(Subcommand::Report { output, pager, link_template })
+}
+
+/// Parses the options of the `matrix` subcommand.
+fn parse_matrix<I: Iterator<Item = OsString>>(args: I) -> Result<Subcommand, String> {
+    let mut list = false;
+    for arg in args {
+        match arg.to_str() {
+            Some("--list") => list = true,
+            _ => {
+                return Err(tr!(
+                    "unknown {$subcommand} option {$option}",
+                    subcommand = "matrix",
+                    option = arg.display()
+                ))
+            }
+        }
+    }
+    Ok(Subcommand::Matrix { list })
+}
+
+/// The trunk `restack` rebases onto, and `check`, `format-patch`, and
+/// `conflicts` compare with, by default: the default branch of the `origin`
+/// remote.
+pub const DEFAULT_TRUNK: &str = "origin/HEAD";
+
+/// Parses the options of the `bundle` subcommand.
+fn parse_bundle<I: Iterator<Item = OsString>>(mut args: I) -> Result<Subcommand, String> {
+    let mut output = None;
+    while let Some(arg) = args.next() {
+        let arg = arg
+            .into_string()
+            .map_err(|arg| tr!("invalid argument {$argument}", argument = arg.display()))?;
+        if let Some(value) = option_value("-o", &arg, &mut args)? {
+            output = Some(value.into());
+        } else {
+            return Err(tr!(
+                "unknown {$subcommand} option {$option}",
+                subcommand = "bundle",
+                option = arg
+            ));
+        }
+    }
+    let output = output.ok_or("bundle requires -o <file>")?;
+    Ok(Subcommand::Bundle { output })
+}
+
+/// Parses the options of the `format-patch` subcommand: `--trunk`, the
+/// branch (by default HEAD), and the options to pass on.
+fn parse_format_patch<I: Iterator<Item = OsString>>(mut args: I) -> Result<Subcommand, String> {
+    let (mut branch, mut trunk, mut rest) = (None, None, vec![]);
+    while let Some(arg) = args.next() {
+        let Some(arg_str) = arg.to_str() else {
+            rest.push(arg);
+            continue;
+        };
+        if let Some(value) = option_value("--trunk", arg_str, &mut args)? {
+            trunk = Some(unicode("--trunk", value)?);
+        } else if branch.is_none() && !arg_str.starts_with('-') {
+            branch = Some(arg_str.to_owned());
+        } else {
+            rest.push(arg);
+        }
+    }
+    let branch = branch.unwrap_or_else(|| "HEAD".into());
+    Ok(Subcommand::FormatPatch { branch, trunk, args: rest })
+}
+
+/// Parses the options of the `files` subcommand.
+fn parse_files<I: Iterator<Item = OsString>>(mut args: I) -> Result<Subcommand, String> {
+    let mut limit = files::DEFAULT_LIMIT;
+    while let Some(arg) = args.next() {
+        let arg = arg
+            .into_string()
+            .map_err(|arg| tr!("invalid argument {$argument}", argument = arg.display()))?;
+        if let Some(value) = option_value("--limit", &arg, &mut args)? {
+            limit = count("--limit", &unicode("--limit", value)?)?;
+        } else {
+            return Err(tr!(
+                "unknown {$subcommand} option {$option}",
+                subcommand = "files",
+                option = arg
+            ));
+        }
+    }
+    Ok(Subcommand::Files { limit })
+}
+
+/// Parses the options of the `pin` subcommand: `--always` (the default) or
+/// `--never`, and the branches.
+fn parse_pin<I: Iterator<Item = OsString>>(args: I) -> Result<Subcommand, String> {
+    let (mut branches, mut pin) = (vec![], Pin::Always);
+    for arg in args {
+        let arg = arg
+            .into_string()
+            .map_err(|arg| tr!("invalid argument {$argument}", argument = arg.display()))?;
+        match arg.as_str() {
+            "--always" => pin = Pin::Always,
+            "--never" => pin = Pin::Never,
+            _ if arg.starts_with('-') => {
+                return Err(tr!(
+                    "unknown {$subcommand} option {$option}",
+                    subcommand = "pin",
+                    option = arg
+                ))
+            }
+            _ => branches.push(arg),
+        }
+    }
+    Ok(Subcommand::Pin { branches, pin })
+}
+
+/// Parses the arguments of the `unpin` subcommand: the branches.
+fn parse_unpin<I: Iterator<Item = OsString>>(args: I) -> Result<Subcommand, String> {
+    let mut branches = vec![];
+    for arg in args {
+        let arg = arg
+            .into_string()
+            .map_err(|arg| tr!("invalid argument {$argument}", argument = arg.display()))?;
+        if arg.starts_with('-') {
+            return Err(tr!(
+                "unknown {$subcommand} option {$option}",
+                subcommand = "unpin",
+                option = arg
+            ));
+        }
+        branches.push(arg);
+    }
+    if branches.is_empty() {
+        return Err(tr!("unpin requires a branch"));
+    }
+    Ok(Subcommand::Unpin { branches })
+}
+
+/// Parses the arguments of the `help` subcommand (and of `--man`): the topic.
+fn parse_help<I: Iterator<Item = OsString>>(args: I) -> Result<Subcommand, String> {
+    let (mut topic, mut man, mut pager) = (None, false, true);
+    for arg in args {
+        let arg = arg
+            .into_string()
+            .map_err(|arg| tr!("invalid argument {$argument}", argument = arg.display()))?;
+        match arg.as_str() {
+            "--man" => man = true,
+            "--no-pager" => pager = false,
+            _ if arg.starts_with('-') => {
+                return Err(tr!(
+                    "unknown {$subcommand} option {$option}",
+                    subcommand = "help",
+                    option = arg
+                ));
+            }
+            _ if topic.is_some() => return Err(tr!("help takes one topic")),
+            _ if !help::exists(&arg) => {
+                return Err(tr!("unknown help topic {$topic}", topic = arg));
+            }
+            _ => topic = Some(arg),
+        }
+    }
+    Ok(Subcommand::Help { topic, man, pager })
+}
+
+/// Parses the options of the `conflicts` subcommand.
+fn parse_conflicts<I: Iterator<Item = OsString>>(mut args: I) -> Result<Subcommand, String> {
+    let mut with = None;
+    while let Some(arg) = args.next() {
+        let arg = arg
+            .into_string()
+            .map_err(|arg| tr!("invalid argument {$argument}", argument = arg.display()))?;
+        if let Some(value) = option_value("--with", &arg, &mut args)? {
+            with = Some(unicode("--with", value)?);
+        } else {
+            return Err(tr!(
+                "unknown {$subcommand} option {$option}",
+                subcommand = "conflicts",
+                option = arg
+            ));
+        }
+    }
+    Ok(Subcommand::Conflicts { with })
+}
+
+/// Parses the options of the `prompt` subcommand.
+fn parse_prompt<I: Iterator<Item = OsString>>(mut args: I) -> Result<Subcommand, String> {
+    let mut budget = None;
+    while let Some(arg) = args.next() {
+        let arg = arg
+            .into_string()
+            .map_err(|arg| tr!("invalid argument {$argument}", argument = arg.display()))?;
+        if let Some(value) = option_value("--budget", &arg, &mut args)? {
+            let millis = count("--budget", &unicode("--budget", value)?)?;
+            budget = Some(Duration::from_millis(millis.try_into().unwrap_or(u64::MAX)));
+        } else {
+            return Err(tr!(
+                "unknown {$subcommand} option {$option}",
+                subcommand = "prompt",
+                option = arg
+            ));
+        }
+    }
+    Ok(Subcommand::Prompt { budget })
+}
+
+/// Parses the options of the `bench` subcommand.
+fn parse_bench<I: Iterator<Item = OsString>>(mut args: I) -> Result<Subcommand, String> {
+    let mut iterations = bench::DEFAULT_ITERATIONS;
+    while let Some(arg) = args.next() {
+        let arg = arg
+            .into_string()
+            .map_err(|arg| tr!("invalid argument {$argument}", argument = arg.display()))?;
+        if let Some(value) = option_value("--iterations", &arg, &mut args)? {
+            iterations = count("--iterations", &unicode("--iterations", value)?)?;
+            if iterations == 0 {
+                return Err(tr!("--iterations must be at least 1"));
+            }
+        } else {
+            return Err(tr!(
+                "unknown {$subcommand} option {$option}",
+                subcommand = "bench",
+                option = arg
+            ));
+        }
+    }
+    Ok(Subcommand::Bench { iterations })
+}
+
+/// Parses the options of the `check` subcommand.
+fn parse_check<I: Iterator<Item = OsString>>(mut args: I) -> Result<Subcommand, String> {
+    let mut trunk = DEFAULT_TRUNK.to_owned();
+    let mut thresholds = Thresholds { max_ahead: None, max_behind: None };
+    let mut branches = None;
+    while let Some(arg) = args.next() {
+        let arg = arg
+            .into_string()
+            .map_err(|arg| tr!("invalid argument {$argument}", argument = arg.display()))?;
+        if let Some(value) = option_value("--trunk", &arg, &mut args)? {
+            trunk = unicode("--trunk", value)?;
+        } else if let Some(value) = option_value("--max-ahead", &arg, &mut args)? {
+            thresholds.max_ahead = Some(count("--max-ahead", &unicode("--max-ahead", value)?)?);
+        } else if let Some(value) = option_value("--max-behind", &arg, &mut args)? {
+            thresholds.max_behind = Some(count("--max-behind", &unicode("--max-behind", value)?)?);
+        } else if let Some(value) = option_value("--branches", &arg, &mut args)? {
+            branches = Some(unicode("--branches", value)?);
+        } else {
+            return Err(tr!(
+                "unknown {$subcommand} option {$option}",
+                subcommand = "check",
+                option = arg
+            ));
+        }
+    }
+    Ok(Subcommand::Check { trunk, thresholds, branches })
+}
+
+/// Parses the options of the `restack` subcommand.
+fn parse_restack<I: Iterator<Item = OsString>>(mut args: I) -> Result<Subcommand, String> {
+    let mut onto = DEFAULT_TRUNK.to_owned();
+    let (mut all, mut exec) = (false, false);
+    while let Some(arg) = args.next() {
+        let arg = arg
+            .into_string()
+            .map_err(|arg| tr!("invalid argument {$argument}", argument = arg.display()))?;
+        if let Some(value) = option_value("--onto", &arg, &mut args)? {
+            onto = value.into_string().map_err(|value| {
+                tr!("invalid {$option} {$value}", option = "--onto", value = value.display())
+            })?;
+        } else if arg == "--all" {
+            all = true;
+        } else if arg == "--exec" {
+            exec = true;
+        } else if arg != "--dry-run" {
+            return Err(tr!(
+                "unknown {$subcommand} option {$option}",
+                subcommand = "restack",
+                option = arg
+            ));
+        }
+    }
+    Ok(Subcommand::Restack { onto, all, exec })
+}
@@ -0,0 +1,45 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reading git config settings.
+//!
+//! A setting that is unset, empty, or cannot be read (e.g. outside a
+//! repository) counts as unset, without an error from git on stderr.
+
+use crate::log::Logged as _;
+use std::process::{Command, Stdio};
+
+/// Returns the standard output of `git config <option> <key>`, if it succeeds.
+fn read(option: &str, key: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["config", option, key])
+        .stderr(Stdio::null())
+        .logged()
+        .output()
+        .ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Returns the value of the setting `key`, if set.
+pub fn get(key: &str) -> Option<String> {
+    let value = read("--get", key)?.trim_end().to_owned();
+    (!value.is_empty()).then_some(value)
+}
+
+/// Returns the values of the multi-valued setting `key`, in order.
+pub fn get_all(key: &str) -> Vec<String> {
+    read("--get-all", key).map_or_else(Vec::new, |stdout| {
+        stdout.lines().filter(|value| !value.is_empty()).map(str::to_owned).collect()
+    })
+}
@@ -0,0 +1,97 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `git-tree conflicts`: which local branches would conflict with the trunk
+//! (or another branch), and in which files, to decide what to rebase first.
+//!
+//! Each local branch with commits the target does not have is merged with it
+//! by `git merge-tree --write-tree`, which merges in memory, touching neither
+//! the working tree nor the index. The branches with nothing of their own to
+//! merge are left out, as found from a single walk of the history (see
+//! `matrix::Counts`).
+
+use crate::log::Logged as _;
+use crate::matrix::Counts;
+use crate::{graph, interesting_branches, quote, rev_parse};
+use std::io::{self, Write};
+use std::process::Command;
+
+/// `git merge-tree`'s exit status when the merge has conflicts.
+const CONFLICTS_STATUS: i32 = 1;
+
+/// Merges `a` and `b` in memory. Returns the ID of the merged tree (with
+/// conflict markers, if any) and the files left conflicted, sorted and quoted
+/// by git, or none if they merge cleanly.
+pub fn merge(a: &str, b: &str) -> io::Result<(String, Vec<String>)> {
+    let output = Command::new("git")
+        .args(["merge-tree", "--write-tree", "--name-only", "--no-messages", a, b])
+        .env(graph::NO_LAZY_FETCH, "1")
+        .logged()
+        .output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    // The first line is the ID of the tree.
+    let tree = lines.next().unwrap_or_default().to_owned();
+    match output.status.code() {
+        Some(0) => Ok((tree, vec![])),
+        Some(CONFLICTS_STATUS) => {
+            let mut files: Vec<_> = lines.map(str::to_owned).collect();
+            files.sort_unstable();
+            files.dedup();
+            Ok((tree, files))
+        }
+        _ => Err(io::Error::other(tr!(
+            "git merge-tree failed: {$error}",
+            error = String::from_utf8_lossy(&output.stderr).trim_end()
+        ))),
+    }
+}
+
+/// Writes, for each local branch with commits that `target` does not have,
+/// whether it merges cleanly with `target` or which files would conflict.
+pub fn write<W: Write>(mut out: W, target: &str) -> io::Result<()> {
+    let target_id = rev_parse(target)
+        .ok_or_else(|| io::Error::other(tr!("unknown branch {$branch}", branch = target)))?;
+    let mut buffer = Vec::with_capacity(256);
+    let (branches, _) = interesting_branches(&mut buffer)?;
+    let locals: Vec<_> =
+        branches.iter().filter(|branch| branch.refname.starts_with("refs/heads/")).collect();
+    let tips = locals.iter().map(|branch| branch.id.clone()).chain([target_id.clone()]);
+    let counts = Counts::new(&mut buffer, tips)?;
+    let target_name = quote::name(target);
+    for branch in locals.into_iter().filter(|branch| counts.ahead(&branch.id, &target_id) != 0) {
+        let name = quote::name(&branch.name);
+        let (_, files) = merge(&branch.id, &target_id)?;
+        if files.is_empty() {
+            writeln!(
+                out,
+                "{}",
+                tr!("{$name}: merges cleanly with {$target}", name = name, target = target_name)
+            )?;
+        } else {
+            let files = files.join(", ");
+            writeln!(
+                out,
+                "{}",
+                tr!(
+                    "{$name}: conflicts with {$target} in {$files}",
+                    name = name,
+                    target = target_name,
+                    files = files
+                )
+            )?;
+        }
+    }
+    out.flush()
+}
@@ -0,0 +1,81 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--context <n>`: a few commits below the merge bases, to show what the
+//! trunk looked like before the branches forked.
+//!
+//! Each merge base is replaced by the commit `n` first parents below it (or
+//! the root its first-parent chain ends at), and the view is computed as usual
+//! from those. The commits on a path from a tip to one of them are displayed,
+//! which includes the first-parent chains between them and the merge bases,
+//! and excludes side branches merged into those chains from further down.
+
+use crate::log::{self, Level, Logged as _};
+use crate::{git_status, graph, short_ids, trim_line_ending};
+use core::str;
+use std::io::{self, BufRead as _, BufReader};
+use std::process::{Command, Stdio};
+
+/// Returns the commits `lines` first parents below each of the `bases`,
+/// keeping only those that are not ancestors of another.
+/// Precondition: `buffer` must be empty.
+/// Postcondition: `buffer` will be empty.
+pub fn lower(buffer: &mut Vec<u8>, bases: &[String], lines: usize) -> io::Result<Vec<String>> {
+    let mut lowered = vec![];
+    for base in bases {
+        let output = Command::new("git")
+            .args(["rev-list", "--first-parent", "--max-count"])
+            .arg(lines.saturating_add(1).to_string())
+            .args(["--end-of-options", base, "--"])
+            .env(graph::NO_LAZY_FETCH, "1")
+            .logged()
+            .output()?;
+        git_status("rev-list", output.status)?;
+        let stdout = str::from_utf8(&output.stdout).map_err(io::Error::other)?;
+        lowered.extend(stdout.lines().last().map(str::to_owned));
+    }
+    lowered.sort_unstable();
+    lowered.dedup();
+    // A lowered base can be an ancestor of another, which would hide the
+    // commits between them.
+    if lowered.len() > 1 {
+        lowered = independent(buffer, &lowered)?;
+    }
+    let fields = vec![("lines", lines.into()), ("bases", short_ids(&lowered))];
+    log::event(Level::Debug, "context", "lowered the merge bases", fields);
+    Ok(lowered)
+}
+
+/// Returns those of `commits` that are not ancestors of another, sorted.
+fn independent(buffer: &mut Vec<u8>, commits: &[String]) -> io::Result<Vec<String>> {
+    let mut git = Command::new("git")
+        .args(["merge-base", "--independent"])
+        .args(commits)
+        .env(graph::NO_LAZY_FETCH, "1")
+        .stdout(Stdio::piped())
+        .logged()
+        .spawn()?;
+    let mut independent = vec![];
+    #[allow(clippy::unwrap_used, reason = "stdout is piped")]
+    let mut reader = BufReader::new(git.stdout.as_mut().unwrap());
+    while reader.read_until(b'\n', buffer)? != 0 {
+        independent
+            .push(str::from_utf8(trim_line_ending(buffer)).map_err(io::Error::other)?.to_owned());
+        buffer.clear();
+    }
+    drop(reader);
+    git_status("merge-base", git.wait()?)?;
+    independent.sort_unstable();
+    Ok(independent)
+}
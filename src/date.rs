@@ -0,0 +1,296 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Dates in git-tree's own output, formatted the way git formats them.
+//!
+//! The format is chosen by `--date=<format>`, or else by the `log.date` config
+//! setting, as for `git log`. git-tree formats `relative`, `iso` (or
+//! `iso8601`), `iso-strict` (or `iso8601-strict`), `short`, `raw`, `unix`, and
+//! `default` itself; for any other format, each place uses its own default.
+//! The native renderer passes the format on to git for the placeholders of
+//! `--format`, such as `%ad`, which otherwise ignore both.
+//!
+//! Like git, "now" is `GIT_TEST_DATE_NOW` if it is set, for reproducible
+//! tests.
+
+use crate::config;
+use core::fmt::Write as _;
+use std::env::var;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A date format.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// E.g. `3 days ago`.
+    Relative,
+    /// E.g. `2023-11-14 22:13:20 +0000`.
+    Iso,
+    /// E.g. `2023-11-14T22:13:20Z`.
+    IsoStrict,
+    /// E.g. `2023-11-14`.
+    Short,
+    /// E.g. `1700000000 +0000`.
+    Raw,
+    /// E.g. `1700000000`.
+    Unix,
+    /// E.g. `Tue Nov 14 22:13:20 2023 +0000`.
+    Default,
+}
+
+impl Format {
+    /// Parses a `--date` or `log.date` value, or returns `None` if git-tree
+    /// does not format dates that way.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "relative" => Some(Self::Relative),
+            "iso" | "iso8601" => Some(Self::Iso),
+            "iso-strict" | "iso8601-strict" => Some(Self::IsoStrict),
+            "short" => Some(Self::Short),
+            "raw" => Some(Self::Raw),
+            "unix" => Some(Self::Unix),
+            "default" => Some(Self::Default),
+            _ => None,
+        }
+    }
+}
+
+/// A point in time, with the time zone it was recorded in.
+#[derive(Clone, Copy)]
+pub struct Time {
+    /// Seconds since the epoch.
+    pub seconds: i64,
+    /// The time zone's offset from UTC, in minutes.
+    pub offset: i64,
+}
+
+impl Time {
+    /// Parses git's raw format, e.g. `1700000000 +0100`.
+    pub fn parse_raw(raw: &str) -> Option<Self> {
+        let (seconds, zone) = raw.trim().split_once(' ')?;
+        let (sign, digits) = zone.split_at_checked(1)?;
+        let (hours, minutes) = digits.split_at_checked(2)?;
+        let magnitude =
+            hours.parse::<i64>().ok()?.checked_mul(60)?.checked_add(minutes.parse().ok()?)?;
+        let offset = match sign {
+            "+" => magnitude,
+            "-" => magnitude.checked_neg()?,
+            _ => return None,
+        };
+        Some(Self { seconds: seconds.parse().ok()?, offset })
+    }
+}
+
+/// The `--date` value, if one was given.
+static CHOSEN: OnceLock<String> = OnceLock::new();
+
+/// The `log.date` config setting, if set.
+static CONFIGURED: OnceLock<Option<String>> = OnceLock::new();
+
+/// Makes `name` the date format, over `log.date`.
+pub fn choose(name: &str) {
+    // There is only one command line.
+    drop(CHOSEN.set(name.to_owned()));
+}
+
+/// Returns the name of the date format chosen by `--date` or `log.date`.
+fn chosen() -> Option<&'static str> {
+    CHOSEN
+        .get()
+        .map(String::as_str)
+        .or_else(|| CONFIGURED.get_or_init(|| config::get("log.date")).as_deref())
+}
+
+/// Returns the `--date` option that makes git format dates as chosen, if a
+/// format was chosen.
+pub fn git_option() -> Option<String> {
+    chosen().map(|name| format!("--date={name}"))
+}
+
+/// Returns the current time in seconds since the epoch.
+pub fn now() -> i64 {
+    if let Some(now) = var("GIT_TEST_DATE_NOW").ok().and_then(|now| now.parse().ok()) {
+        return now;
+    }
+    let elapsed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    elapsed.as_secs().try_into().unwrap_or(i64::MAX)
+}
+
+/// Formats `time` in the chosen format if git-tree supports it, and in
+/// `fallback` otherwise.
+pub fn format(time: Time, fallback: Format) -> String {
+    let format = chosen().and_then(Format::parse).unwrap_or(fallback);
+    match format {
+        Format::Relative => relative(time.seconds, now()),
+        Format::Unix => time.seconds.to_string(),
+        Format::Raw => format!("{} {}", time.seconds, zone(time.offset, false)),
+        Format::Iso | Format::IsoStrict | Format::Short | Format::Default => absolute(time, format),
+    }
+}
+
+/// Returns `count` followed by `unit`, made plural unless `count` is 1.
+fn plural(count: i64, unit: &str) -> String {
+    if count == 1 {
+        format!("{count} {unit}")
+    } else {
+        format!("{count} {unit}s")
+    }
+}
+
+/// Returns how long before `now` `seconds` was, rounded like git.
+#[allow(
+    clippy::arithmetic_side_effects,
+    clippy::integer_division,
+    clippy::integer_division_remainder_used,
+    reason = "the difference is not negative, and git's timestamps are far from i64's limits"
+)]
+fn relative(seconds: i64, now: i64) -> String {
+    // The rounding divisions, with the numbers they round at.
+    let round = |value: i64, half: i64, unit: i64| (value + half) / unit;
+    let Some(diff) = now.checked_sub(seconds).filter(|&diff| diff >= 0) else {
+        return "in the future".to_owned();
+    };
+    if diff < 90 {
+        return format!("{} ago", plural(diff, "second"));
+    }
+    let minutes = round(diff, 30, 60);
+    if minutes < 90 {
+        return format!("{} ago", plural(minutes, "minute"));
+    }
+    let hours = round(minutes, 30, 60);
+    if hours < 36 {
+        return format!("{} ago", plural(hours, "hour"));
+    }
+    let days = round(hours, 12, 24);
+    if days < 14 {
+        return format!("{} ago", plural(days, "day"));
+    }
+    if days < 70 {
+        return format!("{} ago", plural(round(days, 3, 7), "week"));
+    }
+    if days < 365 {
+        return format!("{} ago", plural(round(days, 15, 30), "month"));
+    }
+    if days < 1825 {
+        let total_months = round(days * 24, 365, 730);
+        let (years, months) = (total_months.div_euclid(12), total_months.rem_euclid(12));
+        if months == 0 {
+            return format!("{} ago", plural(years, "year"));
+        }
+        return format!("{}, {} ago", plural(years, "year"), plural(months, "month"));
+    }
+    format!("{} ago", plural(round(days, 183, 365), "year"))
+}
+
+/// Returns the time zone `offset` (in minutes) as `+hhmm`, or as `+hh:mm` (and
+/// `Z` for UTC) if `strict`.
+#[allow(
+    clippy::integer_division,
+    clippy::integer_division_remainder_used,
+    reason = "the hours and minutes are the quotient and remainder"
+)]
+fn zone(offset: i64, strict: bool) -> String {
+    if strict && offset == 0 {
+        return "Z".to_owned();
+    }
+    let sign = if offset < 0 { '-' } else { '+' };
+    let magnitude = offset.unsigned_abs();
+    let (hours, minutes) = (magnitude / 60, magnitude % 60);
+    if strict {
+        format!("{sign}{hours:02}:{minutes:02}")
+    } else {
+        format!("{sign}{hours:02}{minutes:02}")
+    }
+}
+
+/// The abbreviated names of the days of the week, from Sunday.
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+/// The abbreviated names of the months.
+const MONTHS: [&str; 12] =
+    ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// The number of seconds in a day.
+const DAY: i64 = 86_400;
+
+/// Returns the year, month (1 to 12), and day of the month of the day `days`
+/// after the epoch, in the proleptic Gregorian calendar.
+#[allow(
+    clippy::arithmetic_side_effects,
+    clippy::integer_division,
+    clippy::integer_division_remainder_used,
+    reason = "the days of git's timestamps are far from i64's limits, and the divisors are constants"
+)]
+const fn civil(days: i64) -> (i64, i64, i64) {
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+    let shifted = days + 719_468;
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+/// Formats `time` in its own time zone in the absolute `format`.
+#[allow(
+    clippy::arithmetic_side_effects,
+    reason = "git's timestamps and time zone offsets are far from i64's limits"
+)]
+#[allow(
+    clippy::unwrap_used,
+    reason = "weekdays and months are in range, and writing to a String cannot fail"
+)]
+fn absolute(time: Time, format: Format) -> String {
+    let local = time.seconds + time.offset * 60;
+    let (days, second_of_day) = (local.div_euclid(DAY), local.rem_euclid(DAY));
+    let (year, month, day) = civil(days);
+    let (hour, minute, second) = (
+        second_of_day.div_euclid(3600),
+        second_of_day.rem_euclid(3600).div_euclid(60),
+        second_of_day.rem_euclid(60),
+    );
+    let mut text = String::new();
+    match format {
+        Format::Short => write!(text, "{year}-{month:02}-{day:02}"),
+        Format::IsoStrict => write!(
+            text,
+            "{year}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}{}",
+            zone(time.offset, true)
+        ),
+        Format::Default => {
+            // The epoch was a Thursday.
+            let weekday = usize::try_from((days + 4).rem_euclid(7)).unwrap();
+            let month_name = usize::try_from(month - 1).unwrap();
+            write!(
+                text,
+                "{} {} {day} {hour:02}:{minute:02}:{second:02} {year} {}",
+                WEEKDAYS.get(weekday).unwrap(),
+                MONTHS.get(month_name).unwrap(),
+                zone(time.offset, false)
+            )
+        }
+        Format::Iso | Format::Relative | Format::Raw | Format::Unix => write!(
+            text,
+            "{year}-{month:02}-{day:02} {hour:02}:{minute:02}:{second:02} {}",
+            zone(time.offset, false)
+        ),
+    }
+    .unwrap();
+    text
+}
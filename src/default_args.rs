@@ -0,0 +1,49 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Default arguments for the log mode, prepended to the command line's: each
+//! `gitxl.logArgs` config value is one argument, and `GITXL_DEFAULT_ARGS` is
+//! split at whitespace, e.g.
+//!
+//! ```text
+//! git config --add gitxl.logArgs --abbrev-commit
+//! git config --add gitxl.logArgs --date=relative
+//! GITXL_DEFAULT_ARGS='--pretty=oneline --abbrev-commit' git tree
+//! ```
+//!
+//! A `--profile`'s `logArgs` (see `profile`) come after `gitxl.logArgs`, and
+//! the environment's after the config's, so they win where git log lets later
+//! options override earlier ones, as do the command line's.
+//! `--no-default-args` ignores both.
+
+use crate::config;
+use crate::profile;
+use std::env::var_os;
+use std::ffi::OsString;
+
+/// The option that ignores the default arguments.
+pub const NO_DEFAULT_ARGS: &str = "--no-default-args";
+
+/// Returns the default arguments, with those of the profile named `profile`.
+pub fn load(profile: Option<&str>) -> Vec<OsString> {
+    let mut args: Vec<_> =
+        config::get_all("gitxl.logArgs").into_iter().map(OsString::from).collect();
+    if let Some(name) = profile {
+        args.extend(profile::log_args(name).into_iter().map(OsString::from));
+    }
+    if let Some(env) = var_os("GITXL_DEFAULT_ARGS") {
+        args.extend(env.to_string_lossy().split_whitespace().map(OsString::from));
+    }
+    args
+}
@@ -0,0 +1,72 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--diffstat`: how big each local branch's work is, for dashboards charting
+//! the work in flight.
+//!
+//! A branch's work is the diff from where it forked from its upstream, or from
+//! the trunk if it has none (see `fork::forked`), to its tip: the changes its
+//! own commits make together. It takes a single `git diff` per branch.
+
+use crate::json::Value;
+use crate::report::git;
+use crate::{fork, Branch};
+use std::io;
+
+/// The size of a branch's work.
+pub struct Diffstat {
+    /// The branch's name.
+    branch: String,
+    /// The short name of the branch it forked from, e.g. `origin/main`.
+    from: String,
+    files: usize,
+    insertions: usize,
+    deletions: usize,
+}
+
+impl Diffstat {
+    /// Returns the diffstat as a JSON object.
+    pub fn json(&self) -> Value {
+        Value::object([
+            ("branch", self.branch.as_str().into()),
+            ("from", self.from.as_str().into()),
+            ("filesChanged", self.files.into()),
+            ("insertions", self.insertions.into()),
+            ("deletions", self.deletions.into()),
+        ])
+    }
+}
+
+/// Returns the diffstats of the local branches among `branches` that have an
+/// upstream or a trunk to have forked from.
+pub fn diffstats(branches: &[Branch]) -> io::Result<Vec<Diffstat>> {
+    let mut stats = vec![];
+    for (branch, from, point) in fork::forked(branches)? {
+        // --numstat rather than --shortstat, whose summary git translates.
+        let output = git(&["diff", "--numstat", &point, &branch.id, "--"])?;
+        let mut stat =
+            Diffstat { branch: branch.name.clone(), from, files: 0, insertions: 0, deletions: 0 };
+        for line in output.lines() {
+            let mut fields = line.split('\t');
+            // Binary files count as changed, with `-` for their lines.
+            let mut count = || fields.next().and_then(|field| field.parse().ok()).unwrap_or(0);
+            let (insertions, deletions): (usize, usize) = (count(), count());
+            stat.files = stat.files.saturating_add(1);
+            stat.insertions = stat.insertions.saturating_add(insertions);
+            stat.deletions = stat.deletions.saturating_add(deletions);
+        }
+        stats.push(stat);
+    }
+    Ok(stats)
+}
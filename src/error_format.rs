@@ -0,0 +1,192 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--error-format=json`: failures reported on stderr as a JSON object, for
+//! scripts and editors, instead of as a line of text:
+//!
+//! ```text
+//! {"code":"not-a-repository","message":"not a git repository ...","stage":"repository"}
+//! ```
+//!
+//! The code is one of a fixed set (see `Code`), and the stage is the part of
+//! the run that failed: `arguments`, `repository`, the log mode's `refs`,
+//! `traversal`, or `output`, or the subcommand's name. The object is the last
+//! line of stderr: the git commands git-tree runs may have reported why they
+//! failed before it.
+
+use crate::json::Value;
+use crate::log::Logged as _;
+use alloc::string::FromUtf8Error;
+use core::str::Utf8Error;
+use core::sync::atomic::{AtomicBool, Ordering};
+use std::ffi::OsString;
+use std::io::{self, stderr, ErrorKind, Write as _};
+use std::panic::{self, PanicHookInfo};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+/// Whether `--error-format=json` was given.
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// The part of the run in progress.
+static STAGE: Mutex<&'static str> = Mutex::new("arguments");
+
+/// What kind of failure it is.
+#[derive(Clone, Copy)]
+pub enum Code {
+    /// The arguments (or the profile they name) are invalid.
+    Usage,
+    /// The current directory is not in a git repository.
+    NotARepository,
+    /// A git command could not be run or failed.
+    Git,
+    /// A lock or a remote service did not answer in time.
+    Timeout,
+    /// git printed something that is not UTF-8 where git-tree needs text, such
+    /// as a ref name.
+    NonUtf8,
+    /// A check (`git-tree check` or `--warn-behind`) found a branch over its
+    /// threshold.
+    Check,
+    /// Reading or writing a file or stream failed.
+    Io,
+    /// A bug in git-tree.
+    Internal,
+}
+
+impl Code {
+    /// Returns the code as reported, which never changes.
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Usage => "usage",
+            Self::NotARepository => "not-a-repository",
+            Self::Git => "git",
+            Self::Timeout => "timeout",
+            Self::NonUtf8 => "non-utf-8",
+            Self::Check => "check",
+            Self::Io => "io",
+            Self::Internal => "internal",
+        }
+    }
+
+    /// Returns the code of an I/O error.
+    pub fn of(error: &io::Error) -> Self {
+        let inner = error.get_ref();
+        if inner.is_some_and(|inner| inner.is::<FromUtf8Error>() || inner.is::<Utf8Error>()) {
+            return Self::NonUtf8;
+        }
+        let kind = error.kind();
+        if kind == ErrorKind::WouldBlock || kind == ErrorKind::TimedOut {
+            Self::Timeout
+        } else if kind == ErrorKind::InvalidData {
+            Self::NonUtf8
+        } else {
+            Self::Io
+        }
+    }
+}
+
+/// Removes the `--error-format=<text|json>` options before `--` from `args`,
+/// and applies the last one. They are taken out before anything else, so that
+/// even invalid arguments are reported in the chosen format.
+pub fn take<I: Iterator<Item = OsString>>(args: I) -> Result<Vec<OsString>, String> {
+    let (mut format, mut rest, mut options) = (None, vec![], true);
+    for arg in args {
+        options &= arg != "--";
+        match arg.to_str().and_then(|arg| arg.strip_prefix("--error-format=")) {
+            Some(value) if options => format = Some(value.to_owned()),
+            _ => rest.push(arg),
+        }
+    }
+    match format.as_deref() {
+        None | Some("text") => {}
+        Some("json") => {
+            JSON.store(true, Ordering::Relaxed);
+            panic::set_hook(Box::new(report_panic));
+        }
+        Some(value) => return Err(tr!("invalid --error-format value {$value}", value = value)),
+    }
+    Ok(rest)
+}
+
+/// Returns whether `--error-format=json` was given.
+pub fn json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Records that the run has reached `stage`.
+pub fn stage(stage: &'static str) {
+    if let Ok(mut current) = STAGE.lock() {
+        *current = stage;
+    }
+}
+
+/// Reports a failure on stderr, as a JSON object with `--error-format=json`,
+/// and otherwise as a line of text if `text` is true (failures already
+/// explained in text, such as `Code::Check`'s, are only reported in JSON).
+pub fn report(code: Code, message: &str, text: bool) {
+    let mut line = String::new();
+    if json() {
+        let stage = STAGE.lock().map_or("", |stage| *stage);
+        Value::object([
+            ("code", code.name().into()),
+            ("message", message.into()),
+            ("stage", stage.into()),
+        ])
+        .write(&mut line);
+    } else if text {
+        line.extend(["git-tree: ", message]);
+    } else {
+        return;
+    }
+    line.push('\n');
+    drop(stderr().write_all(line.as_bytes()));
+}
+
+/// With `--error-format=json`, fails if the current directory is not in a git
+/// repository, rather than letting the first git command print git's error.
+pub fn check_repository() -> Result<(), String> {
+    if !json() {
+        return Ok(());
+    }
+    stage("repository");
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-dir"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .logged()
+        .output()
+        .map_err(|error| tr!("failed to run git: {$error}", error = error))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let message = String::from_utf8_lossy(&output.stderr);
+    let message = message.trim_end();
+    Err(message.strip_prefix("fatal: ").unwrap_or(message).to_owned())
+}
+
+/// Reports a panic in JSON. git-tree panics when git cannot be run, fails, or
+/// prints what git-tree cannot read, which the messages say; any other panic
+/// is a bug.
+fn report_panic(info: &PanicHookInfo<'_>) {
+    let message = info.payload_as_str().unwrap_or("panicked");
+    let code = if message.contains("utf-8") {
+        Code::NonUtf8
+    } else if message.contains("git") {
+        Code::Git
+    } else {
+        Code::Internal
+    };
+    report(code, message, true);
+}
@@ -0,0 +1,80 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--extra-refs[=<n>]`: refs outside `refs/heads` and `refs/remotes` that are
+//! interesting too, such as the patchsets of code reviews.
+//!
+//! Pushing for review to a Gerrit-style server's `refs/for/<branch>` creates
+//! no local ref: the server turns the push into a
+//! `refs/changes/<nn>/<change>/<patchset>` ref, which `--gerrit-fetch` (or a
+//! `git fetch` of `refs/changes/*`) copies into the repository. The refs under
+//! `refs/changes` are the default, and the multi-valued `gitxl.extraRefs`
+//! config setting can choose others (`git for-each-ref` patterns), e.g. the
+//! refs a server keeps signed pushes under, once fetched.
+//!
+//! Such refs have no reflog to say when they were updated, and there can be
+//! many of them, so the `n` whose commits were made last are taken. Refs to
+//! anything but commits are skipped.
+
+use crate::log::Logged as _;
+use crate::{config, git_status, graph, namespace_prefix, Branch};
+use std::io;
+use std::process::Command;
+
+/// The number of refs that `--extra-refs` adds by default.
+pub const DEFAULT_COUNT: usize = 10;
+
+/// The patterns used when `gitxl.extraRefs` is not set.
+const DEFAULT_PATTERNS: [&str; 1] = ["refs/changes"];
+
+/// Returns the patterns of `gitxl.extraRefs`, or the default ones if it is not
+/// set.
+fn patterns() -> Vec<String> {
+    let configured = config::get_all("gitxl.extraRefs");
+    if configured.is_empty() {
+        return DEFAULT_PATTERNS.map(str::to_owned).to_vec();
+    }
+    configured
+}
+
+/// Returns the `count` refs to commits that match the `gitxl.extraRefs`
+/// patterns whose commits were made last, named by their ref name without
+/// `refs/`, e.g. `changes/45/12345/2`.
+pub fn load(count: usize) -> io::Result<Vec<Branch>> {
+    let prefix = namespace_prefix().unwrap_or_default();
+    let output = Command::new("git")
+        .args(["for-each-ref", "--sort=-committerdate"])
+        .arg("--format=%(objecttype)%00%(refname)%00%(objectname)%00%(committerdate:unix)")
+        .args(patterns().iter().map(|pattern| format!("{prefix}{pattern}")))
+        .env(graph::NO_LAZY_FETCH, "1")
+        .logged()
+        .output()?;
+    git_status("for-each-ref", output.status)?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\0');
+            let (kind, refname, id, time) =
+                (fields.next()?, fields.next()?, fields.next()?, fields.next()?);
+            let refname = refname.strip_prefix(prefix.as_str())?;
+            (kind == "commit").then(|| Branch {
+                name: refname.strip_prefix("refs/").unwrap_or(refname).to_owned(),
+                refname: refname.to_owned(),
+                id: id.to_owned(),
+                time: time.parse().unwrap_or_default(),
+            })
+        })
+        .take(count)
+        .collect())
+}
@@ -0,0 +1,129 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `git-tree files`: the paths the displayed commits touch most often, over
+//! all the interesting branches and per branch, to spot work on different
+//! branches that overlaps before it turns into a merge conflict.
+//!
+//! The displayed commits above the merge bases are listed once, with the paths
+//! each one changes (merges change none), in topological order. Which branches
+//! reach each commit is worked out by pushing the set of tips from children to
+//! parents, as `matrix` does.
+
+use crate::log::Logged as _;
+use crate::matrix::Reach;
+use crate::timing::Timing;
+use crate::{graph, interesting_branches, View};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::process::Command;
+
+/// The number of paths listed per section by default.
+pub const DEFAULT_LIMIT: usize = 10;
+
+/// How often each path was touched, by path.
+type Touches = HashMap<String, usize>;
+
+/// Adds one touch of each of `paths` to `touches`.
+fn touch(touches: &mut Touches, paths: &[&str]) {
+    for &path in paths {
+        let count = touches.entry(path.to_owned()).or_default();
+        *count = count.saturating_add(1);
+    }
+}
+
+/// Writes the `limit` paths of `touches` touched most often, with how often
+/// and, if `branches` is given, by which branches.
+fn write_section<W: Write>(
+    out: &mut W,
+    title: &str,
+    touches: &Touches,
+    branches: Option<&HashMap<&str, Vec<&str>>>,
+    limit: usize,
+) -> io::Result<()> {
+    writeln!(out, "{title}:")?;
+    let mut paths: Vec<_> = touches.iter().collect();
+    paths.sort_unstable_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    let width = paths.first().map_or(0, |(_, count)| count.to_string().len());
+    for (path, count) in paths.into_iter().take(limit) {
+        write!(out, "  {count:>width$}  {path}")?;
+        if let Some(names) = branches.and_then(|branches| branches.get(path.as_str())) {
+            write!(out, " ({})", names.join(", "))?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Writes the `limit` paths the displayed commits touch most often, over all
+/// the interesting branches and then per branch.
+pub fn write<W: Write>(mut out: W, limit: usize) -> io::Result<()> {
+    let mut buffer = Vec::with_capacity(256);
+    let (branches, head) = interesting_branches(&mut buffer)?;
+    if branches.is_empty() && head.is_none() {
+        return Ok(());
+    }
+    let view = View::compute(buffer, branches, head, None, false, 0, &mut Timing::new(false))?;
+    let by_id = view.branches_by_id();
+    let mut tips: Vec<_> = by_id.keys().copied().collect();
+    tips.sort_unstable();
+    let output = Command::new("git")
+        .args(["log", "--topo-order", "--name-only", "--no-renames", "--format=%x00%H %P"])
+        .args(view.revisions())
+        .arg("--")
+        .env(graph::NO_LAZY_FETCH, "1")
+        .logged()
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(tr!("git log failed with {$status}", status = output.status)));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut all = Touches::new();
+    let mut per_tip = vec![Touches::new(); tips.len()];
+    let mut walk = Reach::new(&tips, &view.merge_bases);
+    for record in stdout.split('\0').skip(1) {
+        let mut lines = record.lines();
+        let mut ids = lines.next().unwrap_or_default().split(' ');
+        let id = ids.next().unwrap_or_default();
+        let paths: Vec<_> = lines.filter(|line| !line.is_empty()).collect();
+        let Some(reach) = walk.visit(id, ids.filter(|parent| !parent.is_empty())) else {
+            continue;
+        };
+        touch(&mut all, &paths);
+        for tip in reach.indices() {
+            if let Some(touches) = per_tip.get_mut(tip) {
+                touch(touches, &paths);
+            }
+        }
+    }
+    let titles: Vec<_> = tips
+        .iter()
+        .map(|tip| by_id.get(tip).map(|names| names.join(", ")).unwrap_or_default())
+        .collect();
+    let mut sections: Vec<_> = titles.iter().zip(&per_tip).collect();
+    sections.sort_unstable_by_key(|&(title, _)| title);
+    // The branches touching each path, to show where they overlap.
+    let mut touched_by: HashMap<&str, Vec<&str>> = HashMap::new();
+    for &(title, touches) in &sections {
+        for path in touches.keys() {
+            touched_by.entry(path).or_default().push(title);
+        }
+    }
+    write_section(&mut out, &tr!("All branches"), &all, Some(&touched_by), limit)?;
+    for (title, touches) in sections {
+        writeln!(out)?;
+        write_section(&mut out, title, touches, None, limit)?;
+    }
+    out.flush()
+}
@@ -0,0 +1,72 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--find <pattern>`: the displayed commits whose message matches a pattern,
+//! highlighted in the native graph, or with `--find-only`, the only commits
+//! shown besides the landmarks.
+//!
+//! Passing `--grep` to `git log` instead drops the commits that do not match,
+//! and with them the lines that show how the matches relate. The pattern is a
+//! regular expression, matched by `git rev-list --grep` as `--grep` would be.
+
+use crate::git_status;
+use crate::graph::{self, Commit};
+use crate::log::Logged as _;
+use crate::style::Paint;
+use std::collections::HashSet;
+use std::io;
+use std::process::{Command, Stdio};
+
+/// The SGR parameters that highlight a match: reverse video.
+const HIGHLIGHT: &str = "7";
+
+/// Returns the IDs of the commits listed by `revisions` (see `View::revisions`)
+/// whose message matches `pattern`.
+pub fn matches(revisions: &[String], pattern: &str) -> io::Result<HashSet<String>> {
+    let output = Command::new("git")
+        .arg("rev-list")
+        .arg(format!("--grep={pattern}"))
+        .args(revisions)
+        .env(graph::NO_LAZY_FETCH, "1")
+        // git explains an invalid pattern.
+        .stderr(Stdio::inherit())
+        .logged()
+        .output()?;
+    git_status("rev-list", output.status)?;
+    Ok(String::from_utf8_lossy(&output.stdout).lines().map(String::from).collect())
+}
+
+/// Returns `commits` without those that are neither `found` nor in `keep` (the
+/// landmarks), as `graph::hide` does.
+pub fn only(commits: Vec<Commit>, found: &HashSet<String>, keep: &HashSet<&str>) -> Vec<Commit> {
+    let hidden = commits
+        .iter()
+        .filter(|commit| !found.contains(&commit.id) && !keep.contains(commit.id.as_str()))
+        .map(|commit| commit.id.clone())
+        .collect();
+    graph::hide(commits, &hidden)
+}
+
+/// Highlights the text of `commit`, a match: in reverse video if `color` is
+/// true, and with a `[found]` note otherwise.
+pub fn highlight(commit: &mut Commit, color: bool) {
+    if color {
+        let lines: Vec<_> =
+            commit.text.split('\n').map(|line| Paint(HIGHLIGHT, line).to_string()).collect();
+        commit.text = lines.join("\n");
+    } else {
+        let end = commit.text.find('\n').unwrap_or(commit.text.len());
+        commit.text.insert_str(end, " [found]");
+    }
+}
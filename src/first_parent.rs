@@ -0,0 +1,80 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--first-parent`: the view of the history made of first parents only.
+//!
+//! Following only first parents, the history is a forest: the chains from the
+//! tips join and never split. The view is the chains from the tips down to the
+//! commit where they have all joined, which plays the part of the merge base.
+//! The commits on a chain cannot be ancestors of that commit's parents, so the
+//! usual `<tips> --not <base>^@` revisions list exactly the chains, and no
+//! include/exclude traversal is needed.
+
+use crate::log::{self, Level, Logged as _};
+use crate::{graph, short_ids, trim_line_ending};
+use core::str;
+use std::collections::HashMap;
+use std::io::{self, BufRead as _, BufReader};
+use std::process::{Command, Stdio};
+
+/// Returns the commit where the first-parent chains of all the `tips` (sorted)
+/// join, as
+/// the only merge base, or none if some of them never do (their histories are
+/// unrelated).
+/// Precondition: `buffer` must be empty.
+/// Postcondition: `buffer` will be empty.
+pub fn merge_bases(buffer: &mut Vec<u8>, tips: &[String]) -> io::Result<Vec<String>> {
+    let mut git = Command::new("git")
+        .args(["rev-list", "--first-parent", "--parents", "--topo-order"])
+        .args(tips)
+        .env(graph::NO_LAZY_FETCH, "1")
+        .stdout(Stdio::piped())
+        .logged()
+        .spawn()?;
+    // How many tips reach each commit whose children have been listed but
+    // which has not been listed yet. Chains never split, so no tip is counted
+    // twice.
+    let mut pending: HashMap<String, usize> = HashMap::new();
+    let mut bases = vec![];
+    #[allow(clippy::unwrap_used, reason = "stdout is piped")]
+    let mut reader = BufReader::new(git.stdout.as_mut().unwrap());
+    while reader.read_until(b'\n', buffer)? != 0 {
+        let line = str::from_utf8(trim_line_ending(buffer)).map_err(io::Error::other)?;
+        let mut ids = line.split(' ');
+        let id = ids.next().unwrap_or_default();
+        let mut reach = pending.remove(id).unwrap_or_default();
+        if tips.binary_search_by(|tip| tip.as_str().cmp(id)).is_ok() {
+            reach = reach.saturating_add(1);
+        }
+        // Topological order lists every child before its parent, so the
+        // first commit all the tips reach is the lowest.
+        if reach == tips.len() {
+            bases.push(id.to_owned());
+            buffer.clear();
+            break;
+        }
+        if let Some(parent) = ids.next() {
+            let count = pending.entry(parent.to_owned()).or_default();
+            *count = count.saturating_add(reach);
+        }
+        buffer.clear();
+    }
+    drop(reader);
+    // Nothing below the base matters.
+    drop(git.kill());
+    drop(git.wait());
+    let fields = vec![("tips", tips.len().into()), ("bases", short_ids(&bases))];
+    log::event(Level::Debug, "merge-base", "computed the first-parent merge base", fields);
+    Ok(bases)
+}
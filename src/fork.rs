@@ -0,0 +1,91 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--fork-points`: where each local branch forked from its upstream, or from
+//! the trunk if it has none, marked on the commit it forked at.
+//!
+//! The fork point is found as for `git-tree format-patch` (see
+//! `restack::fork_point`): from the merge base, or from the upstream's reflog
+//! if the upstream has been rewritten since. Branches without commits of their
+//! own are not marked.
+
+use crate::cli::DEFAULT_TRUNK;
+use crate::json::Value;
+use crate::report::git;
+use crate::{date, restack, Branch};
+use std::collections::HashMap;
+use std::io;
+
+/// A local branch forking from another branch.
+pub struct Fork {
+    /// The local branch's name.
+    branch: String,
+    /// The short name of the branch it forked from, e.g. `origin/main`.
+    from: String,
+}
+
+impl Fork {
+    /// Returns the annotation of the fork point, whose committer date is
+    /// `time`, e.g. `feature forked from origin/main 12 days ago`.
+    pub fn label(&self, time: i64) -> String {
+        let when = date::format(date::Time { seconds: time, offset: 0 }, date::Format::Relative);
+        format!("{} forked from {} {when}", self.branch, self.from)
+    }
+
+    /// Returns the fork as a JSON object.
+    pub fn json(&self) -> Value {
+        Value::object([
+            ("branch", self.branch.as_str().into()),
+            ("from", self.from.as_str().into()),
+        ])
+    }
+}
+
+/// Returns the forks of the local branches among `branches`, by the ID of the
+/// commit they forked at.
+pub fn fork_points(branches: &[Branch]) -> io::Result<HashMap<String, Vec<Fork>>> {
+    let mut forks: HashMap<String, Vec<Fork>> = HashMap::new();
+    for (branch, from, point) in forked(branches)? {
+        if point != branch.id {
+            let fork = Fork { branch: branch.name.clone(), from };
+            forks.entry(point).or_default().push(fork);
+        }
+    }
+    Ok(forks)
+}
+
+/// Returns the local branches among `branches` with the short name of the
+/// branch each forked from (its upstream, or the trunk if it has none) and the
+/// ID of the commit it forked at, which is its tip if it has no commits of its
+/// own. Branches with neither an upstream nor a trunk are left out.
+pub fn forked(branches: &[Branch]) -> io::Result<Vec<(&Branch, String, String)>> {
+    let output = git(&["for-each-ref", "--format=%(refname)%00%(upstream:short)", "refs/heads"])?;
+    let upstreams: HashMap<&str, &str> = output
+        .lines()
+        .filter_map(|line| line.split_once('\0'))
+        .filter(|(_, upstream)| !upstream.is_empty())
+        .collect();
+    let trunk = git(&["rev-parse", "--abbrev-ref", DEFAULT_TRUNK]).ok();
+    let mut forked = vec![];
+    for branch in branches.iter().filter(|branch| branch.refname.starts_with("refs/heads/")) {
+        let Some(from) = upstreams.get(branch.refname.as_str()).copied().or(trunk.as_deref())
+        else {
+            continue;
+        };
+        let from = from.trim_end();
+        let Ok(point) = restack::fork_point(from, &branch.refname) else { continue };
+        forked.push((branch, from.to_owned(), point));
+    }
+    Ok(forked)
+}
@@ -0,0 +1,60 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `git-tree format-patch`: the patch series of a branch's own commits, from
+//! the point where it forked, so that nobody has to work out the range.
+//!
+//! The branch is compared with its upstream, or, if it has none or `--trunk`
+//! is given, with the trunk. Its commits since the fork point (see
+//! `restack::fork_point`) are passed to `git format-patch` as a numbered
+//! series, recording the fork point as the base commit.
+
+use crate::cli::DEFAULT_TRUNK;
+use crate::log::Logged as _;
+use crate::{graph, restack, rev_parse};
+use std::ffi::OsString;
+use std::io;
+use std::process::Command;
+
+/// Writes the patch series of `branch` with `git format-patch`, passing it
+/// `args`. The fork point is found from `trunk` if given, and otherwise from
+/// the branch's upstream or, failing that, the default trunk.
+pub fn format_patch(branch: &str, trunk: Option<&str>, args: &[OsString]) -> io::Result<()> {
+    let upstream = format!("{branch}@{{upstream}}");
+    let against = match trunk {
+        Some(trunk) => trunk,
+        None if rev_parse(&upstream).is_some() => &upstream,
+        None => DEFAULT_TRUNK,
+    };
+    if rev_parse(against).is_none() {
+        return Err(io::Error::other(tr!("unknown trunk {$trunk}", trunk = against)));
+    }
+    let base = restack::fork_point(against, branch)?;
+    let status = Command::new("git")
+        .args(["format-patch", "--numbered"])
+        .arg(format!("--base={base}"))
+        .args(args)
+        .arg(format!("{base}..{branch}"))
+        .arg("--")
+        .env(graph::NO_LAZY_FETCH, "1")
+        .logged()
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::other(tr!(
+            "git format-patch failed with {$status}",
+            status = status
+        )));
+    }
+    Ok(())
+}
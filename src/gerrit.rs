@@ -0,0 +1,196 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--gerrit`: groups the displayed commits by the Gerrit change they belong
+//! to.
+//!
+//! A commit's change is given by its `Change-Id` trailer, so the commits with
+//! the same `Change-Id` are patchsets of the same change. Patchset numbers come
+//! from the `refs/changes/<nn>/<number>/<patchset>` refs, which
+//! `--gerrit-fetch` fetches for the changes of the displayed commits.
+
+use crate::abbrev;
+use crate::graph::Commit;
+use crate::json::Value;
+use crate::report::git;
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+/// A Gerrit change with displayed patchsets.
+struct Change {
+    /// The `Change-Id` trailer.
+    id: String,
+    /// The change number, if known.
+    number: Option<u64>,
+    /// The highest known patchset number.
+    latest: Option<u64>,
+    /// The displayed commits with this `Change-Id`, and their patchset numbers
+    /// (if known), in display order.
+    commits: Vec<(String, Option<u64>)>,
+}
+
+/// The changes of the displayed commits.
+pub struct Changes {
+    changes: Vec<Change>,
+    /// The index in `changes` of each commit's change.
+    by_commit: HashMap<String, usize>,
+}
+
+/// A patchset ref: the change number and patchset number.
+type Patchset = (u64, u64);
+
+/// Parses a line of `git for-each-ref` or `git ls-remote` output into the
+/// commit ID and the patchset, if the ref is a patchset ref.
+fn parse_ref(line: &str) -> Option<(&str, Patchset)> {
+    let (id, refname) = line.split_once([' ', '\t'])?;
+    let mut parts = refname.strip_prefix("refs/changes/")?.split('/');
+    let (_, number, patchset) = (parts.next()?, parts.next()?, parts.next()?);
+    Some((id, (number.parse().ok()?, patchset.parse().ok()?)))
+}
+
+/// Fetches all patchsets of the changes that have a displayed commit as a
+/// patchset on `remote`, into the same refs locally.
+pub fn fetch(remote: &str, commits: &[Commit]) -> io::Result<()> {
+    let displayed: HashSet<&str> = commits.iter().map(|commit| commit.id.as_str()).collect();
+    let refs = git(&["ls-remote", remote, "refs/changes/*"])?;
+    let mut refspecs: Vec<String> = refs
+        .lines()
+        .filter_map(parse_ref)
+        .filter(|&(id, _)| displayed.contains(id))
+        .filter_map(|(_, (number, _))| {
+            let shard = number.checked_rem(100)?;
+            Some(format!("+refs/changes/{shard:02}/{number}/*:refs/changes/{shard:02}/{number}/*"))
+        })
+        .collect();
+    refspecs.sort_unstable();
+    refspecs.dedup();
+    if refspecs.is_empty() {
+        return Ok(());
+    }
+    let mut args = vec!["fetch", "--quiet", "--no-write-fetch-head", remote];
+    args.extend(refspecs.iter().map(String::as_str));
+    git(&args).map(drop)
+}
+
+impl Changes {
+    /// Loads the changes of `commits`, the displayed commits listed by
+    /// `revisions` (see `View::revisions`).
+    pub fn load(revisions: &[String], commits: &[Commit]) -> io::Result<Self> {
+        let displayed: HashSet<&str> = commits.iter().map(|commit| commit.id.as_str()).collect();
+        let mut args =
+            vec!["log", "--format=%H %(trailers:key=Change-Id,valueonly,separator=%x2C)"];
+        args.extend(revisions.iter().map(String::as_str));
+        let trailers = git(&args)?;
+        let refs = git(&["for-each-ref", "--format=%(objectname) %(refname)", "refs/changes"])?;
+        let mut patchsets: HashMap<&str, Patchset> = HashMap::new();
+        let mut latest: HashMap<u64, u64> = HashMap::new();
+        for (id, (number, patchset)) in refs.lines().filter_map(parse_ref) {
+            patchsets.insert(id, (number, patchset));
+            let highest = latest.entry(number).or_default();
+            *highest = (*highest).max(patchset);
+        }
+        let mut changes = Self { changes: vec![], by_commit: HashMap::new() };
+        let mut by_change_id: HashMap<&str, usize> = HashMap::new();
+        for line in trailers.lines() {
+            let Some((id, change_ids)) = line.split_once(' ') else { continue };
+            // Collapsed commits are not displayed.
+            if !displayed.contains(id) {
+                continue;
+            }
+            let Some(change_id) = change_ids.split(',').next().filter(|value| !value.is_empty())
+            else {
+                continue;
+            };
+            let patchset = patchsets.get(id).copied();
+            let index = *by_change_id.entry(change_id).or_insert_with(|| {
+                changes.changes.push(Change {
+                    id: change_id.to_owned(),
+                    number: None,
+                    latest: None,
+                    commits: vec![],
+                });
+                changes.changes.len().saturating_sub(1)
+            });
+            #[allow(clippy::unwrap_used, reason = "index was just found or pushed")]
+            let change = changes.changes.get_mut(index).unwrap();
+            if let Some((number, _)) = patchset {
+                change.number = Some(number);
+                change.latest = latest.get(&number).copied();
+            }
+            change.commits.push((id.to_owned(), patchset.map(|(_, patchset)| patchset)));
+            changes.by_commit.insert(id.to_owned(), index);
+        }
+        Ok(changes)
+    }
+
+    /// Returns the annotation for `commit`, e.g. "I0123abc patchset 2 of 3,
+    /// also 89abcde (patchset 1)", or `None` if it has no `Change-Id`.
+    pub fn label(&self, commit: &str) -> Option<String> {
+        let change = self.changes.get(*self.by_commit.get(commit)?)?;
+        let short_id = change.id.get(..8).unwrap_or(&change.id);
+        let label = match (change.commits.iter().find(|other| other.0 == commit), change.latest) {
+            (Some(&(_, Some(patchset))), Some(latest)) => {
+                format!("{short_id} patchset {patchset} of {latest}")
+            }
+            _ => short_id.to_owned(),
+        };
+        let others: Vec<String> = change
+            .commits
+            .iter()
+            .filter(|other| other.0 != commit)
+            .map(|(other, patchset)| {
+                let short = abbrev::short(other);
+                patchset.map_or_else(|| short.to_owned(), |p| format!("{short} (patchset {p})"))
+            })
+            .collect();
+        if others.is_empty() {
+            return Some(label);
+        }
+        Some(format!("{label}, also {}", others.join(", ")))
+    }
+
+    /// Returns the `Change-Id` of `commit`, if it has one.
+    pub fn change_id(&self, commit: &str) -> Option<&str> {
+        Some(&self.changes.get(*self.by_commit.get(commit)?)?.id)
+    }
+
+    /// Returns the changes as a JSON array.
+    pub fn json(&self) -> Value {
+        let number =
+            |number: Option<u64>| number.map_or(Value::Null, |n| Value::Number(n.to_string()));
+        Value::Array(
+            self.changes
+                .iter()
+                .map(|change| {
+                    let commits = change
+                        .commits
+                        .iter()
+                        .map(|(id, patchset)| {
+                            Value::object([
+                                ("id", id.as_str().into()),
+                                ("patchset", number(*patchset)),
+                            ])
+                        })
+                        .collect();
+                    Value::object([
+                        ("changeId", change.id.as_str().into()),
+                        ("number", number(change.number)),
+                        ("latestPatchset", number(change.latest)),
+                        ("commits", Value::Array(commits)),
+                    ])
+                })
+                .collect(),
+        )
+    }
+}
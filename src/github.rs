@@ -0,0 +1,162 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--github`: the GitHub pull requests for the interesting branches.
+//!
+//! The open pull requests come from the GitHub CLI (`gh pr list`, which uses
+//! the user's `gh` login or the `GH_TOKEN`/`GITHUB_TOKEN` environment
+//! variables). If `gh` is unavailable or fails, they come from the
+//! `refs/pull/<number>/head` refs fetched from GitHub instead, which only give
+//! the number and head commit (and also include closed pull requests).
+//!
+//! A pull request belongs to a branch if its head is the branch's tip or, for
+//! pull requests from `gh` whose head branch is in the repository itself
+//! rather than a fork, if its head branch has the (local) branch's name. A
+//! fork's branch of the same name is someone else's work.
+
+use crate::json::Value;
+use crate::log::Logged as _;
+use crate::Branch;
+use core::str;
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+
+/// The most pull requests requested from `gh`.
+const LIMIT: &str = "500";
+
+/// A pull request.
+pub struct PullRequest {
+    number: u64,
+    /// The head commit's ID.
+    head: String,
+    /// The head branch's name, if known and the branch is not in a fork.
+    head_branch: Option<String>,
+    /// "open" or "draft", if known.
+    state: Option<&'static str>,
+    /// "approved", "changes requested", or "review required", if known.
+    review: Option<&'static str>,
+}
+
+impl PullRequest {
+    /// Returns the annotation for the pull request, e.g. "PR #123 (open,
+    /// approved)".
+    pub fn label(&self) -> String {
+        let details: Vec<_> = self.state.iter().chain(&self.review).copied().collect();
+        if details.is_empty() {
+            format!("PR #{}", self.number)
+        } else {
+            format!("PR #{} ({})", self.number, details.join(", "))
+        }
+    }
+
+    /// Returns the pull request as a JSON object.
+    pub fn json(&self) -> Value {
+        Value::object([
+            ("number", Value::Number(self.number.to_string())),
+            ("state", self.state.into()),
+            ("review", self.review.into()),
+        ])
+    }
+}
+
+/// Returns the open pull requests according to `gh`, or `None` if `gh` is
+/// unavailable or fails.
+fn from_gh() -> Option<Vec<PullRequest>> {
+    let output = Command::new("gh")
+        .args(["pr", "list", "--state=open", "--limit", LIMIT, "--json"])
+        .arg("number,headRefOid,headRefName,isCrossRepository,isDraft,reviewDecision")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .logged()
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let Value::Array(list) = Value::parse(str::from_utf8(&output.stdout).ok()?)? else {
+        return None;
+    };
+    Some(
+        list.iter()
+            .filter_map(|pull| {
+                Some(PullRequest {
+                    number: pull.get("number")?.as_usize()?.try_into().ok()?,
+                    head: pull.get("headRefOid")?.as_str()?.to_owned(),
+                    head_branch: pull
+                        .get("headRefName")
+                        .and_then(Value::as_str)
+                        .filter(|_| pull.get("isCrossRepository") == Some(&Value::Bool(false)))
+                        .map(str::to_owned),
+                    state: Some(if pull.get("isDraft") == Some(&Value::Bool(true)) {
+                        "draft"
+                    } else {
+                        "open"
+                    }),
+                    review: match pull.get("reviewDecision").and_then(Value::as_str) {
+                        Some("APPROVED") => Some("approved"),
+                        Some("CHANGES_REQUESTED") => Some("changes requested"),
+                        Some("REVIEW_REQUIRED") => Some("review required"),
+                        _ => None,
+                    },
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Returns the pull requests whose head refs (`refs/pull/<number>/head`, or a
+/// remote-tracking copy ending in `/pull/<number>/head`) have been fetched.
+fn from_refs() -> Vec<PullRequest> {
+    let Ok(output) = Command::new("git")
+        .args(["for-each-ref", "--format=%(objectname) %(refname)", "refs/pull", "refs/remotes"])
+        .logged()
+        .output()
+    else {
+        return vec![];
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (id, refname) = line.split_once(' ')?;
+            let (_, number) = refname.strip_suffix("/head")?.rsplit_once("/pull/")?;
+            Some(PullRequest {
+                number: number.parse().ok()?,
+                head: id.to_owned(),
+                head_branch: None,
+                state: None,
+                review: None,
+            })
+        })
+        .collect()
+}
+
+/// Returns the pull requests for `branches`, keyed by the ID of the commit
+/// the branch points to.
+pub fn pull_requests(branches: &[Branch]) -> HashMap<String, Vec<PullRequest>> {
+    let mut by_id: HashMap<String, Vec<PullRequest>> = HashMap::new();
+    for pull in from_gh().unwrap_or_else(from_refs) {
+        let matching = branches.iter().find(|branch| {
+            pull.head == branch.id || pull.head_branch.as_ref() == Some(&branch.name)
+        });
+        let Some(branch) = matching else { continue };
+        let pulls = by_id.entry(branch.id.clone()).or_default();
+        if pulls.iter().all(|other| other.number != pull.number) {
+            pulls.push(pull);
+        }
+    }
+    for pulls in by_id.values_mut() {
+        pulls.sort_unstable_by_key(|pull| pull.number);
+    }
+    by_id
+}
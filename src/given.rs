@@ -0,0 +1,93 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The interesting set given explicitly instead of chosen by the heuristic, to
+//! see how any commits relate: the revisions on the command line
+//! (`git-tree <rev>...`), or with `--stdin-refs`, those read from stdin, one
+//! per line or NUL-terminated, for scripts.
+//!
+//! Each revision is a ref or anything else git understands, such as a commit
+//! ID or a tag, and stands for a branch named as given. The merge bases and
+//! the commits between are computed as usual. Profiles, pins, and
+//! `gitxl.refHook` do not apply, and HEAD is only shown if it is one of the
+//! commits given.
+
+use crate::log::Logged as _;
+use crate::{peel_to_commit, rev_parse, Branch};
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+/// The branches given, if any.
+static CHOSEN: OnceLock<Vec<Branch>> = OnceLock::new();
+
+/// Reads the revisions from `input` for `--stdin-refs`. Returns an error
+/// message if there are none.
+pub fn read<R: Read>(mut input: R) -> Result<Vec<String>, String> {
+    let mut text = String::new();
+    input
+        .read_to_string(&mut text)
+        .map_err(|error| tr!("--stdin-refs: {$error}", error = error))?;
+    let revs: Vec<_> = text
+        .split(['\n', '\0'])
+        .map(str::trim)
+        .filter(|rev| !rev.is_empty())
+        .map(str::to_owned)
+        .collect();
+    if revs.is_empty() {
+        return Err(tr!("--stdin-refs: no revisions on stdin"));
+    }
+    Ok(revs)
+}
+
+/// Resolves the revisions `revs` to the branches `chosen` returns, unless
+/// there are none. Returns an error message if one does not name a commit.
+pub fn choose(revs: &[String]) -> Result<(), String> {
+    if revs.is_empty() {
+        return Ok(());
+    }
+    let mut branches = vec![];
+    for rev in revs {
+        let (id, time) = peel_to_commit(rev)
+            .ok_or_else(|| tr!("{$revision} is not a commit", revision = rev))?;
+        let refname = full_name(rev).unwrap_or_else(|| rev.clone());
+        branches.push(Branch { name: rev.clone(), refname, id, time });
+    }
+    branches.sort_unstable_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+    branches.dedup_by(|a, b| a.sort_key() == b.sort_key());
+    // There is only one command line.
+    drop(CHOSEN.set(branches));
+    Ok(())
+}
+
+/// Returns the branches given, sorted (see `Branch::sort_key`), and the commit
+/// HEAD points to if it is one of theirs, if any were given.
+pub fn chosen() -> Option<(Vec<Branch>, Option<String>)> {
+    let chosen = CHOSEN.get()?;
+    let head = rev_parse("HEAD").filter(|head| chosen.iter().any(|branch| branch.id == *head));
+    Some((chosen.clone(), head))
+}
+
+/// Returns the full name of the ref `rev` names, or `None` if it is not a ref.
+pub fn full_name(rev: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--symbolic-full-name", "--end-of-options", rev])
+        .stderr(Stdio::null())
+        .logged()
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8(output.stdout).ok().filter(|_| output.status.success())?;
+    let refname = stdout.trim_end();
+    refname.starts_with("refs/").then(|| refname.to_owned())
+}
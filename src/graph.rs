@@ -0,0 +1,398 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Loads the displayed commits, for the output modes that do not delegate to
+//! `git log`.
+
+use crate::log::Logged as _;
+use crate::{abbrev, date, git_status, malformed};
+use alloc::collections::BinaryHeap;
+use core::cmp::Reverse;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::{mem, slice};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::process::Command;
+
+/// A displayed commit, or a placeholder for a run of collapsed commits.
+#[derive(Clone)]
+pub struct Commit {
+    pub id: String,
+    /// IDs of all of this commit's parents, including those that are not
+    /// displayed.
+    pub parents: Vec<String>,
+    pub author: String,
+    /// Author date, in seconds since the epoch.
+    pub time: i64,
+    /// Committer date, in seconds since the epoch.
+    pub committer_time: i64,
+    pub subject: String,
+    /// The commit formatted with the format passed to `load`, if any. May span
+    /// multiple lines.
+    pub text: String,
+    /// For a placeholder, the number of commits it stands for; 0 for real
+    /// commits.
+    pub collapsed: usize,
+}
+
+/// The environment variable that stops git from fetching missing objects in a
+/// partial clone. git-tree only reads commits, which a partial clone always
+/// has, so it sets this on the git commands it runs to read history: a bug
+/// that makes one of them read a blob then fails instead of quietly fetching
+/// it. (`git log` itself is left alone, as the user may ask it for diffs.)
+pub const NO_LAZY_FETCH: &str = "GIT_NO_LAZY_FETCH";
+
+/// Whether `--no-mailmap` was given.
+static NO_MAILMAP: AtomicBool = AtomicBool::new(false);
+
+/// Stops mapping authors through `.mailmap`.
+pub fn ignore_mailmap() {
+    NO_MAILMAP.store(true, Ordering::Relaxed);
+}
+
+/// Returns whether authors are mapped through `.mailmap` to their canonical
+/// names and addresses, as `git log --use-mailmap` does (unless
+/// `--no-mailmap` was given).
+pub fn mailmap() -> bool {
+    !NO_MAILMAP.load(Ordering::Relaxed)
+}
+
+/// Loads the commits listed by `revisions` (see `View::revisions`), in
+/// topological order (children before parents). If `text_format` is given,
+/// each commit's `text` is formatted with it (using git's pretty format
+/// placeholders), with color codes if `color` is true. If `revisions` start
+/// with `--first-parent`, each commit's only parent is its first. Authors are
+/// mapped through `.mailmap` (see `mailmap`).
+pub fn load(
+    revisions: &[String],
+    text_format: Option<&str>,
+    color: bool,
+) -> io::Result<Vec<Commit>> {
+    // `%P` lists every parent even then.
+    let first_parent = revisions.first().is_some_and(|first| first == "--first-parent");
+    // In an empty repository, nothing is included (and rev-list would fail).
+    let included = revisions.get(usize::from(first_parent));
+    if included.is_none_or(|first| first == "--not") {
+        return Ok(vec![]);
+    }
+    // rev-list heads each commit with a "commit <id>" line. Every field but the
+    // last, the text, is followed by a NUL. The text may contain anything, NULs
+    // and newlines included, so it is last, and ends only where the next
+    // commit's header does (see `records`).
+    let author = if mailmap() { "%aN" } else { "%an" };
+    let output = Command::new("git")
+        .args(["rev-list", "--topo-order"])
+        .env(NO_LAZY_FETCH, "1")
+        .arg(format!(
+            "--format=%H%x00%P%x00{author}%x00%at%x00%ct%x00%s%x00{}",
+            text_format.unwrap_or("")
+        ))
+        .arg(if color { "--color=always" } else { "--color=never" })
+        // rev-list ignores `log.date`, which `%ad` and the like honor in git log.
+        .args(text_format.and_then(|_| date::git_option()))
+        .args(revisions)
+        .logged()
+        .output()?;
+    git_status("rev-list", output.status)?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    let commits = records(&output)?
+        .into_iter()
+        .map(|record| {
+            let mut fields = record.splitn(7, '\0');
+            let mut field = || fields.next().ok_or_else(|| malformed("rev-list"));
+            let date = |seconds: &str| seconds.parse().ok().ok_or_else(|| malformed("rev-list"));
+            Ok(Commit {
+                id: field()?.to_owned(),
+                parents: field()?
+                    .split_whitespace()
+                    .take(if first_parent { 1 } else { usize::MAX })
+                    .map(String::from)
+                    .collect(),
+                author: field()?.to_owned(),
+                time: date(field()?)?,
+                committer_time: date(field()?)?,
+                subject: field()?.to_owned(),
+                text: field()?.to_owned(),
+                collapsed: 0,
+            })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    abbrev::disambiguate(commits.iter().map(|commit| commit.id.as_str()));
+    Ok(commits)
+}
+
+/// Splits the output of `load`'s rev-list into the commits' records, without
+/// their headers and line endings (which are "\r\n" on some Windows builds of
+/// git).
+fn records(output: &str) -> io::Result<Vec<&str>> {
+    let mut records = vec![];
+    if output.is_empty() {
+        return Ok(records);
+    }
+    let mut rest = after_header(output).ok_or_else(|| malformed("rev-list"))?;
+    loop {
+        // The first newline followed by a header ends the record. A text that
+        // contains a header line is told apart as it is not followed by the
+        // header's ID.
+        let next = rest.match_indices("\ncommit ").find_map(|(newline, _)| {
+            let (record, next) = rest.split_at_checked(newline)?;
+            Some((record, after_header(next.strip_prefix('\n')?)?))
+        });
+        let Some((record, next)) = next else {
+            let record = rest.strip_suffix('\n').unwrap_or(rest);
+            records.push(record.strip_suffix('\r').unwrap_or(record));
+            return Ok(records);
+        };
+        records.push(record.strip_suffix('\r').unwrap_or(record));
+        rest = next;
+    }
+}
+
+/// If `output` starts with the header rev-list writes before a commit's record
+/// ("commit <id>" and a line ending), and the record starts with the same ID
+/// and a NUL, returns the rest of `output`, from the record on.
+fn after_header(output: &str) -> Option<&str> {
+    let (id, rest) = output.strip_prefix("commit ")?.split_once('\n')?;
+    let id = id.strip_suffix('\r').unwrap_or(id);
+    let is_id = !id.is_empty() && id.bytes().all(|byte| byte.is_ascii_hexdigit());
+    (is_id && rest.strip_prefix(id)?.starts_with('\0')).then_some(rest)
+}
+
+/// Which date orders the commits (`--priority-date`).
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DatePriority {
+    Author,
+    Committer,
+    /// The later of the two, so that rebased commits, which keep their author
+    /// date, and cherry-picks of old commits both count as recent.
+    Max,
+}
+
+impl DatePriority {
+    /// Parses a `--priority-date` value.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "author" => Some(Self::Author),
+            "committer" => Some(Self::Committer),
+            "max" => Some(Self::Max),
+            _ => None,
+        }
+    }
+
+    /// Returns the `git log` option that orders commits the same way, if any.
+    pub const fn git_option(self) -> Option<&'static str> {
+        match self {
+            Self::Author => Some("--author-date-order"),
+            Self::Committer => Some("--date-order"),
+            Self::Max => None,
+        }
+    }
+
+    /// Returns `commit`'s date.
+    fn date(self, commit: &Commit) -> i64 {
+        match self {
+            Self::Author => commit.time,
+            Self::Committer => commit.committer_time,
+            Self::Max => commit.time.max(commit.committer_time),
+        }
+    }
+}
+
+/// Which commits `--no-merges` and `--merges-only` hide.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MergeFilter {
+    /// `--no-merges`: hide the merges.
+    NoMerges,
+    /// `--merges-only`: hide the commits that are not merges.
+    MergesOnly,
+}
+
+/// Hides the commits that `filter` selects, except those in `keep` (the tips
+/// and merge bases), as `hide` does. `commits` must be in topological order,
+/// and so is the result.
+pub fn filter_merges(
+    commits: Vec<Commit>,
+    keep: &HashSet<&str>,
+    filter: MergeFilter,
+) -> Vec<Commit> {
+    let hidden: HashSet<String> = commits
+        .iter()
+        .filter(|commit| {
+            let merge = commit.parents.len() > 1;
+            merge == (filter == MergeFilter::NoMerges) && !keep.contains(commit.id.as_str())
+        })
+        .map(|commit| commit.id.clone())
+        .collect();
+    hide(commits, &hidden)
+}
+
+/// Hides the `hidden` commits. Each remaining commit's hidden parents are
+/// replaced with their own parents, recursively, so that the edges drawn
+/// still join each commit to its nearest visible ancestors. `commits` must be
+/// in topological order, and so is the result.
+pub fn hide(commits: Vec<Commit>, hidden: &HashSet<String>) -> Vec<Commit> {
+    // The visible ancestors standing in for each hidden commit, found parents
+    // first.
+    let mut replacements: HashMap<String, Vec<String>> = HashMap::new();
+    for commit in commits.iter().rev().filter(|commit| hidden.contains(&commit.id)) {
+        let parents = replace_parents(&commit.parents, &replacements);
+        replacements.insert(commit.id.clone(), parents);
+    }
+    commits
+        .into_iter()
+        .filter(|commit| !hidden.contains(&commit.id))
+        .map(|mut commit| {
+            commit.parents = replace_parents(&commit.parents, &replacements);
+            commit
+        })
+        .collect()
+}
+
+/// Returns `parents` with each one in `replacements` replaced by its
+/// replacements, without duplicates.
+fn replace_parents(parents: &[String], replacements: &HashMap<String, Vec<String>>) -> Vec<String> {
+    let mut replaced: Vec<String> = vec![];
+    for parent in parents {
+        for id in replacements.get(parent).map_or_else(|| slice::from_ref(parent), Vec::as_slice) {
+            if !replaced.contains(id) {
+                replaced.push(id.clone());
+            }
+        }
+    }
+    replaced
+}
+
+/// Reorders `commits` (in topological order) so that each next commit is, of
+/// those whose displayed children have all been listed, the one with the
+/// latest `priority` date. Ties keep the topological order.
+#[allow(clippy::unwrap_used, reason = "the indices are of commits")]
+pub fn sort_by_date(commits: Vec<Commit>, priority: DatePriority) -> Vec<Commit> {
+    let index: HashMap<&str, usize> =
+        commits.iter().enumerate().map(|(i, commit)| (commit.id.as_str(), i)).collect();
+    // The displayed parents of each commit, and how many displayed children
+    // each commit has that are not listed yet.
+    let parents: Vec<Vec<usize>> = commits
+        .iter()
+        .map(|commit| {
+            commit.parents.iter().filter_map(|parent| index.get(parent.as_str()).copied()).collect()
+        })
+        .collect();
+    let mut children: Vec<usize> = vec![0; commits.len()];
+    for &parent in parents.iter().flatten() {
+        let count = children.get_mut(parent).unwrap();
+        *count = count.saturating_add(1);
+    }
+    let key = |i: usize| (priority.date(commits.get(i).unwrap()), Reverse(i));
+    let mut ready: BinaryHeap<_> =
+        (0..commits.len()).filter(|&i| *children.get(i).unwrap() == 0).map(key).collect();
+    let mut order = Vec::with_capacity(commits.len());
+    while let Some((_, Reverse(i))) = ready.pop() {
+        order.push(i);
+        for &parent in parents.get(i).unwrap() {
+            let count = children.get_mut(parent).unwrap();
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                ready.push(key(parent));
+            }
+        }
+    }
+    let mut slots: Vec<_> = commits.into_iter().map(Some).collect();
+    order.into_iter().filter_map(|i| slots.get_mut(i).and_then(Option::take)).collect()
+}
+
+/// Replaces each run of more than `max_run` linear commits with a placeholder.
+/// A commit is linear if it has at most one parent and exactly one displayed
+/// child, and is not in `keep` (the tips and merge bases), so merges, tips,
+/// and bases always remain visible. `commits` must be in topological order,
+/// and so is the result.
+#[allow(clippy::unwrap_used, reason = "the indices are of commits, and runs are not empty")]
+pub fn collapse(mut commits: Vec<Commit>, keep: &HashSet<&str>, max_run: usize) -> Vec<Commit> {
+    let index: HashMap<&str, usize> =
+        commits.iter().enumerate().map(|(i, commit)| (commit.id.as_str(), i)).collect();
+    let mut children = vec![vec![]; commits.len()];
+    for (i, commit) in commits.iter().enumerate() {
+        for parent in &commit.parents {
+            if let Some(&parent) = index.get(parent.as_str()) {
+                children.get_mut(parent).unwrap().push(i);
+            }
+        }
+    }
+    let linear = |i: usize| {
+        let commit = commits.get(i).unwrap();
+        commit.parents.len() <= 1
+            && children.get(i).unwrap().len() == 1
+            && !keep.contains(commit.id.as_str())
+    };
+    // Each run as (start, length), found by following the first parents from
+    // each linear commit whose child is not linear.
+    let mut runs = vec![];
+    for start in (0..commits.len()).filter(|&i| linear(i)) {
+        if children.get(start).unwrap().iter().any(|&child| linear(child)) {
+            continue;
+        }
+        let mut members = vec![start];
+        let mut current = start;
+        while let Some(&parent) = commits
+            .get(current)
+            .unwrap()
+            .parents
+            .first()
+            .and_then(|parent| index.get(parent.as_str()))
+            .filter(|&&parent| linear(parent))
+        {
+            members.push(parent);
+            current = parent;
+        }
+        if members.len() > max_run {
+            runs.push(members);
+        }
+    }
+    let mut removed = vec![false; commits.len()];
+    for members in runs {
+        let (&first, &last) = (members.first().unwrap(), members.last().unwrap());
+        let child = *children.get(first).unwrap().first().unwrap();
+        let placeholder = Commit {
+            id: format!("collapsed-{}", commits.get(first).unwrap().id),
+            parents: commits.get(last).unwrap().parents.clone(),
+            author: String::new(),
+            time: commits.get(first).unwrap().time,
+            committer_time: commits.get(first).unwrap().committer_time,
+            subject: String::new(),
+            text: String::new(),
+            // Counting what earlier placeholders (see `skeleton`) stand for.
+            collapsed: members
+                .iter()
+                .map(|&member| commits.get(member).unwrap().collapsed.max(1))
+                .sum(),
+        };
+        for &member in &members {
+            *removed.get_mut(member).unwrap() = true;
+        }
+        // The placeholder takes the first commit's place in the order.
+        *removed.get_mut(first).unwrap() = false;
+        let placeholder_id = placeholder.id.clone();
+        let first_id = mem::replace(commits.get_mut(first).unwrap(), placeholder).id;
+        for parent in &mut commits.get_mut(child).unwrap().parents {
+            if *parent == first_id {
+                parent.clone_from(&placeholder_id);
+            }
+        }
+    }
+    commits
+        .into_iter()
+        .zip(removed)
+        .filter(|&(_, removed)| !removed)
+        .map(|(commit, _)| commit)
+        .collect()
+}
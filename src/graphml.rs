@@ -0,0 +1,65 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--format=graphml`: the displayed commits as a GraphML document, for graph
+//! analysis tools such as Gephi and NetworkX.
+//!
+//! Each commit is a node with its refs, author, and author date; each edge
+//! points from a commit to one of its displayed parents.
+
+use crate::graph::Commit;
+use crate::html::escape;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+/// The node attributes, as (id, type) pairs.
+const KEYS: [(&str, &str); 5] = [
+    ("refs", "string"),
+    ("author", "string"),
+    ("time", "long"),
+    ("subject", "string"),
+    ("collapsed", "int"),
+];
+
+/// Writes the document. `refs` maps commit IDs to the refs pointing to them.
+pub fn write<W: Write>(
+    commits: &[Commit],
+    refs: &HashMap<&str, Vec<&str>>,
+    mut out: W,
+) -> io::Result<()> {
+    let displayed: HashSet<&str> = commits.iter().map(|commit| commit.id.as_str()).collect();
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(out, "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">")?;
+    for (id, kind) in KEYS {
+        writeln!(out, "  <key id=\"{id}\" for=\"node\" attr.name=\"{id}\" attr.type=\"{kind}\"/>")?;
+    }
+    writeln!(out, "  <graph id=\"G\" edgedefault=\"directed\">")?;
+    for commit in commits {
+        let names = refs.get(commit.id.as_str()).map(Vec::as_slice).unwrap_or_default();
+        writeln!(out, "    <node id=\"{}\">", commit.id)?;
+        writeln!(out, "      <data key=\"refs\">{}</data>", escape(&names.join(",")))?;
+        writeln!(out, "      <data key=\"author\">{}</data>", escape(&commit.author))?;
+        writeln!(out, "      <data key=\"time\">{}</data>", commit.time)?;
+        writeln!(out, "      <data key=\"subject\">{}</data>", escape(&commit.subject))?;
+        writeln!(out, "      <data key=\"collapsed\">{}</data>", commit.collapsed)?;
+        writeln!(out, "    </node>")?;
+    }
+    for commit in commits {
+        for parent in commit.parents.iter().filter(|parent| displayed.contains(parent.as_str())) {
+            writeln!(out, "    <edge source=\"{}\" target=\"{parent}\"/>", commit.id)?;
+        }
+    }
+    writeln!(out, "  </graph>\n</graphml>")?;
+    out.flush()
+}
@@ -0,0 +1,187 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `git-tree help [<topic>]` and `git-tree --man`: the manual, built into the
+//! binary so that it is there offline.
+//!
+//! The overview and the commands' usage come from `COMMANDS`; the long-form
+//! topics are the text files in `help/`. A topic is made of blocks separated
+//! by blank lines: a block whose second line is indented by four spaces
+//! defines the term on its first line, and any other block is a paragraph.
+//! `help` prints them as they are, and `--man` converts them to roff.
+
+use std::io::{self, Write};
+
+/// The subcommands: their names, their usage after `git-tree`, and what they
+/// do.
+const COMMANDS: [(&str, &str, &str); 16] = [
+    ("help", "help [--no-pager] [<topic>]", "Show the overview, a command's usage, or a topic."),
+    ("status", "status [--date=<format>]", "Print a line per local branch, like git branch -vv."),
+    ("matrix", "matrix [--list]", "Print how far the branches are ahead of and behind each other."),
+    (
+        "authors",
+        "authors [--no-mailmap]",
+        "Print, per author, the branches with commits of theirs.",
+    ),
+    ("files", "files [--limit <n>]", "Print the paths the displayed commits touch most often."),
+    ("conflicts", "conflicts [--with <branch>]", "Print which local branches would conflict."),
+    (
+        "check",
+        "check [--trunk <trunk>] [--max-ahead <n>] [--max-behind <n>] [--branches <pattern>]",
+        "Fail if a branch has diverged too far from the trunk.",
+    ),
+    (
+        "restack",
+        "restack [--onto <trunk>] [--all] [--dry-run | --exec]",
+        "Rebase a stack of branches onto the trunk's new tip.",
+    ),
+    (
+        "report",
+        "report [--format=markdown] [-o <file>] [--link-template=<url>] [--no-pager]",
+        "Write a Markdown report on the local branches.",
+    ),
+    (
+        "format-patch",
+        "format-patch [--trunk <trunk>] [<branch>] [<options>...]",
+        "Run git format-patch on a branch's commits since it forked.",
+    ),
+    ("bundle", "bundle -o <file>", "Write a git bundle of the displayed commits."),
+    (
+        "pin",
+        "pin [--always | --never] [<branch>...]",
+        "Make branches always or never interesting, or list the pins.",
+    ),
+    ("unpin", "unpin <branch>...", "Remove the pins of branches."),
+    ("prompt", "prompt [--budget <ms>]", "Print a one-line summary for a shell prompt."),
+    ("serve", "serve [--socket <path>]", "Run the JSON-RPC server for editor integrations."),
+    ("bench", "bench [--iterations <n>]", "Time the stages of computing the graph."),
+];
+
+/// The long-form topics: their names, their sections' titles in the man page,
+/// what they are about, and their text.
+const TOPICS: [(&str, &str, &str, &str); 4] = [
+    (
+        "heuristics",
+        "DESCRIPTION",
+        "which commits git-tree shows, and why",
+        include_str!("../help/heuristics.txt"),
+    ),
+    ("options", "OPTIONS", "git-tree's own options", include_str!("../help/options.txt")),
+    (
+        "formats",
+        "OUTPUT FORMATS",
+        "the renderers and export formats",
+        include_str!("../help/formats.txt"),
+    ),
+    (
+        "config",
+        "CONFIGURATION",
+        "the config keys and environment variables",
+        include_str!("../help/config.txt"),
+    ),
+];
+
+/// What git-tree is, for the overview and the man page's name.
+const DESCRIPTION: &str = "show the commits that matter to the branches being worked on";
+
+/// The log mode's usage after `git-tree`.
+const USAGE: &str = "[<options>] [<git log options>] [<revision>...] [-- <paths>]";
+
+/// Returns whether `topic` is a topic or a command that `write` can show.
+pub fn exists(topic: &str) -> bool {
+    TOPICS.iter().any(|&(name, ..)| name == topic)
+        || COMMANDS.iter().any(|&(name, ..)| name == topic)
+}
+
+/// Writes the help on `topic`, a topic or a command, or the overview.
+pub fn write<W: Write>(mut out: W, topic: Option<&str>) -> io::Result<()> {
+    if let Some(&(_, _, _, text)) = TOPICS.iter().find(|&&(name, ..)| Some(name) == topic) {
+        out.write_all(text.as_bytes())?;
+        return out.flush();
+    }
+    if let Some(&(_, usage, summary)) = COMMANDS.iter().find(|&&(name, ..)| Some(name) == topic) {
+        writeln!(out, "usage: git-tree {usage}\n\n{summary}")?;
+        return out.flush();
+    }
+    writeln!(out, "git-tree: {DESCRIPTION}\n")?;
+    writeln!(out, "usage: git-tree {USAGE}\n   or: git-tree <command> [<options>]")?;
+    writeln!(out, "\nCommands:")?;
+    let width = COMMANDS.iter().map(|&(name, ..)| name.len()).max().unwrap_or_default();
+    for &(name, _, summary) in &COMMANDS {
+        writeln!(out, "  {name:width$}  {summary}")?;
+    }
+    writeln!(out, "\nTopics:")?;
+    for &(name, _, about, _) in &TOPICS {
+        writeln!(out, "  {name:width$}  {about}")?;
+    }
+    writeln!(
+        out,
+        "\n`git-tree help <command>` shows a command's usage, `git-tree help <topic>` a\n\
+         topic, and `git-tree --man` the whole manual as a man page."
+    )?;
+    out.flush()
+}
+
+/// Writes the whole manual as a man page, in roff.
+pub fn write_man<W: Write>(mut out: W) -> io::Result<()> {
+    let version = env!("CARGO_PKG_VERSION");
+    writeln!(out, ".TH GIT\\-TREE 1 \"\" \"git\\-tree {version}\" \"git\\-tree manual\"")?;
+    writeln!(out, ".SH NAME\ngit\\-tree \\- {}", roff(DESCRIPTION))?;
+    writeln!(out, ".SH SYNOPSIS\n.nf")?;
+    writeln!(out, "\\fBgit\\-tree\\fR {}", roff(USAGE))?;
+    writeln!(out, "\\fBgit\\-tree\\fR <command> [<options>]")?;
+    writeln!(out, "\\fBgit\\-tree \\-\\-man\\fR\n.fi")?;
+    // The description and the options come before the commands.
+    let (first, rest) = TOPICS.split_at(2);
+    for &(_, title, _, text) in first {
+        writeln!(out, ".SH {title}")?;
+        write_blocks(&mut out, text)?;
+    }
+    writeln!(out, ".SH COMMANDS")?;
+    for &(_, usage, summary) in &COMMANDS {
+        writeln!(out, ".TP\n\\fBgit\\-tree {}\\fR\n{}", roff(usage), roff(summary))?;
+    }
+    for &(_, title, _, text) in rest {
+        writeln!(out, ".SH {title}")?;
+        write_blocks(&mut out, text)?;
+    }
+    out.flush()
+}
+
+/// Writes the blocks of a topic's `text` in roff.
+fn write_blocks<W: Write>(out: &mut W, text: &str) -> io::Result<()> {
+    for block in text.split("\n\n") {
+        let mut lines = block.lines();
+        let first = lines.next().unwrap_or_default();
+        if block.lines().nth(1).is_some_and(|line| line.starts_with("    ")) {
+            writeln!(out, ".TP\n\\fB{}\\fR", roff(first))?;
+        } else {
+            writeln!(out, ".PP\n{}", roff(first))?;
+        }
+        for line in lines {
+            writeln!(out, "{}", roff(line.trim_start()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns `text` escaped for roff.
+fn roff(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+    if escaped.starts_with(['.', '\'']) {
+        format!("\\&{escaped}")
+    } else {
+        escaped
+    }
+}
@@ -0,0 +1,212 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--format=html`: a self-contained HTML report with the graph drawn as SVG.
+//!
+//! Commits are placed by `layout`, one per row. Hovering over a commit shows
+//! its author, date, and message, and if a link template is given each commit
+//! links to the URL it produces. The issues mentioned in the subjects link to
+//! the issue tracker (see `issues`).
+
+use crate::abbrev;
+use crate::graph::{self, Commit};
+use crate::issues::Issues;
+use crate::layout::{self, COLORS};
+use crate::link;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Horizontal distance between lanes, in pixels.
+const LANE_WIDTH: usize = 16;
+/// Height of a row, in pixels.
+const ROW_HEIGHT: usize = 24;
+/// Half a row: how far below and above the commits the edges curve.
+#[allow(
+    clippy::integer_division,
+    clippy::integer_division_remainder_used,
+    reason = "ROW_HEIGHT is even"
+)]
+const HALF_ROW: usize = ROW_HEIGHT / 2;
+/// Margin around the graph, in pixels.
+const MARGIN: usize = 12;
+/// Width reserved for the commit labels, in pixels.
+const LABEL_WIDTH: usize = 900;
+
+/// Returns the git pretty format for the commit tooltips, with the author
+/// mapped through `.mailmap` unless `--no-mailmap` was given.
+pub fn tooltip_format() -> &'static str {
+    if graph::mailmap() {
+        "%aN <%aE>%n%ad%n%n%B"
+    } else {
+        "%an <%ae>%n%ad%n%n%B"
+    }
+}
+
+/// Lane colors, indexed by `layout` color.
+const LANE_COLORS: [&str; COLORS] =
+    ["#d62728", "#2ca02c", "#ff7f0e", "#1f77b4", "#9467bd", "#17becf"];
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 0; }
+svg { display: block; }
+text { font-family: monospace; font-size: 13px; dominant-baseline: middle; }
+path { fill: none; stroke-width: 2; }
+.commit:hover text { font-weight: bold; }
+.id { fill: #555; }
+.branch { fill: #1f77b4; font-weight: bold; }
+.head { fill: #2ca02c; font-weight: bold; }
+.collapsed { fill: #888; font-style: italic; }
+.issue { fill: #1f77b4; text-decoration: underline; }
+a { cursor: pointer; }";
+
+/// Returns `text` with the characters that are special in HTML (and XML)
+/// escaped.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Returns the x coordinate of the center of `lane`.
+const fn x(lane: usize) -> usize {
+    MARGIN.saturating_add(lane.saturating_mul(LANE_WIDTH))
+}
+
+/// Returns the y coordinate of the center of `row`.
+const fn y(row: usize) -> usize {
+    MARGIN.saturating_add(row.saturating_mul(ROW_HEIGHT))
+}
+
+/// Writes the subject of commit `id`, with the issues it mentions linking to
+/// their tracker.
+fn write_subject<W: Write>(
+    out: &mut W,
+    subject: &str,
+    issues: &Issues,
+    id: &str,
+) -> io::Result<()> {
+    let mut rest = subject;
+    for issue in issues.of(id) {
+        let (Some(url), Some((before, after))) =
+            (issues.url(issue), rest.split_once(issue.as_str()))
+        else {
+            continue;
+        };
+        write!(out, "{}", escape(before))?;
+        write!(out, "<a href=\"{}\" target=\"_blank\">", escape(&url))?;
+        write!(out, "<tspan class=\"issue\">{}</tspan></a>", escape(issue))?;
+        rest = after;
+    }
+    write!(out, "{}", escape(rest))
+}
+
+/// Writes the report. `commits` must be in topological order (children
+/// first), with each commit's `text` formatted with `tooltip_format`.
+/// `branches` maps commit IDs to the branches pointing to them. See `link`
+/// for `link_template`, and `issues` for the issues linked in the subjects.
+pub fn write<W: Write>(
+    commits: &[Commit],
+    branches: &HashMap<&str, Vec<&str>>,
+    head: Option<&str>,
+    link_template: Option<&str>,
+    issues: Option<&Issues>,
+    mut out: W,
+) -> io::Result<()> {
+    let placements = layout::layout(commits);
+    let lanes = placements.iter().map(|placement| placement.after.len()).max().unwrap_or_default();
+    let text_x = x(lanes.max(1)).saturating_add(MARGIN);
+    let width = text_x.saturating_add(LABEL_WIDTH);
+    let height = y(commits.len()).saturating_add(MARGIN);
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html><head><meta charset=\"utf-8\"><title>git-tree</title>")?;
+    writeln!(out, "<style>\n{STYLE}\n</style></head><body>")?;
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\">"
+    )?;
+
+    // Edges first, so that the commits are drawn on top of them. Each edge
+    // curves into its lane below the commit, runs down the lane, and curves
+    // into the parent.
+    for (row, placement) in placements.iter().enumerate() {
+        for route in &placement.routes {
+            let color = placement.after.get(route.lane).copied().flatten().unwrap_or_default();
+            #[allow(clippy::unwrap_used, reason = "layout colors are less than COLORS")]
+            let color = LANE_COLORS.get(color).unwrap();
+            let (x1, y1) = (x(placement.column), y(row));
+            let lane_x = x(route.lane);
+            let parent_x =
+                x(placements.get(route.parent).map_or(route.lane, |parent| parent.column));
+            let y2 = y(route.parent);
+            let (top, bottom) = (y1.saturating_add(HALF_ROW), y2.saturating_sub(HALF_ROW));
+            writeln!(
+                out,
+                "<path stroke=\"{color}\" d=\"M{x1} {y1} Q{lane_x} {y1} {lane_x} {top} \
+                 L{lane_x} {bottom} Q{lane_x} {y2} {parent_x} {y2}\"/>"
+            )?;
+        }
+    }
+
+    for (row, (commit, placement)) in commits.iter().zip(&placements).enumerate() {
+        let color = placement.before.get(placement.column).copied().flatten().unwrap_or_default();
+        #[allow(clippy::unwrap_used, reason = "layout colors are less than COLORS")]
+        let color = LANE_COLORS.get(color).unwrap();
+        let (cx, cy) = (x(placement.column), y(row));
+        let url =
+            link_template.filter(|_| commit.collapsed == 0).map(|t| link::expand(t, &commit.id));
+        if let Some(url) = &url {
+            writeln!(out, "<a href=\"{}\" target=\"_blank\">", escape(url))?;
+        }
+        writeln!(out, "<g class=\"commit\">")?;
+        if commit.collapsed == 0 {
+            writeln!(out, "<title>{}\n{}</title>", commit.id, escape(commit.text.trim_end()))?;
+        }
+        writeln!(out, "<circle cx=\"{cx}\" cy=\"{cy}\" r=\"5\" fill=\"{color}\"/>")?;
+        write!(out, "<text x=\"{text_x}\" y=\"{cy}\">")?;
+        if commit.collapsed == 0 {
+            let id = abbrev::short(&commit.id);
+            write!(out, "<tspan class=\"id\">{id}</tspan> ")?;
+            if head == Some(commit.id.as_str()) {
+                write!(out, "<tspan class=\"head\">HEAD</tspan> ")?;
+            }
+            for branch in branches.get(commit.id.as_str()).into_iter().flatten() {
+                write!(out, "<tspan class=\"branch\">{}</tspan> ", escape(branch))?;
+            }
+            match issues {
+                Some(issues) => write_subject(&mut out, &commit.subject, issues, &commit.id)?,
+                None => write!(out, "{}", escape(&commit.subject))?,
+            }
+        } else {
+            write!(
+                out,
+                "<tspan class=\"collapsed\">\u{2026} {} commits \u{2026}</tspan>",
+                commit.collapsed
+            )?;
+        }
+        writeln!(out, "</text>\n</g>")?;
+        if url.is_some() {
+            writeln!(out, "</a>")?;
+        }
+    }
+    writeln!(out, "</svg>\n</body></html>")?;
+    out.flush()
+}
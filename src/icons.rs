@@ -0,0 +1,168 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--icons`: markers in front of the native graph's commits saying what kind
+//! of commit each is.
+//!
+//! The markers are Nerd Font glyphs, or ASCII characters with `--no-icons`,
+//! `--ascii`, or a locale that is not UTF-8 (whose terminal is unlikely to
+//! have the glyphs).
+
+use crate::graph::{self, Commit};
+use crate::log::Logged as _;
+use crate::View;
+use std::collections::HashSet;
+use std::env::var;
+use std::process::{Command, Stdio};
+
+/// How to draw the markers.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Icons {
+    /// Nerd Font glyphs (`--icons`).
+    Glyphs,
+    /// ASCII characters (`--no-icons`).
+    Ascii,
+}
+
+/// What a marker says about a commit.
+#[derive(Clone, Copy)]
+pub enum Kind {
+    Head,
+    /// The tip of an interesting branch.
+    Tip,
+    Tag,
+    /// A stash entry.
+    Stash,
+    Merge,
+    /// A commit whose parents a shallow clone does not have.
+    Shallow,
+}
+
+impl Kind {
+    /// Returns the marker's Nerd Font glyph.
+    const fn glyph(self) -> char {
+        match self {
+            Self::Head => '\u{f061}',
+            Self::Tip => '\u{e725}',
+            Self::Tag => '\u{f02b}',
+            Self::Stash => '\u{f187}',
+            Self::Merge => '\u{e727}',
+            Self::Shallow => '\u{f0c4}',
+        }
+    }
+
+    /// Returns the marker's ASCII character.
+    const fn ascii(self) -> char {
+        match self {
+            Self::Head => '@',
+            Self::Tip => '>',
+            Self::Tag => '#',
+            Self::Stash => '$',
+            Self::Merge => '&',
+            Self::Shallow => '~',
+        }
+    }
+}
+
+impl Icons {
+    /// Returns how to draw the markers that were requested as `requested`,
+    /// falling back to ASCII if `ascii` (`--ascii`) is set or the locale is
+    /// not UTF-8.
+    pub fn choose(requested: Self, ascii: bool) -> Self {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .find_map(|name| var(name).ok().filter(|value| !value.is_empty()))
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let utf8 = locale.contains("utf-8") || locale.contains("utf8");
+        if ascii || !utf8 {
+            Self::Ascii
+        } else {
+            requested
+        }
+    }
+
+    /// Returns the markers of `kinds`, followed by a space, or nothing if
+    /// there are none.
+    pub fn prefix(self, kinds: &[Kind]) -> String {
+        if kinds.is_empty() {
+            return String::new();
+        }
+        let mut prefix: String = kinds
+            .iter()
+            .map(|&kind| if self == Self::Glyphs { kind.glyph() } else { kind.ascii() })
+            .collect();
+        prefix.push(' ');
+        prefix
+    }
+}
+
+/// The markers of the commits of a view.
+pub struct Markers<'view> {
+    view: &'view View,
+    icons: Icons,
+    /// The commits that tags point to.
+    tags: HashSet<String>,
+    /// The commits that stash entries point to.
+    stashes: HashSet<String>,
+}
+
+impl<'view> Markers<'view> {
+    /// Finds the tags and stash entries to mark the commits of `view` with.
+    pub fn load(view: &'view View, icons: Icons) -> Self {
+        let output = git(&["for-each-ref", "--format=%(objectname) %(*objectname)", "refs/tags"]);
+        let tags = output
+            .lines()
+            .filter_map(|line| {
+                let (id, peeled) = line.split_once(' ')?;
+                Some(if peeled.is_empty() { id } else { peeled }.to_owned())
+            })
+            .collect();
+        // Fails if there is no stash.
+        let stashes = git(&["log", "--walk-reflogs", "--format=%H", "refs/stash", "--"]);
+        Self { view, icons, tags, stashes: stashes.lines().map(String::from).collect() }
+    }
+
+    /// Puts `commit`'s markers in front of its text.
+    pub fn mark(&self, commit: &mut Commit) {
+        if commit.collapsed != 0 {
+            return;
+        }
+        let id = &commit.id;
+        let kinds = [
+            (self.view.head.as_ref() == Some(id), Kind::Head),
+            (self.view.branches.iter().any(|branch| branch.id == *id), Kind::Tip),
+            (self.tags.contains(id), Kind::Tag),
+            (self.stashes.contains(id), Kind::Stash),
+            (commit.parents.len() > 1, Kind::Merge),
+            (self.view.shallow.contains(id), Kind::Shallow),
+        ];
+        let kinds: Vec<_> = kinds.into_iter().filter(|&(is, _)| is).map(|(_, kind)| kind).collect();
+        commit.text.insert_str(0, &self.icons.prefix(&kinds));
+    }
+}
+
+/// Runs git with `args` and returns its output, or nothing if it fails.
+fn git(args: &[&str]) -> String {
+    Command::new("git")
+        .args(args)
+        .env(graph::NO_LAZY_FETCH, "1")
+        .stderr(Stdio::null())
+        .logged()
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+        .unwrap_or_default()
+}
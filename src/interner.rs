@@ -0,0 +1,158 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Interning of hex commit IDs. The traversal sees hundreds of thousands of
+//! IDs in large repositories; storing them in fixed-size slots of a single
+//! arena avoids an allocation per commit, and lets the traversal refer to
+//! commits by small handles. Removed IDs' slots are reused, so the arena only
+//! grows as large as the number of IDs held at once.
+//!
+//! IDs are looked up by their first `PREFIX_LEN` hex digits, which are as good
+//! as random, falling back to the whole ID when two IDs share a prefix.
+
+use core::str;
+use std::collections::HashMap;
+
+/// The number of leading hex digits that key the lookup table.
+const PREFIX_LEN: usize = 16;
+
+/// A handle to an interned ID: the number of its slot in the arena.
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct GitId(u32);
+
+impl GitId {
+    #[allow(clippy::expect_used, reason = "there are never more slots than usize holds")]
+    /// Returns the handle's number, for use as an index.
+    pub fn index(self) -> usize {
+        usize::try_from(self.0).expect("GitId out of range")
+    }
+}
+
+/// Stores IDs of the same length in fixed-size slots.
+pub struct Interner {
+    arena: Vec<u8>,
+    /// The length of every ID, set by the first one interned.
+    id_len: usize,
+    /// The number of slots in the arena.
+    slots: u32,
+    /// The slots of removed IDs, to be reused.
+    free: Vec<GitId>,
+    /// Maps prefixes to IDs with that prefix.
+    by_prefix: HashMap<[u8; PREFIX_LEN], GitId>,
+    /// IDs whose prefix was taken by another ID when they were interned.
+    overflow: HashMap<Box<[u8]>, GitId>,
+}
+
+#[allow(
+    clippy::unwrap_used,
+    clippy::expect_used,
+    reason = "handles index their slots, and the IDs are checked with `accepts`"
+)]
+impl Interner {
+    pub fn new() -> Self {
+        Self {
+            arena: vec![],
+            id_len: 0,
+            slots: 0,
+            free: vec![],
+            by_prefix: HashMap::new(),
+            overflow: HashMap::new(),
+        }
+    }
+
+    /// Reserves room for `additional` more IDs of the same length as those
+    /// already interned.
+    pub fn reserve(&mut self, additional: usize) {
+        self.arena.reserve(additional.saturating_mul(self.id_len));
+        self.by_prefix.reserve(additional);
+    }
+
+    /// Returns whether `id` can be interned: it is ASCII, long enough to have a
+    /// lookup key and, unless it is the first, as long as the IDs already
+    /// interned.
+    pub const fn accepts(&self, id: &[u8]) -> bool {
+        id.len() >= PREFIX_LEN && (self.slots == 0 || id.len() == self.id_len) && id.is_ascii()
+    }
+
+    /// Returns the ID's lookup key.
+    fn prefix(id: &[u8]) -> [u8; PREFIX_LEN] {
+        id.get(..PREFIX_LEN).and_then(|prefix| prefix.try_into().ok()).expect("commit ID too short")
+    }
+
+    /// Returns the handle of `id`, if it is interned.
+    pub fn get(&self, id: &[u8]) -> Option<GitId> {
+        match self.by_prefix.get(&Self::prefix(id)) {
+            Some(&handle) if self.bytes(handle) == id => Some(handle),
+            _ if self.overflow.is_empty() => None,
+            _ => self.overflow.get(id).copied(),
+        }
+    }
+
+    /// Interns `id`, returning its handle. All IDs must have the same length.
+    pub fn intern(&mut self, id: &[u8]) -> GitId {
+        if let Some(handle) = self.get(id) {
+            return handle;
+        }
+        if self.slots == 0 {
+            self.id_len = id.len();
+        }
+        assert!(id.len() == self.id_len, "commit IDs of different lengths");
+        let handle = if let Some(handle) = self.free.pop() {
+            let start = handle.index().checked_mul(self.id_len).unwrap();
+            let slot = self.arena.get_mut(start..start.checked_add(self.id_len).unwrap());
+            slot.unwrap().copy_from_slice(id);
+            handle
+        } else {
+            self.arena.extend_from_slice(id);
+            let handle = GitId(self.slots);
+            self.slots = self.slots.checked_add(1).expect("too many commits");
+            handle
+        };
+        let first = *self.by_prefix.entry(Self::prefix(id)).or_insert(handle);
+        if first != handle {
+            self.overflow.insert(id.into(), handle);
+        }
+        handle
+    }
+
+    /// Removes the ID, freeing its handle for reuse.
+    pub fn remove(&mut self, handle: GitId) {
+        let start = handle.index().checked_mul(self.id_len).unwrap();
+        let id = self.arena.get(start..start.checked_add(self.id_len).unwrap()).unwrap();
+        let prefix = Self::prefix(id);
+        if self.by_prefix.get(&prefix) == Some(&handle) {
+            self.by_prefix.remove(&prefix);
+        } else {
+            self.overflow.remove(id);
+        }
+        self.free.push(handle);
+    }
+
+    /// Returns the interned ID.
+    pub fn bytes(&self, handle: GitId) -> &[u8] {
+        let start = handle.index().checked_mul(self.id_len).unwrap();
+        self.arena.get(start..start.checked_add(self.id_len).unwrap()).unwrap()
+    }
+
+    /// Returns the interned ID as a string.
+    pub fn string(&self, handle: GitId) -> String {
+        str::from_utf8(self.bytes(handle)).expect("non-utf-8 id").to_owned()
+    }
+
+    /// Returns every handle issued so far, in slot order, including those of
+    /// removed IDs.
+    pub fn handles(&self) -> impl Iterator<Item = GitId> {
+        (0..self.slots).map(GitId)
+    }
+}
@@ -0,0 +1,194 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Issue tracker references in commit subjects, e.g. `b/12345` or `#678`.
+//!
+//! The `gitxl.issuePattern` config setting is an extended regular expression
+//! matching an issue ID, and `gitxl.issueUrl` the link to an issue, in which
+//! `{id}` is replaced by the issue ID and `{number}` by the digits it ends
+//! with, e.g. `https://github.com/org/repo/issues/{number}`. The issues are
+//! hyperlinks in the native graph and the HTML report, and
+//! `--group-by-issue` lists the interesting branches touching each issue.
+//!
+//! The pattern is matched by `git grep -o`, the only git command that prints
+//! what a pattern matched, so the subjects are written to a file in
+//! `.git/gitxl/` for it to search.
+
+use crate::graph::Commit;
+use crate::lock::Lock;
+use crate::log::Logged as _;
+use crate::matrix::{Reach, Tips};
+use crate::{cache, config};
+use crate::{link, preview, View};
+use core::time::Duration;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::process::Command;
+
+/// The exit status of `git grep` when nothing matches.
+const NO_MATCH: i32 = 1;
+
+/// How long to wait for another git-tree searching its subjects.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The issues mentioned by the displayed commits.
+pub struct Issues {
+    /// The issue IDs in the subject of each commit mentioning any, in order,
+    /// by commit ID.
+    by_commit: HashMap<String, Vec<String>>,
+    /// The link template (`gitxl.issueUrl`), if set.
+    url: Option<String>,
+}
+
+impl Issues {
+    /// Finds the issues mentioned in the subjects of `commits`. Returns `None`
+    /// if `gitxl.issuePattern` is not set.
+    pub fn load(commits: &[Commit]) -> io::Result<Option<Self>> {
+        let Some(pattern) = config::get("gitxl.issuePattern") else { return Ok(None) };
+        let real: Vec<_> = commits
+            .iter()
+            .filter(|commit| commit.collapsed == 0 && commit.id != preview::ID)
+            .collect();
+        let subjects: Vec<_> = real.iter().map(|commit| commit.subject.as_str()).collect();
+        let mut by_commit: HashMap<String, Vec<String>> = HashMap::new();
+        for (line, issue) in grep(&pattern, &subjects)? {
+            let Some(commit) = line.checked_sub(1).and_then(|index| real.get(index)) else {
+                continue;
+            };
+            let issues = by_commit.entry(commit.id.clone()).or_default();
+            if !issues.contains(&issue) {
+                issues.push(issue);
+            }
+        }
+        Ok(Some(Self { by_commit, url: config::get("gitxl.issueUrl") }))
+    }
+
+    /// Returns the issues mentioned by commit `id`.
+    pub fn of(&self, id: &str) -> &[String] {
+        self.by_commit.get(id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the link to `issue`, if there is a link template.
+    #[allow(clippy::literal_string_with_formatting_args, reason = "they are placeholders")]
+    pub fn url(&self, issue: &str) -> Option<String> {
+        let number = issue.rsplit(|c: char| !c.is_ascii_digit()).next().unwrap_or_default();
+        Some(self.url.as_ref()?.replace("{id}", issue).replace("{number}", number))
+    }
+
+    /// Makes the issues mentioned by `commit` terminal hyperlinks in its text.
+    pub fn hyperlink(&self, commit: &mut Commit) {
+        for issue in self.of(&commit.id) {
+            if let Some(url) = self.url(issue) {
+                link::hyperlink(&mut commit.text, issue, &url);
+            }
+        }
+    }
+}
+
+/// Returns the matches of the extended regular expression `pattern` in
+/// `lines`, with the (1-based) number of the line each is in.
+fn grep(pattern: &str, lines: &[&str]) -> io::Result<Vec<(usize, String)>> {
+    // The file is the repository's own, rather than one in a shared temporary
+    // directory, and the lock keeps concurrent runs from writing it at once.
+    let path = cache::git_path("gitxl/subjects")
+        .ok_or_else(|| io::Error::other(tr!("not in a git repository")))?;
+    let lock = Lock::acquire(&path, LOCK_TIMEOUT)?;
+    let mut contents = lines.join("\n");
+    contents.push('\n');
+    fs::write(&path, contents)?;
+    // --no-index searches the file even though it is in no worktree. git
+    // grep skips the git directory when searching from outside it.
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Err(io::Error::other(tr!("not in a git repository")));
+    };
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(["grep", "--no-index", "--no-color", "--no-column", "-h", "-n", "-o", "-E"])
+        .args(["-e", pattern, "--"])
+        .arg(name)
+        .logged()
+        .output();
+    drop(fs::remove_file(&path));
+    drop(lock);
+    let output = output?;
+    if !output.status.success() && output.status.code() != Some(NO_MATCH) {
+        return Err(io::Error::other(tr!(
+            "invalid gitxl.issuePattern: {$error}",
+            error = String::from_utf8_lossy(&output.stderr).trim_end()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|record| {
+            let (line, issue) = record.split_once(':')?;
+            Some((line.parse().ok()?, issue.to_owned()))
+        })
+        .collect())
+}
+
+/// Writes, for each issue mentioned by `commits`, the interesting branches of
+/// `view` touching it, e.g. `b/12345: feature-x, fix-y`, in the order the
+/// graph first mentions them. A branch touches the issues of the displayed
+/// commits it reaches above the merge bases. The issues are hyperlinks if
+/// `color` is true.
+pub fn write_groups<W: Write>(
+    view: &View,
+    commits: &[Commit],
+    issues: &Issues,
+    color: bool,
+    mut out: W,
+) -> io::Result<()> {
+    let by_id = view.branches_by_id();
+    let mut tips: Vec<_> = by_id.keys().copied().collect();
+    tips.sort_unstable();
+    let mut order: Vec<&str> = vec![];
+    let mut touching: HashMap<&str, Tips> = HashMap::new();
+    let mut walk = Reach::new(&tips, &view.merge_bases);
+    for commit in commits {
+        let Some(reach) = walk.visit(&commit.id, commit.parents.iter().map(String::as_str)) else {
+            continue;
+        };
+        for issue in issues.of(&commit.id) {
+            match touching.entry(issue) {
+                Entry::Occupied(mut entry) => entry.get_mut().union(&reach),
+                Entry::Vacant(entry) => {
+                    order.push(issue);
+                    entry.insert(reach.clone());
+                }
+            }
+        }
+    }
+    for issue in order {
+        let mut names: Vec<_> = touching
+            .get(issue)
+            .into_iter()
+            .flat_map(Tips::indices)
+            .filter_map(|tip| by_id.get(tips.get(tip)?))
+            .flatten()
+            .copied()
+            .collect();
+        if names.is_empty() {
+            continue;
+        }
+        names.sort_unstable();
+        let mut label = issue.to_owned();
+        if let Some(url) = issues.url(issue).filter(|_| color) {
+            link::hyperlink(&mut label, issue, &url);
+        }
+        writeln!(out, "{label}: {}", names.join(", "))?;
+    }
+    out.flush()
+}
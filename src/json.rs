@@ -0,0 +1,349 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A minimal JSON value type with a parser and serializer.
+
+use core::fmt::Write as _;
+use core::str;
+
+/// A JSON value. Numbers are kept in their textual form, as we only ever echo
+/// them back (e.g. JSON-RPC request IDs) or produce them from integers.
+#[derive(Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Self>),
+    /// An object, as (key, value) pairs in order.
+    Object(Vec<(String, Self)>),
+}
+
+impl Value {
+    /// Creates a number value from an integer.
+    pub fn number(number: i64) -> Self {
+        Self::Number(number.to_string())
+    }
+
+    /// Creates an object value from (key, value) pairs.
+    pub fn object<const N: usize>(members: [(&str, Self); N]) -> Self {
+        Self::Object(members.into_iter().map(|(key, value)| (key.to_owned(), value)).collect())
+    }
+
+    /// Creates an array of strings.
+    pub fn strings<I: IntoIterator<Item = S>, S: Into<String>>(strings: I) -> Self {
+        Self::Array(strings.into_iter().map(|string| Self::String(string.into())).collect())
+    }
+
+    /// Returns the value of the member `key` if this is an object containing it.
+    pub fn get(&self, key: &str) -> Option<&Self> {
+        let Self::Object(members) = self else { return None };
+        members.iter().find(|member| member.0 == key).map(|member| &member.1)
+    }
+
+    /// Returns the contained string if this is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        if let Self::String(string) = self {
+            Some(string)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the contained number if this is a number that fits in a
+    /// `usize`.
+    pub fn as_usize(&self) -> Option<usize> {
+        if let Self::Number(number) = self {
+            number.parse().ok()
+        } else {
+            None
+        }
+    }
+
+    /// Parses a JSON document. Returns `None` if `text` is not valid JSON.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parser = Parser { text: text.as_bytes(), pos: 0, depth: 0 };
+        let value = parser.value()?;
+        parser.whitespace();
+        (parser.pos == parser.text.len()).then_some(value)
+    }
+
+    /// Appends the compact serialization of this value to `out`.
+    pub fn write(&self, out: &mut String) {
+        match self {
+            Self::Null => out.push_str("null"),
+            Self::Bool(true) => out.push_str("true"),
+            Self::Bool(false) => out.push_str("false"),
+            Self::Number(number) => out.push_str(number),
+            Self::String(string) => write_string(string, out),
+            Self::Array(values) => {
+                out.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    if i != 0 {
+                        out.push(',');
+                    }
+                    value.write(out);
+                }
+                out.push(']');
+            }
+            Self::Object(members) => {
+                out.push('{');
+                for (i, member) in members.iter().enumerate() {
+                    if i != 0 {
+                        out.push(',');
+                    }
+                    write_string(&member.0, out);
+                    out.push(':');
+                    member.1.write(out);
+                }
+                out.push('}');
+            }
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(string: &str) -> Self {
+        Self::String(string.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(string: String) -> Self {
+        Self::String(string)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(number: usize) -> Self {
+        Self::Number(number.to_string())
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl<T: Into<Self>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+/// Appends `string` to `out` as a JSON string literal.
+fn write_string(string: &str, out: &mut String) {
+    out.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            #[allow(clippy::unwrap_used, reason = "writing to a String cannot fail")]
+            '\0'..='\x1f' => write!(out, "\\u{:04x}", u32::from(c)).unwrap(),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// How deeply arrays and objects may nest, so that a hostile document cannot
+/// overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+/// Recursive-descent JSON parser.
+struct Parser<'text> {
+    text: &'text [u8],
+    pos: usize,
+    /// The number of arrays and objects being parsed.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    /// Returns the next byte without consuming it.
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.pos).copied()
+    }
+
+    /// Enters an array or object, failing if that nests too deeply.
+    fn enter(&mut self) -> Option<()> {
+        self.depth = self.depth.checked_add(1).filter(|&depth| depth <= MAX_DEPTH)?;
+        Some(())
+    }
+
+    /// Leaves an array or object, returning `value`.
+    const fn leave(&mut self, value: Value) -> Value {
+        self.depth = self.depth.saturating_sub(1);
+        value
+    }
+
+    /// Consumes and returns the next byte.
+    fn next(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
+        self.pos = self.pos.checked_add(1)?;
+        Some(byte)
+    }
+
+    /// Consumes `literal` if the input continues with it.
+    fn eat(&mut self, literal: &str) -> bool {
+        let matches =
+            self.text.get(self.pos..).is_some_and(|rest| rest.starts_with(literal.as_bytes()));
+        if matches {
+            self.pos = self.pos.saturating_add(literal.len());
+        }
+        matches
+    }
+
+    fn whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos = self.pos.saturating_add(1);
+        }
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        self.whitespace();
+        match self.peek()? {
+            b'n' => self.eat("null").then_some(Value::Null),
+            b't' => self.eat("true").then_some(Value::Bool(true)),
+            b'f' => self.eat("false").then_some(Value::Bool(false)),
+            b'"' => Some(Value::String(self.string()?)),
+            b'[' => self.array(),
+            b'{' => self.object(),
+            b'-' | b'0'..=b'9' => self.number(),
+            _ => None,
+        }
+    }
+
+    fn number(&mut self) -> Option<Value> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) {
+            self.pos = self.pos.saturating_add(1);
+        }
+        let number = str::from_utf8(self.text.get(start..self.pos)?).ok()?;
+        // Validate the number's syntax using the standard float parser, which
+        // accepts a superset of JSON numbers; reject the extras by hand.
+        let digits = number.strip_prefix('-').unwrap_or(number);
+        if number.parse::<f64>().is_err()
+            || digits.starts_with(['+', '.'])
+            || (digits.starts_with('0')
+                && digits.get(1..2).is_some_and(|c| c != "." && c != "e" && c != "E"))
+        {
+            return None;
+        }
+        Some(Value::Number(number.to_owned()))
+    }
+
+    fn string(&mut self) -> Option<String> {
+        if self.next()? != b'"' {
+            return None;
+        }
+        let mut string = Vec::new();
+        loop {
+            match self.next()? {
+                b'"' => return String::from_utf8(string).ok(),
+                b'\\' => {
+                    let c = match self.next()? {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\x08',
+                        b'f' => '\x0c',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return None,
+                    };
+                    string.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                0..=0x1f => return None,
+                byte => string.push(byte),
+            }
+        }
+    }
+
+    /// Parses the part of a `\u` escape after the `u`, including a following
+    /// low surrogate escape if this is a high surrogate.
+    fn unicode_escape(&mut self) -> Option<char> {
+        let high = self.hex4()?;
+        if !(0xd800..0xdc00).contains(&high) {
+            return char::from_u32(high);
+        }
+        if !self.eat("\\u") {
+            return None;
+        }
+        let low = self.hex4()?.checked_sub(0xdc00).filter(|&low| low < 0x400)?;
+        char::from_u32(
+            high.checked_sub(0xd800)?.checked_shl(10)?.checked_add(low)?.checked_add(0x1_0000)?,
+        )
+    }
+
+    /// Parses four hexadecimal digits.
+    fn hex4(&mut self) -> Option<u32> {
+        let digits = self.text.get(self.pos..self.pos.checked_add(4)?)?;
+        if !digits.iter().all(u8::is_ascii_hexdigit) {
+            return None;
+        }
+        let value = u32::from_str_radix(str::from_utf8(digits).ok()?, 16).ok()?;
+        self.pos = self.pos.checked_add(4)?;
+        Some(value)
+    }
+
+    fn array(&mut self) -> Option<Value> {
+        self.enter()?;
+        self.next()?;
+        let mut values = vec![];
+        self.whitespace();
+        if self.eat("]") {
+            return Some(self.leave(Value::Array(values)));
+        }
+        loop {
+            values.push(self.value()?);
+            self.whitespace();
+            match self.next()? {
+                b',' => {}
+                b']' => return Some(self.leave(Value::Array(values))),
+                _ => return None,
+            }
+        }
+    }
+
+    fn object(&mut self) -> Option<Value> {
+        self.enter()?;
+        self.next()?;
+        let mut members = vec![];
+        self.whitespace();
+        if self.eat("}") {
+            return Some(self.leave(Value::Object(members)));
+        }
+        loop {
+            self.whitespace();
+            let key = self.string()?;
+            self.whitespace();
+            if !self.eat(":") {
+                return None;
+            }
+            members.push((key, self.value()?));
+            self.whitespace();
+            match self.next()? {
+                b',' => {}
+                b'}' => return Some(self.leave(Value::Object(members))),
+                _ => return None,
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--format=json-edges`: the displayed commits as a JSON node/edge list, in
+//! the layout NetworkX's `node_link_graph` reads (with `edges="edges"`).
+
+use crate::age::Bucket;
+use crate::bisect::Bisect;
+use crate::ci::Status;
+use crate::diffstat::Diffstat;
+use crate::fork::Fork;
+use crate::gerrit::Changes;
+use crate::github::PullRequest;
+use crate::graph::Commit;
+use crate::json::Value;
+use crate::preview::Preview;
+use crate::rebase_todo::Plan;
+use crate::trailers::{self, Trailers};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+/// The optional annotations of the nodes.
+pub struct Annotations<'maps> {
+    /// If given (see `github::pull_requests`), each node lists its pull
+    /// requests.
+    pub pull_requests: Option<&'maps HashMap<String, Vec<PullRequest>>>,
+    /// If given, each node has its `changeId` and the graph lists the Gerrit
+    /// changes with their patchsets.
+    pub changes: Option<&'maps Changes>,
+    /// If given (see `ci::statuses`), each node has its `ciStatus`.
+    pub statuses: Option<&'maps HashMap<String, Status>>,
+    /// If given (see `cherry::equivalents`), each node lists the commits that
+    /// make the same change as it (`equivalentTo`).
+    pub equivalents: Option<&'maps HashMap<String, Vec<String>>>,
+    /// If given (see `fork::fork_points`), each node lists the branches that
+    /// forked at it (`forkPoints`).
+    pub forks: Option<&'maps HashMap<String, Vec<Fork>>>,
+    /// If given (the current time, see `date::now`), each node has its `age`
+    /// bucket (see `age::Bucket`).
+    pub now: Option<i64>,
+    /// If given (see `find::matches`), each node says whether it matches
+    /// `--find` (`found`).
+    pub found: Option<&'maps HashSet<String>>,
+    /// If given (see `Bisect::load`), each node has its `bisect` mark.
+    pub bisect: Option<&'maps Bisect>,
+    /// If given (see `Plan::load`), each node has its `rebase` step.
+    pub plan: Option<&'maps Plan>,
+    /// If given (see `preview::get`), its merge commit lists the files that
+    /// would conflict (`conflicts`).
+    pub preview: Option<&'maps Preview>,
+    /// If given (see `diffstat::diffstats`), the graph lists the size of each
+    /// local branch's work (`diffstats`).
+    pub diffstats: Option<&'maps [Diffstat]>,
+    /// If given (see `trailers::load`), each node lists its `trailers`.
+    pub trailers: Option<&'maps HashMap<String, Trailers>>,
+    /// If given (see `notes::load`), each node has its `notes`, or `null`.
+    pub notes: Option<&'maps HashMap<String, String>>,
+}
+
+/// Writes the document. `refs` maps commit IDs to the refs pointing to them.
+pub fn write<W: Write>(
+    commits: &[Commit],
+    refs: &HashMap<&str, Vec<&str>>,
+    annotations: &Annotations<'_>,
+    mut out: W,
+) -> io::Result<()> {
+    let displayed: HashSet<&str> = commits.iter().map(|commit| commit.id.as_str()).collect();
+    let nodes = commits.iter().map(|commit| node(commit, refs, annotations)).collect();
+    let edges = commits
+        .iter()
+        .flat_map(|commit| {
+            commit.parents.iter().filter(|parent| displayed.contains(parent.as_str())).map(
+                |parent| {
+                    Value::object([
+                        ("source", commit.id.as_str().into()),
+                        ("target", parent.as_str().into()),
+                    ])
+                },
+            )
+        })
+        .collect();
+    let mut graph = vec![];
+    if let Some(changes) = annotations.changes {
+        graph.push(("changes".into(), changes.json()));
+    }
+    if let Some(diffstats) = annotations.diffstats {
+        graph.push((
+            "diffstats".into(),
+            Value::Array(diffstats.iter().map(Diffstat::json).collect()),
+        ));
+    }
+    let mut document = String::new();
+    Value::object([
+        ("directed", true.into()),
+        ("multigraph", false.into()),
+        ("graph", Value::Object(graph)),
+        ("nodes", Value::Array(nodes)),
+        ("edges", Value::Array(edges)),
+    ])
+    .write(&mut document);
+    writeln!(out, "{document}")?;
+    out.flush()
+}
+
+/// Returns the node of `commit`, with the refs `refs` lists for it and its
+/// annotations.
+fn node(commit: &Commit, refs: &HashMap<&str, Vec<&str>>, annotations: &Annotations<'_>) -> Value {
+    let Annotations {
+        pull_requests,
+        changes,
+        statuses,
+        equivalents,
+        forks,
+        now,
+        found,
+        bisect,
+        plan,
+        preview,
+        trailers,
+        notes,
+        ..
+    } = *annotations;
+    let mut node = Value::object([
+        ("id", commit.id.as_str().into()),
+        ("refs", Value::strings(refs.get(commit.id.as_str()).into_iter().flatten().copied())),
+        ("author", commit.author.as_str().into()),
+        ("time", Value::number(commit.time)),
+        ("subject", commit.subject.as_str().into()),
+        ("collapsed", Value::number(commit.collapsed.try_into().unwrap_or(i64::MAX))),
+    ]);
+    if let (Some(pull_requests), Value::Object(members)) = (pull_requests, &mut node) {
+        let pulls = pull_requests.get(&commit.id).into_iter().flatten();
+        members.push(("pullRequests".into(), Value::Array(pulls.map(PullRequest::json).collect())));
+    }
+    if let (Some(changes), Value::Object(members)) = (changes, &mut node) {
+        members.push(("changeId".into(), changes.change_id(&commit.id).into()));
+    }
+    if let (Some(statuses), Value::Object(members)) = (statuses, &mut node) {
+        let status = statuses.get(&commit.id).map(|status| status.name());
+        members.push(("ciStatus".into(), status.into()));
+    }
+    if let (Some(equivalents), Value::Object(members)) = (equivalents, &mut node) {
+        let others = equivalents.get(&commit.id).into_iter().flatten();
+        members.push(("equivalentTo".into(), Value::strings(others.cloned())));
+    }
+    if let (Some(now), Value::Object(members)) = (now, &mut node) {
+        let bucket = Bucket::of(commit.committer_time, now);
+        members.push(("age".into(), bucket.name().into()));
+    }
+    if let (Some(found), Value::Object(members)) = (found, &mut node) {
+        members.push(("found".into(), found.contains(&commit.id).into()));
+    }
+    if let (Some(forks), Value::Object(members)) = (forks, &mut node) {
+        let forks = forks.get(&commit.id).into_iter().flatten();
+        members.push(("forkPoints".into(), Value::Array(forks.map(Fork::json).collect())));
+    }
+    if let (Some(bisect), Value::Object(members)) = (bisect, &mut node) {
+        members.push(("bisect".into(), bisect.json(&commit.id)));
+    }
+    if let (Some(plan), Value::Object(members)) = (plan, &mut node) {
+        members.push(("rebase".into(), plan.json(&commit.id)));
+    }
+    let conflicts = preview.and_then(|preview| preview.json(&commit.id));
+    if let (Some(conflicts), Value::Object(members)) = (conflicts, &mut node) {
+        members.push(("conflicts".into(), conflicts));
+    }
+    if let (Some(trailers), Value::Object(members)) = (trailers, &mut node) {
+        let listed = trailers.get(&commit.id).map_or(Value::Object(vec![]), trailers::json);
+        members.push(("trailers".into(), listed));
+    }
+    if let (Some(notes), Value::Object(members)) = (notes, &mut node) {
+        members.push(("notes".into(), notes.get(&commit.id).map(String::as_str).into()));
+    }
+    node
+}
@@ -0,0 +1,123 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The `--since-last` snapshot: the commits HEAD and the interesting branches
+//! pointed to on the previous `--since-last` run, stored in
+//! `.git/gitxl/last-view`:
+//!
+//! ```text
+//! gitxl last-view v1
+//! <id> HEAD
+//! <id> <branch>
+//! ```
+//!
+//! Like the cache, the snapshot is best-effort: a missing or malformed one is
+//! treated as no previous run, and failing to write it (or to lock it, see
+//! `lock`) is ignored.
+
+use crate::abbrev::short;
+use crate::lock::Lock;
+use crate::{cache, quote};
+use alloc::collections::BTreeMap;
+use core::fmt::{self, Display, Formatter};
+use core::time::Duration;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const HEADER: &str = "gitxl last-view v1";
+
+/// The commit each ref pointed to, by ref name (`HEAD` or a branch name).
+pub type Snapshot = BTreeMap<String, String>;
+
+/// How a ref changed between two snapshots.
+pub enum Change<'snapshot> {
+    New { name: &'snapshot str, id: &'snapshot str },
+    Moved { name: &'snapshot str, from: &'snapshot str, to: &'snapshot str },
+    Gone { name: &'snapshot str, id: &'snapshot str },
+}
+
+impl Display for Change<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::New { name, id } => {
+                f.write_str(&tr!("{$name}: new at {$id}", name = quote::name(name), id = short(id)))
+            }
+            Self::Moved { name, from, to } => f.write_str(&tr!(
+                "{$name}: moved from {$from} to {$to}",
+                name = quote::name(name),
+                from = short(from),
+                to = short(to),
+            )),
+            Self::Gone { name, id } => f.write_str(&tr!(
+                "{$name}: gone (was at {$id})",
+                name = quote::name(name),
+                id = short(id)
+            )),
+        }
+    }
+}
+
+/// How long to wait for another run to release the snapshot's lock.
+const LOCK_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Returns the location of the snapshot, or `None` if it cannot be
+/// determined. Each linked worktree has its own, as it has its own `HEAD`.
+pub fn path() -> Option<PathBuf> {
+    cache::git_path("gitxl/last-view")
+}
+
+/// Reads the snapshot, if there is a valid one.
+pub fn load(path: &Path) -> Option<Snapshot> {
+    let contents = fs::read_to_string(path).ok()?;
+    let mut lines = contents.lines();
+    if lines.next() != Some(HEADER) {
+        return None;
+    }
+    lines
+        .map(|line| line.split_once(' ').map(|(id, name)| (name.to_owned(), id.to_owned())))
+        .collect()
+}
+
+/// Replaces the snapshot.
+pub fn store(path: &Path, snapshot: &Snapshot) {
+    let mut contents = String::new();
+    contents.push_str(HEADER);
+    contents.push('\n');
+    for (name, id) in snapshot {
+        contents.extend([id.as_str(), " ", name.as_str(), "\n"]);
+    }
+    if let Ok(lock) = Lock::acquire(path, LOCK_TIMEOUT) {
+        drop(lock.commit(&contents));
+    }
+}
+
+/// Returns how the refs changed from `old` to `new`, by name.
+pub fn changes<'snapshot>(
+    old: &'snapshot Snapshot,
+    new: &'snapshot Snapshot,
+) -> Vec<Change<'snapshot>> {
+    let mut changes = vec![];
+    let mut names: Vec<_> = old.keys().chain(new.keys()).collect();
+    names.sort_unstable();
+    names.dedup();
+    for name in names {
+        match (old.get(name), new.get(name)) {
+            (None, Some(id)) => changes.push(Change::New { name, id }),
+            (Some(from), Some(to)) if from != to => changes.push(Change::Moved { name, from, to }),
+            (Some(id), None) => changes.push(Change::Gone { name, id }),
+            _ => {}
+        }
+    }
+    changes
+}
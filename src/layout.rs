@@ -0,0 +1,161 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Assigns commits and edges to lanes (columns), for the renderers that draw
+//! the graph themselves.
+//!
+//! Each branch of the graph is drawn in a lane. A lane is created when we reach
+//! a commit nobody expects yet (a tip) or a merge commit's additional parent,
+//! and it is freed when it reaches a commit that is already expected by another
+//! lane. New lanes reuse the leftmost free column, which keeps the graph much
+//! narrower than `git log --graph` for repositories with many branches.
+
+use crate::graph::Commit;
+use core::iter::Cycle;
+use core::ops::Range;
+use std::collections::HashMap;
+
+/// The number of lane colors; renderers map them to their own palettes.
+pub const COLORS: usize = 6;
+
+/// The edge from a commit to one of its displayed parents.
+pub struct Route {
+    /// The parent's index in the commit list.
+    pub parent: usize,
+    /// The lane the edge travels down in.
+    pub lane: usize,
+    /// Whether the lane was created for this edge (rather than being the
+    /// commit's own lane or a lane that already led to the parent).
+    pub new: bool,
+}
+
+/// Where a commit and its edges are drawn.
+pub struct Placement {
+    pub column: usize,
+    /// Whether a lane led into the commit; false for tips.
+    pub expected: bool,
+    /// The other lanes that led into the commit, which end at it.
+    pub converging: Vec<usize>,
+    /// The edges to the commit's displayed parents, in parent order.
+    pub routes: Vec<Route>,
+    /// The color of each lane just before the commit (`None` for free lanes).
+    pub before: Vec<Option<usize>>,
+    /// The color of each lane just after the commit.
+    pub after: Vec<Option<usize>>,
+}
+
+/// A lane: the commit it leads to next, and its color.
+struct Lane {
+    id: usize,
+    color: usize,
+}
+
+/// The lane assignment state.
+struct Lanes {
+    lanes: Vec<Option<Lane>>,
+    /// Lane colors, cycled through as lanes are created.
+    colors: Cycle<Range<usize>>,
+}
+
+impl Lanes {
+    /// Creates a lane with a new color leading to commit `id`, in the leftmost
+    /// free column other than `exclude`. Returns the lane's column.
+    #[allow(clippy::unwrap_used, reason = "column is a lane")]
+    fn create(&mut self, id: usize, exclude: usize) -> usize {
+        let free =
+            self.lanes.iter().enumerate().position(|(i, lane)| lane.is_none() && i != exclude);
+        let column = free.unwrap_or_else(|| {
+            self.lanes.push(None);
+            self.lanes.len().saturating_sub(1)
+        });
+        let color = self.colors.next().unwrap_or_default();
+        *self.lanes.get_mut(column).unwrap() = Some(Lane { id, color });
+        column
+    }
+
+    /// Returns the lanes leading to commit `id`.
+    fn leading_to(&self, id: usize) -> impl Iterator<Item = usize> + '_ {
+        self.lanes
+            .iter()
+            .enumerate()
+            .filter(move |(_, lane)| lane.as_ref().is_some_and(|lane| lane.id == id))
+            .map(|(i, _)| i)
+    }
+
+    /// Returns the color of each lane.
+    fn colors(&self) -> Vec<Option<usize>> {
+        self.lanes.iter().map(|lane| lane.as_ref().map(|lane| lane.color)).collect()
+    }
+
+    /// Places commit `index`, whose displayed parents are `parents`.
+    #[allow(clippy::unwrap_used, reason = "the columns are lanes")]
+    fn place(&mut self, index: usize, parents: &[usize]) -> Placement {
+        let expecting: Vec<usize> = self.leading_to(index).collect();
+        // A commit nobody expects is a tip, which starts a new lane.
+        let column = expecting.first().copied().unwrap_or_else(|| self.create(index, usize::MAX));
+        let before = self.colors();
+        let color = before.get(column).copied().flatten().unwrap_or_default();
+        for &lane in expecting.iter().chain([&column]) {
+            *self.lanes.get_mut(lane).unwrap() = None;
+        }
+
+        // The first parent continues in this commit's lane unless another lane
+        // already leads to it, in which case this lane ends.
+        let mut routes = vec![];
+        for (i, &parent) in parents.iter().enumerate() {
+            let existing = self.leading_to(parent).next();
+            if let Some(lane) = existing {
+                routes.push(Route { parent, lane, new: false });
+            } else if i == 0 {
+                *self.lanes.get_mut(column).unwrap() = Some(Lane { id: parent, color });
+                routes.push(Route { parent, lane: column, new: false });
+            } else {
+                let lane = self.create(parent, column);
+                routes.push(Route { parent, lane, new: true });
+            }
+        }
+        let after = self.colors();
+        while self.lanes.last().is_some_and(Option::is_none) {
+            self.lanes.pop();
+        }
+        Placement {
+            column,
+            expected: !expecting.is_empty(),
+            converging: expecting.get(1..).unwrap_or_default().to_vec(),
+            routes,
+            before,
+            after,
+        }
+    }
+}
+
+/// Places each of `commits`, which must be in topological order (children
+/// first). Edges to commits that are not in `commits` are dropped.
+pub fn layout(commits: &[Commit]) -> Vec<Placement> {
+    let index: HashMap<&str, usize> =
+        commits.iter().enumerate().map(|(i, commit)| (commit.id.as_str(), i)).collect();
+    let mut lanes = Lanes { lanes: vec![], colors: (0..COLORS).cycle() };
+    commits
+        .iter()
+        .enumerate()
+        .map(|(i, commit)| {
+            let parents: Vec<usize> = commit
+                .parents
+                .iter()
+                .filter_map(|parent| index.get(parent.as_str()).copied())
+                .collect();
+            lanes.place(i, &parents)
+        })
+        .collect()
+}
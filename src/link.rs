@@ -0,0 +1,71 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Links from commits to a code review or hosting site.
+//!
+//! The link template comes from `--link-template` or the `gitxl.linkTemplate`
+//! config setting. In it, `{hash}` (or `%H`) is replaced by the commit ID and
+//! `{short}` (or `%h`) by its abbreviation, e.g.
+//! `https://crrev.com/{hash}`.
+
+use crate::{abbrev, config};
+
+/// Returns the link template: `explicit` if given, and otherwise the
+/// `gitxl.linkTemplate` config setting, if set.
+pub fn template(explicit: Option<&str>) -> Option<String> {
+    if let Some(template) = explicit {
+        return Some(template.to_owned());
+    }
+    config::get("gitxl.linkTemplate")
+}
+
+/// Returns the link for commit `id` built from `template`.
+#[allow(clippy::literal_string_with_formatting_args, reason = "they are placeholders")]
+pub fn expand(template: &str, id: &str) -> String {
+    let short = abbrev::short(id);
+    template.replace("{hash}", id).replace("%H", id).replace("{short}", short).replace("%h", short)
+}
+
+/// Makes the first abbreviation of commit `id` (at least as long as git's) in
+/// `text` a terminal hyperlink (OSC 8) to `url`. Leaves `text` unchanged if it
+/// does not contain one.
+pub fn hyperlink_id(text: &mut String, id: &str, url: &str) {
+    let short = id.get(..abbrev::git_length()).unwrap_or(id);
+    let Some(start) = text.find(short) else { return };
+    let rest = text.get(start..).unwrap_or_default();
+    let len = rest.bytes().zip(id.bytes()).take_while(|&(a, b)| a == b).count();
+    wrap(text, start, start.saturating_add(len), url);
+}
+
+/// Makes the first occurrence of `target` in `text` that is not part of a
+/// hyperlink already a terminal hyperlink (OSC 8) to `url`.
+pub fn hyperlink(text: &mut String, target: &str, url: &str) {
+    let linked = |start: usize| {
+        let before = text.get(..start).unwrap_or_default();
+        before.rfind("\x1b]8;;").is_some_and(|open| before.rfind("\x1b]8;;\x1b\\") != Some(open))
+    };
+    let Some((start, _)) = text.match_indices(target).find(|&(start, _)| !linked(start)) else {
+        return;
+    };
+    wrap(text, start, start.saturating_add(target.len()), url);
+}
+
+/// Makes the bytes from `start` to `end` of `text` a terminal hyperlink to
+/// `url`. Control characters are left out of `url`, as they could end the
+/// escape sequence early and send the rest to the terminal.
+fn wrap(text: &mut String, start: usize, end: usize, url: &str) {
+    let url: String = url.chars().filter(|c| !c.is_control()).collect();
+    text.insert_str(end, "\x1b]8;;\x1b\\");
+    text.insert_str(start, &format!("\x1b]8;;{url}\x1b\\"));
+}
@@ -0,0 +1,103 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lock files for the state under `.git/gitxl/`, so that concurrent git-tree
+//! runs (e.g. a shell prompt's and an interactive one) neither write the same
+//! file at once nor lose each other's updates.
+//!
+//! As in git, `<file>.lock` is created exclusively, the new contents are
+//! written to it, and it is renamed over `<file>`, which readers therefore see
+//! either before or after the update and need not lock. A writer waits for
+//! another's lock up to a timeout. A lock older than a few seconds is left by
+//! a run that died (every writer holds its lock only for as long as it takes
+//! to read and write a small file), so it is removed.
+
+use core::time::Duration;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind, Write as _};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Instant, SystemTime};
+
+/// How long to wait between attempts to take a lock.
+const RETRY: Duration = Duration::from_millis(10);
+
+/// How old a lock must be to be considered abandoned.
+const STALE: Duration = Duration::from_secs(10);
+
+/// A lock on a file, released when dropped unless committed.
+pub struct Lock {
+    /// The locked file.
+    target: PathBuf,
+    /// The lock file.
+    path: PathBuf,
+    /// The open lock file, until it is committed.
+    file: Option<File>,
+}
+
+/// Returns whether the lock file `path` was abandoned.
+fn stale(path: &Path) -> bool {
+    let modified = fs::metadata(path).and_then(|metadata| metadata.modified());
+    modified.is_ok_and(|modified| {
+        SystemTime::now().duration_since(modified).is_ok_and(|age| age > STALE)
+    })
+}
+
+impl Lock {
+    /// Locks `target`, creating its directory if needed, waiting up to
+    /// `timeout` for another lock to be released.
+    pub fn acquire(target: &Path, timeout: Duration) -> io::Result<Self> {
+        let mut path = OsString::from(target);
+        path.push(".lock");
+        let path = PathBuf::from(path);
+        if let Some(dir) = target.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let start = Instant::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => return Ok(Self { target: target.to_owned(), path, file: Some(file) }),
+                Err(error) if error.kind() != ErrorKind::AlreadyExists => return Err(error),
+                Err(_) => {}
+            }
+            if stale(&path) {
+                drop(fs::remove_file(&path));
+            } else if start.elapsed() >= timeout {
+                return Err(io::Error::new(
+                    ErrorKind::WouldBlock,
+                    tr!("{$path} is locked by another git-tree", path = target.display()),
+                ));
+            } else {
+                thread::sleep(RETRY);
+            }
+        }
+    }
+
+    /// Replaces the locked file with `contents`, releasing the lock.
+    pub fn commit(mut self, contents: &str) -> io::Result<()> {
+        let mut file = self.file.take().ok_or_else(|| io::Error::other("lock already released"))?;
+        file.write_all(contents.as_bytes())?;
+        drop(file);
+        fs::rename(&self.path, &self.target).inspect_err(|_| drop(fs::remove_file(&self.path)))
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        if self.file.take().is_some() {
+            drop(fs::remove_file(&self.path));
+        }
+    }
+}
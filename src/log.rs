@@ -0,0 +1,164 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Diagnostic logging, for bug reports. Logging is off unless `GITXL_LOG` is
+//! set to a level (`info`, `debug`, or `trace`) or `-v` (debug) or `-vv`
+//! (trace) is given. Each event has a target (the part of git-tree it comes
+//! from), a message, and fields, and is written to stderr as a line of text,
+//! or as a JSON object if `GITXL_LOG_FORMAT=json`:
+//!
+//! ```text
+//! git-tree: debug: merge-base: computed the merge bases tips=2 bases=["1a2b..."]
+//! {"level":"debug","target":"merge-base","message":"computed the merge bases","fields":{...}}
+//! ```
+
+use crate::json::Value;
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::env::var;
+use std::io::{stderr, Write as _};
+use std::process::Command;
+
+/// How much to log. Each level includes the ones before it.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Off,
+    /// What git-tree decided: e.g. whether the cache was used.
+    Info,
+    /// How it decided: the refs, merge bases, and traversal.
+    Debug,
+    /// Every branch considered and every command run.
+    Trace,
+}
+
+impl Level {
+    /// Parses a `GITXL_LOG` value.
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(Self::Off),
+            "info" => Some(Self::Info),
+            "debug" => Some(Self::Debug),
+            "trace" => Some(Self::Trace),
+            _ => None,
+        }
+    }
+
+    /// Returns the level's name, as in `GITXL_LOG`.
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Info => "info",
+            Self::Debug => "debug",
+            Self::Trace => "trace",
+        }
+    }
+
+    const fn from_u8(level: u8) -> Self {
+        match level {
+            0 => Self::Off,
+            1 => Self::Info,
+            2 => Self::Debug,
+            _ => Self::Trace,
+        }
+    }
+
+    const fn to_u8(self) -> u8 {
+        match self {
+            Self::Off => 0,
+            Self::Info => 1,
+            Self::Debug => 2,
+            Self::Trace => 3,
+        }
+    }
+}
+
+/// The current level, as `Level::to_u8`.
+static LEVEL: AtomicU8 = AtomicU8::new(0);
+
+/// Whether to log JSON objects instead of text.
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Sets up logging from the environment and the number of `-v` options. The
+/// more verbose of the two wins.
+pub fn init(verbosity: u8) {
+    let from_env = var("GITXL_LOG").ok().map_or(Level::Off, |name| {
+        Level::parse(&name).unwrap_or_else(|| {
+            drop(writeln!(stderr(), "git-tree: warning: unknown GITXL_LOG level {name}"));
+            Level::Off
+        })
+    });
+    let from_flags = match verbosity {
+        0 => Level::Off,
+        1 => Level::Debug,
+        _ => Level::Trace,
+    };
+    LEVEL.store(from_env.max(from_flags).to_u8(), Ordering::Relaxed);
+    JSON.store(var("GITXL_LOG_FORMAT").is_ok_and(|format| format == "json"), Ordering::Relaxed);
+}
+
+/// Returns whether events at `level` are logged, for callers that would
+/// otherwise compute fields for nothing.
+pub fn enabled(level: Level) -> bool {
+    level != Level::Off && level <= Level::from_u8(LEVEL.load(Ordering::Relaxed))
+}
+
+/// Logs an event.
+pub fn event(level: Level, target: &str, message: &str, fields: Vec<(&str, Value)>) {
+    if !enabled(level) {
+        return;
+    }
+    let mut line = String::new();
+    if JSON.load(Ordering::Relaxed) {
+        let fields = fields.into_iter().map(|(name, value)| (name.to_owned(), value)).collect();
+        Value::object([
+            ("level", level.name().into()),
+            ("target", target.into()),
+            ("message", message.into()),
+            ("fields", Value::Object(fields)),
+        ])
+        .write(&mut line);
+    } else {
+        line.extend(["git-tree: ", level.name(), ": ", target, ": ", message]);
+        for (name, value) in fields {
+            line.extend([" ", name, "="]);
+            value.write(&mut line);
+        }
+    }
+    line.push('\n');
+    // Logging is best-effort, like the rest of stderr.
+    drop(stderr().write_all(line.as_bytes()));
+}
+
+/// Logs commands before they run.
+pub trait Logged {
+    /// Logs (at trace level) that the command is about to run, and returns it.
+    fn logged(&mut self) -> &mut Self;
+}
+
+impl Logged for Command {
+    fn logged(&mut self) -> &mut Self {
+        if enabled(Level::Trace) {
+            let args = self.get_args().map(|arg| arg.to_string_lossy().into_owned());
+            event(
+                Level::Trace,
+                "process",
+                "running",
+                vec![
+                    ("program", self.get_program().to_string_lossy().into_owned().into()),
+                    ("args", Value::strings(args)),
+                ],
+            );
+        }
+        self
+    }
+}
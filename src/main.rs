@@ -21,268 +21,1599 @@
 // displays the interesting commits, their collective merge bases, and any
 // commits on the paths between the merge bases and the interesting commits.
 
-use core::iter::{once, repeat_n};
+// Nothing that git prints or that a repository contains may make git-tree
+// panic. The few unwraps left are on invariants of git-tree's own code, each
+// allowed where it is made.
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+
+extern crate alloc;
+
+// Declared first, so that its `tr!` macro is in scope in the other modules.
+#[macro_use]
+mod messages;
+
+mod abbrev;
+mod age;
+mod allocations;
+mod authors;
+mod behind;
+mod bench;
+mod bisect;
+mod bundle;
+mod cache;
+mod check;
+mod cherry;
+mod ci;
+mod cli;
+mod commit_graph;
+mod config;
+mod conflicts;
+mod context;
+mod date;
+mod default_args;
+mod diffstat;
+mod error_format;
+mod extra_refs;
+mod files;
+mod find;
+mod first_parent;
+mod fork;
+mod format_patch;
+mod gerrit;
+mod github;
+mod given;
+mod graph;
+mod graphml;
+mod help;
+mod html;
+mod icons;
+mod interner;
+mod issues;
+mod json;
+mod json_edges;
+mod last_view;
+mod layout;
+mod link;
+mod lock;
+mod log;
+mod matrix;
+mod mine_only;
+mod notes;
+mod pager;
+mod pick;
+mod pins;
+mod porcelain;
+mod preview;
+mod profile;
+mod prompt;
+mod quote;
+mod rebase_todo;
+mod ref_hook;
+mod reflog;
+mod remotes;
+mod render;
+mod replace;
+mod report;
+mod restack;
+mod serve;
+mod shallow;
+mod skeleton;
+mod status;
+mod style;
+mod svg;
+mod symref;
+mod template;
+mod timing;
+mod trailers;
+mod watch;
+mod width;
+
+use alloc::borrow::Cow;
+use bisect::Bisect;
+use cache::Entry;
+use cli::{Export, Options, Render, Subcommand};
+use commit_graph::CommitGraph;
+use core::iter::repeat_n;
 use core::str;
+use core::sync::atomic::{AtomicBool, Ordering};
+use error_format::Code;
+use graph::{Commit, DatePriority};
+use icons::Icons;
+use interner::{GitId, Interner};
+use issues::Issues;
+use json::Value;
+use log::{Level, Logged as _};
 use std::collections::{HashMap, HashSet};
-use std::env::args_os;
-use std::io::{BufRead as _, BufReader};
-use std::process::{Command, Stdio};
-
-/// Returns all interesting branches. Note that some commits may be in the list
-/// multiple times under different names.
-/// Precondition: `buffer` must be empty
-/// Postcondition: `buffer` will be empty
-fn interesting_branches(buffer: &mut Vec<u8>) -> Vec<String> {
-    // This considers a branch interesting if it is a local branch or if it has
-    // the same name as a local branch.
+use std::env::{args_os, set_var, var};
+use std::fs::File;
+use std::io::{self, stderr, stdout, BufRead as _, BufReader, BufWriter, ErrorKind, Write};
+#[cfg(unix)]
+use std::os::unix::process::ExitStatusExt as _;
+use std::path::Path;
+use std::process::{exit, Command, ExitStatus, Stdio};
+use style::Paint;
+use timing::Timing;
+
+/// A branch and the commit it points to.
+#[derive(Clone)]
+struct Branch {
+    name: String,
+    /// The full name of the ref, e.g. `refs/heads/main`, outside of any
+    /// namespace.
+    refname: String,
+    id: String,
+    /// The commit time of the commit, in seconds since the epoch.
+    time: i64,
+}
+
+impl Branch {
+    /// Returns what branches are sorted by: their names, then their full ref
+    /// names, which tell apart a local and a remote branch of the same name.
+    fn sort_key(&self) -> (&str, &str) {
+        (&self.name, &self.refname)
+    }
+}
+
+/// Strips the line ending from a line of git's output read with `read_until`.
+/// Handles `\r\n` too, which some Windows builds of git write.
+fn trim_line_ending(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// Returns an error if `git <command>` exited with the unsuccessful `status`.
+/// git has reported why on stderr.
+fn git_status(command: &str, status: ExitStatus) -> io::Result<()> {
+    if status.success() {
+        return Ok(());
+    }
+    Err(io::Error::other(tr!("git {$command} failed: {$error}", command = command, error = status)))
+}
+
+/// Returns the error for output of `git <command>` that git-tree cannot parse.
+fn malformed(command: &str) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        tr!("malformed git {$command} output", command = command),
+    )
+}
+
+/// Returns the commit ID `rev` resolves to, or `None` if it does not resolve
+/// to a commit.
+fn rev_parse(rev: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet", "--end-of-options"])
+        .arg(format!("{rev}^{{commit}}"))
+        .stderr(Stdio::null())
+        .logged()
+        .output()
+        .ok()?;
+    let id = String::from_utf8(output.stdout).ok()?;
+    output.status.success().then(|| id.trim_end().to_owned())
+}
+
+/// Returns the commit that the object `id` peels to (itself if it is a commit,
+/// or the commit at the end of a chain of annotated tags) and the commit's
+/// time, or `None` if it does not peel to a commit, e.g. for a tag of a tree.
+fn peel_to_commit(id: &str) -> Option<(String, i64)> {
+    let output = Command::new("git")
+        .args(["log", "--no-walk", "--format=%H %ct", "--end-of-options"])
+        .arg(format!("{id}^{{commit}}"))
+        .arg("--")
+        .env(graph::NO_LAZY_FETCH, "1")
+        .stderr(Stdio::null())
+        .logged()
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8(output.stdout).ok().filter(|_| output.status.success())?;
+    let (commit, time) = stdout.trim_end().split_once(' ')?;
+    Some((commit.to_owned(), time.parse().ok()?))
+}
+
+/// Returns the abbreviations of the commits `ids`, for logging.
+fn short_ids(ids: &[String]) -> Value {
+    Value::strings(ids.iter().map(|id| abbrev::short(id)))
+}
+
+/// Returns the prefix of the refs in the namespace set by `GIT_NAMESPACE` (see
+/// gitnamespaces(7)), e.g. `refs/namespaces/a/refs/namespaces/b/` for `a/b`,
+/// or `None` if there is none.
+fn namespace_prefix() -> Option<String> {
+    let namespace = var("GIT_NAMESPACE").ok()?;
+    let mut prefix = String::new();
+    for component in namespace.split('/').filter(|component| !component.is_empty()) {
+        prefix.extend(["refs/namespaces/", component, "/"]);
+    }
+    (!prefix.is_empty()).then_some(prefix)
+}
+
+/// Returns a line of `interesting_branches`'s `git for-each-ref` output as a
+/// string, or `None` with a warning if the ref name is not UTF-8. git allows
+/// any bytes in ref names, but branches are passed around by name as strings,
+/// so such a branch cannot be shown (nor can an upstream or symref target that
+/// is not UTF-8 match a branch, so they are decoded lossily).
+fn ref_line<'line>(line: &'line [u8], prefix: &str) -> Option<Cow<'line, str>> {
+    let refname = line.split(|&byte| byte == b'\0').nth(2).unwrap_or_default();
+    if str::from_utf8(refname).is_err() {
+        let name = quote::bytes(refname.strip_prefix(prefix.as_bytes()).unwrap_or(refname));
+        warn(&tr!("warning: ignoring {$name}, whose name is not UTF-8", name = name));
+        return None;
+    }
+    Some(String::from_utf8_lossy(line))
+}
+
+/// Warns about the broken symbolic refs in `refs/heads`, given the `locals`
+/// and `aliases` `interesting_branches` listed.
+fn warn_broken_symrefs(prefix: &str, locals: &HashMap<String, Branch>, aliases: &HashSet<String>) {
+    let heads = format!("{prefix}refs/heads/");
+    let listed =
+        |name: &str| locals.contains_key(name) || aliases.contains(&format!("refs/heads/{name}"));
+    for name in symref::broken(&heads, listed) {
+        let refname = format!("{heads}{name}");
+        warn(&tr!("warning: ignoring broken symbolic ref {$name}", name = quote::name(&refname)));
+    }
+}
+
+/// Returns all interesting branches, sorted (see `Branch::sort_key`), and the
+/// ID of the commit HEAD points to (or `None` if HEAD does not point to a
+/// commit). Note that some commits may be in the list multiple times under
+/// different names. Symbolic refs (e.g.
+/// `origin/HEAD`) are aliases rather than branches of their own, so they are
+/// left out, and broken ones are warned about. Branches pointing to annotated
+/// tags stand for the commits the tags peel to; those pointing to other
+/// objects are warned about and left out, as are branches whose names are not
+/// UTF-8 (see `ref_line`). If `GIT_NAMESPACE` is set, the branches and HEAD are
+/// those of the namespace. A `--profile` replaces the heuristic (see
+/// `profile`), pinned branches override both (see `pins`), and a
+/// `gitxl.refHook` has the last word (see `ref_hook`), unless the revisions
+/// given replace them all (see `given`).
+/// Precondition: `buffer` must be empty.
+/// Postcondition: `buffer` will be empty.
+fn interesting_branches(buffer: &mut Vec<u8>) -> io::Result<(Vec<Branch>, Option<String>)> {
+    // This considers a branch interesting if it is a local branch, or a remote
+    // branch that is the upstream of a local branch or has the same name as
+    // one (of the remotes `--remote` or `gitxl.remotes` chooses, if any).
+    // All refs are listed by a single for-each-ref, which scales to many refs.
+    // Each line is "<HEAD marker>\0<commit ID>\0<ref name>\0<upstream>\0<commit
+    // time>\0<symref target>\0<object type>", where the HEAD marker is '*' for
+    // the branch HEAD points to and ' ' for other branches, and the upstream
+    // and target are empty unless the branch has an upstream or is symbolic.
+    if let Some(chosen) = given::chosen() {
+        return Ok(chosen);
+    }
+    let namespace = namespace_prefix();
+    let prefix = namespace.as_deref().unwrap_or_default();
     let mut git = Command::new("git")
-        .args(["branch", "-a", "--format=%(refname)"])
+        .arg("for-each-ref")
+        .arg(
+            "--format=%(HEAD)%00%(objectname)%00%(refname)%00%(upstream)%00\
+             %(committerdate:unix)%00%(symref)%00%(objecttype)",
+        )
+        .args([format!("{prefix}refs/heads"), format!("{prefix}refs/remotes")])
+        .env(graph::NO_LAZY_FETCH, "1")
         .stdout(Stdio::piped())
-        .spawn()
-        .expect("failed to run git");
-    let mut locals = HashSet::new();
+        .logged()
+        .spawn()?;
+    let mut head = None;
+    let mut locals = HashMap::new();
     let mut remotes = vec![];
+    let mut upstreams = HashSet::new();
+    let mut aliases = HashSet::new();
+    #[allow(clippy::unwrap_used, reason = "stdout is piped")]
     let mut reader = BufReader::new(git.stdout.as_mut().unwrap());
-    while let Some(len) =
-        reader.read_until(b'\n', buffer).expect("git stdout read failed").checked_sub(1)
-    {
-        if buffer.first_chunk() == Some(b"refs/remotes/") {
-            remotes.push(buffer.get(b"refs/remotes/".len()..len).unwrap().to_vec());
-        } else if buffer.first_chunk() == Some(b"refs/heads/") {
-            locals.insert(buffer.get(b"refs/heads/".len()..len).unwrap().into());
+    while reader.read_until(b'\n', buffer)? != 0 {
+        let Some(line) = ref_line(trim_line_ending(buffer), prefix) else {
+            buffer.clear();
+            continue;
+        };
+        let mut fields = line.split('\0');
+        let mut field = || fields.next().ok_or_else(|| malformed("for-each-ref"));
+        let (marker, id, refname, upstream, time, target, kind) =
+            (field()?, field()?, field()?, field()?, field()?, field()?, field()?);
+        let strip = |full: &str| full.strip_prefix(prefix).unwrap_or(full).to_owned();
+        let refname = strip(refname);
+        // git only lets branches in refs/heads point to commits, but remote
+        // branches can point to anything.
+        let (id, time) = if kind == "commit" || !target.is_empty() {
+            (id.to_owned(), time.parse().unwrap_or_default())
+        } else if let Some(peeled) = peel_to_commit(id) {
+            peeled
+        } else {
+            let name = quote::name(&refname);
+            warn(&tr!("warning: ignoring {$name}, which does not point to a commit", name = name));
+            buffer.clear();
+            continue;
+        };
+        // In a namespace, the marker is for the repository's HEAD instead.
+        if marker == "*" && namespace.is_none() {
+            head = Some(id.clone());
+        }
+        let branch = |name: &str| Branch {
+            name: name.to_owned(),
+            refname: refname.clone(),
+            id: id.clone(),
+            time,
+        };
+        if !target.is_empty() {
+            // Points to another branch, which is listed too.
+            aliases.insert(refname);
+        } else if let Some(name) = refname.strip_prefix("refs/remotes/") {
+            remotes.push(branch(name));
+        } else if let Some(name) = refname.strip_prefix("refs/heads/") {
+            if let Some(upstream) = strip(upstream).strip_prefix("refs/remotes/") {
+                upstreams.insert(upstream.to_owned());
+            }
+            locals.insert(name.to_owned(), branch(name));
         }
         buffer.clear();
     }
     drop(reader);
-    let mut interesting = vec![];
-    for remote in remotes {
-        let Some(idx) = remote.iter().position(|&b| b == b'/') else { continue };
-        #[allow(clippy::arithmetic_side_effects, reason = "idx is less than buffer.len()")]
-        let (_, name) = remote.split_at(idx + 1);
-        if locals.contains(name) {
-            interesting.push(String::from_utf8(remote).expect("non-utf-8 branch"));
-        }
-    }
-    interesting.extend(
-        locals.into_iter().map(|local| String::from_utf8(local).expect("non-utf-8 branch")),
-    );
-    let status = git.wait().expect("failed to wait for git");
-    assert!(status.success(), "git returned unsuccessful status {status}");
-    interesting
-}
-
-/// Returns all merge bases of the interesting commits.
-/// Precondition: `buffer` must be empty
-/// Postcondition: `buffer` will be empty
-fn merge_bases(buffer: &mut Vec<u8>, interesting_branches: &Vec<String>) -> Vec<String> {
+    git_status("for-each-ref", git.wait()?)?;
+    warn_broken_symrefs(prefix, &locals, &aliases);
+    // for-each-ref does not list a detached HEAD, or a namespace's HEAD.
+    if head.is_none() {
+        head = rev_parse(&format!("{prefix}HEAD"));
+    }
+    let (mut interesting, mut others) = remotes::partition(remotes, |remote| {
+        upstreams.contains(&remote.name)
+            || remote.name.split_once('/').is_some_and(|(_, name)| locals.contains_key(name))
+    })?;
+    interesting.extend(locals.into_values());
+    if let Some(profile) = profile::active() {
+        (interesting, others) = profile.select(interesting, others)?;
+    }
+    (interesting, others) = pins::apply(interesting, others);
+    interesting.sort_unstable_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+    if let Some(hook) = ref_hook::command() {
+        interesting = ref_hook::adjust(&hook, interesting, others);
+    }
+    if log::enabled(Level::Trace) {
+        for branch in &interesting {
+            let fields = vec![
+                ("name", branch.name.as_str().into()),
+                ("id", abbrev::short(&branch.id).into()),
+            ];
+            log::event(Level::Trace, "refs", "interesting branch", fields);
+        }
+    }
+    let fields = vec![
+        ("branches", interesting.len().into()),
+        ("head", head.as_deref().map(abbrev::short).into()),
+    ];
+    log::event(Level::Debug, "refs", "listed the refs", fields);
+    Ok((interesting, head))
+}
+
+/// `git merge-base`'s exit status when the commits have no common ancestor.
+const NO_COMMON_ANCESTOR: i32 = 1;
+
+/// Returns all merge bases of the interesting commits `tips`, or none if their
+/// histories are unrelated (or cut off by a shallow clone).
+/// Precondition: `buffer` must be empty.
+/// Postcondition: `buffer` will be empty.
+fn merge_bases(buffer: &mut Vec<u8>, tips: &[String]) -> io::Result<Vec<String>> {
     let mut git = Command::new("git")
-        .args(["merge-base", "-a", "--octopus", "HEAD"])
-        .args(interesting_branches)
+        .args(["merge-base", "-a", "--octopus"])
+        .args(tips)
+        .env(graph::NO_LAZY_FETCH, "1")
         .stdout(Stdio::piped())
-        .spawn()
-        .expect("failed to run git");
+        .logged()
+        .spawn()?;
     let mut merge_bases = Vec::with_capacity(1);
+    #[allow(clippy::unwrap_used, reason = "stdout is piped")]
     let mut reader = BufReader::new(git.stdout.as_mut().unwrap());
-    while let Some(len) =
-        reader.read_until(b'\n', buffer).expect("git stdout read failed").checked_sub(1)
-    {
-        // Reserve enough space for the merge base plus a trailing ^@ (used in
-        // the final `git log` invocation).
-        #[allow(
-            clippy::arithmetic_side_effects,
-            reason = "len is < the size of an allocation so adding 2 shouldn't overflow usize"
-        )]
-        let mut merge_base = String::with_capacity(len + 2);
-        merge_base
-            .push_str(str::from_utf8(buffer.get(..len).unwrap()).expect("non-utf-8 git output"));
-        merge_bases.push(merge_base);
+    while reader.read_until(b'\n', buffer)? != 0 {
+        merge_bases
+            .push(str::from_utf8(trim_line_ending(buffer)).map_err(io::Error::other)?.to_owned());
         buffer.clear();
     }
     drop(reader);
-    let status = git.wait().expect("failed to wait for git");
-    assert!(status.success(), "git returned unsuccessful status {status}");
-    merge_bases
+    let status = git.wait()?;
+    if status.code() != Some(NO_COMMON_ANCESTOR) || !merge_bases.is_empty() {
+        git_status("merge-base", status)?;
+    }
+    let fields = vec![("tips", tips.len().into()), ("bases", short_ids(&merge_bases))];
+    log::event(Level::Debug, "merge-base", "computed the merge bases", fields);
+    Ok(merge_bases)
 }
 
-/// Computes the include and exclude lists to pass to git. The first list
-/// returned is the inclusion list, the second is the exclusion list.
-/// Precondition: buffer is empty.
-fn includes_excludes(
-    mut buffer: Vec<u8>,
-    interesting_branches: Vec<String>,
-    merge_bases: &Vec<String>,
-) -> (Vec<String>, Vec<String>) {
-    // We want to show the interesting commits, merge bases, and the commits on
-    // a path between the two. That is equivalent to showing all commits which
-    // satisfy:
-    // 1. The commit is reachable from an interesting commit, and
-    // 2. A merge base is reachable from the commit.
-    // This graph traversal computes the include and exclude arguments to pass
-    // to git log to show the above set of commits.
-    // We ask `git rev-list` to print all commits that are reachable from an
-    // interesting commit and not reachable from a merge base (note: this
-    // excludes the merge bases themselves). Every commit that git returns
-    // satisfies condition 1, but not all satisfy condition 2 (it may return
-    // commits that cannot reach a merge base).
-    // Since all such commits satisfy condition 1, we only really have to look
-    // at condition 2. If a commit can reach a merge base, then it should be
-    // shown, and we call it "visible". To easily compute which commits are
-    // visible, we ask git rev-list to print out the commits in reverse
-    // topological order, so that we visit all a commit's parents before we
-    // visit that commit. That way, when we visit a node, we know it is visible
-    // iff it has a visible parent.
-    // Once the graph traversal is complete:
-    // A) The includes list should consist of every childless visible commit.
-    // B) The excludes list should consist of every invisible commit that does
-    //    not have an invisible child.
-    // Fortunately, we can track whether a node has a (visible?) child as we
-    // traverse the graph. When we first add a commit, we mark it as having no
-    // (visible?) child, then we update that if we encounter its children. Note
-    // that we do not need to track invisible nodes that have invisible children
-    // -- they can be forgotten about entirely once detected.
-
-    #[derive(Clone, Copy, PartialEq)]
-    enum NodeState {
-        // This node should not be visible in the final graph (it does not see a
-        // merge base), and we have not yet explored any invisible child commits
-        // of it. Note that InvisibleParent does not exist because if we find an
-        // invisible child node of an InvisibleChild node, we remove the
-        // InvisibleChild node entirely.
-        InvisibleChild,
-
-        // This node should be visible in the final graph (it does see a merge
-        // base), and we've found a child node of it.
-        VisibleParent,
-
-        // This node should be visible in the final graph, and we have not yet
-        // explored a child node of it.
-        VisibleChild,
-    }
-    impl NodeState {
-        /// Returns whether this is a visible node.
-        fn is_visible(self) -> bool {
-            self != Self::InvisibleChild
+// We want to show the interesting commits, merge bases, and the commits on a
+// path between the two. That is equivalent to showing all commits which
+// satisfy:
+// 1. The commit is reachable from an interesting commit, and
+// 2. A merge base is reachable from the commit.
+// This graph traversal computes the include and exclude arguments to pass to
+// git log to show the above set of commits.
+// We visit all commits that are reachable from an interesting commit and not
+// reachable from a merge base (note: this excludes the merge bases
+// themselves). We get this list from the commit-graph if the repository has
+// one, and from `git rev-list` otherwise. Every commit visited satisfies
+// condition 1, but not all satisfy condition 2 (there may be commits that
+// cannot reach a merge base).
+// Since all such commits satisfy condition 1, we only really have to look at
+// condition 2. If a commit can reach a merge base, then it should be shown, and
+// we call it "visible". To easily compute which commits are visible, we visit
+// the commits in reverse topological order, so that we visit all a commit's
+// parents before we visit that commit. That way, when we visit a node, we know
+// it is visible iff it has a visible parent.
+// Once the graph traversal is complete:
+// A) The includes list should consist of every childless visible commit.
+// B) The excludes list should consist of every invisible commit that does not
+//    have an invisible child.
+// Fortunately, we can track whether a node has a (visible?) child as we
+// traverse the graph. When we first add a commit, we mark it as having no
+// (visible?) child, then we update that if we encounter its children. Note that
+// we do not need to track invisible nodes that have invisible children -- they
+// can be forgotten about entirely once detected.
+
+#[derive(Clone, Copy, PartialEq)]
+enum NodeState {
+    // This node should not be visible in the final graph (it does not see a
+    // merge base), and we have not yet explored any invisible child commits of
+    // it. Note that InvisibleParent does not exist because if we find an
+    // invisible child node of an InvisibleChild node, we remove the
+    // InvisibleChild node entirely.
+    InvisibleChild,
+
+    // This node should be visible in the final graph (it does see a merge
+    // base), and we've found a child node of it.
+    VisibleParent,
+
+    // This node should be visible in the final graph, and we have not yet
+    // explored a child node of it.
+    VisibleChild,
+}
+
+impl NodeState {
+    /// Returns whether this is a visible node.
+    fn is_visible(self) -> bool {
+        self != Self::InvisibleChild
+    }
+}
+
+/// The state of the include/exclude graph traversal.
+struct Traversal {
+    /// The merge bases and the commits visited so far, except those that have
+    /// been forgotten.
+    ids: Interner,
+    /// The state of each commit in `ids`, by handle, or `None` if the handle's
+    /// commit has been forgotten.
+    nodes: Vec<Option<NodeState>>,
+    // The parents of the commit being visited that are in `ids`.
+    parents: Vec<GitId>,
+    /// The number of commits visited.
+    visited: usize,
+}
+
+impl Traversal {
+    fn new(merge_bases: &[String]) -> Self {
+        let mut ids = Interner::new();
+        for id in merge_bases {
+            ids.intern(id.as_bytes());
+        }
+        Self {
+            nodes: repeat_n(Some(NodeState::VisibleChild), merge_bases.len()).collect(),
+            ids,
+            parents: Vec::with_capacity(2),
+            visited: 0,
         }
     }
 
-    let mut git = Command::new("git")
-        .args(["rev-list", "--parents", "--reverse", "--topo-order", "HEAD"])
-        .args(interesting_branches)
-        .arg("--not")
-        .args(merge_bases)
-        .stdout(Stdio::piped())
-        .spawn()
-        .expect("failed to run git");
-    let mut nodes: Vec<_> = repeat_n(NodeState::VisibleChild, merge_bases.len()).collect();
-    let mut free_slots = Vec::with_capacity(2);
-    let mut node_lookup: HashMap<_, _> =
-        merge_bases.iter().enumerate().map(|(i, id)| (id.clone().into(), i)).collect();
-    // (index range of the parent's id in buffer, Option<index in nodes>) for
-    // each parent of this commit.
-    let mut parents = Vec::with_capacity(2);
-    let mut reader = BufReader::new(git.stdout.as_mut().unwrap());
-    while let Some(len) =
-        reader.read_until(b'\n', &mut buffer).expect("git stdout read failed").checked_sub(1)
-    {
-        // Construct an iterator over the indexes of the returned commit IDs.
-        // The first ID is the ID of this commit, the rest are this commit's
-        // parents.
-        let mut next_start = 0; // Start of the next range.
-        #[allow(clippy::arithmetic_side_effects, reason = "i is at most buffer.len()")]
-        let mut id_ranges = buffer
-            // Iterate over the buffer excluding the newline.
-            .get(..len)
-            .unwrap()
-            .iter()
-            // enumerate-filter-map to get the indexes of the spaces
-            .enumerate()
-            .filter(|&(_, &b)| b == b' ')
-            .map(|(i, _)| i)
-            // End with the index of the ending newline
-            .chain(once(len))
-            .map(|i| {
-                let start = next_start;
-                next_start = i + 1; // + 1 skips the space
-                start..i
-            });
-        // This commit's ID.
-        let id = buffer.get(id_ranges.next().expect("empty rev-list output line")).unwrap();
-        parents
-            .extend(id_ranges.map(|range| {
-                (range.clone(), node_lookup.get(buffer.get(range).unwrap()).copied())
-            }));
-        let visible = parents
+    /// Reserves room for visiting `additional` more commits, when the caller
+    /// knows how many there will be.
+    fn reserve(&mut self, additional: usize) {
+        self.ids.reserve(additional);
+        self.nodes.reserve(additional);
+    }
+
+    /// Returns the state of the commit with the given handle, unless it has
+    /// been forgotten.
+    fn state(&self, handle: GitId) -> Option<NodeState> {
+        self.nodes.get(handle.index()).copied().flatten()
+    }
+
+    /// Visits a commit. `line` is in `git rev-list --parents` format (without
+    /// the trailing newline): the commit's ID followed by its parents' IDs,
+    /// separated by spaces. Commits must be visited in reverse topological
+    /// order. Returns an error, without visiting the commit, if `line` is not
+    /// in that format.
+    #[allow(clippy::unwrap_used, reason = "every tracked commit has a node")]
+    fn visit(&mut self, line: &[u8]) -> io::Result<()> {
+        // Every ID in the line has the same length (that of the repository's
+        // hash), so only the first one is searched for its end; the parents'
+        // IDs are then sliced out at fixed offsets rather than by examining
+        // every byte.
+        let id_len = line.iter().position(|&b| b == b' ').unwrap_or(line.len());
+        let stride = id_len.saturating_add(1); // + 1 skips the space
+        let parents_len = line.len().saturating_sub(id_len);
+        let id = line.get(..id_len).unwrap_or_default();
+        if !self.ids.accepts(id) || parents_len.checked_rem(stride) != Some(0) {
+            return Err(malformed("rev-list"));
+        }
+        self.parents.extend(
+            (stride..line.len())
+                .step_by(stride)
+                .filter_map(|start| self.ids.get(line.get(start..start.saturating_add(id_len))?)),
+        );
+        let visible = self
+            .parents
             .iter()
-            .filter_map(|&(_, idx)| idx)
-            .any(|idx| nodes.get(idx).unwrap().is_visible());
+            .any(|&parent| self.state(parent).is_some_and(NodeState::is_visible));
         let new_state = if visible {
-            for idx in parents.drain(..).filter_map(|(_, idx)| idx) {
-                let parent = nodes.get_mut(idx).unwrap();
-                if *parent == NodeState::VisibleChild {
-                    *parent = NodeState::VisibleParent;
+            for &parent in &self.parents {
+                let node = self.nodes.get_mut(parent.index()).unwrap();
+                if *node == Some(NodeState::VisibleChild) {
+                    *node = Some(NodeState::VisibleParent);
                 }
             }
             NodeState::VisibleChild
         } else {
-            for (range, parent_idx) in parents.drain(..) {
-                let Some(parent_idx) = parent_idx else { continue };
-                if nodes.get(parent_idx) != Some(&NodeState::InvisibleChild) {
-                    continue;
+            for &parent in &self.parents {
+                let node = self.nodes.get_mut(parent.index()).unwrap();
+                if *node == Some(NodeState::InvisibleChild) {
+                    *node = None;
+                    self.ids.remove(parent);
                 }
-                node_lookup.remove(buffer.get(range).unwrap());
-                free_slots.push(parent_idx);
             }
             NodeState::InvisibleChild
         };
-        if let Some(new_idx) = free_slots.pop() {
-            node_lookup.insert(id.to_vec(), new_idx);
-            *nodes.get_mut(new_idx).unwrap() = new_state;
-        } else {
-            node_lookup.insert(id.to_vec(), nodes.len());
-            nodes.push(new_state);
+        self.parents.clear();
+        self.visited = self.visited.saturating_add(1);
+        let handle = self.ids.intern(id);
+        match self.nodes.get_mut(handle.index()) {
+            Some(node) => *node = Some(new_state),
+            None => self.nodes.push(Some(new_state)),
+        }
+        Ok(())
+    }
+
+    /// Completes the traversal, returning the include and exclude lists.
+    fn finish(self) -> (Vec<String>, Vec<String>) {
+        let mut includes = vec![];
+        let mut excludes = vec![];
+        for (handle, node) in self.ids.handles().zip(self.nodes) {
+            match node {
+                Some(NodeState::InvisibleChild) => excludes.push(self.ids.string(handle)),
+                Some(NodeState::VisibleChild) => includes.push(self.ids.string(handle)),
+                Some(NodeState::VisibleParent) | None => {}
+            }
         }
+        // In a stable order, whichever way the commits were visited.
+        includes.sort_unstable();
+        excludes.sort_unstable();
+        (includes, excludes)
+    }
+}
+
+/// Visits the commits between the interesting branches and the merge bases
+/// using the repository's commit-graph. Returns false without visiting any
+/// commits if the commit-graph is missing, stale (does not contain every tip),
+/// or otherwise unusable, in which case the caller should fall back to
+/// `git rev-list`. Returns an error if the commit-graph turns out to be corrupt
+/// after some commits were visited.
+/// Precondition: `buffer` must be empty.
+/// Postcondition: `buffer` will be empty.
+fn commit_graph_traversal(
+    buffer: &mut Vec<u8>,
+    traversal: &mut Traversal,
+    tips: &[String],
+    merge_bases: &[String],
+) -> io::Result<bool> {
+    let Ok(output) = Command::new("git")
+        .args(["rev-parse", "--is-shallow-repository", "--git-path", "objects/info"])
+        .env(graph::NO_LAZY_FETCH, "1")
+        .stderr(Stdio::null())
+        .logged()
+        .output()
+    else {
+        return Ok(false);
+    };
+    let Ok(stdout) = String::from_utf8(output.stdout) else { return Ok(false) };
+    let mut lines = stdout.lines();
+    // The commit-graph does not account for shallow clones, so git ignores it
+    // in that case (and so do we). Grafts and replace refs are handled by the
+    // caller.
+    let (true, Some("false"), Some(info_dir)) =
+        (output.status.success(), lines.next(), lines.next())
+    else {
+        return Ok(false);
+    };
+    let Some(graph) = CommitGraph::open(Path::new(info_dir)) else { return Ok(false) };
+    let lookup = |ids: &mut dyn Iterator<Item = &str>| -> Option<Vec<u32>> {
+        ids.map(|id| graph.lookup_hex(id)).collect()
+    };
+    let Some(tips) = lookup(&mut tips.iter().map(String::as_str)) else { return Ok(false) };
+    let Some(bases) = lookup(&mut merge_bases.iter().map(String::as_str)) else { return Ok(false) };
+    let Some(commits) = graph.walk(&tips, &bases) else { return Ok(false) };
+    traversal.reserve(commits.len());
+    let mut parents = Vec::with_capacity(2);
+    let corrupt = || io::Error::new(ErrorKind::InvalidData, tr!("corrupt commit-graph"));
+    for pos in commits {
+        graph.write_hex_oid(pos, buffer).ok_or_else(corrupt)?;
+        graph.parents(pos, &mut parents).ok_or_else(corrupt)?;
+        for parent in parents.drain(..) {
+            buffer.push(b' ');
+            graph.write_hex_oid(parent, buffer).ok_or_else(corrupt)?;
+        }
+        traversal.visit(buffer)?;
+        buffer.clear();
+    }
+    Ok(true)
+}
+
+/// The size of the chunks in which `rev_list_traversal` reads git's output.
+const REV_LIST_CHUNK: usize = 1 << 16;
+
+/// Visits the commits between the interesting branches and the merge bases
+/// using `git rev-list`.
+/// Precondition: `buffer` must be empty.
+/// Postcondition: `buffer` will be empty.
+fn rev_list_traversal(
+    buffer: &mut Vec<u8>,
+    traversal: &mut Traversal,
+    tips: &[String],
+    merge_bases: &[String],
+) -> io::Result<()> {
+    let mut git = Command::new("git")
+        .args(["rev-list", "--parents", "--reverse", "--topo-order"])
+        .args(tips)
+        .arg("--not")
+        .args(merge_bases)
+        .env(graph::NO_LAZY_FETCH, "1")
+        .stdout(Stdio::piped())
+        .logged()
+        .spawn()?;
+    // rev-list can print hundreds of thousands of lines, so read them in large
+    // chunks. read_until finds each line's end with memchr.
+    #[allow(clippy::unwrap_used, reason = "stdout is piped")]
+    let mut reader = BufReader::with_capacity(REV_LIST_CHUNK, git.stdout.as_mut().unwrap());
+    while reader.read_until(b'\n', buffer)? != 0 {
+        traversal.visit(trim_line_ending(buffer))?;
         buffer.clear();
     }
     drop(reader);
-    drop(parents);
-    drop(free_slots);
+    git_status("rev-list", git.wait()?)
+}
+
+/// Computes the include and exclude lists to pass to git. The first list
+/// returned is the inclusion list, the second is the exclusion list. The
+/// commit-graph is only used if `rewritten` (see `replace::rewritten`) is
+/// false, as it records the original parents.
+/// Precondition: buffer is empty.
+fn includes_excludes(
+    mut buffer: Vec<u8>,
+    tips: &[String],
+    merge_bases: &[String],
+    rewritten: bool,
+    timing: &mut Timing,
+) -> io::Result<(Vec<String>, Vec<String>)> {
+    let mut traversal = Traversal::new(merge_bases);
+    let source = if rewritten {
+        "rev-list (replace refs or grafts)"
+    } else if commit_graph_traversal(&mut buffer, &mut traversal, tips, merge_bases)? {
+        "commit-graph"
+    } else {
+        "rev-list"
+    };
+    if source != "commit-graph" {
+        rev_list_traversal(&mut buffer, &mut traversal, tips, merge_bases)?;
+    }
     drop(buffer);
-    let mut includes = vec![];
-    let mut excludes = vec![];
-    for (id, idx) in node_lookup {
-        match *nodes.get(idx).unwrap() {
-            NodeState::InvisibleChild => {
-                excludes.push(String::from_utf8(id).expect("non-utf-8 id"));
+    timing.record("traversal", Some((traversal.visited, "commits")));
+    let visited = traversal.visited;
+    let (includes, excludes) = traversal.finish();
+    let fields = vec![
+        ("source", source.into()),
+        ("visited", visited.into()),
+        ("includes", includes.len().into()),
+        ("excludes", excludes.len().into()),
+    ];
+    log::event(Level::Debug, "traversal", "explored the history", fields);
+    Ok((includes, excludes))
+}
+
+/// git-tree's analysis of the repository: the interesting branches and which
+/// commits to display.
+struct View {
+    branches: Vec<Branch>,
+    /// The commit HEAD points to, if any.
+    head: Option<String>,
+    merge_bases: Vec<String>,
+    /// The commits below which nothing is displayed: the merge bases, or with
+    /// `--context`, the commits that many first parents below them.
+    boundaries: Vec<String>,
+    includes: Vec<String>,
+    excludes: Vec<String>,
+    /// The commits at which a shallow clone's history is cut off.
+    shallow: HashSet<String>,
+    /// The commits only recent reflog entries point to (see `reflog`).
+    reflog: Vec<String>,
+    /// Whether this is the first-parent view (see `first_parent`).
+    first_parent: bool,
+}
+
+impl View {
+    /// Returns the IDs of the commits pointed to by HEAD (unless it is unborn),
+    /// the interesting branches, and the `reflog` entries, sorted and
+    /// deduplicated, so that branches sharing a commit are passed to git once.
+    /// Their names stay in `branches`.
+    fn tips(branches: &[Branch], head: Option<&String>, reflog: &[String]) -> Vec<String> {
+        let mut tips: Vec<_> = branches.iter().map(|branch| branch.id.clone()).collect();
+        tips.extend(head.cloned());
+        tips.extend(reflog.iter().cloned());
+        tips.sort_unstable();
+        tips.dedup();
+        tips
+    }
+
+    /// Computes the view for the given interesting branches and HEAD (as
+    /// returned by `interesting_branches`), plus the commits that only their
+    /// last `reflog_entries` reflog entries point to (see `reflog`), using the
+    /// cache when possible. If `first_parent` is true, only first parents are
+    /// followed. `context` is how many first parents below the merge bases to
+    /// display (see `context`).
+    /// Precondition: `buffer` must be empty.
+    fn compute(
+        buffer: Vec<u8>,
+        branches: Vec<Branch>,
+        head: Option<String>,
+        reflog_entries: Option<usize>,
+        first_parent: bool,
+        context: usize,
+        timing: &mut Timing,
+    ) -> io::Result<Self> {
+        let reflog = reflog_entries
+            .map(|entries| {
+                reflog::orphans(entries, &branches, &Self::tips(&branches, head.as_ref(), &[]))
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let tips = Self::tips(&branches, head.as_ref(), &reflog);
+        let shallow = shallow::boundary();
+        let rewritten = replace::rewritten();
+        // Deepening a shallow clone, or changing grafts or replace refs, can
+        // change the view without moving any tip, so those views are not
+        // cached. First-parent views take no traversal, so they are not either,
+        // and the cache only records views without context.
+        let cacheable =
+            !tips.is_empty() && shallow.is_empty() && !rewritten && !first_parent && context == 0;
+        let cache_path = cacheable.then(cache::path).flatten();
+        let cached = cache_path.as_ref().and_then(|path| cache::load(path, &tips));
+        timing.record(if cached.is_some() { "cache hit" } else { "cache miss" }, None);
+        let fields = vec![
+            ("hit", cached.is_some().into()),
+            ("shallow", (!shallow.is_empty()).into()),
+            ("rewritten", rewritten.into()),
+        ];
+        log::event(Level::Info, "cache", "looked up the view", fields);
+        let (Entry { merge_bases, includes, excludes }, boundaries) = if let Some(entry) = cached {
+            let boundaries = entry.merge_bases.clone();
+            (entry, boundaries)
+        } else {
+            let (entry, boundaries) =
+                Self::traverse(buffer, &tips, first_parent, context, rewritten, timing)?;
+            if let Some(path) = &cache_path {
+                cache::store(path, &tips, &entry);
+            }
+            (entry, boundaries)
+        };
+        let known = [&tips, &merge_bases, &boundaries, &includes, &excludes];
+        abbrev::disambiguate(known.into_iter().flatten().map(String::as_str));
+        Ok(Self {
+            branches,
+            head,
+            merge_bases,
+            boundaries,
+            includes,
+            excludes,
+            shallow,
+            reflog,
+            first_parent,
+        })
+    }
+
+    /// Computes the merge bases of the `tips` and which commits to display,
+    /// without the cache. Returns them and the commits below which nothing is
+    /// displayed: the merge bases, lowered by `context` first parents (see
+    /// `context`).
+    fn traverse(
+        mut buffer: Vec<u8>,
+        tips: &[String],
+        first_parent: bool,
+        context: usize,
+        rewritten: bool,
+        timing: &mut Timing,
+    ) -> io::Result<(Entry, Vec<String>)> {
+        // An empty repository has nothing to display.
+        let mut merge_bases = if tips.is_empty() {
+            vec![]
+        } else if first_parent {
+            first_parent::merge_bases(&mut buffer, tips)?
+        } else {
+            merge_bases(&mut buffer, tips)?
+        };
+        merge_bases.sort_unstable();
+        timing.record("merge bases", Some((merge_bases.len(), "bases")));
+        // Without merge bases, only the interesting commits are displayed,
+        // unless there is context to display below them.
+        let boundaries = if context == 0 || tips.is_empty() {
+            merge_bases.clone()
+        } else {
+            let bases = if merge_bases.is_empty() { tips } else { &merge_bases };
+            context::lower(&mut buffer, bases, context)?
+        };
+        let (includes, excludes) = if first_parent || boundaries.is_empty() {
+            (tips.to_vec(), vec![])
+        } else {
+            includes_excludes(buffer, tips, &boundaries, rewritten, timing)?
+        };
+        Ok((Entry { merge_bases, includes, excludes }, boundaries))
+    }
+
+    /// Returns a map from commit ID to the names of the branches pointing to
+    /// it.
+    fn branches_by_id(&self) -> HashMap<&str, Vec<&str>> {
+        let mut map: HashMap<_, Vec<_>> = HashMap::new();
+        for branch in &self.branches {
+            map.entry(branch.id.as_str()).or_default().push(branch.name.as_str());
+        }
+        map
+    }
+
+    /// Returns a map from commit ID to the refs pointing to it: `HEAD` and the
+    /// interesting branches.
+    fn refs_by_id(&self) -> HashMap<&str, Vec<&str>> {
+        let mut map = self.branches_by_id();
+        if let Some(head) = &self.head {
+            map.entry(head.as_str()).or_default().insert(0, "HEAD");
+        }
+        map
+    }
+
+    /// Returns a map from commit ID to its decoration with
+    /// `--decorate-interesting`: `HEAD` and the names of the interesting
+    /// branches pointing to it, separated by commas, and colored like git's if
+    /// `color` is true.
+    fn decorations(&self, color: bool) -> HashMap<&str, String> {
+        let mut map: HashMap<_, String> = HashMap::new();
+        let head = self.head.iter().map(|id| (id, "HEAD", "1;36"));
+        let branches = self.branches.iter().map(|branch| {
+            let remote = branch.refname.starts_with("refs/remotes/");
+            (&branch.id, branch.name.as_str(), if remote { "1;31" } else { "1;32" })
+        });
+        for (id, name, code) in head.chain(branches) {
+            let refs = map.entry(id.as_str()).or_default();
+            if !refs.is_empty() {
+                refs.push_str(", ");
+            }
+            if color {
+                refs.push_str(&Paint(code, name).to_string());
+            } else {
+                refs.push_str(name);
             }
-            NodeState::VisibleChild => includes.push(String::from_utf8(id).expect("non-utf-8 id")),
-            NodeState::VisibleParent => {}
         }
+        map
+    }
+
+    /// Returns the `git log` options that decorate exactly HEAD and the
+    /// interesting branches, overriding git's decoration settings (such as
+    /// `log.excludeDecoration`) but not decoration options given by the user.
+    fn decorate_refs(&self) -> Vec<String> {
+        let prefix = namespace_prefix().unwrap_or_default();
+        let mut args = vec!["--decorate".to_owned(), "--decorate-refs=HEAD".to_owned()];
+        let branches = self.branches.iter();
+        args.extend(branches.map(|branch| format!("--decorate-refs={prefix}{}", branch.refname)));
+        args
+    }
+
+    /// Returns the IDs of the commits that must stay visible: HEAD, the
+    /// branch tips, the reflog entries, and the merge bases.
+    fn landmarks(&self) -> HashSet<&str> {
+        self.head
+            .iter()
+            .chain(self.branches.iter().map(|branch| &branch.id))
+            .chain(&self.reflog)
+            .chain(&self.merge_bases)
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Returns the revision arguments that make `git log` (or `git rev-list`)
+    /// list exactly the displayed commits.
+    fn revisions(&self) -> Vec<String> {
+        let mut revisions = vec![];
+        if self.first_parent {
+            revisions.push("--first-parent".into());
+        }
+        revisions.extend(self.includes.iter().cloned());
+        revisions.push("--not".into());
+        revisions.extend(self.negatives());
+        revisions
+    }
+
+    /// Returns the commits whose ancestors are not displayed, to exclude after
+    /// the includes.
+    fn negatives(&self) -> Vec<String> {
+        // Without merge bases, the includes are the interesting commits, and
+        // only they are displayed.
+        let bases = if self.boundaries.is_empty() { &self.includes } else { &self.boundaries };
+        let mut negatives: Vec<_> = bases.iter().map(|id| format!("{id}^@")).collect();
+        negatives.extend(self.excludes.iter().cloned());
+        negatives
     }
-    drop(nodes);
-    let status = git.wait().expect("failed to wait for git");
-    assert!(status.success(), "git returned unsuccessful status {status}");
-    (includes, excludes)
 }
 
-fn main() {
+/// Loads the displayed commits with `text_format` (see `graph::load`),
+/// hiding merges or other commits, keeping only the matches of `--find`,
+/// reducing them to the branch skeleton, and collapsing linear runs if
+/// requested.
+fn load_commits(
+    view: &View,
+    options: &Options,
+    text_format: &str,
+    color: bool,
+) -> io::Result<Vec<Commit>> {
+    let mut commits = graph::load(&view.revisions(), Some(text_format), color)?;
+    if let Some(priority) = options.priority_date {
+        commits = graph::sort_by_date(commits, priority);
+    }
+    if let Some(filter) = options.merges {
+        commits = graph::filter_merges(commits, &view.landmarks(), filter);
+    }
+    if let Some(pattern) = options.find.as_deref().filter(|_| options.find_only) {
+        let found = find::matches(&view.revisions(), pattern)?;
+        commits = find::only(commits, &found, &view.landmarks());
+    }
+    if options.decorations_only {
+        let decorated = skeleton::decorated()?;
+        let mut keep = view.landmarks();
+        keep.extend(decorated.iter().map(String::as_str));
+        commits = skeleton::simplify(commits, &keep);
+    }
+    if let Some(max_run) = options.collapse {
+        commits = graph::collapse(commits, &view.landmarks(), max_run);
+    }
+    if let Some(preview) = preview::get() {
+        commits.insert(0, preview.commit());
+    }
+    Ok(commits)
+}
+
+/// Loads the Gerrit changes of `commits` if `options.gerrit` is set,
+/// fetching their patchsets first if `options.gerrit_fetch` is set.
+fn load_changes(
+    view: &View,
+    options: &Options,
+    commits: &[Commit],
+) -> io::Result<Option<gerrit::Changes>> {
+    if !options.gerrit {
+        return Ok(None);
+    }
+    if let Some(remote) = &options.gerrit_fetch {
+        gerrit::fetch(remote, commits)?;
+    }
+    gerrit::Changes::load(&view.revisions(), commits).map(Some)
+}
+
+/// Appends ` [<note>]` to the first line of `text`.
+fn annotate(text: &mut String, note: &str) {
+    let end = text.find('\n').unwrap_or(text.len());
+    text.insert_str(end, &format!(" [{note}]"));
+}
+
+/// Opens the output file `path` (or stdout, if `None`).
+fn create_output(path: Option<&Path>) -> io::Result<BufWriter<Box<dyn Write>>> {
+    let out: Box<dyn Write> = match path {
+        Some(path) => Box::new(File::create(path).map_err(|error| {
+            io::Error::new(error.kind(), format!("{}: {error}", path.display()))
+        })?),
+        None => Box::new(stdout().lock()),
+    };
+    Ok(BufWriter::new(out))
+}
+
+/// Calls `write` with stdout, piped through the pager if `pager` is true (see
+/// `pager::spawn`), and waits for the pager to exit.
+fn with_pager<F>(pager: bool, write: F) -> io::Result<()>
+where
+    F: FnOnce(BufWriter<Box<dyn Write>>) -> io::Result<()>,
+{
+    let Some(mut child) = pager.then(pager::spawn).flatten() else {
+        return write(BufWriter::new(Box::new(stdout().lock())));
+    };
+    let stdin = child.stdin.take().ok_or_else(|| io::Error::other("pager has no stdin"))?;
+    let result = write(BufWriter::new(Box::new(stdin)));
+    child.wait()?;
+    // Quitting the pager early is not an error.
+    result.or_else(|error| if error.kind() == ErrorKind::BrokenPipe { Ok(()) } else { Err(error) })
+}
+
+/// Writes the displayed commits to `options.output` (or stdout) in the
+/// `export` format.
+fn export(view: &View, options: &Options, export: Export) -> io::Result<()> {
+    let out = create_output(options.output.as_deref())?;
+    let link_template = link::template(options.link_template.as_deref());
+    match export {
+        Export::Html => {
+            let commits = load_commits(view, options, html::tooltip_format(), false)?;
+            html::write(
+                &commits,
+                &view.branches_by_id(),
+                view.head.as_deref(),
+                link_template.as_deref(),
+                Issues::load(&commits)?.as_ref(),
+                out,
+            )
+        }
+        Export::Svg => svg::write(
+            &load_commits(view, options, "", false)?,
+            &view.branches_by_id(),
+            view.head.as_deref(),
+            &view.merge_bases,
+            link_template.as_deref(),
+            out,
+        ),
+        Export::GraphMl => {
+            graphml::write(&load_commits(view, options, "", false)?, &view.refs_by_id(), out)
+        }
+        Export::Porcelain => porcelain::write(
+            view.head.as_deref(),
+            &view.branches,
+            &view.merge_bases,
+            &load_commits(view, options, "", false)?,
+            &trailers::load(&options.trailers, &view.revisions())?,
+            options.nul,
+            out,
+        ),
+        Export::JsonEdges => {
+            let commits = load_commits(view, options, "", false)?;
+            let pull_requests = options.github.then(|| github::pull_requests(&view.branches));
+            let changes = load_changes(view, options, &commits)?;
+            let statuses = options.ci_source().map(|source| ci::statuses(&source, &view.branches));
+            let equivalents =
+                options.cherry_mark.then(|| cherry::equivalents(&commits)).transpose()?;
+            let forks =
+                options.fork_points.then(|| fork::fork_points(&view.branches)).transpose()?;
+            let found = options
+                .find
+                .as_deref()
+                .map(|pattern| find::matches(&view.revisions(), pattern))
+                .transpose()?;
+            let bisect = Bisect::load();
+            let plan = rebase_todo::Plan::load();
+            let diffstats =
+                options.diffstat.then(|| diffstat::diffstats(&view.branches)).transpose()?;
+            let trailers = trailers::load(&options.trailers, &view.revisions())?;
+            let notes = (!options.notes.is_empty())
+                .then(|| notes::load(&options.notes, &view.revisions()))
+                .transpose()?;
+            let annotations = json_edges::Annotations {
+                pull_requests: pull_requests.as_ref(),
+                changes: changes.as_ref(),
+                statuses: statuses.as_ref(),
+                equivalents: equivalents.as_ref(),
+                forks: forks.as_ref(),
+                now: options.color_by_age.then(date::now),
+                found: found.as_ref(),
+                bisect: bisect.as_ref(),
+                plan: plan.as_ref(),
+                preview: preview::get(),
+                diffstats: diffstats.as_deref(),
+                trailers: (!options.trailers.is_empty()).then_some(&trailers),
+                notes: notes.as_ref(),
+            };
+            json_edges::write(&commits, &view.refs_by_id(), &annotations, out)
+        }
+    }
+}
+
+/// Adds the refs of the bisection in progress to `branches` (`--bisect`), or
+/// warns that there is none.
+fn add_bisect_refs(branches: &mut Vec<Branch>) {
+    let Some(bisect) = Bisect::load() else {
+        warn(&tr!("warning: --bisect: no bisection is in progress"));
+        return;
+    };
+    add_branches(branches, bisect.refs().to_vec());
+}
+
+/// Adds `more` to `branches`, keeping them sorted and without duplicates.
+fn add_branches(branches: &mut Vec<Branch>, more: Vec<Branch>) {
+    branches.extend(more);
+    branches.sort_unstable_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+    branches.dedup_by(|a, b| a.sort_key() == b.sort_key());
+}
+
+/// Computes the commits to display and displays them, either with the native
+/// renderer or by running `git log` with `options.log_args` and
+/// `options.pathspecs`, or exports them.
+/// If `pager` is false, git's pager is disabled.
+fn show(options: &Options, pager: bool) -> Result<(), Failure> {
+    let mut timing = Timing::new(options.timing);
+    let result = show_timed(options, pager, &mut timing);
+    timing.record("output", None);
+    timing.report()?;
+    result
+}
+
+/// Does the work of `show`, recording each stage but the output in `timing`.
+fn show_timed(options: &Options, pager: bool, timing: &mut Timing) -> Result<(), Failure> {
     // Capacity estimate is a guess -- 4x as large as a SHA-256 hash seems
     // reasonable (and is a power of two).
     let mut buffer = Vec::with_capacity(256);
-    let interesting_branches = interesting_branches(&mut buffer);
-    let merge_bases = merge_bases(&mut buffer, &interesting_branches);
-    let (includes, excludes) = includes_excludes(buffer, interesting_branches, &merge_bases);
-    Command::new("git")
-        .arg("log")
-        .args(args_os().skip(1))
-        .args(includes)
-        .arg("--not")
-        .args(merge_bases.into_iter().map(|mut id| {
-            id.push_str("^@");
-            id
-        }))
-        .args(excludes)
-        .spawn()
-        .expect("Failed to run git")
-        .wait()
-        .expect("failed to wait for git");
+    error_format::stage("refs");
+    let (mut branches, head) = interesting_branches(&mut buffer)?;
+    if options.mine_only {
+        branches = mine_only::without_upstreams(branches)?;
+    }
+    if options.bisect {
+        add_bisect_refs(&mut branches);
+    }
+    if let Some(count) = options.extra_refs {
+        add_branches(&mut branches, extra_refs::load(count)?);
+    }
+    if !options.preview_merge.is_empty() {
+        let joined = preview::prepare(&options.preview_merge)?;
+        add_branches(&mut branches, joined);
+    }
+    timing.record("refs", Some((branches.len(), "branches")));
+    if branches.is_empty() && head.is_none() {
+        warn(&tr!("repository has no commits yet"));
+        return Ok(());
+    }
+    error_format::stage("traversal");
+    let view = View::compute(
+        buffer,
+        branches,
+        head,
+        options.reflog,
+        options.first_parent,
+        options.context,
+        timing,
+    )?;
+    let old_tips = if options.since_last { since_last(&view)? } else { vec![] };
+    error_format::stage("output");
+    let result = show_view(&view, options, pager, &old_tips);
+    // After the output, so that the pager does not hide the warning.
+    let Some(threshold) = options.warn_behind.or_else(behind::configured) else { return result };
+    let stale = behind::stale(&view, threshold)?;
+    behind::warn_stale(&stale, threshold);
+    if result.is_ok() && options.warn_behind.is_some() && !stale.is_empty() {
+        return Err(Failure::Check);
+    }
+    result
+}
+
+/// Shows `view` as `options` say, with the commits new since `old_tips` (see
+/// `--since-last`).
+fn show_view(
+    view: &View,
+    options: &Options,
+    pager: bool,
+    old_tips: &[String],
+) -> Result<(), Failure> {
+    if let Some(format) = options.export {
+        return export(view, options, format).map_err(Failure::Io);
+    }
+    if options.pick {
+        let commits = graph::load(&view.revisions(), Some("%h%d %s"), false)?;
+        let items: Vec<_> = commits.into_iter().map(|commit| (commit.id, commit.text)).collect();
+        let mut out = stdout().lock();
+        for id in pick::pick(&items)? {
+            writeln!(out, "{id}")?;
+        }
+        return Ok(());
+    }
+    if options.group_by_issue {
+        return group_by_issue(view, options).map_err(Failure::Io);
+    }
+    if options.render == Render::Native {
+        return show_native(view, options, pager, old_tips).map_err(Failure::Io);
+    }
+    let mut git = Command::new("git");
+    if !pager {
+        git.arg("--no-pager");
+    }
+    git.arg("log");
+    if !options.mailmap {
+        git.arg("--no-use-mailmap");
+    }
+    if options.decorate_interesting {
+        git.args(view.decorate_refs());
+    }
+    git.args(options.priority_date.and_then(DatePriority::git_option));
+    // The revisions end with the excluded ones, after --not.
+    git.args(&options.log_args).args(view.revisions()).args(old_tips);
+    let status = git.arg("--").args(&options.pathspecs).logged().spawn()?.wait()?;
+    // git log dies from SIGPIPE when its output is closed early (without the
+    // pager, e.g. `git tree --no-pager | head`). Report that like our own
+    // writes to a closed pipe.
+    #[cfg(unix)]
+    if status.signal() == Some(SIGPIPE) {
+        return Err(Failure::Io(ErrorKind::BrokenPipe.into()));
+    }
+    if status.success() {
+        return Ok(());
+    }
+    // Killed by a signal: exit with the status the shell would report.
+    #[cfg(unix)]
+    let code = status.code().or_else(|| SIGNAL_STATUS.checked_add(status.signal()?));
+    #[cfg(not(unix))]
+    let code = status.code();
+    Err(Failure::GitLog(code.unwrap_or(FAILURE_STATUS)))
+}
+
+/// Lists the interesting branches of `view` touching each issue
+/// (`--group-by-issue`).
+fn group_by_issue(view: &View, options: &Options) -> io::Result<()> {
+    let commits = load_commits(view, options, "", false)?;
+    let issues = Issues::load(&commits)?.ok_or_else(|| {
+        io::Error::other(tr!("--group-by-issue requires the gitxl.issuePattern setting"))
+    })?;
+    let color = options.color.stdout() && style::enable_escapes();
+    issues::write_groups(view, &commits, &issues, color, stdout().lock())
+}
+
+/// Reports on stderr how HEAD and the interesting branches moved since the
+/// previous `--since-last` run, and records them for the next one. Returns the
+/// commits they pointed to then (those that still exist), whose history has
+/// been seen.
+fn since_last(view: &View) -> io::Result<Vec<String>> {
+    let mut snapshot: last_view::Snapshot =
+        view.branches.iter().map(|branch| (branch.name.clone(), branch.id.clone())).collect();
+    if let Some(head) = &view.head {
+        snapshot.insert("HEAD".into(), head.clone());
+    }
+    let Some(path) = last_view::path() else { return Ok(vec![]) };
+    let old = last_view::load(&path);
+    last_view::store(&path, &snapshot);
+    let Some(old) = old else {
+        warn(&tr!("no previous --since-last run; showing everything"));
+        return Ok(vec![]);
+    };
+    let mut err = stderr().lock();
+    let changes = last_view::changes(&old, &snapshot);
+    if changes.is_empty() {
+        writeln!(err, "git-tree: {}", tr!("nothing changed since the last run"))?;
+    }
+    for change in changes {
+        writeln!(err, "git-tree: {change}")?;
+    }
+    let mut old_tips: Vec<_> = old.into_values().filter(|id| rev_parse(id).is_some()).collect();
+    old_tips.sort_unstable();
+    old_tips.dedup();
+    Ok(old_tips)
+}
+
+/// Makes the ID of `commit` a terminal hyperlink to the link `template`
+/// produces, if any, and the issues it mentions hyperlinks to their tracker.
+fn hyperlink(commit: &mut Commit, template: Option<&str>, issues: Option<&Issues>) {
+    let real = commit.collapsed == 0 && commit.id != preview::ID;
+    if let Some(template) = template.filter(|_| real) {
+        link::hyperlink_id(&mut commit.text, &commit.id, &link::expand(template, &commit.id));
+    }
+    if let Some(issues) = issues {
+        issues.hyperlink(commit);
+    }
+}
+
+/// Shows `view` with the native renderer, annotating the commits new since
+/// `old_tips` (see `--since-last`).
+fn show_native(view: &View, options: &Options, pager: bool, old_tips: &[String]) -> io::Result<()> {
+    let color = options.color.stdout() && style::enable_escapes();
+    let text_format = options.text_format().map_err(io::Error::other)?;
+    let mut commits = load_commits(view, options, &text_format, color)?;
+    let tips = view.branches_by_id();
+    let changes = load_changes(view, options, &commits)?;
+    // Hyperlinks are terminal styling, like color.
+    let link_template = link::template(options.link_template.as_deref()).filter(|_| color);
+    let statuses =
+        options.ci_source().map(|source| ci::statuses(&source, &view.branches)).unwrap_or_default();
+    let pull_requests =
+        if options.github { github::pull_requests(&view.branches) } else { HashMap::new() };
+    let decorations =
+        if options.decorate_interesting { view.decorations(color) } else { HashMap::new() };
+    let new = new_commits(view, old_tips)?;
+    let equivalents =
+        if options.cherry_mark { cherry::equivalents(&commits)? } else { HashMap::new() };
+    let forks =
+        if options.fork_points { fork::fork_points(&view.branches)? } else { HashMap::new() };
+    let palette = options.color_by_age.then(age::Palette::load).filter(|_| color);
+    let now = date::now();
+    let markers =
+        options.icons.map(|icons| icons::Markers::load(view, Icons::choose(icons, options.ascii)));
+    let found = options
+        .find
+        .as_deref()
+        .map(|pattern| find::matches(&view.revisions(), pattern))
+        .transpose()?;
+    let bisect = Bisect::load();
+    let plan = rebase_todo::Plan::load();
+    let trailers = trailers::load(&options.trailers, &view.revisions())?;
+    let issues = if color { Issues::load(&commits)? } else { None };
+    let notes = notes::load(&options.notes, &view.revisions())?;
+    for commit in &mut commits {
+        let facts = template::Facts {
+            tip: tips.contains_key(commit.id.as_str()),
+            base: view.merge_bases.contains(&commit.id),
+            head: view.head.as_ref() == Some(&commit.id),
+            refs: decorations.get(commit.id.as_str()).map_or("", String::as_str),
+        };
+        commit.text = template::expand(&commit.text, &facts);
+        hyperlink(commit, link_template.as_deref(), issues.as_ref());
+        if let Some(palette) = palette.as_ref().filter(|_| commit.collapsed == 0) {
+            palette.paint(age::Bucket::of(commit.committer_time, now), &mut commit.text);
+        }
+        if let Some(markers) = &markers {
+            markers.mark(commit);
+        }
+        if found.as_ref().is_some_and(|found| found.contains(&commit.id)) {
+            find::highlight(commit, color);
+        }
+        if let Some(pulls) = pull_requests.get(&commit.id) {
+            let labels: Vec<_> = pulls.iter().map(github::PullRequest::label).collect();
+            annotate(&mut commit.text, &labels.join(", "));
+        }
+        if let Some(&status) = statuses.get(&commit.id) {
+            let note = format!("CI {}", status.name());
+            if color {
+                annotate(&mut commit.text, &Paint(status.color(), note).to_string());
+            } else {
+                annotate(&mut commit.text, &note);
+            }
+        }
+        if let Some(label) = changes.as_ref().and_then(|changes| changes.label(&commit.id)) {
+            annotate(&mut commit.text, &label);
+        }
+        if view.shallow.contains(&commit.id) {
+            annotate(&mut commit.text, "shallow boundary");
+        }
+        if view.reflog.contains(&commit.id) {
+            annotate(&mut commit.text, "reflog");
+        }
+        if let Some(others) = equivalents.get(&commit.id) {
+            annotate(&mut commit.text, &cherry::label(others));
+        }
+        for fork in forks.get(&commit.id).into_iter().flatten() {
+            annotate(&mut commit.text, &fork.label(commit.committer_time));
+        }
+        if let Some(label) = bisect.as_ref().and_then(|bisect| bisect.label(&commit.id)) {
+            annotate(&mut commit.text, &label);
+        }
+        if let Some(label) = plan.as_ref().and_then(|plan| plan.label(&commit.id)) {
+            annotate(&mut commit.text, &label);
+        }
+        if let Some(listed) = trailers.get(&commit.id) {
+            for label in options.trailers.iter().filter_map(|key| trailers::label(listed, key)) {
+                annotate(&mut commit.text, &label);
+            }
+        }
+        if new.contains(&commit.id) {
+            annotate(&mut commit.text, "new");
+        }
+        if let Some(note) = notes.get(&commit.id) {
+            notes::append(&mut commit.text, note);
+        }
+    }
+    let style = render::Style { ascii: options.ascii, color, columns: width::columns() };
+    with_pager(pager, |out| render::render(&commits, style, out))
+}
+
+/// Returns the displayed commits not reachable from `old_tips` (see
+/// `since_last`), or none if there are no old tips to compare with.
+fn new_commits(view: &View, old_tips: &[String]) -> io::Result<HashSet<String>> {
+    if old_tips.is_empty() {
+        return Ok(HashSet::new());
+    }
+    // The revisions end with the excluded ones, after --not.
+    let mut revisions = view.revisions();
+    revisions.extend(old_tips.iter().cloned());
+    Ok(graph::load(&revisions, None, false)?.into_iter().map(|commit| commit.id).collect())
+}
+
+/// The number of the SIGPIPE signal.
+#[cfg(unix)]
+const SIGPIPE: i32 = 13;
+
+/// The shell's status for a process killed by a signal, less the signal's
+/// number.
+#[cfg(unix)]
+const SIGNAL_STATUS: i32 = 128;
+
+/// The status to exit with when git-tree fails, e.g. to write its output.
+const FAILURE_STATUS: i32 = 1;
+
+/// The status to exit with when the arguments are invalid.
+const USAGE_STATUS: i32 = 2;
+
+/// The status to exit with when the output is closed early (e.g. by `head` or
+/// by quitting the pager): the shell's status for a process killed by SIGPIPE.
+const BROKEN_PIPE_STATUS: i32 = 141;
+
+/// Why `show` or a subcommand failed.
+enum Failure {
+    /// git-tree failed, e.g. to write its output.
+    Io(io::Error),
+    /// git log exited with this unsuccessful status, having reported why.
+    GitLog(i32),
+    /// `git-tree check` found a branch over a threshold, and reported it.
+    Check,
+}
+
+impl From<io::Error> for Failure {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Prints the error, if any, of a call to `show` or a subcommand, and returns
+/// the status to exit with if there was one. A closed output is not reported,
+/// as it is not a failure of git-tree's, and git log reports its own errors.
+fn error_status(result: Result<(), Failure>) -> Option<i32> {
+    match result.err()? {
+        Failure::GitLog(status) => {
+            error_format::report(Code::Git, &format!("git log exited with status {status}"), false);
+            Some(status)
+        }
+        Failure::Check => {
+            error_format::report(Code::Check, "a branch is over its threshold", false);
+            Some(FAILURE_STATUS)
+        }
+        Failure::Io(error) if error.kind() == ErrorKind::BrokenPipe => Some(BROKEN_PIPE_STATUS),
+        Failure::Io(error) => {
+            error_format::report(Code::of(&error), &error.to_string(), true);
+            Some(FAILURE_STATUS)
+        }
+    }
+}
+
+/// Whether `-q` was given, which silences `warn`.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Prints a warning or notice to stderr, unless `-q` was given.
+fn warn(message: &str) {
+    if !QUIET.load(Ordering::Relaxed) {
+        drop(writeln!(stderr(), "git-tree: {message}"));
+    }
+}
+
+/// Runs the JSON-RPC server on the given Unix socket, or on stdin/stdout.
+fn serve(socket: Option<&Path>) -> io::Result<()> {
+    #[cfg(unix)]
+    return socket.map_or_else(serve::serve_stdio, serve::serve_socket);
+    #[cfg(not(unix))]
+    if socket.is_some() {
+        Err(io::Error::new(ErrorKind::Unsupported, tr!("--socket is only supported on Unix")))
+    } else {
+        serve::serve_stdio()
+    }
+}
+
+/// Runs `subcommand`, or returns `None` for the log mode, which is not one.
+fn run_subcommand(subcommand: &Subcommand) -> Option<Result<(), Failure>> {
+    error_format::stage(subcommand.name());
+    let result = match subcommand {
+        Subcommand::Log => return None,
+        Subcommand::Serve { socket } => serve(socket.as_deref()),
+        Subcommand::Restack { onto, all, exec } => {
+            restack::restack(stdout().lock(), onto, *all, *exec)
+        }
+        Subcommand::Matrix { list } => matrix::write(stdout().lock(), *list),
+        Subcommand::Check { trunk, thresholds, branches } => {
+            return Some(
+                match check::check(stdout().lock(), trunk, thresholds, branches.as_deref()) {
+                    Ok(true) => Ok(()),
+                    Ok(false) => Err(Failure::Check),
+                    Err(error) => Err(Failure::Io(error)),
+                },
+            );
+        }
+        Subcommand::Files { limit } => files::write(stdout().lock(), *limit),
+        Subcommand::Conflicts { with } => {
+            conflicts::write(stdout().lock(), with.as_deref().unwrap_or(cli::DEFAULT_TRUNK))
+        }
+        Subcommand::FormatPatch { branch, trunk, args } => {
+            format_patch::format_patch(branch, trunk.as_deref(), args)
+        }
+        Subcommand::Bundle { output } => bundle::create(output),
+        Subcommand::Prompt { budget } => {
+            let budget = budget.or_else(prompt::configured_budget);
+            prompt::write(stdout().lock(), budget.unwrap_or(prompt::DEFAULT_BUDGET))
+        }
+        Subcommand::Bench { iterations } => bench::run(stdout().lock(), *iterations),
+        Subcommand::Status { date } => {
+            if let Some(format) = date {
+                date::choose(format);
+            }
+            status::write(stdout().lock())
+        }
+        Subcommand::Authors { mailmap } => {
+            if !mailmap {
+                graph::ignore_mailmap();
+            }
+            authors::write(stdout().lock())
+        }
+        Subcommand::Help { topic, man: false, pager } => {
+            with_pager(*pager, |out| help::write(out, topic.as_deref()))
+        }
+        Subcommand::Help { man: true, .. } => help::write_man(stdout().lock()),
+        Subcommand::Pin { branches, pin } => pins::pin(stdout().lock(), branches, *pin),
+        Subcommand::Unpin { branches } => pins::unpin(branches),
+        Subcommand::Report { output, pager, link_template } => {
+            let link_template = link::template(link_template.as_deref());
+            let write = |out| report::write(out, link_template.as_deref());
+            output.as_deref().map_or_else(
+                || with_pager(*pager, write),
+                |path| create_output(Some(path)).and_then(write),
+            )
+        }
+    };
+    Some(result.map_err(Failure::Io))
+}
+
+fn main() {
+    let options = error_format::take(args_os().skip(1))
+        .and_then(|args| Options::parse(args, default_args::load))
+        .unwrap_or_else(|message| {
+            error_format::report(Code::Usage, &message, true);
+            exit(USAGE_STATUS);
+        });
+    log::init(options.verbosity);
+    QUIET.store(options.quiet, Ordering::Relaxed);
+    if options.no_replace_objects {
+        // Through the environment, so that git-tree's git commands and git log
+        // see the same history.
+        set_var(replace::NO_REPLACE_OBJECTS, "1");
+    }
+    if let Some(namespace) = &options.namespace {
+        set_var("GIT_NAMESPACE", namespace);
+    }
+    if options.full_hashes {
+        abbrev::full_hashes();
+    }
+    if !options.mailmap {
+        graph::ignore_mailmap();
+    }
+    if let Some(format) = &options.date {
+        date::choose(format);
+    }
+    remotes::choose(options.remotes.clone());
+    if let Some(name) = &options.profile {
+        match profile::load(name) {
+            Ok(profile) => profile::activate(profile),
+            Err(message) => {
+                error_format::report(Code::Usage, &message, true);
+                exit(USAGE_STATUS);
+            }
+        }
+    }
+    if let Err(message) = error_format::check_repository() {
+        error_format::report(Code::NotARepository, &message, true);
+        exit(FAILURE_STATUS);
+    }
+    let revisions = if options.stdin_refs {
+        given::read(io::stdin().lock())
+    } else {
+        Ok(options.revisions.clone())
+    };
+    if let Err(message) = revisions.and_then(|revisions| given::choose(&revisions)) {
+        error_format::report(Code::Usage, &message, true);
+        exit(USAGE_STATUS);
+    }
+    if let Some(result) = run_subcommand(&options.subcommand) {
+        if let Some(status) = error_status(result) {
+            exit(status);
+        }
+        return;
+    }
+    if options.watch {
+        // The pager would block re-rendering until the user exits it.
+        // Keep watching after errors, but not once nobody is reading.
+        let watched =
+            watch::watch(|| error_status(show(&options, false)) != Some(BROKEN_PIPE_STATUS));
+        exit(error_status(watched.map_err(Failure::from)).unwrap_or(BROKEN_PIPE_STATUS));
+    }
+    if let Some(status) = error_status(show(&options, options.pager)) {
+        exit(status);
+    }
 }
@@ -0,0 +1,295 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `git-tree matrix`: how far each interesting branch is ahead of and behind
+//! each other one, as a table or as a list of pairs.
+//!
+//! Rather than counting each pair with its own `git rev-list`, this lists the
+//! commits above the merge bases of all the branches once, and works out which
+//! tips reach each commit by pushing a bit set from children to parents. A
+//! commit below the merge bases is reachable from every tip, so it counts for
+//! no pair. Commits reachable from the same tips are counted together, so the
+//! work per commit does not grow with the number of pairs.
+
+use crate::log::Logged as _;
+use crate::{git_status, graph, interesting_branches, merge_bases, quote, trim_line_ending, width};
+use core::str;
+use std::collections::HashMap;
+use std::io::{self, BufRead as _, BufReader, Write};
+use std::process::{Command, Stdio};
+
+/// A set of tips, as bits indexed by tip.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Tips(Vec<u64>);
+
+/// The number of tips per word of `Tips`.
+const WORD_BITS: usize = 64;
+
+impl Tips {
+    /// Returns the empty set of `count` tips.
+    fn none(count: usize) -> Self {
+        Self(vec![0; count.div_ceil(WORD_BITS)])
+    }
+
+    /// Returns whether the set contains tip `index`.
+    #[allow(clippy::unwrap_used, reason = "the divisor is not zero, and a bit index fits a shift")]
+    pub fn contains(&self, index: usize) -> bool {
+        let word = self.0.get(index.checked_div(WORD_BITS).unwrap()).copied().unwrap_or_default();
+        word.checked_shr(index.checked_rem(WORD_BITS).unwrap().try_into().unwrap()).unwrap() & 1
+            == 1
+    }
+
+    /// Returns the tips in the set, in order.
+    pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.0.len().saturating_mul(WORD_BITS)).filter(|&index| self.contains(index))
+    }
+
+    /// Adds tip `index` to the set.
+    #[allow(
+        clippy::unwrap_used,
+        reason = "the set has a word for every tip, and a bit index fits a shift"
+    )]
+    fn insert(&mut self, index: usize) {
+        let word = self.0.get_mut(index.checked_div(WORD_BITS).unwrap()).unwrap();
+        let bit: u64 = 1;
+        *word |=
+            bit.checked_shl(index.checked_rem(WORD_BITS).unwrap().try_into().unwrap()).unwrap();
+    }
+
+    /// Adds the tips of `other`, a set of as many tips, to the set.
+    pub fn union(&mut self, other: &Self) {
+        for (word, &other_word) in self.0.iter_mut().zip(&other.0) {
+            *word |= other_word;
+        }
+    }
+}
+
+/// Works out which tips reach each commit by pushing sets of tips from
+/// children to parents. The commits must be visited with every child before
+/// its parents, as topological order lists them.
+pub struct Reach<'tips, T> {
+    /// The tips, sorted.
+    tips: &'tips [T],
+    /// The merge bases of all the tips.
+    merge_bases: &'tips [String],
+    /// Whether a tip is reached by itself alone, so that a commit is reached
+    /// by the tips reaching it without going through another tip.
+    owners: bool,
+    /// The tips reaching each commit whose children have been visited but
+    /// which has not been visited yet.
+    pending: HashMap<String, Tips>,
+}
+
+impl<'tips, T: AsRef<str>> Reach<'tips, T> {
+    /// Starts with `tips` (sorted), given the `merge_bases` of all of them.
+    pub fn new(tips: &'tips [T], merge_bases: &'tips [String]) -> Self {
+        Self { tips, merge_bases, owners: false, pending: HashMap::new() }
+    }
+
+    /// Like `new`, except that the tips reach a commit only without going
+    /// through another tip, so a tip owns itself alone.
+    pub fn owners(tips: &'tips [T], merge_bases: &'tips [String]) -> Self {
+        Self { owners: true, ..Self::new(tips, merge_bases) }
+    }
+
+    /// Visits the commit `id` with `parents`, and returns the tips reaching
+    /// it, or `None` if it is a merge base: the merge bases are where the
+    /// branches start, not work on them.
+    pub fn visit<'id, P: IntoIterator<Item = &'id str>>(
+        &mut self,
+        id: &str,
+        parents: P,
+    ) -> Option<Tips> {
+        let count = self.tips.len();
+        let mut reach = self.pending.remove(id).unwrap_or_else(|| Tips::none(count));
+        if let Ok(index) = self.tips.binary_search_by(|tip| tip.as_ref().cmp(id)) {
+            if self.owners {
+                reach = Tips::none(count);
+            }
+            reach.insert(index);
+        }
+        for parent in parents {
+            let parent_reach =
+                self.pending.entry(parent.to_owned()).or_insert_with(|| Tips::none(count));
+            parent_reach.union(&reach);
+        }
+        (!self.merge_bases.iter().any(|base| base == id)).then_some(reach)
+    }
+}
+
+/// How far each of a set of tips is ahead of each other one.
+pub struct Counts {
+    /// The tips, sorted and without duplicates.
+    tips: Vec<String>,
+    /// `rows[i][j]` is how far tip `i` is ahead of tip `j`.
+    rows: Vec<Vec<usize>>,
+}
+
+impl Counts {
+    /// Counts how far each of `tips` (in any order) is ahead of each other
+    /// one.
+    /// Precondition: `buffer` must be empty.
+    /// Postcondition: `buffer` will be empty.
+    pub fn new<I: IntoIterator<Item = String>>(buffer: &mut Vec<u8>, tips: I) -> io::Result<Self> {
+        let tips = sorted(tips);
+        let merge_bases = merge_bases(buffer, &tips)?;
+        let rows = rows(buffer, &tips, &merge_bases)?;
+        Ok(Self { tips, rows })
+    }
+
+    /// Like `new`, given the `merge_bases` of all the `tips`.
+    pub fn with_merge_bases<I: IntoIterator<Item = String>>(
+        buffer: &mut Vec<u8>,
+        tips: I,
+        merge_bases: &[String],
+    ) -> io::Result<Self> {
+        let tips = sorted(tips);
+        let rows = rows(buffer, &tips, merge_bases)?;
+        Ok(Self { tips, rows })
+    }
+
+    /// Returns how many commits `a` has that `b` does not, or 0 if either is
+    /// not one of the tips.
+    pub fn ahead(&self, a: &str, b: &str) -> usize {
+        let tip = |id: &str| self.tips.binary_search_by(|tip| tip.as_str().cmp(id)).ok();
+        let count = || self.rows.get(tip(a)?)?.get(tip(b)?).copied();
+        count().unwrap_or_default()
+    }
+}
+
+/// Returns `tips` sorted and without duplicates.
+fn sorted<I: IntoIterator<Item = String>>(tips: I) -> Vec<String> {
+    let mut tips: Vec<_> = tips.into_iter().collect();
+    tips.sort_unstable();
+    tips.dedup();
+    tips
+}
+
+/// Returns, for each pair of `tips` (sorted), the number of commits reachable
+/// from the first but not the second: `rows[i][j]` is how far tip `i` is
+/// ahead of tip `j`. `merge_bases` are the merge bases of all of them.
+/// Precondition: `buffer` must be empty.
+/// Postcondition: `buffer` will be empty.
+fn rows(
+    buffer: &mut Vec<u8>,
+    tips: &[String],
+    merge_bases: &[String],
+) -> io::Result<Vec<Vec<usize>>> {
+    let mut git = Command::new("git")
+        .args(["rev-list", "--parents", "--topo-order"])
+        .args(tips)
+        .arg("--not")
+        .args(merge_bases)
+        .env(graph::NO_LAZY_FETCH, "1")
+        .stdout(Stdio::piped())
+        .logged()
+        .spawn()?;
+    let mut walk = Reach::new(tips, merge_bases);
+    // The number of commits reachable from exactly each set of tips.
+    let mut histogram: HashMap<Tips, usize> = HashMap::new();
+    #[allow(clippy::unwrap_used, reason = "stdout is piped")]
+    let mut reader = BufReader::new(git.stdout.as_mut().unwrap());
+    while reader.read_until(b'\n', buffer)? != 0 {
+        let line = str::from_utf8(trim_line_ending(buffer)).map_err(io::Error::other)?;
+        let mut ids = line.split(' ');
+        let id = ids.next().unwrap_or_default();
+        if let Some(reach) = walk.visit(id, ids) {
+            let count = histogram.entry(reach).or_default();
+            *count = count.saturating_add(1);
+        }
+        buffer.clear();
+    }
+    drop(reader);
+    git_status("rev-list", git.wait()?)?;
+    let mut counts: Vec<Vec<usize>> = vec![vec![0; tips.len()]; tips.len()];
+    for (reach, count) in histogram {
+        for (ahead, row) in counts.iter_mut().enumerate().filter(|&(i, _)| reach.contains(i)) {
+            for (behind, cell) in row.iter_mut().enumerate() {
+                if !reach.contains(behind) && behind != ahead {
+                    *cell = cell.saturating_add(count);
+                }
+            }
+        }
+    }
+    Ok(counts)
+}
+
+/// Writes the matrix of the interesting branches, as a table, or as a list of
+/// pairs if `list` is true.
+pub fn write<W: Write>(mut out: W, list: bool) -> io::Result<()> {
+    let mut buffer = Vec::with_capacity(256);
+    let (branches, _) = interesting_branches(&mut buffer)?;
+    if branches.is_empty() {
+        return Ok(());
+    }
+    let counts = Counts::new(&mut buffer, branches.iter().map(|branch| branch.id.clone()))?;
+    let names: Vec<_> = branches.iter().map(|branch| quote::name(&branch.name)).collect();
+    // How far the branch `a` is ahead of the branch `b`.
+    let ahead = |a: usize, b: usize| {
+        let (Some(a), Some(b)) = (branches.get(a), branches.get(b)) else { return 0 };
+        counts.ahead(&a.id, &b.id)
+    };
+    if list {
+        for (a, first) in names.iter().enumerate() {
+            for (b, second) in names.iter().enumerate().skip(a.saturating_add(1)) {
+                let (ahead, behind) = (ahead(a, b), ahead(b, a));
+                let summary =
+                    tr!("ahead {$ahead}, behind {$behind}", ahead = ahead, behind = behind);
+                let relation = match (ahead, behind) {
+                    (0, 0) => Some(tr!("same commit")),
+                    (_, 0) => Some(tr!("contains {$branch}", branch = second)),
+                    (0, _) => Some(tr!("contained in {$branch}", branch = second)),
+                    _ => None,
+                };
+                match relation {
+                    Some(relation) => writeln!(out, "{first}...{second}: {summary}, {relation}")?,
+                    None => writeln!(out, "{first}...{second}: {summary}")?,
+                }
+            }
+        }
+        return Ok(());
+    }
+    // Each cell is how far the row's branch is ahead and behind the column's.
+    let cell = |a: usize, b: usize| {
+        if a == b {
+            "-".to_owned()
+        } else {
+            format!("+{} -{}", ahead(a, b), ahead(b, a))
+        }
+    };
+    // Padding counts columns, which a name outside ASCII may have fewer (or,
+    // in CJK, more) of than bytes when it is not quoted.
+    let name_width = names.iter().map(|name| width::width(name)).max().unwrap_or_default();
+    let widths: Vec<_> = names
+        .iter()
+        .enumerate()
+        .map(|(b, name)| {
+            let cells = (0..names.len()).map(|a| cell(a, b).len());
+            cells.chain([width::width(name)]).max().unwrap_or_default()
+        })
+        .collect();
+    write!(out, "{:name_width$}", "")?;
+    for (name, &column_width) in names.iter().zip(&widths) {
+        write!(out, "  {}", width::pad_left(name, column_width))?;
+    }
+    writeln!(out)?;
+    for (a, name) in names.iter().enumerate() {
+        write!(out, "{}", width::pad(name, name_width))?;
+        for (b, width) in widths.iter().enumerate() {
+            write!(out, "  {:>width$}", cell(a, b))?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,137 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Translations of the messages git-tree prints itself: its warnings, errors,
+//! and summaries. Output meant for programs (the graph, the porcelain and
+//! export formats, JSON) is never translated, nor are the `--timing`, `bench`,
+//! and `-v` diagnostics, which are meant for bug reports.
+//!
+//! The language is that of the first of `LC_ALL`, `LC_MESSAGES`, and `LANG`
+//! that is set, as for git itself; English is the fallback for the C locale
+//! and for languages without a catalog. Catalogs are gettext-style `.po` files
+//! in `locales/`, compiled into the binary, mapping each English message (the
+//! `msgid`) to its translation (the `msgstr`). Messages name their arguments
+//! with Fluent-style `{$name}` placeholders, which the translation may reorder
+//! but must keep.
+
+use core::fmt::Display;
+use core::mem::take;
+use std::collections::HashMap;
+use std::env::var;
+use std::sync::OnceLock;
+
+/// The catalogs, by language.
+const CATALOGS: [(&str, &str); 1] = [("de", include_str!("../locales/de.po"))];
+
+/// The chosen language's translations, by English message.
+static CATALOG: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Returns `msgid` translated, with each `{$name}` placeholder replaced by the
+/// argument of that name. Use `tr!` rather than calling this directly.
+pub fn format(msgid: &str, args: &[(&str, &dyn Display)]) -> String {
+    let catalog = CATALOG.get_or_init(|| {
+        language()
+            .and_then(|language| CATALOGS.iter().find(|&&(name, _)| name == language))
+            .map_or_else(HashMap::new, |&(_, po)| parse(po))
+    });
+    let template = catalog.get(msgid).map_or(msgid, String::as_str);
+    let mut message = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some((before, after)) = rest.split_once("{$") {
+        message.push_str(before);
+        let argument = after.split_once('}').and_then(|(name, end)| {
+            args.iter().find(|&&(arg, _)| arg == name).map(|&(_, value)| (value, end))
+        });
+        if let Some((value, end)) = argument {
+            message.push_str(&value.to_string());
+            rest = end;
+        } else {
+            message.push_str("{$");
+            rest = after;
+        }
+    }
+    message.push_str(rest);
+    message
+}
+
+/// Translates a message, replacing its `{$name}` placeholders with the named
+/// arguments: `tr!("unknown trunk {$trunk}", trunk = name)`.
+macro_rules! tr {
+    ($msgid:literal $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::messages::format(
+            $msgid,
+            &[$((stringify!($name), &$value as &dyn ::core::fmt::Display)),*],
+        )
+    };
+}
+
+/// Returns the language of the messages locale, such as `de` for
+/// `de_DE.UTF-8`, or none for the C locale.
+fn language() -> Option<String> {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .find_map(|name| var(name).ok().filter(|value| !value.is_empty()))?;
+    let language = locale.split(['_', '.', '@']).next().unwrap_or_default();
+    (language != "C" && language != "POSIX").then(|| language.to_owned())
+}
+
+/// Parses a `.po` catalog: `msgid` and `msgstr` entries, each a string that
+/// may continue on the following lines, and `#` comments. Untranslated
+/// entries, with an empty `msgstr`, and the header are left out.
+fn parse(po: &str) -> HashMap<String, String> {
+    let mut catalog = HashMap::new();
+    let (mut msgid, mut msgstr) = (String::new(), None::<String>);
+    let mut finish = |id: &mut String, translation: Option<String>| {
+        if let Some(text) = translation.filter(|text| !id.is_empty() && !text.is_empty()) {
+            catalog.insert(take(id), text);
+        }
+        id.clear();
+    };
+    for line in po.lines().map(str::trim) {
+        if let Some(string) = line.strip_prefix("msgid ") {
+            finish(&mut msgid, msgstr.take());
+            msgid = unquote(string);
+        } else if let Some(string) = line.strip_prefix("msgstr ") {
+            msgstr = Some(unquote(string));
+        } else if line.starts_with('"') {
+            match msgstr.as_mut() {
+                Some(text) => text.push_str(&unquote(line)),
+                None => msgid.push_str(&unquote(line)),
+            }
+        }
+    }
+    finish(&mut msgid, msgstr);
+    catalog
+}
+
+/// Returns the contents of a quoted `.po` string, with its `\"`, `\\`, `\n`,
+/// and `\t` escapes replaced.
+fn unquote(string: &str) -> String {
+    let string = string.strip_prefix('"').and_then(|string| string.strip_suffix('"'));
+    let mut unquoted = String::new();
+    let mut chars = string.unwrap_or_default().chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unquoted.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unquoted.push('\n'),
+            Some('t') => unquoted.push('\t'),
+            Some(escaped) => unquoted.push(escaped),
+            None => {}
+        }
+    }
+    unquoted
+}
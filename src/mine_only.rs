@@ -0,0 +1,47 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--mine-only`: the view of one's own work, without the commits other people
+//! pushed to the upstreams of the local branches.
+//!
+//! The remote branches that are the upstream of a local branch are not treated
+//! as interesting, so the commits only they reach (those a local branch is
+//! behind by) are not displayed. Upstreams that are local branches are kept,
+//! as their commits are one's own.
+
+use crate::log::Logged as _;
+use crate::{git_status, graph, namespace_prefix, Branch};
+use std::collections::HashSet;
+use std::io;
+use std::process::Command;
+
+/// Returns `branches` without the remote branches that are the upstream of a
+/// local branch.
+pub fn without_upstreams(branches: Vec<Branch>) -> io::Result<Vec<Branch>> {
+    let prefix = namespace_prefix().unwrap_or_default();
+    let output = Command::new("git")
+        .args(["for-each-ref", "--format=%(upstream)"])
+        .arg(format!("{prefix}refs/heads"))
+        .env(graph::NO_LAZY_FETCH, "1")
+        .logged()
+        .output()?;
+    git_status("for-each-ref", output.status)?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let upstreams: HashSet<_> = stdout
+        .lines()
+        .map(|upstream| upstream.strip_prefix(prefix.as_str()).unwrap_or(upstream))
+        .filter(|upstream| upstream.starts_with("refs/remotes/"))
+        .collect();
+    Ok(branches.into_iter().filter(|branch| !upstreams.contains(branch.refname.as_str())).collect())
+}
@@ -0,0 +1,67 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--notes[=<ref>]`: the notes of the displayed commits (see
+//! git-notes(1)), e.g. code review notes in `refs/notes/review`.
+//!
+//! The options are passed to `git log` as they are. For the native graph and
+//! the JSON output, the notes are read with `git log` too (`rev-list` cannot
+//! show notes), so they are the ones it would show: `--notes=<ref>` alone
+//! shows only that ref's, and `--notes` adds the default ones
+//! (`core.notesRef` and `notes.displayRef`).
+
+use crate::log::Logged as _;
+use crate::{git_status, graph};
+use std::collections::HashMap;
+use std::io;
+use std::process::Command;
+
+/// Returns the notes of the commits listed by `revisions` (see
+/// `View::revisions`) that have any, by commit ID. `options` are the
+/// `--notes` options choosing them; there are none without any.
+pub fn load(options: &[String], revisions: &[String]) -> io::Result<HashMap<String, String>> {
+    if options.is_empty() {
+        return Ok(HashMap::new());
+    }
+    // Each commit is its ID and its notes, if any, on the following lines.
+    let output = Command::new("git")
+        .args(["log", "-z", "--format=%H%n%N", "--no-show-signature"])
+        .args(options)
+        .args(revisions)
+        .env(graph::NO_LAZY_FETCH, "1")
+        .logged()
+        .output()?;
+    git_status("log", output.status)?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split_terminator('\0')
+        .filter_map(|record| {
+            let (id, notes) = record.split_once('\n')?;
+            let notes = notes.trim_end();
+            (!notes.is_empty()).then(|| (id.to_owned(), notes.to_owned()))
+        })
+        .collect())
+}
+
+/// Appends `notes` to the native graph's `text` of a commit, indented under
+/// a `Notes:` line as `git log` shows them.
+pub fn append(text: &mut String, notes: &str) {
+    text.push_str("\nNotes:");
+    for line in notes.lines() {
+        text.push('\n');
+        if !line.is_empty() {
+            text.push_str("    ");
+            text.push_str(line);
+        }
+    }
+}
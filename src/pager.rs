@@ -0,0 +1,45 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pages the output git-tree writes itself, the way git pages its own output.
+
+use crate::log::Logged as _;
+use std::env::var_os;
+use std::io::{stdout, IsTerminal as _};
+use std::process::{Child, Command, Stdio};
+
+/// Starts the pager git would use (`GIT_PAGER`, `core.pager`, `PAGER`, or
+/// `less`), with git's default `LESS` and `LV` settings. Returns `None` if
+/// stdout is not a terminal, the pager is disabled (set to `cat` or an empty
+/// string), or it fails to start.
+pub fn spawn() -> Option<Child> {
+    if !stdout().is_terminal() {
+        return None;
+    }
+    let output = Command::new("git").args(["var", "GIT_PAGER"]).logged().output().ok()?;
+    let pager = String::from_utf8(output.stdout).ok()?;
+    let pager = pager.trim();
+    if !output.status.success() || pager.is_empty() || pager == "cat" {
+        return None;
+    }
+    let mut command = Command::new("sh");
+    command.args(["-c", pager]).stdin(Stdio::piped());
+    if var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+    if var_os("LV").is_none() {
+        command.env("LV", "-c");
+    }
+    command.logged().spawn().ok()
+}
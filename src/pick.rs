@@ -0,0 +1,256 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--pick`: a fuzzy picker over the displayed commits, in the style of fzf.
+//!
+//! The picker runs on the terminal (`/dev/tty`), so stdout stays free for the
+//! picked commit IDs, e.g. `git checkout $(git tree --pick)`. Typing filters
+//! the commits: each space-separated word of the query must appear in the
+//! commit's line as a subsequence (case-insensitively, unless the word has
+//! uppercase letters), and tighter matches are listed first. Up/Down (or
+//! Ctrl-P/Ctrl-N) move the cursor, Tab marks commits to pick several, Enter
+//! picks the marked commits (or the one under the cursor), and Esc or Ctrl-C
+//! cancels.
+//!
+//! The terminal is put in non-canonical mode with `stty`, which keeps this
+//! dependency-free.
+
+use crate::log::Logged as _;
+use crate::width;
+use alloc::collections::BTreeSet;
+use core::str;
+use std::fs::{File, OpenOptions};
+use std::io::{self, ErrorKind, Read as _, Write as _};
+use std::process::{Command, Stdio};
+
+/// A key press.
+enum Key {
+    Char(char),
+    Backspace,
+    Up,
+    Down,
+    Toggle,
+    Enter,
+    Cancel,
+}
+
+/// The terminal, in non-canonical mode and on the alternate screen until
+/// dropped.
+struct Terminal {
+    tty: File,
+    /// The `stty` settings to restore.
+    saved: String,
+}
+
+/// Runs `stty` with `args` on `tty`, returning its output.
+fn stty(tty: &File, args: &[&str]) -> io::Result<String> {
+    let output =
+        Command::new("stty").args(args).stdin(Stdio::from(tty.try_clone()?)).logged().output()?;
+    if !output.status.success() {
+        return Err(io::Error::other("stty failed"));
+    }
+    String::from_utf8(output.stdout).map_err(io::Error::other)
+}
+
+impl Terminal {
+    fn open() -> io::Result<Self> {
+        let tty = OpenOptions::new().read(true).write(true).open("/dev/tty").map_err(|error| {
+            io::Error::new(error.kind(), tr!("--pick needs a terminal: {$error}", error = error))
+        })?;
+        let saved = stty(&tty, &["-g"])?.trim_end().to_owned();
+        // Reads return after at most 0.1s, so a lone Esc can be told apart
+        // from an escape sequence.
+        stty(&tty, &["-icanon", "-echo", "-isig", "min", "0", "time", "1"])?;
+        let mut terminal = Self { tty, saved };
+        write!(terminal.tty, "\x1b[?1049h")?;
+        Ok(terminal)
+    }
+
+    /// Returns the terminal's size, as (rows, columns).
+    fn size(&self) -> (usize, usize) {
+        let size = stty(&self.tty, &["size"]).unwrap_or_default();
+        let mut numbers = size.split_whitespace().map(|number| number.parse().unwrap_or_default());
+        let rows = numbers.next().filter(|&rows| rows > 0).unwrap_or(24);
+        let columns = numbers.next().filter(|&columns| columns > 0).unwrap_or(80);
+        (rows, columns)
+    }
+
+    /// Reads a byte, or returns `None` if none arrives in time.
+    fn byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        Ok((self.tty.read(&mut byte)? == 1).then_some(byte[0]))
+    }
+
+    /// Waits for a key press. Returns `None` for keys the picker ignores.
+    fn key(&mut self) -> io::Result<Option<Key>> {
+        let first = loop {
+            if let Some(byte) = self.byte()? {
+                break byte;
+            }
+        };
+        Ok(match first {
+            b'\r' | b'\n' => Some(Key::Enter),
+            b'\t' => Some(Key::Toggle),
+            0x7f | 0x08 => Some(Key::Backspace),
+            0x03 | 0x07 => Some(Key::Cancel),
+            0x10 => Some(Key::Up),
+            0x0e => Some(Key::Down),
+            0x1b => match (self.byte()?, self.byte()?) {
+                (None, _) => Some(Key::Cancel),
+                (Some(b'[' | b'O'), Some(b'A')) => Some(Key::Up),
+                (Some(b'[' | b'O'), Some(b'B')) => Some(Key::Down),
+                _ => None,
+            },
+            0x20..0x7f => Some(Key::Char(char::from(first))),
+            0xc0.. => {
+                let len = if first >= 0xf0 {
+                    4
+                } else if first >= 0xe0 {
+                    3
+                } else {
+                    2
+                };
+                let mut bytes = vec![first];
+                for _ in 1..len {
+                    bytes.extend(self.byte()?);
+                }
+                str::from_utf8(&bytes).ok().and_then(|text| text.chars().next()).map(Key::Char)
+            }
+            _ => None,
+        })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        drop(write!(self.tty, "\x1b[?1049l").and_then(|()| self.tty.flush()));
+        drop(stty(&self.tty, &[&self.saved]));
+    }
+}
+
+/// Returns how well `line` matches `query` (lower is better), or `None` if it
+/// does not match: the total length of the spans each word matches.
+fn score(line: &str, query: &str) -> Option<usize> {
+    let lower = line.to_lowercase();
+    query.split_whitespace().try_fold(0, |total: usize, word| {
+        let haystack = if word.chars().any(char::is_uppercase) { line } else { &lower };
+        let mut chars = haystack.char_indices();
+        let mut start = None;
+        let mut end = 0;
+        for wanted in word.chars() {
+            let (index, _) = chars.find(|&(_, c)| c == wanted)?;
+            start.get_or_insert(index);
+            end = index;
+        }
+        Some(total.saturating_add(end.saturating_sub(start.unwrap_or_default())))
+    })
+}
+
+/// The picker's state.
+struct Picker<'items> {
+    /// The commits: (ID, line shown).
+    items: &'items [(String, String)],
+    query: String,
+    /// The indexes of the items matching `query`, best first.
+    matches: Vec<usize>,
+    /// The cursor's position in `matches`.
+    cursor: usize,
+    marked: BTreeSet<usize>,
+}
+
+impl Picker<'_> {
+    /// Recomputes `matches` after a change to `query`.
+    fn filter(&mut self) {
+        let mut scored: Vec<(usize, usize)> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| Some((score(&item.1, &self.query)?, i)))
+            .collect();
+        // Ties keep the graph's order.
+        scored.sort_by_key(|&(score, _)| score);
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.cursor = self.cursor.min(self.matches.len().saturating_sub(1));
+    }
+
+    /// Draws the picker on `terminal`.
+    fn draw(&self, terminal: &mut Terminal) -> io::Result<()> {
+        let (rows, columns) = terminal.size();
+        let mut screen = vec![];
+        writeln!(screen, "\x1b[H\x1b[2J> {}", self.query)?;
+        write!(screen, "  {}/{}", self.matches.len(), self.items.len())?;
+        // Scroll so the cursor is always visible.
+        let visible = rows.saturating_sub(2).max(1);
+        let first = self.cursor.saturating_add(1).saturating_sub(visible);
+        for (position, &i) in self.matches.iter().enumerate().skip(first).take(visible) {
+            let pointer = if position == self.cursor { '>' } else { ' ' };
+            let mark = if self.marked.contains(&i) { '*' } else { ' ' };
+            let line = self.items.get(i).map(|item| item.1.as_str()).unwrap_or_default();
+            let line = width::truncate(line, columns.saturating_sub(3));
+            write!(screen, "\n{pointer}{mark} {line}")?;
+        }
+        terminal.tty.write_all(&screen)?;
+        terminal.tty.flush()
+    }
+}
+
+/// Lets the user pick among `items`, (commit ID, line shown) pairs, and
+/// returns the IDs of the picked commits in the graph's order. Returns an
+/// `Interrupted` error if the user cancels.
+pub fn pick(items: &[(String, String)]) -> io::Result<Vec<String>> {
+    let mut terminal = Terminal::open()?;
+    let mut picker =
+        Picker { items, query: String::new(), matches: vec![], cursor: 0, marked: BTreeSet::new() };
+    picker.filter();
+    loop {
+        picker.draw(&mut terminal)?;
+        match terminal.key()? {
+            Some(Key::Char(c)) => {
+                picker.query.push(c);
+                picker.filter();
+            }
+            Some(Key::Backspace) => {
+                picker.query.pop();
+                picker.filter();
+            }
+            Some(Key::Up) => picker.cursor = picker.cursor.saturating_sub(1),
+            Some(Key::Down) => {
+                picker.cursor =
+                    picker.cursor.saturating_add(1).min(picker.matches.len().saturating_sub(1));
+            }
+            Some(Key::Toggle) => {
+                if let Some(&i) = picker.matches.get(picker.cursor) {
+                    if !picker.marked.remove(&i) {
+                        picker.marked.insert(i);
+                    }
+                }
+            }
+            Some(Key::Enter) => {
+                let chosen: Vec<usize> = if picker.marked.is_empty() {
+                    picker.matches.get(picker.cursor).copied().into_iter().collect()
+                } else {
+                    picker.marked.iter().copied().collect()
+                };
+                return Ok(chosen
+                    .into_iter()
+                    .filter_map(|i| items.get(i).map(|item| item.0.clone()))
+                    .collect());
+            }
+            Some(Key::Cancel) => {
+                return Err(io::Error::new(ErrorKind::Interrupted, tr!("nothing picked")))
+            }
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `git-tree pin` and `git-tree unpin`: branches that are always, or never,
+//! interesting, whatever the heuristic (or `--profile`) says, stored in
+//! `.git/gitxl/pins` (shared by the linked worktrees, like the branches):
+//!
+//! ```text
+//! gitxl pins v1
+//! always refs/heads/integration
+//! never refs/remotes/origin/wip
+//! ```
+//!
+//! Unlike the cache, the pins are the user's choice, so failing to write them
+//! (or to lock them for long, see `lock`) is an error. A missing or malformed
+//! file is treated as no pins, though.
+
+use crate::lock::Lock;
+use crate::log::Logged as _;
+use crate::{cache, quote, Branch};
+use alloc::collections::BTreeMap;
+use core::time::Duration;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+const HEADER: &str = "gitxl pins v1";
+
+/// Whether a pinned branch is always or never interesting.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Pin {
+    Always,
+    Never,
+}
+
+impl Pin {
+    /// Returns the pin's keyword in the file.
+    const fn keyword(self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::Never => "never",
+        }
+    }
+}
+
+/// The pins, by full ref name.
+type Pins = BTreeMap<String, Pin>;
+
+/// How long to wait for another run to release the pins' lock.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Returns the location of the pins, or `None` if it cannot be determined.
+fn path() -> Option<PathBuf> {
+    cache::common_path("gitxl/pins")
+}
+
+/// Reads the pins, if there are valid ones.
+fn load() -> Pins {
+    let Some(contents) = path().and_then(|path| fs::read_to_string(path).ok()) else {
+        return Pins::new();
+    };
+    let mut lines = contents.lines();
+    if lines.next() != Some(HEADER) {
+        return Pins::new();
+    }
+    let mut pins = Pins::new();
+    for line in lines {
+        let Some((keyword, refname)) = line.split_once(' ') else { return Pins::new() };
+        let pin = match keyword {
+            "always" => Pin::Always,
+            "never" => Pin::Never,
+            _ => return Pins::new(),
+        };
+        pins.insert(refname.to_owned(), pin);
+    }
+    pins
+}
+
+/// Locks the pins, to update them.
+fn lock() -> io::Result<Lock> {
+    let path = path().ok_or_else(|| io::Error::other(tr!("not in a git repository")))?;
+    Lock::acquire(&path, LOCK_TIMEOUT)
+}
+
+/// Replaces the pins, locked by `lock`.
+fn store(lock: Lock, pins: &Pins) -> io::Result<()> {
+    let mut contents = String::new();
+    contents.push_str(HEADER);
+    contents.push('\n');
+    for (refname, pin) in pins {
+        contents.extend([pin.keyword(), " ", refname.as_str(), "\n"]);
+    }
+    lock.commit(&contents)
+}
+
+/// Returns the full name of the branch `name`, e.g. `refs/heads/main` for
+/// `main`, or an error if it is not a local or remote branch.
+fn full_name(name: &str) -> io::Result<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet", "--symbolic-full-name", "--end-of-options"])
+        .arg(name)
+        .stderr(Stdio::null())
+        .logged()
+        .output()?;
+    let refname = String::from_utf8_lossy(&output.stdout).trim_end().to_owned();
+    if output.status.success()
+        && (refname.starts_with("refs/heads/") || refname.starts_with("refs/remotes/"))
+    {
+        Ok(refname)
+    } else {
+        Err(io::Error::other(tr!("{$name} is not a branch", name = quote::name(name))))
+    }
+}
+
+/// Pins the branches `names` with `pin`, replacing any pin they had, or lists
+/// the pins if there are no `names`.
+pub fn pin<W: Write>(mut out: W, names: &[String], pin: Pin) -> io::Result<()> {
+    if names.is_empty() {
+        for (refname, pinned) in &load() {
+            writeln!(out, "{} {}", pinned.keyword(), quote::name(refname))?;
+        }
+        return out.flush();
+    }
+    let refnames = names.iter().map(|name| full_name(name)).collect::<io::Result<Vec<_>>>()?;
+    let lock = lock()?;
+    let mut pins = load();
+    pins.extend(refnames.into_iter().map(|refname| (refname, pin)));
+    store(lock, &pins)
+}
+
+/// Removes the pins of the branches `names`, which may be given by full ref
+/// name or by short name, and need not exist any more.
+pub fn unpin(names: &[String]) -> io::Result<()> {
+    let lock = lock()?;
+    let mut pins = load();
+    for name in names {
+        let candidates =
+            [name.clone(), format!("refs/heads/{name}"), format!("refs/remotes/{name}")];
+        if !candidates.iter().any(|refname| pins.remove(refname).is_some()) {
+            return Err(io::Error::other(tr!("{$name} is not pinned", name = quote::name(name))));
+        }
+    }
+    store(lock, &pins)
+}
+
+/// Splits the branches into the interesting ones and the others by their pins,
+/// given the branches found `interesting` otherwise and the `others`.
+pub fn apply(interesting: Vec<Branch>, others: Vec<Branch>) -> (Vec<Branch>, Vec<Branch>) {
+    let pins = load();
+    if pins.is_empty() {
+        return (interesting, others);
+    }
+    let (chosen, rest): (Vec<_>, Vec<_>) = interesting
+        .into_iter()
+        .map(|branch| (true, branch))
+        .chain(others.into_iter().map(|branch| (false, branch)))
+        .partition(|(interesting, branch)| match pins.get(&branch.refname) {
+            Some(&pin) => pin == Pin::Always,
+            None => *interesting,
+        });
+    let branches =
+        |pairs: Vec<(bool, Branch)>| pairs.into_iter().map(|(_, branch)| branch).collect();
+    (branches(chosen), branches(rest))
+}
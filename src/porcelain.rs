@@ -0,0 +1,76 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--porcelain=v1`: a stable, line-oriented description of the view for
+//! scripts and editor plugins. The format is specified in the README's
+//! "Porcelain output" section; any change to it needs a new version.
+
+use crate::graph::Commit;
+use crate::trailers::Trailers;
+use crate::Branch;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+/// Writes the view: HEAD (if it points to a commit), the interesting
+/// `branches`, the `merge_bases`, and the displayed `commits` (in topological
+/// order, children first) with their edges and `trailers`. Records end with
+/// NUL if `nul` is set and with a newline otherwise.
+pub fn write<W: Write>(
+    head: Option<&str>,
+    branches: &[Branch],
+    merge_bases: &[String],
+    commits: &[Commit],
+    trailers: &HashMap<String, Trailers>,
+    nul: bool,
+    mut out: W,
+) -> io::Result<()> {
+    let end = if nul { '\0' } else { '\n' };
+    write!(out, "version 1{end}")?;
+    if let Some(id) = head {
+        write!(out, "head {id}{end}")?;
+    }
+    let mut sorted: Vec<&Branch> = branches.iter().collect();
+    sorted.sort_unstable_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+    for branch in sorted {
+        write!(out, "branch {} {}{end}", branch.id, branch.name)?;
+    }
+    for id in merge_bases {
+        write!(out, "base {id}{end}")?;
+    }
+    let tips: HashSet<&str> = branches.iter().map(|branch| branch.id.as_str()).collect();
+    let displayed: HashSet<&str> = commits.iter().map(|commit| commit.id.as_str()).collect();
+    for commit in commits {
+        let mut roles = vec![];
+        if head == Some(commit.id.as_str()) {
+            roles.push("head");
+        }
+        if tips.contains(commit.id.as_str()) {
+            roles.push("tip");
+        }
+        if merge_bases.contains(&commit.id) {
+            roles.push("base");
+        }
+        if roles.is_empty() {
+            roles.push("path");
+        }
+        write!(out, "commit {} {}{end}", commit.id, roles.join(","))?;
+        for parent in commit.parents.iter().filter(|parent| displayed.contains(parent.as_str())) {
+            write!(out, "edge {} {parent}{end}", commit.id)?;
+        }
+        for (key, value) in trailers.get(&commit.id).into_iter().flatten() {
+            write!(out, "trailer {} {key} {value}{end}", commit.id)?;
+        }
+    }
+    out.flush()
+}
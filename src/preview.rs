@@ -0,0 +1,131 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--preview-merge`: the graph as it would look after merging branches into
+//! the trunk, before pressing the merge button.
+//!
+//! The branches are merged into the trunk one after the other, in memory, as
+//! for `git-tree conflicts`. Merging several writes a commit object for each
+//! merge but the last, to merge the next branch into; nothing refers to them,
+//! so `git gc` prunes them. The merge is shown as a commit on top of the trunk
+//! and the branches, saying which files would conflict, if any. No ref, index,
+//! or working tree is touched.
+
+use crate::cli::DEFAULT_TRUNK;
+use crate::graph::Commit;
+use crate::json::Value;
+use crate::report::git;
+use crate::{conflicts, date, given, peel_to_commit, Branch};
+use std::io;
+use std::sync::OnceLock;
+
+/// The ID of the merge commit shown, which is not a commit in the repository.
+pub const ID: &str = "preview-merge";
+
+/// The merge to preview, if any.
+static PREVIEW: OnceLock<Preview> = OnceLock::new();
+
+/// A merge of branches into the trunk.
+pub struct Preview {
+    trunk: Branch,
+    branches: Vec<Branch>,
+    /// The files that would conflict, by branch, for the branches with any.
+    conflicts: Vec<(String, Vec<String>)>,
+}
+
+impl Preview {
+    /// Returns the merge commit to show on top of the graph.
+    pub fn commit(&self) -> Commit {
+        let names: Vec<_> = self.branches.iter().map(|branch| branch.name.as_str()).collect();
+        let subject = format!("Merge {} into {}", names.join(", "), self.trunk.name);
+        let mut parents = vec![self.trunk.id.clone()];
+        parents.extend(self.branches.iter().map(|branch| branch.id.clone()));
+        let now = date::now();
+        Commit {
+            id: ID.to_owned(),
+            parents,
+            author: String::new(),
+            time: now,
+            committer_time: now,
+            text: format!("{subject} [{}]", self.label()),
+            subject,
+            collapsed: 0,
+        }
+    }
+
+    /// Returns the annotation of the merge, e.g. `feature would conflict in
+    /// a.txt, b.txt`.
+    fn label(&self) -> String {
+        if self.conflicts.is_empty() {
+            return "would merge cleanly".into();
+        }
+        let conflicts: Vec<_> = self
+            .conflicts
+            .iter()
+            .map(|(branch, files)| format!("{branch} would conflict in {}", files.join(", ")))
+            .collect();
+        conflicts.join("; ")
+    }
+
+    /// Returns the files that would conflict as a JSON array of objects with
+    /// the `branch` and its `files`, if `id` is the merge commit's.
+    pub fn json(&self, id: &str) -> Option<Value> {
+        if id != ID {
+            return None;
+        }
+        let conflicts = self.conflicts.iter().map(|(branch, files)| {
+            Value::object([
+                ("branch", branch.as_str().into()),
+                ("files", Value::strings(files.iter().cloned())),
+            ])
+        });
+        Some(Value::Array(conflicts.collect()))
+    }
+}
+
+/// Merges the branches `names` into the trunk, for `get` to return. Returns
+/// the trunk and the branches, to add to the interesting ones.
+pub fn prepare(names: &[String]) -> io::Result<Vec<Branch>> {
+    let resolve = |name: &str| {
+        let (id, time) = peel_to_commit(name)
+            .ok_or_else(|| io::Error::other(tr!("unknown branch {$branch}", branch = name)))?;
+        let refname = given::full_name(name).unwrap_or_else(|| name.to_owned());
+        Ok::<_, io::Error>(Branch { name: name.to_owned(), refname, id, time })
+    };
+    let trunk = resolve(DEFAULT_TRUNK)?;
+    let branches = names.iter().map(|name| resolve(name)).collect::<io::Result<Vec<_>>>()?;
+    let mut merged = trunk.id.clone();
+    let mut conflicts = vec![];
+    for (i, branch) in branches.iter().enumerate() {
+        let (tree, files) = conflicts::merge(&merged, &branch.id)?;
+        if !files.is_empty() {
+            conflicts.push((branch.name.clone(), files));
+        }
+        if i.saturating_add(1) < branches.len() {
+            let message = format!("Merge {}", branch.name);
+            let args = ["commit-tree", &tree, "-p", &merged, "-p", &branch.id, "-m", &message];
+            git(&args)?.trim_end().clone_into(&mut merged);
+        }
+    }
+    let mut joined = vec![trunk.clone()];
+    joined.extend(branches.iter().cloned());
+    // There is only one command line.
+    drop(PREVIEW.set(Preview { trunk, branches, conflicts }));
+    Ok(joined)
+}
+
+/// Returns the merge to preview, if `--preview-merge` asked for one.
+pub fn get() -> Option<&'static Preview> {
+    PREVIEW.get()
+}
@@ -0,0 +1,185 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--profile <name>`: a named definition of which branches are interesting,
+//! set up in git config, e.g.
+//!
+//! ```text
+//! git config gitxl.profile.recent.remotes all
+//! git config gitxl.profile.recent.since 2.weeks.ago
+//! git config --add gitxl.profile.mine.ref 'refs/heads/*'
+//! git config --add gitxl.profile.mine.ref refs/remotes/origin/main
+//! git config gitxl.profile.mine.remotes all
+//! ```
+//!
+//! A profile's settings are:
+//!
+//! - `remotes`: which remote branches are candidates, `none`, `upstreams` (the
+//!   default: those that are the upstream of a local branch or have the same
+//!   name as one), or `all`. Local branches always are.
+//! - `ref`: ref name patterns, as in `git for-each-ref` (e.g. `refs/heads/*`).
+//!   If any are set, only the candidates matching one are interesting.
+//! - `since`: a date, in any format `git log --since` takes (e.g.
+//!   `2.weeks.ago`). Branches last committed to before then are left out.
+//! - `logArgs`: default arguments for `git log`, after `gitxl.logArgs` (see
+//!   `default_args`).
+//!
+//! The profile applies to the branches before a `gitxl.refHook` sees them.
+
+use crate::log::{self, Level, Logged as _};
+use crate::{config, git_status, namespace_prefix, Branch};
+use std::collections::HashSet;
+use std::io;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+/// Which remote branches a profile considers.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Remotes {
+    None,
+    Upstreams,
+    All,
+}
+
+/// A profile's definition of the interesting branches.
+pub struct Profile {
+    remotes: Remotes,
+    /// The ref name patterns, or none to keep every candidate.
+    refs: Vec<String>,
+    /// The earliest commit time of the branches kept, in seconds since the
+    /// epoch.
+    since: Option<i64>,
+}
+
+/// The profile chosen on the command line, if any.
+static ACTIVE: OnceLock<Profile> = OnceLock::new();
+
+/// Returns the values of the profile setting `key` of profile `name`.
+fn get_all(name: &str, key: &str) -> Vec<String> {
+    config::get_all(&format!("gitxl.profile.{name}.{key}"))
+}
+
+/// Returns the default `git log` arguments of profile `name`.
+pub fn log_args(name: &str) -> Vec<String> {
+    get_all(name, "logArgs")
+}
+
+/// Returns the time in seconds since the epoch that `git log --since=<date>`
+/// means.
+fn parse_since(date: &str) -> Option<i64> {
+    let output = Command::new("git")
+        .arg("rev-parse")
+        .arg(format!("--since={date}"))
+        .stderr(Stdio::null())
+        .logged()
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    stdout.trim_end().strip_prefix("--max-age=")?.parse().ok()
+}
+
+/// Loads profile `name` from git config, or returns an error message if it is
+/// not defined or invalid.
+pub fn load(name: &str) -> Result<Profile, String> {
+    let remotes = get_all(name, "remotes");
+    let refs = get_all(name, "ref");
+    let since = get_all(name, "since");
+    if remotes.is_empty() && refs.is_empty() && since.is_empty() && log_args(name).is_empty() {
+        return Err(tr!(
+            "unknown profile {$name} (no gitxl.profile.{$name}.* settings)",
+            name = name
+        ));
+    }
+    let remotes = match remotes.last().map(String::as_str) {
+        None | Some("upstreams") => Remotes::Upstreams,
+        Some("none") => Remotes::None,
+        Some("all") => Remotes::All,
+        Some(other) => {
+            return Err(tr!(
+                "invalid gitxl.profile.{$name}.remotes value {$value}",
+                name = name,
+                value = other
+            ))
+        }
+    };
+    let since = since
+        .last()
+        .map(|date| {
+            parse_since(date).ok_or_else(|| {
+                tr!("invalid gitxl.profile.{$name}.since value {$value}", name = name, value = date)
+            })
+        })
+        .transpose()?;
+    Ok(Profile { remotes, refs, since })
+}
+
+/// Makes `profile` the one `active` returns.
+pub fn activate(profile: Profile) {
+    // There is only one command line.
+    drop(ACTIVE.set(profile));
+}
+
+/// Returns the profile chosen on the command line, if any.
+pub fn active() -> Option<&'static Profile> {
+    ACTIVE.get()
+}
+
+impl Profile {
+    /// Returns the ref names (without the namespace) matching the profile's
+    /// patterns.
+    fn matching_refs(&self) -> io::Result<HashSet<String>> {
+        let prefix = namespace_prefix().unwrap_or_default();
+        let output = Command::new("git")
+            .args(["for-each-ref", "--format=%(refname)"])
+            .args(self.refs.iter().map(|pattern| format!("{prefix}{pattern}")))
+            .stderr(Stdio::null())
+            .logged()
+            .output()?;
+        git_status("for-each-ref", output.status)?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout
+            .lines()
+            .map(|refname| refname.strip_prefix(prefix.as_str()).unwrap_or(refname).to_owned())
+            .collect())
+    }
+
+    /// Splits the branches into those the profile finds interesting and the
+    /// others, given the branches the default heuristic finds `interesting`
+    /// and the `others`.
+    pub fn select(
+        &self,
+        interesting: Vec<Branch>,
+        others: Vec<Branch>,
+    ) -> io::Result<(Vec<Branch>, Vec<Branch>)> {
+        let (candidates, mut rest): (Vec<_>, Vec<_>) = match self.remotes {
+            Remotes::None => {
+                let (locals, remotes): (Vec<_>, Vec<_>) = interesting
+                    .into_iter()
+                    .partition(|branch| branch.refname.starts_with("refs/heads/"));
+                (locals, remotes.into_iter().chain(others).collect())
+            }
+            Remotes::Upstreams => (interesting, others),
+            Remotes::All => (interesting.into_iter().chain(others).collect(), vec![]),
+        };
+        let matching = (!self.refs.is_empty()).then(|| self.matching_refs()).transpose()?;
+        let (chosen, dropped): (Vec<_>, Vec<_>) = candidates.into_iter().partition(|branch| {
+            matching.as_ref().is_none_or(|matching| matching.contains(&branch.refname))
+                && self.since.is_none_or(|since| branch.time >= since)
+        });
+        rest.extend(dropped);
+        let fields = vec![("branches", chosen.len().into()), ("others", rest.len().into())];
+        log::event(Level::Debug, "refs", "applied the profile", fields);
+        Ok((chosen, rest))
+    }
+}
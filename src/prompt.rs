@@ -0,0 +1,282 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `git-tree prompt`: a one-line summary for a shell prompt, e.g.
+//! `main +2 -1, 3 unmerged`: the current branch, how far it is ahead of and
+//! behind its upstream, and how many other local branches are not merged into
+//! the trunk (`origin/HEAD`).
+//!
+//! A prompt must not make the shell wait, so the summary is computed within a
+//! time budget (`--budget=<ms>` or `gitxl.promptBudget`, 50ms by default, 0
+//! for none). The refs are read at once, which is cheap, and the summary is
+//! cached in `.git/gitxl/prompt` keyed by the commits they point to, so it is
+//! reused until one of them moves. If the cache is cold and the walks take
+//! longer than the budget, the parts they compute are shown as `?`, and a
+//! `git-tree prompt` without a budget is left running in the background to
+//! warm the cache for the next prompt.
+
+use crate::cli::DEFAULT_TRUNK;
+use crate::lock::Lock;
+use crate::log::Logged as _;
+use crate::{abbrev, cache, config, graph, namespace_prefix, quote, rev_parse};
+use core::fmt::Write as _;
+use core::time::Duration;
+use std::collections::HashMap;
+use std::env::current_exe;
+use std::fs;
+use std::io::{self, Read as _};
+use std::process::{Child, Command, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+const HEADER: &str = "gitxl prompt v1";
+
+/// The time budget when none is configured.
+pub const DEFAULT_BUDGET: Duration = Duration::from_millis(50);
+
+/// How often to check whether the walks are done.
+const POLL: Duration = Duration::from_millis(1);
+
+/// How long to wait for another run to release the cache's lock.
+const LOCK_TIMEOUT: Duration = Duration::from_millis(5);
+
+/// Returns the time budget set by `gitxl.promptBudget`, in milliseconds, if
+/// any.
+pub fn configured_budget() -> Option<Duration> {
+    config::get("gitxl.promptBudget")?.parse().ok().map(Duration::from_millis)
+}
+
+/// What the summary is about: the commits HEAD, its upstream, the trunk, and
+/// the other local branches point to.
+struct Refs {
+    /// The current branch's short name, or `None` if HEAD is detached.
+    branch: Option<String>,
+    head: String,
+    upstream: Option<String>,
+    trunk: Option<String>,
+    /// The other local branches' commits.
+    others: Vec<String>,
+}
+
+impl Refs {
+    /// Reads the refs, with a `git for-each-ref` and two `git rev-parse`.
+    fn read() -> io::Result<Self> {
+        let prefix = namespace_prefix().unwrap_or_default();
+        let output = Command::new("git")
+            .args(["for-each-ref", "--format=%(HEAD)%00%(refname)%00%(objectname)%00%(upstream)"])
+            .args([format!("{prefix}refs/heads"), format!("{prefix}refs/remotes")])
+            .stderr(Stdio::null())
+            .logged()
+            .output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(tr!("not in a git repository")));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let strip = |full: &str| full.strip_prefix(prefix.as_str()).unwrap_or(full).to_owned();
+        let (mut ids, mut current, mut others) = (HashMap::new(), None, vec![]);
+        for line in stdout.lines() {
+            let mut fields = line.split('\0');
+            let mut field = || fields.next().unwrap_or_default();
+            let (marker, refname, id, upstream) = (field(), strip(field()), field(), field());
+            if let Some(name) = refname.strip_prefix("refs/heads/") {
+                // In a namespace, the marker is for the repository's HEAD.
+                if marker == "*" && prefix.is_empty() {
+                    current =
+                        Some((name.to_owned(), (!upstream.is_empty()).then(|| strip(upstream))));
+                } else {
+                    others.push(id.to_owned());
+                }
+            }
+            ids.insert(refname, id.to_owned());
+        }
+        others.sort_unstable();
+        let head = rev_parse("HEAD").ok_or_else(|| io::Error::other(tr!("no commits yet")))?;
+        let upstream = current.as_ref().and_then(|(_, upstream)| ids.get(upstream.as_ref()?));
+        Ok(Self {
+            upstream: upstream.cloned(),
+            branch: current.map(|(name, _)| name),
+            head,
+            trunk: rev_parse(DEFAULT_TRUNK),
+            others,
+        })
+    }
+
+    /// Returns the cache key: the current branch and the commits the summary
+    /// depends on.
+    fn key(&self) -> String {
+        let or_none = |id: Option<&String>| id.map_or("-", String::as_str).to_owned();
+        let mut key = format!(
+            "{} {} {} {}",
+            or_none(self.branch.as_ref()),
+            self.head,
+            or_none(self.upstream.as_ref()),
+            or_none(self.trunk.as_ref())
+        );
+        for id in &self.others {
+            key.push(' ');
+            key.push_str(id);
+        }
+        key
+    }
+}
+
+/// A git command running in the background, with its output being read.
+struct Walk {
+    child: Child,
+    stdout: JoinHandle<String>,
+}
+
+impl Walk {
+    /// Starts `git` with `args`.
+    fn start(args: &[&str]) -> io::Result<Self> {
+        let mut child = Command::new("git")
+            .args(args)
+            .env(graph::NO_LAZY_FETCH, "1")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .logged()
+            .spawn()?;
+        let mut pipe = child.stdout.take().ok_or_else(|| io::Error::other("git has no stdout"))?;
+        // Read as it comes, so that git never blocks on a full pipe.
+        let stdout = thread::spawn(move || {
+            let mut stdout = String::new();
+            drop(pipe.read_to_string(&mut stdout));
+            stdout
+        });
+        Ok(Self { child, stdout })
+    }
+
+    /// Returns the output, or `None` if git fails or is not done by
+    /// `deadline` (in which case it is killed).
+    fn finish(mut self, deadline: Option<Instant>) -> Option<String> {
+        loop {
+            if let Some(status) = self.child.try_wait().ok()? {
+                let stdout = self.stdout.join().ok()?;
+                return status.success().then_some(stdout);
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                drop(self.child.kill());
+                drop(self.child.wait());
+                return None;
+            }
+            thread::sleep(POLL);
+        }
+    }
+}
+
+/// Returns the summary of `refs`, computed by `deadline` if there is one, and
+/// whether it is complete.
+fn summarize(refs: &Refs, deadline: Option<Instant>) -> (String, bool) {
+    let head = refs.head.as_str();
+    let track = refs.upstream.as_ref().map(|upstream| {
+        Walk::start(&["rev-list", "--left-right", "--count", &format!("{head}...{upstream}")])
+    });
+    let prefix = namespace_prefix().unwrap_or_default();
+    let unmerged = refs.trunk.as_ref().map(|trunk| {
+        Walk::start(&[
+            "for-each-ref",
+            &format!("--no-merged={trunk}"),
+            "--format=%(refname)",
+            &format!("{prefix}refs/heads"),
+        ])
+    });
+    let mut complete = true;
+    let name = refs
+        .branch
+        .as_deref()
+        .map_or_else(|| abbrev::short(head).to_owned(), |branch| quote::name(branch).into_owned());
+    let mut summary = name;
+    if let Some(walk) = track {
+        let counts = walk.ok().and_then(|walk| walk.finish(deadline));
+        let counts = counts.as_deref().and_then(|counts| counts.trim_end().split_once('\t'));
+        if let Some((ahead, behind)) = counts {
+            #[allow(clippy::unwrap_used, reason = "writing to a String cannot fail")]
+            write!(summary, " +{ahead} -{behind}").unwrap();
+        } else {
+            complete = false;
+            summary.push_str(" +? -?");
+        }
+    }
+    if let Some(walk) = unmerged {
+        let refnames = walk.ok().and_then(|walk| walk.finish(deadline));
+        // The current branch is not one of the others.
+        let current = refs.branch.as_ref().map(|branch| format!("{prefix}refs/heads/{branch}"));
+        let count = refnames.map(|refnames| {
+            refnames.lines().filter(|&refname| current.as_deref() != Some(refname)).count()
+        });
+        if let Some(count) = count {
+            if count > 0 {
+                #[allow(clippy::unwrap_used, reason = "writing to a String cannot fail")]
+                write!(summary, ", {count} unmerged").unwrap();
+            }
+        } else {
+            complete = false;
+            summary.push_str(", ? unmerged");
+        }
+    }
+    (summary, complete)
+}
+
+/// Returns the cached summary for `key`, if any.
+fn load(key: &str) -> Option<String> {
+    let contents = fs::read_to_string(cache::git_path("gitxl/prompt")?).ok()?;
+    let mut lines = contents.lines();
+    if lines.next() != Some(HEADER) || lines.next()?.strip_prefix("key ")? != key {
+        return None;
+    }
+    lines.next().map(str::to_owned)
+}
+
+/// Caches `summary` for `key`, if the cache is free.
+fn store(key: &str, summary: &str) {
+    let Some(path) = cache::git_path("gitxl/prompt") else { return };
+    if let Ok(lock) = Lock::acquire(&path, LOCK_TIMEOUT) {
+        drop(lock.commit(&format!("{HEADER}\nkey {key}\n{summary}\n")));
+    }
+}
+
+/// Starts a `git-tree prompt` without a budget in the background, to warm the
+/// cache.
+fn refresh_in_background() {
+    let Ok(exe) = current_exe() else { return };
+    let child = Command::new(exe)
+        .args(["prompt", "--budget=0"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .logged()
+        .spawn();
+    // It outlives this run, and is not waited for.
+    drop(child);
+}
+
+/// Writes the summary, computed within `budget` (or without a limit if it is
+/// zero).
+pub fn write<W: io::Write>(mut out: W, budget: Duration) -> io::Result<()> {
+    let deadline = Instant::now().checked_add(budget).filter(|_| !budget.is_zero());
+    let refs = Refs::read()?;
+    let key = refs.key();
+    if let Some(summary) = load(&key) {
+        writeln!(out, "{summary}")?;
+        return out.flush();
+    }
+    let (summary, complete) = summarize(&refs, deadline);
+    if complete {
+        store(&key, &summary);
+    } else {
+        refresh_in_background();
+    }
+    writeln!(out, "{summary}")?;
+    out.flush()
+}
@@ -0,0 +1,102 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Quoting of ref names and paths in git-tree's own messages and summaries,
+//! the way git quotes paths: a name containing a double quote, a backslash, or
+//! a control character, or a byte outside ASCII unless `core.quotePath` is
+//! false, is put in double quotes with C-style escapes, e.g. `"caf\303\251"`.
+//! Other names, including those with spaces, are left as they are.
+//!
+//! The porcelain format is not quoted: it is versioned, and `-z` already
+//! makes it unambiguous.
+
+use crate::log::Logged as _;
+use alloc::borrow::Cow;
+use core::fmt::Write as _;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+/// Whether to quote bytes outside ASCII (`core.quotePath`, true by default).
+static QUOTE_PATH: OnceLock<bool> = OnceLock::new();
+
+/// Returns the `core.quotePath` setting, reading it the first time.
+fn quote_path() -> bool {
+    *QUOTE_PATH.get_or_init(|| {
+        let output = Command::new("git")
+            .args(["config", "--type=bool", "core.quotePath"])
+            .stderr(Stdio::null())
+            .logged()
+            .output();
+        output.map_or(true, |output| output.stdout != b"false\n")
+    })
+}
+
+/// Returns `name` quoted as git would, quoting characters outside ASCII only
+/// if `non_ascii` is true.
+pub fn c_quote(name: &str, non_ascii: bool) -> Cow<'_, str> {
+    let special =
+        |c: char| c.is_ascii_control() || c == '"' || c == '\\' || (non_ascii && !c.is_ascii());
+    if !name.chars().any(special) {
+        return Cow::Borrowed(name);
+    }
+    let mut quoted = String::with_capacity(name.len().saturating_add(2));
+    quoted.push('"');
+    for c in name.chars() {
+        match c {
+            '\x07' => quoted.push_str("\\a"),
+            '\x08' => quoted.push_str("\\b"),
+            '\t' => quoted.push_str("\\t"),
+            '\n' => quoted.push_str("\\n"),
+            '\x0b' => quoted.push_str("\\v"),
+            '\x0c' => quoted.push_str("\\f"),
+            '\r' => quoted.push_str("\\r"),
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            // Each byte of the UTF-8 encoding, in octal.
+            _ if special(c) => {
+                for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                    #[allow(clippy::unwrap_used, reason = "writing to a String cannot fail")]
+                    write!(quoted, "\\{byte:03o}").unwrap();
+                }
+            }
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    Cow::Owned(quoted)
+}
+
+/// Returns the ref name or path `name` quoted for display, as git would with
+/// the repository's `core.quotePath` setting.
+pub fn name(name: &str) -> Cow<'_, str> {
+    c_quote(name, quote_path())
+}
+
+/// Returns the ref name `name`, which is not valid UTF-8, quoted for display
+/// as git would: the bytes that are not UTF-8 are escaped in octal.
+pub fn bytes(name: &[u8]) -> String {
+    let mut quoted = String::with_capacity(name.len().saturating_add(2));
+    quoted.push('"');
+    for chunk in name.utf8_chunks() {
+        let valid = self::name(chunk.valid());
+        let inner = valid.strip_prefix('"').and_then(|valid| valid.strip_suffix('"'));
+        quoted.push_str(inner.unwrap_or(&valid));
+        for byte in chunk.invalid() {
+            #[allow(clippy::unwrap_used, reason = "writing to a String cannot fail")]
+            write!(quoted, "\\{byte:03o}").unwrap();
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,124 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The plan of a rebase in progress, overlaid on the graph: which action each
+//! of the original commits is down for, and which have been applied.
+//!
+//! While a rebase that uses the merge backend (`git rebase -i`, and plain
+//! `git rebase` by default) is in progress, `rebase-merge` in the git
+//! directory holds the steps still to do (`git-rebase-todo`) and those done
+//! (`done`), the last of which is the one the rebase is at. Steps that do not
+//! name a commit (`exec`, `break`, `label`, ...) are left out.
+
+use crate::cache;
+use crate::json::Value;
+use std::fs;
+
+/// Where a step is in the rebase.
+#[derive(Clone, Copy)]
+enum State {
+    Pending,
+    Applied,
+    /// The last step done, where the rebase stopped (e.g. for `edit`, or a
+    /// conflict).
+    Current,
+}
+
+impl State {
+    /// Returns the state's name.
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Applied => "applied",
+            Self::Current => "current",
+        }
+    }
+}
+
+/// A step of the rebase that names a commit.
+struct Step {
+    /// The commit's ID, as abbreviated in the todo list.
+    id: String,
+    /// The action, spelled out (`pick` rather than `p`).
+    action: &'static str,
+    state: State,
+}
+
+/// The steps of a rebase in progress.
+pub struct Plan {
+    steps: Vec<Step>,
+}
+
+impl Plan {
+    /// Returns the plan of the rebase in progress, or `None` if there is none.
+    pub fn load() -> Option<Self> {
+        let dir = cache::git_path("rebase-merge")?;
+        let todo = fs::read_to_string(dir.join("git-rebase-todo")).ok()?;
+        let done = fs::read_to_string(dir.join("done")).unwrap_or_default();
+        let mut steps: Vec<Step> =
+            done.lines().filter_map(|line| parse(line, State::Applied)).collect();
+        if let Some(last) = steps.last_mut() {
+            last.state = State::Current;
+        }
+        steps.extend(todo.lines().filter_map(|line| parse(line, State::Pending)));
+        Some(Self { steps })
+    }
+
+    /// Returns the step for the commit `id`, the first if there are several.
+    fn step(&self, id: &str) -> Option<&Step> {
+        self.steps.iter().find(|step| id.starts_with(&step.id))
+    }
+
+    /// Returns the annotation of the commit `id`, e.g. `rebase: pick` or
+    /// `rebase: edit, current`, or `None` if the plan does not name it.
+    pub fn label(&self, id: &str) -> Option<String> {
+        let step = self.step(id)?;
+        Some(match step.state {
+            State::Pending => format!("rebase: {}", step.action),
+            State::Applied | State::Current => {
+                format!("rebase: {}, {}", step.action, step.state.name())
+            }
+        })
+    }
+
+    /// Returns the step for the commit `id` as a JSON object with its `action`
+    /// and `state`, or null.
+    pub fn json(&self, id: &str) -> Value {
+        self.step(id).map_or(Value::Null, |step| {
+            Value::object([("action", step.action.into()), ("state", step.state.name().into())])
+        })
+    }
+}
+
+/// Parses a line of the todo list, returning `None` for comments, blank lines,
+/// and steps that do not name a commit.
+fn parse(line: &str, state: State) -> Option<Step> {
+    let mut words = line.split_whitespace();
+    let action = match words.next()? {
+        "p" | "pick" => "pick",
+        "r" | "reword" => "reword",
+        "e" | "edit" => "edit",
+        "s" | "squash" => "squash",
+        "f" | "fixup" => "fixup",
+        "d" | "drop" => "drop",
+        "m" | "merge" => "merge",
+        _ => return None,
+    };
+    // Skips options such as `fixup -C` and `merge -C`. A merge without one
+    // names a label rather than a commit, which is not hexadecimal (or is too
+    // short to match by accident).
+    let id = words.find(|word| !word.starts_with('-'))?;
+    let hex = id.len() >= 7 && id.chars().all(|c| c.is_ascii_hexdigit());
+    hex.then(|| Step { id: id.to_ascii_lowercase(), action, state })
+}
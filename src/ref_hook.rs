@@ -0,0 +1,94 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `gitxl.refHook`: a shell command that adjusts which branches are
+//! interesting, e.g. to keep only the branches with open reviews.
+//!
+//! The hook reads the branches git-tree finds interesting on stdin, one per
+//! line as `<commit ID> <ref name>` (e.g. `1a2b... refs/heads/main`), and
+//! writes the ref names of the branches to show instead, one per line (the
+//! same lines, filtered, will do). It may add any other local or remote
+//! branch. If it fails, git-tree warns and keeps its own choice.
+
+use crate::log::{self, Level, Logged as _};
+use crate::{config, quote, warn, Branch};
+use std::collections::HashMap;
+use std::io::{self, Write as _};
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// Returns the hook command, if one is configured.
+pub fn command() -> Option<String> {
+    config::get("gitxl.refHook")
+}
+
+/// Runs `command` on the `interesting` branches, and returns the branches it
+/// chose from those and the `others`, sorted like them.
+pub fn adjust(command: &str, interesting: Vec<Branch>, others: Vec<Branch>) -> Vec<Branch> {
+    let mut input = String::new();
+    for branch in &interesting {
+        input.extend([branch.id.as_str(), " ", branch.refname.as_str(), "\n"]);
+    }
+    let stdout = match run(command, input.as_bytes()) {
+        Ok(stdout) => stdout,
+        Err(error) => {
+            warn(&tr!("warning: gitxl.refHook failed ({$error}); ignoring it", error = error));
+            return interesting;
+        }
+    };
+    let mut by_refname: HashMap<_, _> = interesting
+        .into_iter()
+        .chain(others)
+        .map(|branch| (branch.refname.clone(), branch))
+        .collect();
+    let mut chosen = vec![];
+    // Ref names cannot contain spaces, so the name is the last word.
+    for refname in stdout.lines().filter_map(|line| line.split_whitespace().next_back()) {
+        if let Some(branch) = by_refname.remove(refname) {
+            chosen.push(branch);
+        } else if !chosen.iter().any(|branch: &Branch| branch.refname == refname) {
+            warn(&tr!(
+                "warning: gitxl.refHook chose unknown branch {$name}",
+                name = quote::name(refname)
+            ));
+        }
+    }
+    chosen.sort_unstable_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+    log::event(Level::Debug, "refs", "ran gitxl.refHook", vec![("branches", chosen.len().into())]);
+    chosen
+}
+
+/// Runs `command` with `input` on stdin, and returns its stdout.
+fn run(command: &str, input: &[u8]) -> io::Result<String> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .logged()
+        .spawn()?;
+    #[allow(clippy::unwrap_used, reason = "stdin is piped")]
+    let mut stdin = child.stdin.take().unwrap();
+    // Write from another thread, so that neither side blocks on a full pipe.
+    let output = thread::scope(|scope| {
+        let writer = scope.spawn(move || stdin.write_all(input));
+        let output = child.wait_with_output()?;
+        // A hook that does not read all of its input is fine.
+        drop(writer.join());
+        io::Result::Ok(output)
+    })?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!("exit status {}", output.status)));
+    }
+    String::from_utf8(output.stdout).map_err(io::Error::other)
+}
@@ -0,0 +1,67 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--reflog`: commits that HEAD or a local branch pointed to recently but that
+//! nothing points to any more, e.g. after a rebase or a reset. git-tree treats
+//! them as interesting, so that lost work shows up in the graph.
+
+use crate::log::Logged as _;
+use crate::{graph, namespace_prefix, Branch};
+use core::iter;
+use std::collections::HashSet;
+use std::io;
+use std::process::{Command, Stdio};
+
+/// The number of reflog entries per ref that `--reflog` looks at by default.
+pub const DEFAULT_ENTRIES: usize = 10;
+
+/// Returns the commits in the last `entries` reflog entries of HEAD and the
+/// local `branches` that are not reachable from `tips`, sorted.
+pub fn orphans(entries: usize, branches: &[Branch], tips: &[String]) -> io::Result<Vec<String>> {
+    let prefix = namespace_prefix().unwrap_or_default();
+    let refs = branches
+        .iter()
+        .filter(|branch| branch.refname.starts_with("refs/heads/"))
+        .map(|branch| format!("{prefix}{}", branch.refname));
+    let mut logged = HashSet::new();
+    for refname in iter::once("HEAD".to_owned()).chain(refs) {
+        // A ref without a reflog has nothing to add.
+        let Ok(output) = Command::new("git")
+            .args(["reflog", "show", "--format=%H", "--max-count"])
+            .arg(entries.to_string())
+            .args([&refname, "--"])
+            .env(graph::NO_LAZY_FETCH, "1")
+            .stderr(Stdio::null())
+            .logged()
+            .output()
+        else {
+            continue;
+        };
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        logged.extend(stdout.lines().map(str::to_owned));
+    }
+    if logged.is_empty() {
+        return Ok(vec![]);
+    }
+    // The logged commits that are not reachable from the tips, and so would
+    // not be displayed otherwise.
+    let mut revisions: Vec<_> = logged.iter().cloned().collect();
+    revisions.push("--not".into());
+    revisions.extend(tips.iter().cloned());
+    let unreachable: HashSet<_> =
+        graph::load(&revisions, None, false)?.into_iter().map(|commit| commit.id).collect();
+    let mut orphans: Vec<_> = logged.into_iter().filter(|id| unreachable.contains(id)).collect();
+    orphans.sort_unstable();
+    Ok(orphans)
+}
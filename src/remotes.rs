@@ -0,0 +1,91 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--remote <spec>` and `gitxl.remotes`: which remotes' branches can be
+//! interesting, e.g. to compare against `upstream/*` while the local branches
+//! track `origin/*`.
+//!
+//! Each spec is a remote name, whose branches are interesting by the default
+//! rule (the upstreams of local branches and those with the same name as one),
+//! or `<remote>/<pattern>`, a `git for-each-ref` pattern of the remote's
+//! branches that are interesting, e.g. `upstream/main` or `upstream/release`
+//! (every branch under `release/`). The branches of the other remotes are not.
+//! The `--remote` options replace the multi-valued `gitxl.remotes`.
+
+use crate::json::Value;
+use crate::log::{self, Level, Logged as _};
+use crate::{config, git_status, namespace_prefix, Branch};
+use std::collections::HashSet;
+use std::io;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+/// The specs given on the command line, if any.
+static CHOSEN: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Makes `specs` replace `gitxl.remotes`, unless there are none.
+pub fn choose(specs: Vec<String>) {
+    if !specs.is_empty() {
+        // There is only one command line.
+        drop(CHOSEN.set(specs));
+    }
+}
+
+/// Returns the specs of `gitxl.remotes`.
+fn configured() -> Vec<String> {
+    config::get_all("gitxl.remotes")
+}
+
+/// Returns the full ref names (without the namespace) of the remote branches
+/// matching the `patterns`.
+fn matching_refs(patterns: &[&str]) -> io::Result<HashSet<String>> {
+    if patterns.is_empty() {
+        return Ok(HashSet::new());
+    }
+    let prefix = namespace_prefix().unwrap_or_default();
+    let output = Command::new("git")
+        .args(["for-each-ref", "--format=%(refname)"])
+        .args(patterns.iter().map(|pattern| format!("{prefix}refs/remotes/{pattern}")))
+        .stderr(Stdio::null())
+        .logged()
+        .output()?;
+    git_status("for-each-ref", output.status)?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout
+        .lines()
+        .map(|refname| refname.strip_prefix(prefix.as_str()).unwrap_or(refname).to_owned())
+        .collect())
+}
+
+/// Splits the `remotes` branches into the interesting ones and the others, by
+/// the specs if there are any and by the `default` rule otherwise.
+pub fn partition<F>(remotes: Vec<Branch>, default: F) -> io::Result<(Vec<Branch>, Vec<Branch>)>
+where
+    F: Fn(&Branch) -> bool,
+{
+    let specs = CHOSEN.get().cloned().unwrap_or_else(configured);
+    if specs.is_empty() {
+        return Ok(remotes.into_iter().partition(default));
+    }
+    let (names, patterns): (Vec<&str>, Vec<&str>) =
+        specs.iter().map(String::as_str).partition(|spec| !spec.contains('/'));
+    let matching = matching_refs(&patterns)?;
+    let (interesting, others): (Vec<_>, Vec<_>) = remotes.into_iter().partition(|branch| {
+        let remote = branch.name.split_once('/').map_or("", |(remote, _)| remote);
+        (names.contains(&remote) && default(branch)) || matching.contains(&branch.refname)
+    });
+    let fields = vec![("specs", Value::strings(&specs)), ("branches", interesting.len().into())];
+    log::event(Level::Debug, "refs", "applied the remote specs", fields);
+    Ok((interesting, others))
+}
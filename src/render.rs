@@ -0,0 +1,258 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The native graph renderer (`--render=native`).
+//!
+//! Commits are placed in lanes by `layout`. Every row of the graph is a list of
+//! cells, each of which records which directions (up, down, left, right) it
+//! connects to; the cells are converted into box-drawing (or ASCII) characters
+//! when the row is written.
+
+use crate::graph::Commit;
+use crate::layout::{self, Placement};
+use crate::style::{Paint, LANE_COLORS};
+use crate::width;
+use alloc::borrow::Cow;
+use core::cmp::Reverse;
+use std::io::{self, Write};
+
+/// How to draw the graph.
+#[derive(Clone, Copy)]
+pub struct Style {
+    /// Use ASCII characters rather than Unicode box-drawing characters.
+    pub ascii: bool,
+    /// Color each lane's edges.
+    pub color: bool,
+    /// The terminal width to cut the commits' text to, if any.
+    pub columns: Option<usize>,
+}
+
+/// What a cell's marker represents.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+enum Marker {
+    /// No marker: the cell only holds lines.
+    #[default]
+    None,
+    /// A commit.
+    Commit,
+    /// A run of collapsed commits (see `graph::collapse`).
+    Collapsed,
+}
+
+/// A single cell of a graph row.
+#[derive(Clone, Copy, Default)]
+#[allow(clippy::struct_excessive_bools, reason = "one flag per direction")]
+struct Cell {
+    up: bool,
+    down: bool,
+    left: bool,
+    right: bool,
+    marker: Marker,
+    /// Index into `LANE_COLORS` for the lines in this cell.
+    color: usize,
+}
+
+impl Cell {
+    /// Returns the character to draw for this cell.
+    const fn glyph(self, ascii: bool) -> char {
+        match self.marker {
+            Marker::None => {}
+            Marker::Commit => return if ascii { '*' } else { '\u{25cf}' },
+            Marker::Collapsed => return if ascii { ':' } else { '\u{22ee}' },
+        }
+        match (ascii, self.up, self.down, self.left, self.right) {
+            (_, false, false, false, false) => ' ',
+            (false, _, _, false, false) => '\u{2502}',
+            (false, false, false, _, _) => '\u{2500}',
+            (false, true, true, true, true) => '\u{253c}',
+            (false, true, true, false, true) => '\u{251c}',
+            (false, true, true, true, false) => '\u{2524}',
+            (false, true, false, true, true) => '\u{2534}',
+            (false, false, true, true, true) => '\u{252c}',
+            (false, true, false, false, true) => '\u{2570}',
+            (false, true, false, true, false) => '\u{256f}',
+            (false, false, true, false, true) => '\u{256d}',
+            (false, false, true, true, false) => '\u{256e}',
+            (true, _, _, false, false) => '|',
+            (true, false, false, _, _) => '-',
+            (true, false, true, _, _) => '.',
+            (true, true, false, false, true) => '`',
+            (true, true, false, true, false) => '\'',
+            (true, true, _, _, _) => '+',
+        }
+    }
+}
+
+/// Renders the graph row by row.
+struct Renderer<W: Write> {
+    out: W,
+    style: Style,
+}
+
+/// Returns a row with a vertical line in every lane with a color in `colors`
+/// (except those in `skip`).
+fn verticals(colors: &[Option<usize>], skip: &[usize]) -> Vec<Cell> {
+    colors
+        .iter()
+        .enumerate()
+        .map(|(i, &color)| match color {
+            Some(color) if !skip.contains(&i) => {
+                Cell { up: true, down: true, color, ..Cell::default() }
+            }
+            _ => Cell::default(),
+        })
+        .collect()
+}
+
+/// Draws a horizontal line in `row` from `from` to `to` (exclusive of both
+/// endpoints' vertical connections, which the caller sets).
+fn horizontal(row: &mut [Cell], from: usize, to: usize, color: usize) {
+    let (low, high) = if from < to { (from, to) } else { (to, from) };
+    for (i, cell) in row.iter_mut().enumerate().take(high.saturating_add(1)).skip(low) {
+        if i != low {
+            cell.left = true;
+        }
+        if i != high {
+            cell.right = true;
+        }
+        if i != from && cell.marker == Marker::None && !(cell.up && cell.down) {
+            cell.color = color;
+        }
+    }
+}
+
+impl<W: Write> Renderer<W> {
+    /// Writes a row of the graph followed by `text`, padding the graph to at
+    /// least `width` cells. Returns the number of cells written.
+    #[allow(clippy::unwrap_used, reason = "layout colors are less than COLORS")]
+    fn write_row(&mut self, row: &[Cell], width: usize, text: &str) -> io::Result<usize> {
+        let used = row
+            .iter()
+            .rposition(|cell| cell.glyph(self.style.ascii) != ' ')
+            .map_or(0, |end| end.saturating_add(1))
+            .max(width);
+        for i in 0..used {
+            let cell = row.get(i).copied().unwrap_or_default();
+            let glyph = cell.glyph(self.style.ascii);
+            // Don't leave trailing whitespace after the graph.
+            let spacer = match (cell.right, text.is_empty() && i.saturating_add(1) == used) {
+                (true, _) => self.horizontal_glyph().to_string(),
+                (false, false) => " ".to_owned(),
+                (false, true) => String::new(),
+            };
+            if self.style.color && cell.marker == Marker::None && glyph != ' ' {
+                let color = LANE_COLORS.get(cell.color).unwrap();
+                write!(self.out, "{}", Paint(color, format_args!("{glyph}{spacer}")))?;
+            } else {
+                write!(self.out, "{glyph}{spacer}")?;
+            }
+        }
+        // Each cell is a glyph and a spacer wide.
+        let text = self.style.columns.map_or(Cow::Borrowed(text), |columns| {
+            width::truncate(text, columns.saturating_sub(used.saturating_mul(2)))
+        });
+        writeln!(self.out, "{text}")?;
+        Ok(used)
+    }
+
+    const fn horizontal_glyph(&self) -> char {
+        if self.style.ascii {
+            '-'
+        } else {
+            '\u{2500}'
+        }
+    }
+
+    /// Draws a commit and the edges to its parents.
+    #[allow(clippy::unwrap_used, reason = "the rows have a cell for every lane")]
+    fn commit(&mut self, commit: &Commit, placement: &Placement) -> io::Result<()> {
+        let column = placement.column;
+        let color = placement.before.get(column).copied().flatten().unwrap_or_default();
+
+        // The commit row: the commit marker, plus lanes converging into it.
+        let mut row = verticals(&placement.before, &placement.converging);
+        let marker = if commit.collapsed == 0 { Marker::Commit } else { Marker::Collapsed };
+        *row.get_mut(column).unwrap() =
+            Cell { up: placement.expected, marker, color, ..Cell::default() };
+        // Draw farther lanes first, so that nearer lanes' endpoints keep their
+        // own color.
+        for &lane in placement.converging.iter().rev() {
+            let lane_color = placement.before.get(lane).copied().flatten().unwrap_or_default();
+            let cell = row.get_mut(lane).unwrap();
+            cell.up = true;
+            cell.color = lane_color;
+            horizontal(&mut row, lane, column, lane_color);
+        }
+        let placeholder;
+        let text = if commit.collapsed == 0 {
+            commit.text.as_str()
+        } else {
+            let ellipsis = if self.style.ascii { "..." } else { "\u{2026}" };
+            placeholder = format!("{ellipsis} {} commits {ellipsis}", commit.collapsed);
+            placeholder.as_str()
+        };
+        let mut lines = text.split('\n');
+        let width = self.write_row(&row, 0, lines.next().unwrap_or_default())?;
+
+        // (lane, whether the lane is new) for each edge to a parent in another
+        // lane.
+        let mut edges: Vec<(usize, bool)> = placement
+            .routes
+            .iter()
+            .filter(|route| route.lane != column)
+            .map(|route| (route.lane, route.new))
+            .collect();
+        let continues = edges.len() < placement.routes.len();
+
+        // Continuation lines of multi-line commit text.
+        let mut skip: Vec<usize> = edges.iter().filter(|edge| edge.1).map(|edge| edge.0).collect();
+        skip.push(column);
+        for line in lines {
+            let mut continuation = verticals(&placement.after, &skip);
+            if !placement.routes.is_empty() {
+                *continuation.get_mut(column).unwrap() =
+                    Cell { up: true, down: true, color, ..Cell::default() };
+            }
+            self.write_row(&continuation, width, line)?;
+        }
+
+        // The edge row, connecting this commit to parents in other lanes.
+        if !edges.is_empty() {
+            let mut edge_row = verticals(&placement.after, &skip);
+            *edge_row.get_mut(column).unwrap() =
+                Cell { up: true, down: continues, color, ..Cell::default() };
+            edges.sort_by_key(|edge| Reverse(edge.0.abs_diff(column)));
+            for &(lane, new) in &edges {
+                let lane_color = placement.after.get(lane).copied().flatten().unwrap_or_default();
+                let cell = edge_row.get_mut(lane).unwrap();
+                cell.up = !new;
+                cell.down = true;
+                cell.color = lane_color;
+                horizontal(&mut edge_row, column, lane, lane_color);
+            }
+            self.write_row(&edge_row, 0, "")?;
+        }
+        Ok(())
+    }
+}
+
+/// Renders `commits` (which must be in topological order, children first) as
+/// a graph, followed by each commit's text.
+pub fn render<W: Write>(commits: &[Commit], style: Style, out: W) -> io::Result<()> {
+    let mut renderer = Renderer { out, style };
+    for (commit, placement) in commits.iter().zip(layout::layout(commits)) {
+        renderer.commit(commit, &placement)?;
+    }
+    renderer.out.flush()
+}
@@ -0,0 +1,64 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Replace refs (`git replace`) and grafts (`.git/info/grafts`), which rewrite
+//! the ancestry git reports.
+//!
+//! git-tree computes the displayed commits with the same git commands and
+//! environment as the final `git log`, so both see the same rewritten history.
+//! The exceptions are the commit-graph, which records the original parents,
+//! and the cache, which is keyed by the tips alone; neither is used while the
+//! history is rewritten. `--no-replace-objects` turns replace refs off for
+//! every git command git-tree runs.
+
+use crate::log::Logged as _;
+use std::env::var_os;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// The environment variable that turns replace refs off.
+pub const NO_REPLACE_OBJECTS: &str = "GIT_NO_REPLACE_OBJECTS";
+
+/// Runs git with `args`, returning its output if it succeeds.
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).stderr(Stdio::null()).logged().output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+/// Returns whether replace refs are in effect: there are some, and they are
+/// not turned off.
+fn replace_refs() -> bool {
+    if var_os(NO_REPLACE_OBJECTS).is_some() {
+        return false;
+    }
+    if git(&["config", "--type=bool", "core.useReplaceRefs"])
+        .is_some_and(|value| value == "false\n")
+    {
+        return false;
+    }
+    let base = var_os("GIT_REPLACE_REF_BASE")
+        .and_then(|base| base.into_string().ok())
+        .unwrap_or_else(|| "refs/replace/".into());
+    git(&["for-each-ref", "--count=1", "--format=x", &base]).is_some_and(|refs| !refs.is_empty())
+}
+
+/// Returns whether grafts or replace refs rewrite the history git reports.
+pub fn rewritten() -> bool {
+    let grafts = git(&["rev-parse", "--git-path", "info/grafts"])
+        .is_some_and(|path| Path::new(path.trim_end()).exists());
+    grafts || replace_refs()
+}
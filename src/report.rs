@@ -0,0 +1,163 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `git-tree report`: a Markdown report on the local branches, for status
+//! updates and PR descriptions.
+//!
+//! Each branch gets a section with its upstream, how far ahead and behind the
+//! upstream it is, and its unmerged commits. For a branch with an upstream,
+//! those are the commits not in the upstream; otherwise they are the commits
+//! not on any other local branch.
+
+use crate::log::Logged as _;
+use crate::{graph, link};
+use core::fmt::Write as _;
+use std::io::{self, Write};
+use std::process::Command;
+
+/// The most unmerged commits listed per branch.
+const MAX_COMMITS: usize = 50;
+
+/// A local branch.
+struct Branch {
+    name: String,
+    /// The upstream branch, unless there is none or it is gone.
+    upstream: Option<String>,
+}
+
+/// Runs git with `args` and returns its output. Returns an error if git fails.
+pub fn git(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("git").args(args).env(graph::NO_LAZY_FETCH, "1").logged().output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(tr!(
+            "git {$command} failed: {$error}",
+            command = args.first().unwrap_or(&""),
+            error = String::from_utf8_lossy(&output.stderr).trim_end()
+        )));
+    }
+    String::from_utf8(output.stdout).map_err(io::Error::other)
+}
+
+/// Returns the local branches, sorted by name.
+fn branches() -> io::Result<Vec<Branch>> {
+    let output = git(&[
+        "for-each-ref",
+        "--format=%(refname:short)%00%(upstream:short)%00%(upstream:track)",
+        "refs/heads",
+    ])?;
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\0');
+            let name = fields.next()?.to_owned();
+            let upstream = fields.next().filter(|upstream| !upstream.is_empty());
+            let gone = fields.next() == Some("[gone]");
+            Some(Branch { name, upstream: upstream.filter(|_| !gone).map(str::to_owned) })
+        })
+        .collect())
+}
+
+/// Returns `text` with the characters that Markdown would interpret escaped.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Returns `url` percent-encoded where Markdown would otherwise end the link
+/// destination early or interpret it.
+fn destination(url: &str) -> String {
+    let mut encoded = String::with_capacity(url.len());
+    for c in url.chars() {
+        if matches!(c, ' ' | '(' | ')' | '<' | '>' | '\\') || c.is_control() {
+            for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                #[allow(clippy::unwrap_used, reason = "writing to a String cannot fail")]
+                write!(encoded, "%{byte:02X}").unwrap();
+            }
+        } else {
+            encoded.push(c);
+        }
+    }
+    encoded
+}
+
+/// Writes the report. If `link_template` is given (see `link`), each commit
+/// links to its URL.
+pub fn write<W: Write>(mut out: W, link_template: Option<&str>) -> io::Result<()> {
+    let branches = branches()?;
+    writeln!(out, "# {}", tr!("Branch report"))?;
+    for branch in &branches {
+        writeln!(out, "\n## {}\n", escape(&branch.name))?;
+        let full_name = format!("refs/heads/{}", branch.name);
+        let mut range = vec![full_name.clone()];
+        if let Some(upstream) = &branch.upstream {
+            let counts = git(&[
+                "rev-list",
+                "--left-right",
+                "--count",
+                &format!("{full_name}...{upstream}"),
+            ])?;
+            let (ahead, behind) = counts.trim_end().split_once('\t').unwrap_or_default();
+            let line = tr!(
+                "Upstream: {$upstream} (ahead {$ahead}, behind {$behind})",
+                upstream = escape(upstream),
+                ahead = ahead,
+                behind = behind,
+            );
+            writeln!(out, "- {line}")?;
+            range.push(format!("^{upstream}"));
+        } else {
+            writeln!(out, "- {}", tr!("Upstream: none"))?;
+            range.push("--not".into());
+            range.extend(
+                branches
+                    .iter()
+                    .filter(|other| other.name != branch.name)
+                    .map(|other| format!("refs/heads/{}", other.name)),
+            );
+        }
+        let mut args = vec!["log", "--no-color", "--format=%H %h %s"];
+        args.extend(range.iter().map(String::as_str));
+        let log = git(&args)?;
+        let commits: Vec<&str> = log.lines().collect();
+        if commits.is_empty() {
+            writeln!(out, "- {}", tr!("No unmerged commits."))?;
+            continue;
+        }
+        writeln!(out, "- {}", tr!("Unmerged commits ({$count}):", count = commits.len()))?;
+        for commit in commits.iter().take(MAX_COMMITS) {
+            let mut fields = commit.splitn(3, ' ');
+            let id = fields.next().unwrap_or_default();
+            let short_id = fields.next().unwrap_or(id);
+            let subject = escape(fields.next().unwrap_or_default());
+            match link_template {
+                Some(template) => {
+                    let url = destination(&link::expand(template, id));
+                    writeln!(out, "  - [`{short_id}`]({url}) {subject}")?;
+                }
+                None => writeln!(out, "  - `{short_id}` {subject}")?,
+            }
+        }
+        if commits.len() > MAX_COMMITS {
+            let more = commits.len().saturating_sub(MAX_COMMITS);
+            writeln!(out, "  - ... {}", tr!("and {$count} more", count = more))?;
+        }
+    }
+    out.flush()
+}
@@ -0,0 +1,197 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `git-tree restack`: the `git rebase --onto` commands that move a stack of
+//! dependent local branches onto the trunk's new tip.
+//!
+//! A stack is found from the ancestry of the branch tips (see `matrix`): a
+//! branch's parent is the nearest other local branch it contains that has
+//! commits of its own, or the trunk if there is none. The bottom branch of a
+//! stack is rebased from its fork point with the trunk, and every other branch
+//! from its parent's old tip, parents first. Branches pointing to the same
+//! commit as an earlier one are moved along with it.
+
+use crate::log::Logged as _;
+use crate::matrix::Counts;
+use crate::report::git;
+use crate::{interesting_branches, rev_parse, Branch};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::process::Command;
+
+/// A local branch in a stack.
+struct Node<'branches> {
+    branch: &'branches Branch,
+    /// The index of the parent branch, or `None` for the bottom of a stack.
+    parent: Option<usize>,
+    /// How many branches are below this one in its stack.
+    depth: usize,
+}
+
+/// Returns `arg` quoted for the shell, if it needs to be.
+fn quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./@^~:=+,%".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        return arg.to_owned();
+    }
+    let mut quoted = String::with_capacity(arg.len().saturating_add(2));
+    quoted.push('\'');
+    quoted.push_str(&arg.replace('\'', "'\\''"));
+    quoted.push('\'');
+    quoted
+}
+
+/// Returns `args` as a `git` command line.
+fn command_line(args: &[String]) -> String {
+    let mut line = "git".to_owned();
+    for arg in args {
+        line.push(' ');
+        line.push_str(&quote(arg));
+    }
+    line
+}
+
+/// Returns the commit at which `branch` forked from `trunk`, preferring the
+/// trunk's reflog (which survives the trunk being rewritten) to the merge base.
+pub fn fork_point(trunk: &str, branch: &str) -> io::Result<String> {
+    let point = git(&["merge-base", "--fork-point", trunk, branch])
+        .or_else(|_| git(&["merge-base", trunk, branch]))?;
+    Ok(point.trim_end().to_owned())
+}
+
+/// Returns the git commands that restack onto `trunk` the stack of the branch
+/// HEAD is on, or every stack if `all` is true.
+#[allow(clippy::unwrap_used, reason = "the indices are of nodes")]
+fn plan(trunk: &str, all: bool) -> io::Result<Vec<Vec<String>>> {
+    let trunk_id = rev_parse(trunk)
+        .ok_or_else(|| io::Error::other(tr!("unknown trunk {$trunk}", trunk = trunk)))?;
+    let mut buffer = Vec::with_capacity(256);
+    let (branches, _) = interesting_branches(&mut buffer)?;
+    let tips = branches.iter().map(|branch| branch.id.clone()).chain([trunk_id.clone()]);
+    let counts = Counts::new(&mut buffer, tips)?;
+    // The local branches with commits of their own, sorted by name.
+    let locals: Vec<_> = branches
+        .iter()
+        .filter(|branch| branch.refname.starts_with("refs/heads/"))
+        .filter(|branch| counts.ahead(&branch.id, &trunk_id) != 0)
+        .collect();
+    let mut nodes: Vec<_> = locals
+        .iter()
+        .map(|&branch| {
+            let candidates = locals.iter().enumerate().filter(|&(_, other)| {
+                counts.ahead(&other.id, &branch.id) == 0 && counts.ahead(&branch.id, &other.id) != 0
+            });
+            // The nearest is the one the fewest of the branch's commits are
+            // missing from; of several on the same commit, the first by name.
+            let parent = candidates.min_by_key(|&(_, other)| counts.ahead(&branch.id, &other.id));
+            Node { branch, parent: parent.map(|(index, _)| index), depth: 0 }
+        })
+        .collect();
+    for index in 0..nodes.len() {
+        let mut depth: usize = 0;
+        let mut parent = nodes.get(index).unwrap().parent;
+        while let Some(above) = parent {
+            depth = depth.saturating_add(1);
+            parent = nodes.get(above).unwrap().parent;
+        }
+        nodes.get_mut(index).unwrap().depth = depth;
+    }
+    let bottom = |mut index: usize| {
+        while let Some(parent) = nodes.get(index).unwrap().parent {
+            index = parent;
+        }
+        index
+    };
+    let head = git(&["symbolic-ref", "--quiet", "--short", "HEAD"]).ok();
+    let head = head.as_deref().map(str::trim_end);
+    let stack = if all {
+        None
+    } else {
+        let on_stack = head.and_then(|head| nodes.iter().position(|node| node.branch.name == head));
+        Some(bottom(on_stack.ok_or_else(|| {
+            io::Error::other(tr!("HEAD is not on a branch with commits to restack (see --all)"))
+        })?))
+    };
+    let mut order: Vec<_> = (0..nodes.len())
+        .filter(|&index| stack.is_none_or(|stack| bottom(index) == stack))
+        .collect();
+    order.sort_by_key(|&index| (nodes.get(index).unwrap().depth, index));
+    let mut commands = vec![];
+    // The branches that will move, by index, and the first branch moved on
+    // each commit.
+    let mut moved = vec![false; nodes.len()];
+    let mut leaders: HashMap<&str, &str> = HashMap::new();
+    for index in order {
+        let node = nodes.get(index).unwrap();
+        let name = node.branch.name.as_str();
+        if let Some(leader) = leaders.get(node.branch.id.as_str()) {
+            commands.push(vec!["branch".into(), "--force".into(), name.into(), (*leader).into()]);
+            *moved.get_mut(index).unwrap() = true;
+            continue;
+        }
+        let (onto, old_base) = match node.parent {
+            None => {
+                let point = fork_point(trunk, &node.branch.id)?;
+                if point == trunk_id {
+                    continue;
+                }
+                (trunk.to_owned(), point)
+            }
+            Some(parent) if *moved.get(parent).unwrap() => {
+                let parent = nodes.get(parent).unwrap().branch;
+                (parent.name.clone(), parent.id.clone())
+            }
+            Some(_) => continue,
+        };
+        commands.push(vec!["rebase".into(), "--onto".into(), onto, old_base, name.into()]);
+        *moved.get_mut(index).unwrap() = true;
+        leaders.insert(&node.branch.id, name);
+    }
+    // Each rebase leaves its branch checked out.
+    if let Some(head) = head.filter(|_| !commands.is_empty()) {
+        commands.push(vec!["switch".into(), "--quiet".into(), head.into()]);
+    }
+    Ok(commands)
+}
+
+/// Writes the commands that restack onto `trunk` the stack HEAD is on (or
+/// every stack, if `all` is true), or, if `exec` is true, runs them one at a
+/// time, stopping at the first that fails (e.g. on a conflict).
+pub fn restack<W: Write>(mut out: W, trunk: &str, all: bool, exec: bool) -> io::Result<()> {
+    let commands = plan(trunk, all)?;
+    if !exec {
+        for command in &commands {
+            writeln!(out, "{}", command_line(command))?;
+        }
+        return out.flush();
+    }
+    for (index, command) in commands.iter().enumerate() {
+        writeln!(out, "{}", command_line(command))?;
+        out.flush()?;
+        let status = Command::new("git").args(command).logged().status()?;
+        if !status.success() {
+            let mut message = tr!("{$command} failed", command = command_line(command));
+            if let Some(rest) =
+                commands.get(index.saturating_add(1)..).filter(|rest| !rest.is_empty())
+            {
+                message.push_str(&tr!("; once it is resolved, run the rest:"));
+                for remaining in rest {
+                    message.extend(["\n  ", &command_line(remaining)]);
+                }
+            }
+            return Err(io::Error::other(message));
+        }
+    }
+    Ok(())
+}
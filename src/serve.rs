@@ -0,0 +1,368 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `git-tree serve`: a JSON-RPC 2.0 server for editor integrations.
+//!
+//! Requests and responses are single-line JSON documents separated by
+//! newlines, exchanged over stdin/stdout or (with `--socket <path>`) over each
+//! connection to a Unix socket. Supported methods:
+//!
+//! - `getGraph` (params: `{"collapse": N}`, optional): the interesting
+//!   branches, merge bases, and displayed commits, with runs of more than `N`
+//!   linear commits replaced by placeholders (see `graph::collapse`).
+//! - `listBranches`: the interesting branches and HEAD.
+//! - `explainCommit` (params: `{"commit": "<rev>"}`): why a commit is or is
+//!   not displayed.
+//!
+//! The server keeps the most recently computed view in memory and only
+//! recomputes it when HEAD or an interesting branch moves.
+
+use crate::abbrev;
+use crate::graph::{self, Commit};
+use crate::json::Value;
+use crate::log::Logged as _;
+use crate::timing::Timing;
+use crate::{git_status, interesting_branches, View};
+use alloc::borrow::Cow;
+#[cfg(unix)]
+use alloc::sync::Arc;
+#[cfg(unix)]
+use std::fs;
+#[cfg(unix)]
+use std::io::ErrorKind;
+use std::io::{self, BufRead, Write};
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt as _;
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::Path;
+use std::process::{Command, Stdio};
+#[cfg(unix)]
+use std::sync::{Mutex, PoisonError};
+#[cfg(unix)]
+use std::thread;
+
+// JSON-RPC error codes.
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+const INTERNAL_ERROR: i32 = -32603;
+
+/// The server's memory of previous computations.
+#[derive(Default)]
+struct State {
+    /// The most recently computed view.
+    computed: Option<Computed>,
+}
+
+/// A view and what has been computed from it.
+struct Computed {
+    /// The tips the view was computed for.
+    tips: Vec<String>,
+    view: View,
+    /// The displayed commits of `view`, loaded on demand.
+    commits: Option<Vec<Commit>>,
+}
+
+impl State {
+    /// Brings the view up to date with the repository, recomputing it only if
+    /// a tip moved.
+    fn refresh(&mut self) -> io::Result<&mut Computed> {
+        let mut buffer = Vec::with_capacity(256);
+        let (branches, head) = interesting_branches(&mut buffer)?;
+        let tips = View::tips(&branches, head.as_ref(), &[]);
+        // The old view is taken first, so that a failure leaves none.
+        let computed = match self.computed.take() {
+            Some(computed) if computed.tips == tips => computed,
+            _ => Computed {
+                tips,
+                view: View::compute(
+                    buffer,
+                    branches,
+                    head,
+                    None,
+                    false,
+                    0,
+                    &mut Timing::new(false),
+                )?,
+                commits: None,
+            },
+        };
+        Ok(self.computed.insert(computed))
+    }
+
+    /// Brings the view up to date and returns it along with its displayed
+    /// commits.
+    fn refresh_commits(&mut self) -> io::Result<(&View, &[Commit])> {
+        let computed = self.refresh()?;
+        let commits = match computed.commits.take() {
+            Some(commits) => commits,
+            None => graph::load(&computed.view.revisions(), None, false)?,
+        };
+        Ok((&computed.view, computed.commits.insert(commits)))
+    }
+}
+
+/// Returns the error response for a failure to run git, which the client can
+/// do nothing about.
+#[allow(clippy::needless_pass_by_value, reason = "it is passed to map_err")]
+fn internal_error(error: io::Error) -> (i32, String) {
+    (INTERNAL_ERROR, error.to_string())
+}
+
+/// Returns the interesting branches as a JSON array.
+fn branches_json(view: &View) -> Value {
+    Value::Array(
+        view.branches
+            .iter()
+            .map(|branch| {
+                Value::object([
+                    ("name", branch.name.as_str().into()),
+                    ("id", branch.id.as_str().into()),
+                    ("time", Value::number(branch.time)),
+                ])
+            })
+            .collect(),
+    )
+}
+
+/// Handles `getGraph`.
+fn get_graph(state: &mut State, params: Option<&Value>) -> Result<Value, (i32, String)> {
+    let collapse = match params.and_then(|params| params.get("collapse")) {
+        None | Some(&Value::Null) => None,
+        Some(max_run) => Some(max_run.as_usize().ok_or_else(|| {
+            (INVALID_PARAMS, "\"collapse\" must be a non-negative integer".to_owned())
+        })?),
+    };
+    let (view, commits) = state.refresh_commits().map_err(internal_error)?;
+    let branches = view.branches_by_id();
+    let commits = collapse.map_or(Cow::Borrowed(commits), |max_run| {
+        Cow::Owned(graph::collapse(commits.to_vec(), &view.landmarks(), max_run))
+    });
+    let commits = commits
+        .iter()
+        .map(|commit| {
+            Value::object([
+                ("id", commit.id.as_str().into()),
+                ("parents", Value::strings(commit.parents.iter().map(String::as_str))),
+                ("author", commit.author.as_str().into()),
+                ("time", Value::number(commit.time)),
+                ("subject", commit.subject.as_str().into()),
+                (
+                    "branches",
+                    Value::strings(branches.get(commit.id.as_str()).into_iter().flatten().copied()),
+                ),
+                ("collapsed", Value::number(commit.collapsed.try_into().unwrap_or(i64::MAX))),
+            ])
+        })
+        .collect();
+    Ok(Value::object([
+        ("head", view.head.as_deref().into()),
+        ("branches", branches_json(view)),
+        ("mergeBases", Value::strings(view.merge_bases.iter().map(String::as_str))),
+        ("commits", Value::Array(commits)),
+    ]))
+}
+
+/// Handles `listBranches`.
+fn list_branches(state: &mut State) -> Result<Value, (i32, String)> {
+    let view = &state.refresh().map_err(internal_error)?.view;
+    Ok(Value::object([("head", view.head.as_deref().into()), ("branches", branches_json(view))]))
+}
+
+/// `git merge-base --is-ancestor`'s exit status when the commit is not an
+/// ancestor.
+const NOT_ANCESTOR: i32 = 1;
+
+/// Returns whether `ancestor` is an ancestor of (or equal to) any of the
+/// `descendants`.
+fn is_ancestor<'id>(
+    ancestor: &str,
+    descendants: impl IntoIterator<Item = &'id String>,
+) -> io::Result<bool> {
+    for descendant in descendants {
+        let status = Command::new("git")
+            .args(["merge-base", "--is-ancestor", ancestor, descendant])
+            .env(graph::NO_LAZY_FETCH, "1")
+            .logged()
+            .status()?;
+        if status.code() != Some(NOT_ANCESTOR) {
+            git_status("merge-base", status)?;
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Handles `explainCommit`.
+fn explain_commit(state: &mut State, params: Option<&Value>) -> Result<Value, (i32, String)> {
+    let Some(rev) = params.and_then(|params| params.get("commit")).and_then(Value::as_str) else {
+        return Err((INVALID_PARAMS, "missing \"commit\" parameter".into()));
+    };
+    let output = Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet", "--end-of-options"])
+        .arg(format!("{rev}^{{commit}}"))
+        .env(graph::NO_LAZY_FETCH, "1")
+        .stderr(Stdio::null())
+        .logged()
+        .output()
+        .map_err(internal_error)?;
+    if !output.status.success() {
+        return Err((INVALID_PARAMS, format!("unknown commit: {rev}")));
+    }
+    let id = String::from_utf8_lossy(&output.stdout).trim_end().to_owned();
+    let (view, commits) = state.refresh_commits().map_err(internal_error)?;
+    let displayed = commits.iter().any(|commit| commit.id == id);
+    let branches = view.branches_by_id().remove(id.as_str()).unwrap_or_default();
+    let is_head = view.head.as_ref() == Some(&id);
+    let is_merge_base = view.merge_bases.contains(&id);
+    let mut roles = vec![];
+    let mut reasons = vec![];
+    if is_head {
+        roles.push("head");
+        reasons.push("it is HEAD".to_owned());
+    }
+    if !branches.is_empty() {
+        roles.push("tip");
+        reasons.push(format!("it is the tip of {}", branches.join(", ")));
+    }
+    if is_merge_base {
+        roles.push("mergeBase");
+        reasons.push("it is a merge base of the interesting commits".to_owned());
+    }
+    if displayed && roles.is_empty() {
+        roles.push("path");
+        reasons.push("it lies between an interesting commit and a merge base".to_owned());
+    }
+    if !displayed {
+        let tips = view.head.iter().chain(view.branches.iter().map(|branch| &branch.id));
+        if is_ancestor(&id, &view.merge_bases).map_err(internal_error)? {
+            roles.push("belowMergeBase");
+            reasons.push("it is an ancestor of a merge base".to_owned());
+        } else if is_ancestor(&id, tips).map_err(internal_error)? {
+            roles.push("deadEnd");
+            reasons.push(
+                "it is reachable from an interesting commit but cannot reach a merge base"
+                    .to_owned(),
+            );
+        } else {
+            roles.push("unreachable");
+            reasons.push("it is not reachable from HEAD or any interesting branch".to_owned());
+        }
+    }
+    let verdict = if displayed { "displayed" } else { "not displayed" };
+    let explanation =
+        format!("{} is {verdict} because {}.", abbrev::short(&id), reasons.join(" and "));
+    Ok(Value::object([
+        ("id", id.as_str().into()),
+        ("displayed", displayed.into()),
+        ("roles", Value::strings(roles)),
+        ("explanation", explanation.into()),
+    ]))
+}
+
+/// Handles a single request line, returning the response line (or `None` for
+/// notifications, which get no response).
+fn handle(state: &mut State, line: &str) -> Option<String> {
+    let (id, result) = match Value::parse(line) {
+        None => (Value::Null, Err((PARSE_ERROR, "parse error".to_owned()))),
+        Some(request) => {
+            let id = request.get("id").cloned();
+            let params = request.get("params");
+            let result = match request.get("method").and_then(Value::as_str) {
+                None => Err((INVALID_REQUEST, "invalid request".to_owned())),
+                Some("getGraph") => get_graph(state, params),
+                Some("listBranches") => list_branches(state),
+                Some("explainCommit") => explain_commit(state, params),
+                Some(method) => Err((METHOD_NOT_FOUND, format!("method not found: {method}"))),
+            };
+            // Requests without an ID are notifications, which get no response
+            // unless they are malformed.
+            match (id, &result) {
+                (Some(id), _) => (id, result),
+                (None, &Err((INVALID_REQUEST, _))) => (Value::Null, result),
+                (None, _) => return None,
+            }
+        }
+    };
+    let outcome = match result {
+        Ok(result) => ("result", result),
+        Err((code, message)) => (
+            "error",
+            Value::object([("code", Value::number(code.into())), ("message", message.into())]),
+        ),
+    };
+    let mut response = String::new();
+    Value::object([("jsonrpc", "2.0".into()), ("id", id), outcome]).write(&mut response);
+    Some(response)
+}
+
+/// Serves requests read from `input`, writing responses to `output`, until
+/// `input` is exhausted. `handle` handles a single request.
+fn serve_stream<R, W, H>(input: R, mut output: W, mut handle: H) -> io::Result<()>
+where
+    R: BufRead,
+    W: Write,
+    H: FnMut(&str) -> Option<String>,
+{
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle(&line) {
+            writeln!(output, "{response}")?;
+            output.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Serves requests on stdin/stdout.
+pub fn serve_stdio() -> io::Result<()> {
+    let mut state = State::default();
+    serve_stream(io::stdin().lock(), io::stdout().lock(), |line| handle(&mut state, line))
+}
+
+/// Serves requests on a Unix socket at `path`, handling each connection on its
+/// own thread.
+#[cfg(unix)]
+pub fn serve_socket(path: &Path) -> io::Result<()> {
+    // Remove a socket left behind by a previous server, but never anything
+    // that isn't a socket, nor the socket of a server that is still running.
+    if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                ErrorKind::AddrInUse,
+                tr!("{$path} is in use by another git-tree serve", path = path.display()),
+            ));
+        }
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    let state = Arc::new(Mutex::new(State::default()));
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let state = Arc::clone(&state);
+        thread::spawn(move || {
+            let Ok(reader) = stream.try_clone() else { return };
+            drop(serve_stream(io::BufReader::new(reader), stream, |line| {
+                handle(&mut state.lock().unwrap_or_else(PoisonError::into_inner), line)
+            }));
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,42 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Shallow clone support. git lists the commits at which a shallow clone's
+//! history is cut off (whose parents are missing) in `.git/shallow`; git
+//! treats them as root commits, so history walks stop there by themselves.
+//! git-tree marks them in the native graph so the cut is not mistaken for the
+//! start of history.
+
+use crate::log::Logged as _;
+use std::collections::HashSet;
+use std::fs;
+use std::process::{Command, Stdio};
+
+/// Returns the shallow boundary commits, or none if the repository is not a
+/// shallow clone.
+pub fn boundary() -> HashSet<String> {
+    let Ok(output) = Command::new("git")
+        .args(["rev-parse", "--git-path", "shallow"])
+        .stderr(Stdio::null())
+        .logged()
+        .output()
+    else {
+        return HashSet::new();
+    };
+    let Ok(path) = String::from_utf8(output.stdout) else { return HashSet::new() };
+    fs::read_to_string(path.trim_end()).map_or_else(
+        |_| HashSet::new(),
+        |contents| contents.split_whitespace().map(String::from).collect(),
+    )
+}
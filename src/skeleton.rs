@@ -0,0 +1,139 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--decorations-only`: the branch skeleton, for histories too big to read
+//! commit by commit.
+//!
+//! Only the commits that matter to the shape of the graph are shown: those a
+//! branch or tag points to, HEAD, the merge bases, and the fork points (the
+//! commits with more than one displayed child). Everything between them is
+//! compressed into a placeholder on the edge, like `--collapse`'s, saying how
+//! many commits it stands for (unless it is a single commit, which is shown).
+//! As every hidden commit has a single displayed child, each belongs to exactly
+//! one such edge.
+
+use crate::graph::{self, Commit};
+use crate::log::Logged as _;
+use crate::{git_status, namespace_prefix};
+use core::mem;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::process::Command;
+
+/// Returns the IDs of the commits that a branch, remote branch, or tag points
+/// to, peeling annotated tags.
+pub fn decorated() -> io::Result<HashSet<String>> {
+    let prefix = namespace_prefix().unwrap_or_default();
+    let output = Command::new("git")
+        .args(["for-each-ref", "--format=%(objectname) %(*objectname)"])
+        .args(["refs/heads", "refs/remotes", "refs/tags"].map(|refs| format!("{prefix}{refs}")))
+        .env(graph::NO_LAZY_FETCH, "1")
+        .logged()
+        .output()?;
+    git_status("for-each-ref", output.status)?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (id, peeled) = line.split_once(' ')?;
+            Some(if peeled.is_empty() { id } else { peeled }.to_owned())
+        })
+        .collect())
+}
+
+/// A run of hidden commits between a shown commit and the shown commits below.
+struct Edge {
+    /// The shown commit above.
+    child: usize,
+    /// The hidden commit that is `child`'s parent, where the placeholder goes.
+    start: usize,
+    /// The number of hidden commits.
+    hidden: usize,
+    /// The IDs of the parents of the hidden commits that are not hidden.
+    below: Vec<String>,
+}
+
+/// Hides the commits of `commits` (in topological order) that are neither in
+/// `keep` (the landmarks and the decorated commits) nor tips or fork points,
+/// replacing each run of more than one of them below a shown commit with a
+/// placeholder. The result is in topological order.
+#[allow(clippy::unwrap_used, reason = "the indices are of commits")]
+pub fn simplify(mut commits: Vec<Commit>, keep: &HashSet<&str>) -> Vec<Commit> {
+    let index: HashMap<&str, usize> =
+        commits.iter().enumerate().map(|(i, commit)| (commit.id.as_str(), i)).collect();
+    let mut children: Vec<usize> = vec![0; commits.len()];
+    for commit in &commits {
+        for &parent in commit.parents.iter().filter_map(|parent| index.get(parent.as_str())) {
+            let count = children.get_mut(parent).unwrap();
+            *count = count.saturating_add(1);
+        }
+    }
+    let mut shown: Vec<bool> = commits
+        .iter()
+        .zip(&children)
+        .map(|(commit, &count)| count != 1 || keep.contains(commit.id.as_str()))
+        .collect();
+    let hidden = |parent: &str| index.get(parent).copied().filter(|&i| !shown.get(i).unwrap());
+    let mut edges = vec![];
+    for (child, commit) in commits.iter().enumerate().filter(|&(i, _)| *shown.get(i).unwrap()) {
+        for start in commit.parents.iter().filter_map(|parent| hidden(parent)) {
+            let mut edge = Edge { child, start, hidden: 0, below: vec![] };
+            // First parents first, so that the placeholder's parents keep
+            // their order.
+            let mut stack = vec![start];
+            while let Some(i) = stack.pop() {
+                edge.hidden = edge.hidden.saturating_add(1);
+                let parents = &commits.get(i).unwrap().parents;
+                for parent in parents {
+                    if hidden(parent).is_none() && !edge.below.contains(parent) {
+                        edge.below.push(parent.clone());
+                    }
+                }
+                stack.extend(parents.iter().rev().filter_map(|parent| hidden(parent)));
+            }
+            edges.push(edge);
+        }
+    }
+    for edge in edges {
+        // A placeholder for a single commit would not save anything.
+        if edge.hidden == 1 {
+            *shown.get_mut(edge.start).unwrap() = true;
+            continue;
+        }
+        let start = commits.get(edge.start).unwrap();
+        let placeholder = Commit {
+            id: format!("collapsed-{}", start.id),
+            parents: edge.below,
+            author: String::new(),
+            time: start.time,
+            committer_time: start.committer_time,
+            subject: String::new(),
+            text: String::new(),
+            collapsed: edge.hidden,
+        };
+        let placeholder_id = placeholder.id.clone();
+        let start_id = mem::replace(commits.get_mut(edge.start).unwrap(), placeholder).id;
+        for parent in &mut commits.get_mut(edge.child).unwrap().parents {
+            if *parent == start_id {
+                parent.clone_from(&placeholder_id);
+            }
+        }
+    }
+    // The placeholders took their first hidden commit's place.
+    commits
+        .into_iter()
+        .zip(shown)
+        .filter(|(commit, shown)| *shown || commit.collapsed != 0)
+        .map(|(commit, _)| commit)
+        .collect()
+}
@@ -0,0 +1,146 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `git-tree status`: a line per local branch with its upstream, how far ahead
+//! and behind the upstream it is, and its last commit, like `git branch -vv`
+//! but as a table.
+//!
+//! The ahead and behind counts of every branch come from a single walk of the
+//! history above the merge bases of the branches and their upstreams (see
+//! `matrix::Counts`), rather than from a `git rev-list` per branch.
+
+use crate::date::{self, Format, Time};
+use crate::matrix::Counts;
+use crate::report::git;
+use crate::{namespace_prefix, quote, width};
+use alloc::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// A local branch.
+struct Branch {
+    name: String,
+    id: String,
+    /// Whether HEAD points to the branch.
+    head: bool,
+    /// The upstream's full ref name, if the branch has one.
+    upstream: Option<String>,
+    /// Whether the upstream no longer exists.
+    gone: bool,
+    /// When the last commit was made, in the chosen date format (relative to
+    /// now by default, e.g. `2 days ago`).
+    age: String,
+    subject: String,
+}
+
+/// Returns the local branches, sorted by name, and the commit each local and
+/// remote branch points to, by full ref name.
+fn branches(prefix: &str) -> io::Result<(Vec<Branch>, HashMap<String, String>)> {
+    let output = git(&[
+        "for-each-ref",
+        "--format=%(HEAD)%00%(refname)%00%(objectname)%00%(upstream)%00%(upstream:track)%00\
+         %(committerdate:raw)%00%(subject)",
+        &format!("{prefix}refs/heads"),
+        &format!("{prefix}refs/remotes"),
+    ])?;
+    let strip = |full: &str| full.strip_prefix(prefix).unwrap_or(full).to_owned();
+    let (mut branches, mut ids) = (vec![], HashMap::new());
+    for line in output.lines() {
+        let mut fields = line.split('\0');
+        let mut field = || fields.next().unwrap_or_default();
+        let (marker, refname, id, upstream, track, age, subject) =
+            (field(), strip(field()), field(), field(), field(), field(), field());
+        if let Some(name) = refname.strip_prefix("refs/heads/") {
+            branches.push(Branch {
+                name: name.to_owned(),
+                id: id.to_owned(),
+                // In a namespace, the marker is for the repository's HEAD.
+                head: marker == "*" && prefix.is_empty(),
+                upstream: (!upstream.is_empty()).then(|| strip(upstream)),
+                gone: track == "[gone]",
+                age: Time::parse_raw(age)
+                    .map_or_else(String::new, |time| date::format(time, Format::Relative)),
+                subject: subject.to_owned(),
+            });
+        }
+        ids.insert(refname, id.to_owned());
+    }
+    Ok((branches, ids))
+}
+
+/// Returns the short name of the full ref name `refname`.
+fn short_name(refname: &str) -> &str {
+    refname
+        .strip_prefix("refs/heads/")
+        .or_else(|| refname.strip_prefix("refs/remotes/"))
+        .unwrap_or(refname)
+}
+
+/// Writes the status of the local branches.
+pub fn write<W: Write>(mut out: W) -> io::Result<()> {
+    let (branches, ids) = branches(&namespace_prefix().unwrap_or_default())?;
+    if branches.is_empty() {
+        return Ok(());
+    }
+    let upstream_id = |branch: &Branch| {
+        let upstream = branch.upstream.as_ref().filter(|_| !branch.gone)?;
+        ids.get(upstream)
+    };
+    let tips = branches
+        .iter()
+        .flat_map(|branch| [Some(&branch.id), upstream_id(branch)])
+        .flatten()
+        .cloned();
+    let counts = Counts::new(&mut Vec::with_capacity(256), tips)?;
+    let rows: Vec<[String; 4]> = branches
+        .iter()
+        .map(|branch| {
+            let upstream = branch.upstream.as_deref().map_or_else(
+                || "-".to_owned(),
+                |refname| quote::name(short_name(refname)).into_owned(),
+            );
+            let track = match upstream_id(branch) {
+                Some(id) => {
+                    format!("+{} -{}", counts.ahead(&branch.id, id), counts.ahead(id, &branch.id))
+                }
+                None if branch.gone => "gone".to_owned(),
+                None => String::new(),
+            };
+            [quote::name(&branch.name).into_owned(), upstream, track, branch.age.clone()]
+        })
+        .collect();
+    // Padding counts columns, which a name outside ASCII may have fewer (or,
+    // in CJK, more) of than bytes when it is not quoted.
+    let mut widths: [usize; 4] = [0; 4];
+    for row in &rows {
+        for (column_width, cell) in widths.iter_mut().zip(row) {
+            *column_width = (*column_width).max(width::width(cell));
+        }
+    }
+    // The subjects are cut to fit the terminal, if its width is known.
+    let used =
+        widths.iter().fold(2, |used: usize, &column| used.saturating_add(column).saturating_add(2));
+    let room = width::columns().map(|columns| columns.saturating_sub(used));
+    for (branch, row) in branches.iter().zip(&rows) {
+        write!(out, "{}", if branch.head { "* " } else { "  " })?;
+        for (cell, &column_width) in row.iter().zip(&widths) {
+            write!(out, "{}  ", width::pad(cell, column_width))?;
+        }
+        let subject = room.map_or(Cow::Borrowed(branch.subject.as_str()), |room| {
+            width::truncate(&branch.subject, room)
+        });
+        writeln!(out, "{subject}")?;
+    }
+    out.flush()
+}
@@ -0,0 +1,110 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Terminal colors for the output git-tree writes itself (rather than leaving
+//! to `git log`).
+//!
+//! Whether to color follows git's rules: `--color`/`--no-color` win, then the
+//! `NO_COLOR` environment variable disables color, and otherwise git's
+//! `color.ui` setting decides (by default, color if stdout is a terminal).
+
+use crate::layout::COLORS;
+use crate::log::Logged as _;
+use core::fmt::{self, Display, Formatter};
+use std::env::var_os;
+use std::io::{stdout, IsTerminal as _};
+use std::process::Command;
+
+/// The `--color` option.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Parses the value of `--color=<when>`.
+    pub fn parse(when: &str) -> Option<Self> {
+        match when {
+            "auto" => Some(Self::Auto),
+            "always" => Some(Self::Always),
+            "never" => Some(Self::Never),
+            _ => None,
+        }
+    }
+
+    /// Returns whether to color output written to stdout.
+    pub fn stdout(self) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto if var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) => false,
+            Self::Auto => {
+                let tty = if stdout().is_terminal() { "true" } else { "false" };
+                Command::new("git")
+                    .args(["config", "--get-colorbool", "color.ui", tty])
+                    .logged()
+                    .output()
+                    .is_ok_and(|output| output.stdout.starts_with(b"true"))
+            }
+        }
+    }
+}
+
+/// Makes sure the console interprets the ANSI escape sequences git-tree writes,
+/// which older Windows consoles only do when asked to. Returns `false` if it
+/// cannot, in which case color should be turned off.
+#[cfg(windows)]
+pub fn enable_escapes() -> bool {
+    use core::ffi::c_void;
+    use std::os::windows::io::AsRawHandle as _;
+
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x0004;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetConsoleMode(console: *mut c_void, mode: *mut u32) -> i32;
+        fn SetConsoleMode(console: *mut c_void, mode: u32) -> i32;
+    }
+
+    let console = stdout().as_raw_handle();
+    let mut mode = 0;
+    // SAFETY: `console` is stdout's handle, which stays open, and `mode` is a
+    // valid place to write the mode to.
+    if unsafe { GetConsoleMode(console, &raw mut mode) } == 0 {
+        // Not a console (e.g. a pipe to the pager), so there is nothing to do.
+        return true;
+    }
+    // SAFETY: `console` is stdout's handle, which stays open.
+    unsafe { SetConsoleMode(console, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0 }
+}
+
+/// Makes sure the console interprets the ANSI escape sequences git-tree writes,
+/// which every supported terminal outside Windows does.
+#[cfg(not(windows))]
+pub const fn enable_escapes() -> bool {
+    true
+}
+
+/// ANSI color codes for the graph lanes, indexed by `layout` color.
+pub const LANE_COLORS: [&str; COLORS] = ["31", "32", "33", "34", "35", "36"];
+
+/// Displays `text` in the ANSI color (SGR parameters) `code`.
+pub struct Paint<'code, T: Display>(pub &'code str, pub T);
+
+impl<T: Display> Display for Paint<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "\x1b[{}m{}\x1b[m", self.0, self.1)
+    }
+}
@@ -0,0 +1,269 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--format=svg`: a standalone SVG picture of the graph, drawn with a layered
+//! (Sugiyama-style) layout rather than one commit per row.
+//!
+//! The layout has three steps:
+//!
+//! 1. Layering: every commit goes one layer below its lowest child, so the
+//!    tips are at the top. Edges that span several layers get a dummy node in
+//!    each layer they cross.
+//! 2. Crossing reduction: the nodes of each layer are repeatedly reordered by
+//!    the average position of their neighbors in the layer above, then in the
+//!    layer below.
+//! 3. Drawing: each node is drawn at its position in its layer, and each edge
+//!    as a path through its dummy nodes.
+
+use crate::graph::Commit;
+use crate::html::escape;
+use crate::link;
+use core::cmp::Ordering;
+use core::mem;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Horizontal distance between nodes in a layer, in pixels.
+const NODE_SPACING: usize = 120;
+/// Vertical distance between layers, in pixels.
+const LAYER_SPACING: usize = 48;
+/// Margin around the graph, in pixels.
+const MARGIN: usize = 24;
+/// Number of crossing reduction passes (each pass sweeps down, then up).
+const PASSES: usize = 8;
+
+const STYLE: &str = "text { font-family: monospace; font-size: 12px; }
+path { fill: none; stroke: #888; stroke-width: 1.5; }
+circle { fill: #1f77b4; stroke: #fff; stroke-width: 1.5; }
+circle.base { fill: #ff7f0e; stroke: #000; stroke-width: 2; }
+circle.collapsed { fill: #ccc; }
+.label { fill: #1f77b4; font-weight: bold; }
+.head { fill: #2ca02c; }";
+
+/// A node of the layered graph: a commit, or a dummy node on a long edge.
+struct Node {
+    /// The commit's index, or `None` for dummy nodes.
+    commit: Option<usize>,
+    layer: usize,
+    /// Neighbors in the layer above and below.
+    up: Vec<usize>,
+    down: Vec<usize>,
+    /// Position within the layer.
+    position: usize,
+}
+
+/// The layered graph.
+struct Layered {
+    nodes: Vec<Node>,
+    /// The nodes of each layer, in order.
+    layers: Vec<Vec<usize>>,
+    /// The chains of nodes (commit, dummies..., parent commit) that make up
+    /// each edge.
+    edges: Vec<Vec<usize>>,
+}
+
+#[allow(clippy::unwrap_used, reason = "node and layer indices are those that were added")]
+impl Layered {
+    /// Layers `commits`, which must be in topological order (children first).
+    fn new(commits: &[Commit]) -> Self {
+        let index: HashMap<&str, usize> =
+            commits.iter().enumerate().map(|(i, commit)| (commit.id.as_str(), i)).collect();
+        let mut layer_of: Vec<usize> = vec![0; commits.len()];
+        for (i, commit) in commits.iter().enumerate() {
+            let below = layer_of.get(i).copied().unwrap_or_default().saturating_add(1);
+            for parent in &commit.parents {
+                if let Some(layer) = index.get(parent.as_str()).and_then(|&p| layer_of.get_mut(p)) {
+                    *layer = (*layer).max(below);
+                }
+            }
+        }
+        let mut layered = Self { nodes: vec![], layers: vec![], edges: vec![] };
+        // Commit nodes come first, so node i is commit i.
+        for (i, &layer) in layer_of.iter().enumerate() {
+            layered.add(Some(i), layer);
+        }
+        for (i, commit) in commits.iter().enumerate() {
+            for parent in commit.parents.iter().filter_map(|parent| index.get(parent.as_str())) {
+                let mut chain = vec![i];
+                let (top, bottom) = (layer_of.get(i).copied(), layer_of.get(*parent).copied());
+                for layer in top.unwrap_or_default().saturating_add(1)..bottom.unwrap_or_default() {
+                    chain.push(layered.add(None, layer));
+                }
+                chain.push(*parent);
+                for pair in chain.windows(2) {
+                    if let &[above, below] = pair {
+                        layered.nodes.get_mut(above).unwrap().down.push(below);
+                        layered.nodes.get_mut(below).unwrap().up.push(above);
+                    }
+                }
+                layered.edges.push(chain);
+            }
+        }
+        layered
+    }
+
+    /// Adds a node at the end of `layer`, returning its index.
+    fn add(&mut self, commit: Option<usize>, layer: usize) -> usize {
+        if self.layers.len() <= layer {
+            self.layers.resize_with(layer.saturating_add(1), Vec::new);
+        }
+        let nodes = self.layers.get_mut(layer).unwrap();
+        let node = self.nodes.len();
+        self.nodes.push(Node { commit, layer, up: vec![], down: vec![], position: nodes.len() });
+        nodes.push(node);
+        node
+    }
+
+    /// Returns the sum of the positions of `neighbors` and their count, or the
+    /// node's own position if it has no neighbors.
+    fn barycenter(&self, node: usize, neighbors: &[usize]) -> (usize, usize) {
+        if neighbors.is_empty() {
+            return (self.nodes.get(node).unwrap().position, 1);
+        }
+        let sum = neighbors
+            .iter()
+            .map(|&neighbor| self.nodes.get(neighbor).unwrap().position)
+            .fold(0, usize::saturating_add);
+        (sum, neighbors.len())
+    }
+
+    /// Reorders `layer` by the barycenters of its nodes' neighbors above (if
+    /// `downward`) or below.
+    fn reorder(&mut self, layer: usize, downward: bool) {
+        let mut order = mem::take(self.layers.get_mut(layer).unwrap());
+        let mut keys: HashMap<usize, (usize, usize)> = HashMap::new();
+        for &node in &order {
+            let neighbors = if downward {
+                &self.nodes.get(node).unwrap().up
+            } else {
+                &self.nodes.get(node).unwrap().down
+            };
+            keys.insert(node, self.barycenter(node, neighbors));
+        }
+        // Compare the averages sum / count by cross-multiplying. The sort is
+        // stable, so ties keep their current order.
+        order.sort_by(|a, b| match (keys.get(a), keys.get(b)) {
+            (Some(&(sum_a, count_a)), Some(&(sum_b, count_b))) => {
+                sum_a.saturating_mul(count_b).cmp(&sum_b.saturating_mul(count_a))
+            }
+            _ => Ordering::Equal,
+        });
+        for (position, &node) in order.iter().enumerate() {
+            self.nodes.get_mut(node).unwrap().position = position;
+        }
+        *self.layers.get_mut(layer).unwrap() = order;
+    }
+
+    /// Reduces edge crossings by sweeping down and up the layers.
+    fn reduce_crossings(&mut self) {
+        for _ in 0..PASSES {
+            for layer in 1..self.layers.len() {
+                self.reorder(layer, true);
+            }
+            for layer in (0..self.layers.len().saturating_sub(1)).rev() {
+                self.reorder(layer, false);
+            }
+        }
+    }
+
+    /// Returns the coordinates of `node`'s center.
+    fn point(&self, node: usize) -> (usize, usize) {
+        let node = self.nodes.get(node).unwrap();
+        (
+            MARGIN.saturating_add(node.position.saturating_mul(NODE_SPACING)),
+            MARGIN.saturating_add(node.layer.saturating_mul(LAYER_SPACING)),
+        )
+    }
+}
+
+/// Writes the picture. `commits` must be in topological order (children
+/// first). `branches` maps commit IDs to the branches pointing to them, which
+/// label the commits; `merge_bases` are highlighted. See `link` for
+/// `link_template`.
+#[allow(clippy::unwrap_used, reason = "node and commit indices are those that were added")]
+pub fn write<W: Write>(
+    commits: &[Commit],
+    branches: &HashMap<&str, Vec<&str>>,
+    head: Option<&str>,
+    merge_bases: &[String],
+    link_template: Option<&str>,
+    mut out: W,
+) -> io::Result<()> {
+    let mut layered = Layered::new(commits);
+    layered.reduce_crossings();
+    let widest = layered.layers.iter().map(Vec::len).max().unwrap_or_default();
+    let width = MARGIN.saturating_mul(2).saturating_add(widest.saturating_mul(NODE_SPACING));
+    let height = MARGIN
+        .saturating_mul(2)
+        .saturating_add(layered.layers.len().saturating_sub(1).saturating_mul(LAYER_SPACING));
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\">"
+    )?;
+    writeln!(out, "<style>\n{STYLE}\n</style>")?;
+    writeln!(out, "<rect width=\"100%\" height=\"100%\" fill=\"#fff\"/>")?;
+
+    // Each edge is a vertical S-curve between each pair of adjacent layers.
+    let half = LAYER_SPACING.checked_div(2).unwrap_or_default();
+    for chain in &layered.edges {
+        let (x, y) = layered.point(*chain.first().unwrap());
+        write!(out, "<path d=\"M{x} {y}")?;
+        for pair in chain.windows(2) {
+            if let &[above, below] = pair {
+                let ((x1, y1), (x2, y2)) = (layered.point(above), layered.point(below));
+                let middle = y1.saturating_add(half);
+                write!(out, " C{x1} {middle} {x2} {middle} {x2} {y2}")?;
+            }
+        }
+        writeln!(out, "\"/>")?;
+    }
+
+    for node in 0..layered.nodes.len() {
+        let Some(commit) = layered.nodes.get(node).unwrap().commit else { continue };
+        let commit = commits.get(commit).unwrap();
+        let (x, y) = layered.point(node);
+        let url =
+            link_template.filter(|_| commit.collapsed == 0).map(|t| link::expand(t, &commit.id));
+        if let Some(url) = &url {
+            writeln!(out, "<a href=\"{}\">", escape(url))?;
+        }
+        let (class, title) = match (commit.collapsed, merge_bases.contains(&commit.id)) {
+            (0, false) => ("", format!("{} {}", commit.id, commit.subject)),
+            (0, true) => {
+                (" class=\"base\"", format!("{} (merge base) {}", commit.id, commit.subject))
+            }
+            (collapsed, _) => (" class=\"collapsed\"", format!("{collapsed} commits")),
+        };
+        writeln!(
+            out,
+            "<g><title>{}</title><circle{class} cx=\"{x}\" cy=\"{y}\" r=\"6\"/>",
+            escape(&title)
+        )?;
+        let is_head = head == Some(commit.id.as_str());
+        let names = branches.get(commit.id.as_str()).map(Vec::as_slice).unwrap_or_default();
+        if is_head || !names.is_empty() {
+            write!(out, "<text x=\"{}\" y=\"{}\">", x.saturating_add(10), y.saturating_add(4))?;
+            if is_head {
+                write!(out, "<tspan class=\"label head\">HEAD</tspan> ")?;
+            }
+            write!(out, "<tspan class=\"label\">{}</tspan></text>", escape(&names.join(", ")))?;
+        }
+        writeln!(out, "</g>")?;
+        if url.is_some() {
+            writeln!(out, "</a>")?;
+        }
+    }
+    writeln!(out, "</svg>")?;
+    out.flush()
+}
@@ -0,0 +1,61 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Broken symbolic branches: local branches that are symbolic refs to a ref
+//! that does not exist (e.g. after the target was deleted), or to each other
+//! in a cycle. git leaves them out of ref listings without a word, so
+//! git-tree looks for them itself to warn about them.
+//!
+//! Symbolic refs are always stored as files (`ref: <target>`), so this scans
+//! the loose refs under `refs/heads` (or the namespace's).
+
+use crate::log::Logged as _;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Appends the names of the symbolic refs in `dir`, whose names start with
+/// `prefix`, to `symrefs`, recursing into subdirectories.
+fn scan(dir: &Path, prefix: &str, symrefs: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let Ok(name) = entry.file_name().into_string() else { continue };
+        let path = entry.path();
+        if path.is_dir() {
+            scan(&path, &format!("{prefix}{name}/"), symrefs);
+        } else if fs::read(&path).is_ok_and(|contents| contents.starts_with(b"ref: ")) {
+            symrefs.push(format!("{prefix}{name}"));
+        }
+    }
+}
+
+/// Returns the local branches that are broken symbolic refs, sorted, by name
+/// relative to `heads` (`refs/heads/`, or its counterpart in a namespace).
+/// `listed` says whether git listed a branch (and so it is not broken).
+pub fn broken<F: Fn(&str) -> bool>(heads: &str, listed: F) -> Vec<String> {
+    let Ok(output) = Command::new("git")
+        .args(["rev-parse", "--git-path", heads])
+        .stderr(Stdio::null())
+        .logged()
+        .output()
+    else {
+        return vec![];
+    };
+    let Ok(dir) = String::from_utf8(output.stdout) else { return vec![] };
+    let mut symrefs = vec![];
+    scan(Path::new(dir.trim_end()), "", &mut symrefs);
+    symrefs.retain(|name| !listed(name));
+    symrefs.sort_unstable();
+    symrefs
+}
@@ -0,0 +1,148 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Conditionals in the native renderer's commit format.
+//!
+//! On top of git's pretty format placeholders, the format may contain
+//! `%(if:<condition>)...%(else)...%(end)` blocks (the `%(else)` part is
+//! optional, and blocks may be nested), where the condition is one of:
+//!
+//! - `tip`: the commit is the tip of an interesting branch.
+//! - `base`: the commit is a merge base of the interesting commits.
+//! - `head`: the commit is HEAD.
+//!
+//! With `--decorate-interesting`, `%d` and `%D` are git-tree's too: they list
+//! HEAD and the interesting branches pointing to the commit, from git-tree's
+//! own scan, instead of the refs git's decoration settings select.
+//!
+//! git knows nothing about these, so `compile` replaces them with control
+//! characters that git passes through, and `expand` evaluates them in each
+//! formatted commit.
+
+/// Marks the start of a block; followed by the condition's code.
+const IF: char = '\x01';
+const ELSE: char = '\x02';
+const END: char = '\x03';
+/// Marks a decoration; followed by `d` (`%d`) or `D` (`%D`).
+const REFS: char = '\x04';
+
+/// What the conditions and decorations know about a commit.
+pub struct Facts<'refs> {
+    pub tip: bool,
+    pub base: bool,
+    pub head: bool,
+    /// The refs pointing to the commit, separated by commas.
+    pub refs: &'refs str,
+}
+
+/// Converts `format` into a git pretty format, replacing the conditionals
+/// (and, if `decorations` is true, the decorations) with markers for `expand`.
+/// Returns an error message if a conditional is malformed.
+pub fn compile(format: &str, decorations: bool) -> Result<String, String> {
+    let mut compiled = String::with_capacity(format.len());
+    // Whether each open block has seen its `%(else)`.
+    let mut open = vec![];
+    let mut rest = format;
+    while let Some(start) = rest.find('%') {
+        compiled.push_str(rest.get(..start).unwrap_or_default());
+        let placeholder = rest.get(start..).unwrap_or_default();
+        if let Some(after) = placeholder.strip_prefix("%%") {
+            compiled.push_str("%%");
+            rest = after;
+        } else if let Some(after) = placeholder.strip_prefix("%(if:") {
+            let (condition, after) =
+                after.split_once(')').ok_or_else(|| tr!("unterminated %(if:"))?;
+            let code = match condition {
+                "tip" => 't',
+                "base" => 'b',
+                "head" => 'h',
+                _ => {
+                    return Err(tr!("unknown condition %(if:{$condition})", condition = condition))
+                }
+            };
+            compiled.push_str("%x01");
+            compiled.push(code);
+            open.push(false);
+            rest = after;
+        } else if let Some(after) = placeholder.strip_prefix("%(else)") {
+            match open.last_mut() {
+                Some(seen_else @ &mut false) => *seen_else = true,
+                Some(&mut true) => return Err(tr!("duplicate %(else)")),
+                None => return Err(tr!("%(else) without %(if:...)")),
+            }
+            compiled.push_str("%x02");
+            rest = after;
+        } else if let Some(after) = placeholder.strip_prefix("%(end)") {
+            open.pop().ok_or_else(|| tr!("%(end) without %(if:...)"))?;
+            compiled.push_str("%x03");
+            rest = after;
+        } else if let Some(after) = placeholder.strip_prefix("%d").filter(|_| decorations) {
+            compiled.push_str("%x04d");
+            rest = after;
+        } else if let Some(after) = placeholder.strip_prefix("%D").filter(|_| decorations) {
+            compiled.push_str("%x04D");
+            rest = after;
+        } else {
+            compiled.push('%');
+            rest = placeholder.get(1..).unwrap_or_default();
+        }
+    }
+    compiled.push_str(rest);
+    if !open.is_empty() {
+        return Err(tr!("%(if:...) without %(end)"));
+    }
+    Ok(compiled)
+}
+
+/// Evaluates the conditionals and decorations in `text`, a commit formatted
+/// with a format returned by `compile`.
+pub fn expand(text: &str, facts: &Facts) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    // For each open block: (whether its enclosing text is shown, its
+    // condition).
+    let mut blocks: Vec<(bool, bool)> = vec![];
+    // Whether the current text is shown.
+    let mut shown = true;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            IF => {
+                let condition = match chars.next() {
+                    Some('t') => facts.tip,
+                    Some('b') => facts.base,
+                    Some('h') => facts.head,
+                    _ => false,
+                };
+                blocks.push((shown, condition));
+                shown = shown && condition;
+            }
+            ELSE => {
+                if let Some(&(outer, condition)) = blocks.last() {
+                    shown = outer && !condition;
+                }
+            }
+            END => shown = blocks.pop().is_none_or(|(outer, _)| outer),
+            REFS => match chars.next() {
+                Some('d') if shown && !facts.refs.is_empty() => {
+                    expanded.extend([" (", facts.refs, ")"]);
+                }
+                Some('D') if shown => expanded.push_str(facts.refs),
+                _ => {}
+            },
+            _ if shown => expanded.push(c),
+            _ => {}
+        }
+    }
+    expanded
+}
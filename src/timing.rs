@@ -0,0 +1,254 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The `--timing` breakdown. Each stage of a run records the wall-clock time
+//! and CPU time since the previous stage ended, git-tree's peak resident set
+//! size when it ended, and what it processed; the table is printed to stderr
+//! at the end. CPU time includes the git commands the stage ran, which is where
+//! most of it goes, while the peak RSS is git-tree's own. Both are only
+//! measured on Unix. With the `count-allocations` feature, each stage also
+//! records the allocations it made (see `allocations`).
+
+use crate::allocations::{self, Allocations};
+use core::time::Duration;
+use std::io::{self, stderr, Write as _};
+use std::time::Instant;
+
+/// A completed stage.
+struct Stage {
+    name: &'static str,
+    wall: Duration,
+    cpu: Option<Duration>,
+    /// The peak resident set size so far, in bytes.
+    peak_rss: Option<u64>,
+    allocations: Option<Allocations>,
+    /// How many of what the stage processed, e.g. `(12, "branches")`.
+    count: Option<(usize, &'static str)>,
+}
+
+/// The measurements a stage starts from.
+#[derive(Clone, Copy)]
+struct Start {
+    wall: Instant,
+    cpu: Option<Duration>,
+    allocations: Option<Allocations>,
+}
+
+impl Start {
+    fn now() -> Self {
+        Self { wall: Instant::now(), cpu: cpu_time(), allocations: allocations::so_far() }
+    }
+}
+
+/// Collects the stages of a run, if `--timing` was given.
+pub struct Timing {
+    /// When the current stage started, or `None` if timing is off.
+    start: Option<Start>,
+    stages: Vec<Stage>,
+}
+
+impl Timing {
+    /// Starts timing the first stage, if `enabled`; otherwise every method
+    /// does nothing.
+    pub fn new(enabled: bool) -> Self {
+        Self { start: enabled.then(Start::now), stages: vec![] }
+    }
+
+    /// Ends the current stage, named `name`, and starts the next one.
+    pub fn record(&mut self, name: &'static str, count: Option<(usize, &'static str)>) {
+        let Some(start) = self.start else { return };
+        let now = Start::now();
+        self.stages.push(Stage {
+            name,
+            wall: now.wall.saturating_duration_since(start.wall),
+            cpu: now.cpu.zip(start.cpu).map(|(end, start)| end.saturating_sub(start)),
+            peak_rss: peak_rss(),
+            allocations: now
+                .allocations
+                .zip(start.allocations)
+                .map(|(end, start)| end.since(start)),
+            count,
+        });
+        self.start = Some(now);
+    }
+
+    /// Returns the name and wall-clock time of each completed stage.
+    pub fn walls(&self) -> impl Iterator<Item = (&'static str, Duration)> + '_ {
+        self.stages.iter().map(|stage| (stage.name, stage.wall))
+    }
+
+    /// Prints the stages to stderr.
+    pub fn report(&self) -> io::Result<()> {
+        if self.start.is_none() {
+            return Ok(());
+        }
+        let mut out = stderr().lock();
+        writeln!(out, "git-tree: timing:")?;
+        let (mut wall, mut cpu) = (Duration::ZERO, Some(Duration::ZERO));
+        for stage in &self.stages {
+            write!(out, "  {:<12} {:>12} wall", stage.name, millis(stage.wall))?;
+            if let Some(stage_cpu) = stage.cpu {
+                write!(out, " {:>12} cpu", millis(stage_cpu))?;
+            }
+            if let Some(peak_rss) = stage.peak_rss {
+                write!(out, " {:>10} peak rss", size(peak_rss))?;
+            }
+            if let Some(Allocations { count, bytes }) = stage.allocations {
+                write!(out, " {count:>8} allocs {:>10}", size(bytes))?;
+            }
+            if let Some((count, what)) = stage.count {
+                write!(out, "  {what}: {count}")?;
+            }
+            writeln!(out)?;
+            wall = wall.saturating_add(stage.wall);
+            cpu = cpu.zip(stage.cpu).map(|(total, stage_cpu)| total.saturating_add(stage_cpu));
+        }
+        write!(out, "  {:<12} {:>12} wall", "total", millis(wall))?;
+        if let Some(total_cpu) = cpu.filter(|_| !self.stages.is_empty()) {
+            write!(out, " {:>12} cpu", millis(total_cpu))?;
+        }
+        // The peak of the run is that of its last stage.
+        if let Some(peak_rss) = self.stages.last().and_then(|stage| stage.peak_rss) {
+            write!(out, " {:>10} peak rss", size(peak_rss))?;
+        }
+        let mut stages = self.stages.iter();
+        let allocations = stages.try_fold(Allocations { count: 0, bytes: 0 }, |total, stage| {
+            let stage = stage.allocations?;
+            Some(Allocations {
+                count: total.count.saturating_add(stage.count),
+                bytes: total.bytes.saturating_add(stage.bytes),
+            })
+        });
+        if let Some(Allocations { count, bytes }) = allocations.filter(|_| !self.stages.is_empty())
+        {
+            write!(out, " {count:>8} allocs {:>10}", size(bytes))?;
+        }
+        writeln!(out)
+    }
+}
+
+/// Formats a duration in milliseconds, e.g. `12.345 ms`.
+pub fn millis(duration: Duration) -> String {
+    let micros = duration.as_micros();
+    let (whole, fraction) = (micros.checked_div(1000), micros.checked_rem(1000));
+    format!("{}.{:03} ms", whole.unwrap_or_default(), fraction.unwrap_or_default())
+}
+
+/// Formats a size in bytes, in KiB or MiB, e.g. `12.3 MiB`.
+fn size(bytes: u64) -> String {
+    const KIB: u64 = 1 << 10;
+    const MIB: u64 = 1 << 20;
+    let (unit, name) = if bytes < MIB { (KIB, "KiB") } else { (MIB, "MiB") };
+    let tenths = bytes.saturating_mul(10).checked_div(unit).unwrap_or_default();
+    let (whole, fraction) = (tenths.checked_div(10), tenths.checked_rem(10));
+    format!("{}.{} {name}", whole.unwrap_or_default(), fraction.unwrap_or_default())
+}
+
+/// The resource usage of git-tree (`Who::Itself`) or of the commands it has
+/// run and waited for (`Who::Children`).
+#[cfg(unix)]
+#[derive(Clone, Copy)]
+enum Who {
+    Itself,
+    Children,
+}
+
+/// `struct rusage`'s CPU times and peak resident set size, which is in bytes
+/// on macOS and KiB elsewhere.
+#[cfg(unix)]
+struct Usage {
+    cpu: Duration,
+    max_rss: u64,
+}
+
+/// Returns the resource usage of `who`, with `getrusage`.
+#[cfg(unix)]
+fn usage(who: Who) -> Option<Usage> {
+    use core::ffi::{c_int, c_long};
+
+    /// `struct timeval`.
+    #[repr(C)]
+    #[derive(Clone, Copy, Default)]
+    struct Timeval {
+        tv_sec: c_long,
+        #[cfg(target_os = "macos")]
+        tv_usec: i32,
+        #[cfg(not(target_os = "macos"))]
+        tv_usec: c_long,
+    }
+
+    /// `struct rusage`: the CPU times, the peak resident set size, then 13
+    /// counters git-tree does not use.
+    #[repr(C)]
+    #[derive(Default)]
+    struct Rusage {
+        ru_utime: Timeval,
+        ru_stime: Timeval,
+        ru_maxrss: c_long,
+        counters: [c_long; 13],
+    }
+
+    const RUSAGE_SELF: c_int = 0;
+    const RUSAGE_CHILDREN: c_int = -1;
+    const SUCCESS: c_int = 0;
+    extern "C" {
+        fn getrusage(who: c_int, usage: *mut Rusage) -> c_int;
+    }
+
+    let duration = |time: Timeval| {
+        let secs = Duration::from_secs(time.tv_sec.try_into().ok()?);
+        Some(secs.saturating_add(Duration::from_micros(time.tv_usec.try_into().ok()?)))
+    };
+    let mut usage = Rusage::default();
+    let who = match who {
+        Who::Itself => RUSAGE_SELF,
+        Who::Children => RUSAGE_CHILDREN,
+    };
+    // SAFETY: `usage` is a valid place to write a `struct rusage` to.
+    let status = unsafe { getrusage(who, &raw mut usage) };
+    if status != SUCCESS {
+        return None;
+    }
+    Some(Usage {
+        cpu: duration(usage.ru_utime)?.saturating_add(duration(usage.ru_stime)?),
+        max_rss: usage.ru_maxrss.try_into().ok()?,
+    })
+}
+
+/// Returns the user and system CPU time used so far by git-tree and by the
+/// commands it has run and waited for.
+#[cfg(unix)]
+fn cpu_time() -> Option<Duration> {
+    let (itself, children) = (usage(Who::Itself)?, usage(Who::Children)?);
+    Some(itself.cpu.saturating_add(children.cpu))
+}
+
+/// Returns git-tree's peak resident set size so far, in bytes.
+#[cfg(unix)]
+fn peak_rss() -> Option<u64> {
+    let max_rss = usage(Who::Itself)?.max_rss;
+    Some(if cfg!(target_os = "macos") { max_rss } else { max_rss.saturating_mul(1024) })
+}
+
+/// CPU time is not measured outside Unix.
+#[cfg(not(unix))]
+const fn cpu_time() -> Option<Duration> {
+    None
+}
+
+/// Memory is not measured outside Unix.
+#[cfg(not(unix))]
+const fn peak_rss() -> Option<u64> {
+    None
+}
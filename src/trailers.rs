@@ -0,0 +1,104 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--trailer <key>`: the trailers of the displayed commits with the key
+//! (`Reviewed-by:`, `Change-Id:`, `Bug:`, `Fixes:`, ...), shown in the native
+//! graph and listed in the JSON and porcelain output.
+//!
+//! They are parsed by git (`%(trailers)`), so they are the lines
+//! `git interpret-trailers --parse` would find, with continuation lines
+//! unfolded.
+
+use crate::json::Value;
+use crate::log::Logged as _;
+use crate::{git_status, graph};
+use std::collections::HashMap;
+use std::io;
+use std::process::Command;
+
+/// A commit's trailers, as keys (as spelled in the commit) and values, in
+/// order.
+pub type Trailers = Vec<(String, String)>;
+
+/// Returns the trailers with any of the `keys` (ignoring case, as git does)
+/// of the commits listed by `revisions` (see `View::revisions`), by commit
+/// ID, for the commits that have any. There are none without any `keys`.
+pub fn load(keys: &[String], revisions: &[String]) -> io::Result<HashMap<String, Trailers>> {
+    if keys.is_empty() {
+        return Ok(HashMap::new());
+    }
+    // Each commit is its ID, a NUL, its trailers, each followed by a NUL, and
+    // a newline.
+    let output = Command::new("git")
+        .args(["rev-list", "--no-commit-header"])
+        .arg("--format=%H%x00%(trailers:only,unfold,separator=%x00)%x00")
+        .args(revisions)
+        .env(graph::NO_LAZY_FETCH, "1")
+        .logged()
+        .output()?;
+    git_status("rev-list", output.status)?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split_terminator("\0\n")
+        .filter_map(|record| {
+            let mut fields = record.split('\0');
+            let id = fields.next()?;
+            let trailers: Trailers = fields
+                .filter_map(|trailer| trailer.split_once(':'))
+                .filter(|(key, _)| {
+                    keys.iter().any(|wanted| wanted.eq_ignore_ascii_case(key.trim()))
+                })
+                // A line break would end a porcelain record early. git's
+                // unfolding leaves none, but make sure.
+                .map(|(key, value)| (key.trim().to_owned(), value.trim().replace('\n', " ")))
+                .collect();
+            (!trailers.is_empty()).then(|| (id.to_owned(), trailers))
+        })
+        .collect())
+}
+
+/// Returns the values of the trailers of `trailers` whose key is `key`,
+/// ignoring case as git does.
+fn values<'trailers>(
+    trailers: &'trailers Trailers,
+    key: &'trailers str,
+) -> impl Iterator<Item = &'trailers str> {
+    trailers
+        .iter()
+        .filter(move |(name, _)| name.eq_ignore_ascii_case(key))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Returns the annotation of a commit with `trailers` for the trailer `key`,
+/// e.g. `Bug: 123, 456`, or `None` if it has none.
+pub fn label(trailers: &Trailers, key: &str) -> Option<String> {
+    let found: Vec<_> = values(trailers, key).collect();
+    let (first, _) = trailers.iter().find(|(name, _)| name.eq_ignore_ascii_case(key))?;
+    Some(format!("{first}: {}", found.join(", ")))
+}
+
+/// Returns `trailers` as a JSON object mapping each key, as first spelled, to
+/// its values.
+pub fn json(trailers: &Trailers) -> Value {
+    let mut keys: Vec<&str> = vec![];
+    for (key, _) in trailers {
+        if !keys.iter().any(|seen| seen.eq_ignore_ascii_case(key)) {
+            keys.push(key);
+        }
+    }
+    Value::Object(
+        keys.into_iter()
+            .map(|key| (key.to_owned(), Value::strings(values(trailers, key))))
+            .collect(),
+    )
+}
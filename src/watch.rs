@@ -0,0 +1,100 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `--watch` support: re-renders the log whenever a ref moves.
+//!
+//! Refs are watched by polling the modification times and lengths of `HEAD`,
+//! `packed-refs`, and everything under `refs/` and `reftable/`. Polling keeps
+//! this portable and dependency-free, and a ref scan is cheap compared to the
+//! rendering it triggers. Its cost is latency: a move shows up to a poll
+//! interval late, and one that rewrites a file in place to the same length
+//! within the same modification time (on file systems with coarse timestamps)
+//! is missed until the next one.
+
+use crate::git_status;
+use crate::log::Logged as _;
+use core::time::Duration;
+use std::fs;
+use std::io::{self, stdout, Write as _};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread::sleep;
+use std::time::SystemTime;
+
+/// How often to check whether the refs changed.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A snapshot of the state of every watched file: (path, modification time,
+/// length). A ref update changes at least one of these.
+type Fingerprint = Vec<(PathBuf, Option<SystemTime>, u64)>;
+
+/// Appends the fingerprint of `path` (recursing into directories) to
+/// `fingerprint`.
+fn add_to_fingerprint(path: &Path, fingerprint: &mut Fingerprint) {
+    let Ok(metadata) = fs::symlink_metadata(path) else { return };
+    if metadata.is_dir() {
+        let Ok(entries) = fs::read_dir(path) else { return };
+        for entry in entries.flatten() {
+            add_to_fingerprint(&entry.path(), fingerprint);
+        }
+    } else {
+        fingerprint.push((path.to_owned(), metadata.modified().ok(), metadata.len()));
+    }
+}
+
+/// Returns the paths to watch: HEAD, packed-refs, and the refs and reftable
+/// directories. Whichever of them the ref storage does not use are missing,
+/// which `fingerprint` skips.
+fn watched_paths() -> io::Result<Vec<PathBuf>> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "HEAD", "--git-path", "packed-refs"])
+        .args(["--git-path", "refs", "--git-path", "reftable"])
+        .logged()
+        .output()?;
+    git_status("rev-parse", output.status)?;
+    Ok(String::from_utf8(output.stdout)
+        .map_err(io::Error::other)?
+        .lines()
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Computes the current fingerprint of `paths`.
+fn fingerprint(paths: &[PathBuf]) -> Fingerprint {
+    let mut fingerprint = vec![];
+    for path in paths {
+        add_to_fingerprint(path, &mut fingerprint);
+    }
+    fingerprint.sort_unstable();
+    fingerprint
+}
+
+/// Calls `render` (after clearing the screen) now and every time a ref
+/// changes, until it returns false. Otherwise, the user exits with Ctrl-C.
+pub fn watch<F: FnMut() -> bool>(mut render: F) -> io::Result<()> {
+    let paths = watched_paths()?;
+    loop {
+        let rendered = fingerprint(&paths);
+        // Move the cursor to the top left and clear the screen.
+        let mut stdout = stdout().lock();
+        drop(write!(stdout, "\x1b[H\x1b[2J").and_then(|()| stdout.flush()));
+        drop(stdout);
+        if !render() {
+            return Ok(());
+        }
+        while fingerprint(&paths) == rendered {
+            sleep(POLL_INTERVAL);
+        }
+    }
+}
@@ -0,0 +1,395 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The width of text on a terminal, in columns, for the tables git-tree
+//! prints: CJK characters and emoji take two columns, combining marks none.
+//!
+//! Text is measured and truncated by grapheme cluster, approximated as a
+//! character followed by the zero-width characters (combining marks, variation
+//! selectors, emoji modifiers) and zero-width-joined characters that extend
+//! it, or a pair of regional indicators (a flag). A cluster is as wide as its
+//! first character, or two columns if it asks for emoji presentation. The wide
+//! characters are those of Unicode's East Asian Width classes W and F, from an
+//! abridged table. ANSI escape sequences (colors and hyperlinks) take no
+//! columns, and are kept when text is truncated, so that it ends with the
+//! styling reset.
+//!
+//! The terminal is as wide as `COLUMNS` says, as for git, or else as the
+//! terminal stdout is on. Output that is not to a terminal has no width, and is
+//! never truncated.
+
+use alloc::borrow::Cow;
+use core::cmp::Ordering;
+use core::iter;
+use std::env::var;
+use std::sync::OnceLock;
+
+/// The ranges of characters two columns wide.
+const WIDE: &[(u32, u32)] = &[
+    (0x1100, 0x115f),
+    (0x231a, 0x231b),
+    (0x2329, 0x232a),
+    (0x23e9, 0x23ec),
+    (0x23f0, 0x23f0),
+    (0x23f3, 0x23f3),
+    (0x25fd, 0x25fe),
+    (0x2614, 0x2615),
+    (0x2648, 0x2653),
+    (0x267f, 0x267f),
+    (0x2693, 0x2693),
+    (0x26a1, 0x26a1),
+    (0x26aa, 0x26ab),
+    (0x26bd, 0x26be),
+    (0x26c4, 0x26c5),
+    (0x26ce, 0x26ce),
+    (0x26d4, 0x26d4),
+    (0x26ea, 0x26ea),
+    (0x26f2, 0x26f3),
+    (0x26f5, 0x26f5),
+    (0x26fa, 0x26fa),
+    (0x26fd, 0x26fd),
+    (0x2705, 0x2705),
+    (0x270a, 0x270b),
+    (0x2728, 0x2728),
+    (0x274c, 0x274c),
+    (0x274e, 0x274e),
+    (0x2753, 0x2755),
+    (0x2757, 0x2757),
+    (0x2795, 0x2797),
+    (0x27b0, 0x27b0),
+    (0x27bf, 0x27bf),
+    (0x2b1b, 0x2b1c),
+    (0x2b50, 0x2b50),
+    (0x2b55, 0x2b55),
+    (0x2e80, 0x303e),
+    (0x3041, 0x33ff),
+    (0x3400, 0x4dbf),
+    (0x4e00, 0x9fff),
+    (0xa000, 0xa4cf),
+    (0xa960, 0xa97f),
+    (0xac00, 0xd7a3),
+    (0xf900, 0xfaff),
+    (0xfe10, 0xfe19),
+    (0xfe30, 0xfe6f),
+    (0xff00, 0xff60),
+    (0xffe0, 0xffe6),
+    (0x16fe0, 0x16fe4),
+    (0x17000, 0x18cff),
+    (0x1b000, 0x1b2ff),
+    (0x1f004, 0x1f004),
+    (0x1f0cf, 0x1f0cf),
+    (0x1f18e, 0x1f18e),
+    (0x1f191, 0x1f19a),
+    (0x1f200, 0x1f251),
+    (0x1f300, 0x1f320),
+    (0x1f32d, 0x1f335),
+    (0x1f337, 0x1f37c),
+    (0x1f37e, 0x1f393),
+    (0x1f3a0, 0x1f3ca),
+    (0x1f3cf, 0x1f3d3),
+    (0x1f3e0, 0x1f3f0),
+    (0x1f3f4, 0x1f3f4),
+    (0x1f3f8, 0x1f43e),
+    (0x1f440, 0x1f440),
+    (0x1f442, 0x1f4fc),
+    (0x1f4ff, 0x1f53d),
+    (0x1f54b, 0x1f54e),
+    (0x1f550, 0x1f567),
+    (0x1f57a, 0x1f57a),
+    (0x1f595, 0x1f596),
+    (0x1f5a4, 0x1f5a4),
+    (0x1f5fb, 0x1f64f),
+    (0x1f680, 0x1f6c5),
+    (0x1f6cc, 0x1f6cc),
+    (0x1f6d0, 0x1f6d2),
+    (0x1f6d5, 0x1f6d7),
+    (0x1f6dc, 0x1f6df),
+    (0x1f6eb, 0x1f6ec),
+    (0x1f6f4, 0x1f6fc),
+    (0x1f7e0, 0x1f7eb),
+    (0x1f7f0, 0x1f7f0),
+    (0x1f90c, 0x1f93a),
+    (0x1f93c, 0x1f945),
+    (0x1f947, 0x1f9ff),
+    (0x1fa70, 0x1faff),
+    (0x20000, 0x2fffd),
+    (0x30000, 0x3fffd),
+];
+
+/// The ranges of characters that take no columns of their own.
+const ZERO: &[(u32, u32)] = &[
+    (0x0300, 0x036f),
+    (0x0483, 0x0489),
+    (0x0591, 0x05bd),
+    (0x0610, 0x061a),
+    (0x064b, 0x065f),
+    (0x0670, 0x0670),
+    (0x06d6, 0x06dc),
+    (0x06df, 0x06e4),
+    (0x0e31, 0x0e31),
+    (0x0e34, 0x0e3a),
+    (0x0e47, 0x0e4e),
+    (0x1160, 0x11ff),
+    (0x1ab0, 0x1aff),
+    (0x1dc0, 0x1dff),
+    (0x200b, 0x200f),
+    (0x2028, 0x202e),
+    (0x2060, 0x206f),
+    (0x20d0, 0x20ff),
+    (0x3099, 0x309a),
+    (0xfe00, 0xfe0f),
+    (0xfe20, 0xfe2f),
+    (0xfeff, 0xfeff),
+    (0x1f3fb, 0x1f3ff),
+    (0xe0000, 0xe0fff),
+];
+
+/// The zero-width joiner, which joins the characters around it (e.g. in emoji
+/// sequences like 👩‍💻).
+const JOINER: char = '\u{200d}';
+
+/// The variation selector asking for emoji presentation, two columns wide.
+const EMOJI_PRESENTATION: char = '\u{fe0f}';
+
+/// The character starting ANSI escape sequences.
+const ESCAPE: char = '\x1b';
+
+/// The ellipsis ending truncated text.
+const ELLIPSIS: &str = "\u{2026}";
+
+/// Returns whether `c` is in one of the sorted `ranges`.
+fn within(ranges: &[(u32, u32)], c: char) -> bool {
+    let code = u32::from(c);
+    ranges
+        .binary_search_by(|&(first, last)| {
+            if last < code {
+                Ordering::Less
+            } else if first > code {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// Returns whether `c` extends the grapheme cluster before it.
+fn extends(c: char) -> bool {
+    c == JOINER || within(ZERO, c)
+}
+
+/// Returns whether `c` is a regional indicator, two of which make a flag.
+fn regional(c: char) -> bool {
+    ('\u{1f1e6}'..='\u{1f1ff}').contains(&c)
+}
+
+/// Returns the length of the ANSI escape sequence at the start of `text`: a
+/// control sequence (e.g. a color, `ESC [ 31 m`) or an operating system
+/// command (e.g. a hyperlink, `ESC ] 8 ; ; url ESC \\`).
+fn escape_len(text: &str) -> usize {
+    let mut chars = text.char_indices().skip(1);
+    let end = match chars.next() {
+        Some((_, '[')) => chars.find(|&(_, c)| ('@'..='~').contains(&c)),
+        Some((_, ']')) => chars.find(|&(i, c)| {
+            c == '\x07'
+                || (c == '\\' && text.get(..i).is_some_and(|before| before.ends_with(ESCAPE)))
+        }),
+        other => other,
+    };
+    end.map_or(text.len(), |(i, c)| i.saturating_add(c.len_utf8()))
+}
+
+/// Returns the length of the grapheme cluster at the start of `text`, which
+/// starts with `first`.
+fn cluster_len(text: &str, first: char) -> usize {
+    if first == ESCAPE {
+        return escape_len(text);
+    }
+    let mut joined = false;
+    let mut flag = regional(first);
+    for (i, c) in text.char_indices().skip(1) {
+        if joined || (c != ESCAPE && extends(c)) {
+            joined = c == JOINER;
+        } else if flag && regional(c) {
+            flag = false;
+        } else {
+            return i;
+        }
+    }
+    text.len()
+}
+
+/// Returns the (approximate) grapheme clusters of `text`, with each escape
+/// sequence as a cluster of its own.
+fn graphemes(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let (cluster, remainder) = rest.split_at(cluster_len(rest, first));
+        rest = remainder;
+        Some(cluster)
+    })
+}
+
+/// Returns the width of the grapheme cluster `cluster`.
+fn cluster_width(cluster: &str) -> usize {
+    let Some(first) = cluster.chars().next() else { return 0 };
+    if first.is_control() || extends(first) {
+        0
+    } else if within(WIDE, first) || regional(first) || cluster.contains(EMOJI_PRESENTATION) {
+        2
+    } else {
+        1
+    }
+}
+
+/// Returns the width of `text`, in columns.
+pub fn width(text: &str) -> usize {
+    graphemes(text).map(cluster_width).sum()
+}
+
+/// Returns `text` followed by enough spaces to make it `width` columns wide.
+pub fn pad(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(self::width(text));
+    iter::once(text).chain(iter::repeat_n(" ", padding)).collect()
+}
+
+/// Returns `text` preceded by enough spaces to make it `width` columns wide.
+pub fn pad_left(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(self::width(text));
+    iter::repeat_n(" ", padding).chain(iter::once(text)).collect()
+}
+
+/// Returns `text` cut to at most `max` columns, ending with an ellipsis if it
+/// was cut. Only whole grapheme clusters are kept, and every escape sequence.
+pub fn truncate(text: &str, max: usize) -> Cow<'_, str> {
+    if width(text) <= max {
+        return Cow::Borrowed(text);
+    }
+    let Some(room) = max.checked_sub(width(ELLIPSIS)) else { return Cow::Borrowed("") };
+    let mut used: usize = 0;
+    let mut cut = false;
+    let mut kept = String::new();
+    for cluster in graphemes(text) {
+        if cluster.starts_with(ESCAPE) {
+            kept.push_str(cluster);
+            continue;
+        }
+        if cut {
+            continue;
+        }
+        used = used.saturating_add(cluster_width(cluster));
+        if used > room {
+            kept.push_str(ELLIPSIS);
+            cut = true;
+        } else {
+            kept.push_str(cluster);
+        }
+    }
+    Cow::Owned(kept)
+}
+
+/// The terminal width, once known.
+static COLUMNS: OnceLock<Option<usize>> = OnceLock::new();
+
+/// Returns the width of the terminal, if the output is to one.
+pub fn columns() -> Option<usize> {
+    *COLUMNS.get_or_init(|| {
+        let configured = var("COLUMNS").ok().and_then(|columns| columns.parse().ok());
+        configured.or_else(terminal_columns).filter(|&columns| columns > 0)
+    })
+}
+
+/// Returns the width of the terminal stdout is on, if it is on one.
+#[cfg(unix)]
+fn terminal_columns() -> Option<usize> {
+    use core::ffi::{c_int, c_ulong};
+    use std::io::stdout;
+    use std::os::fd::AsRawFd as _;
+
+    /// `struct winsize`.
+    #[repr(C)]
+    #[derive(Default)]
+    struct Winsize {
+        rows: u16,
+        columns: u16,
+        x_pixels: u16,
+        y_pixels: u16,
+    }
+
+    /// The request for the terminal size, `_IOR('t', 104, struct winsize)`
+    /// on the BSDs, macOS, and the Linux architectures that follow them, and
+    /// a legacy number on the other Linux architectures. Elsewhere, it is
+    /// not known, and neither is the terminal width.
+    #[cfg(all(
+        any(target_os = "linux", target_os = "android"),
+        not(any(
+            target_arch = "mips",
+            target_arch = "mips64",
+            target_arch = "powerpc",
+            target_arch = "powerpc64",
+            target_arch = "sparc",
+            target_arch = "sparc64"
+        ))
+    ))]
+    const TIOCGWINSZ: Option<c_ulong> = Some(0x5413);
+    #[cfg(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly",
+        all(
+            any(target_os = "linux", target_os = "android"),
+            any(
+                target_arch = "mips",
+                target_arch = "mips64",
+                target_arch = "powerpc",
+                target_arch = "powerpc64",
+                target_arch = "sparc",
+                target_arch = "sparc64"
+            )
+        )
+    ))]
+    const TIOCGWINSZ: Option<c_ulong> = Some(0x4008_7468);
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly"
+    )))]
+    const TIOCGWINSZ: Option<c_ulong> = None;
+    const SUCCESS: c_int = 0;
+    extern "C" {
+        fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    }
+
+    let request = TIOCGWINSZ?;
+    let mut size = Winsize::default();
+    // SAFETY: `size` is a valid place to write a `struct winsize` to, and
+    // stdout stays open.
+    let status = unsafe { ioctl(stdout().as_raw_fd(), request, &raw mut size) };
+    (status == SUCCESS).then_some(usize::from(size.columns))
+}
+
+/// The terminal width is only detected on Unix.
+#[cfg(not(unix))]
+const fn terminal_columns() -> Option<usize> {
+    None
+}
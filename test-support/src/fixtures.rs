@@ -0,0 +1,200 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A shared set of named fixture repositories, for tests that run the same
+//! checks across many topologies.
+
+use crate::Repo;
+
+/// A branch with two commits, and another diverging from its first commit.
+fn diverged() -> Repo {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.branch("feature", "main");
+    repo.commits("m", 2);
+    repo.checkout("feature");
+    repo.commits("f", 2);
+    repo
+}
+
+/// Three branches, each based on the previous one.
+fn stacked() -> Repo {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("s1", "main");
+    repo.commit("s1a");
+    repo.checkout_new("s2", "s1");
+    repo.commits("s2-", 2);
+    repo.checkout_new("s3", "s2");
+    repo.commit("s3a");
+    repo
+}
+
+/// Two branches that merged each other, so they have two merge bases.
+fn criss_cross() -> Repo {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("x", "main");
+    repo.commit("x1");
+    repo.checkout_new("y", "main");
+    repo.commit("y1");
+    repo.checkout("x");
+    repo.merge("x2", &["y~0"]);
+    repo.checkout("y");
+    repo.merge("y2", &["x~1"]);
+    repo.git(&["branch", "--quiet", "--delete", "--force", "main"]);
+    repo
+}
+
+/// Two rounds of criss-cross merges, so that the merge bases are themselves
+/// merges with two merge bases, and a branch forked from one side before the
+/// merges, which moves the merge base of all the tips down to its fork point.
+fn double_criss_cross() -> Repo {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("x", "main");
+    repo.commit("x1");
+    repo.checkout_new("z", "x");
+    repo.commit("z1");
+    repo.checkout_new("y", "main");
+    repo.commit("y1");
+    for round in ["2", "3"] {
+        repo.checkout("x");
+        repo.merge(&format!("x{round}"), &["y~0"]);
+        repo.checkout("y");
+        repo.merge(&format!("y{round}"), &["x~1"]);
+    }
+    repo.git(&["branch", "--quiet", "--delete", "--force", "main"]);
+    repo
+}
+
+/// Criss-cross merges between branches that keep going afterwards, whose
+/// merge bases are far from the tips and from the original fork point.
+fn criss_cross_far() -> Repo {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("x", "main");
+    repo.commits("x", 2);
+    repo.checkout_new("y", "main");
+    repo.commits("y", 2);
+    repo.checkout("x");
+    repo.merge("x merges y", &["y~0"]);
+    repo.checkout("y");
+    repo.merge("y merges x", &["x~1"]);
+    repo.checkout("x");
+    repo.commits("x-after", 2);
+    repo.checkout("y");
+    repo.commits("y-after", 2);
+    repo.checkout("main");
+    repo.commit("m1");
+    repo
+}
+
+/// An octopus merge of three branches.
+fn octopus() -> Repo {
+    let repo = Repo::new();
+    repo.commit("base");
+    for arm in ["a", "b", "c"] {
+        repo.checkout_new(arm, "main");
+        repo.commit(&format!("{arm}1"));
+    }
+    repo.checkout("main");
+    repo.merge("octopus", &["a", "b", "c"]);
+    repo
+}
+
+/// An octopus merge that reaches the merge base only through its last parent:
+/// its other arms forked from below the merge base, so they are not displayed.
+fn octopus_partial() -> Repo {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.branch("side1", "main");
+    repo.branch("side2", "main");
+    repo.commit("m1");
+    repo.checkout_new("topic", "main");
+    repo.commit("t1");
+    repo.checkout_new("arm", "main");
+    repo.commit("a1");
+    for side in ["side1", "side2"] {
+        repo.checkout(side);
+        repo.commit(&format!("{side} work"));
+    }
+    repo.checkout_new("octopus", "side1");
+    repo.merge("octopus", &["side2", "arm"]);
+    repo.git(&["branch", "--quiet", "--delete", "--force", "side1", "side2", "arm"]);
+    repo
+}
+
+/// A long-lived feature branch that merges main back in, with a topic branch
+/// forked from it.
+fn tangled() -> Repo {
+    let repo = Repo::new();
+    repo.commits("c", 2);
+    repo.checkout_new("feature", "main");
+    repo.commits("f", 2);
+    repo.checkout("main");
+    repo.commits("m", 2);
+    repo.checkout("feature");
+    repo.merge("sync main", &["main"]);
+    repo.commit("f3");
+    repo.checkout("main");
+    repo.commit("m3");
+    repo.checkout_new("topic", "feature~1");
+    repo.commit("t1");
+    repo.checkout("main");
+    repo
+}
+
+/// The number of branches in `fresh`.
+const FRESH_BRANCHES: u8 = 10;
+
+/// A freshly branched repository: many branches on the same commit.
+fn fresh() -> Repo {
+    let repo = Repo::new();
+    repo.commits("c", 2);
+    for index in 1..=FRESH_BRANCHES {
+        repo.branch(&format!("b{index}"), "main");
+    }
+    repo
+}
+
+/// Returns every fixture, with its name.
+#[must_use]
+pub fn all() -> Vec<(&'static str, Repo)> {
+    let linear = Repo::new();
+    linear.commits("c", 3);
+    let orphan = Repo::new();
+    orphan.commits("m", 2);
+    orphan.checkout_orphan("orphan");
+    orphan.commits("o", 2);
+    let detached = Repo::new();
+    let base = detached.commit("base");
+    detached.commit("m1");
+    detached.checkout(&base);
+    detached.commit("detached");
+    vec![
+        ("linear", linear),
+        ("diverged", diverged()),
+        ("stacked", stacked()),
+        ("criss-cross", criss_cross()),
+        ("double-criss-cross", double_criss_cross()),
+        ("criss-cross-far", criss_cross_far()),
+        ("octopus", octopus()),
+        ("octopus-partial", octopus_partial()),
+        ("orphan", orphan),
+        ("detached", detached),
+        ("tangled", tangled()),
+        ("fresh", fresh()),
+    ]
+}
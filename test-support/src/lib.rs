@@ -0,0 +1,237 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Temporary git repositories for git-tree's integration tests.
+//!
+//! Every git command runs with a fixed identity, a clock that ticks once per
+//! command, and no user or system config, so a repository built by the same
+//! sequence of calls always has the same commit IDs. Every method panics if
+//! the command it runs fails, as a test should.
+
+#![allow(clippy::missing_inline_in_public_items, reason = "only used by tests")]
+#![allow(clippy::missing_panics_doc, reason = "every method panics on failure")]
+
+pub mod fixtures;
+pub mod snapshot;
+
+use core::cell::Cell;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::env::temp_dir;
+use std::fs;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{id, Command, Stdio};
+
+/// Numbers the repositories created by this process.
+static NEXT_REPO: AtomicUsize = AtomicUsize::new(0);
+
+/// The time of the first commit, in seconds since the epoch.
+const EPOCH: u64 = 1_700_000_000;
+
+/// A repository in a temporary directory, which is deleted on drop.
+pub struct Repo {
+    path: PathBuf,
+    /// The number of git commands run so far, which sets the commit dates.
+    clock: Cell<u64>,
+}
+
+impl Repo {
+    /// Creates an empty repository whose initial branch is `main`.
+    #[must_use]
+    pub fn new() -> Self {
+        let repo = Self::empty();
+        repo.git(&["init", "--quiet", "--initial-branch=main", "."]);
+        repo
+    }
+
+    /// Creates an empty temporary directory (but no repository).
+    fn empty() -> Self {
+        let number = NEXT_REPO.fetch_add(1, Ordering::Relaxed);
+        let path = temp_dir().join(format!("git-tree-test-{}-{number}", id()));
+        drop(fs::remove_dir_all(&path));
+        fs::create_dir_all(&path).expect("failed to create the repository directory");
+        Self { path, clock: Cell::new(0) }
+    }
+
+    /// Returns the repository's directory.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns a command that runs `program` in the repository, in the test
+    /// environment.
+    #[must_use]
+    pub fn command<P: AsRef<Path>>(&self, program: P) -> Command {
+        let tick = self.clock.get();
+        self.clock.set(tick.saturating_add(1));
+        let date = format!("{} +0000", EPOCH.saturating_add(tick.saturating_mul(60)));
+        let mut command = Command::new(program.as_ref());
+        command
+            .current_dir(&self.path)
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .env("HOME", &self.path)
+            .env("GIT_AUTHOR_NAME", "Test")
+            .env("GIT_AUTHOR_EMAIL", "test@example.com")
+            .env("GIT_COMMITTER_NAME", "Test")
+            .env("GIT_COMMITTER_EMAIL", "test@example.com")
+            .env("GIT_AUTHOR_DATE", &date)
+            .env("GIT_COMMITTER_DATE", &date)
+            .env_remove("GIT_DIR")
+            .env_remove("GIT_WORK_TREE")
+            .env_remove("GIT_PAGER")
+            .env_remove("NO_COLOR")
+            .env_remove("COLUMNS")
+            .env_remove("LC_ALL")
+            .env_remove("LC_MESSAGES")
+            .env_remove("LANG");
+        command
+    }
+
+    /// Runs `program` with `args` in the repository and returns its stdout.
+    #[must_use]
+    pub fn run<P: AsRef<Path>>(&self, program: P, args: &[&str]) -> String {
+        let output = self.command(&program).args(args).output().expect("failed to run command");
+        assert!(
+            output.status.success(),
+            "{} {args:?} failed: {}",
+            program.as_ref().display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).expect("non-UTF-8 output")
+    }
+
+    /// Runs git with `args` and returns its stdout, without the trailing
+    /// newline.
+    pub fn git(&self, args: &[&str]) -> String {
+        let mut output = self.run("git", args);
+        output.truncate(output.trim_end().len());
+        output
+    }
+
+    /// Returns the commit ID `rev` resolves to.
+    #[must_use]
+    pub fn rev_parse(&self, rev: &str) -> String {
+        self.git(&["rev-parse", "--verify", "--end-of-options", rev])
+    }
+
+    /// Commits (with no changes) on the current branch and returns the new
+    /// commit's ID.
+    pub fn commit(&self, message: &str) -> String {
+        self.git(&["commit", "--quiet", "--allow-empty", "--message", message]);
+        self.rev_parse("HEAD")
+    }
+
+    /// Writes `contents` to the file `name`, commits it on the current branch,
+    /// and returns the new commit's ID.
+    pub fn commit_file(&self, name: &str, contents: &str, message: &str) -> String {
+        fs::write(self.path.join(name), contents).expect("failed to write the file");
+        self.git(&["add", "--", name]);
+        self.commit(message)
+    }
+
+    /// Commits `count` times, with the messages `<prefix>1`, `<prefix>2`,
+    /// ..., and returns the last commit's ID.
+    pub fn commits(&self, prefix: &str, count: usize) -> String {
+        (1..=count).map(|i| self.commit(&format!("{prefix}{i}"))).last().unwrap_or_default()
+    }
+
+    /// Creates branch `name` at `start`.
+    pub fn branch(&self, name: &str, start: &str) {
+        self.git(&["branch", "--quiet", name, start]);
+    }
+
+    /// Checks out `rev` (a branch, or any commit for a detached HEAD).
+    pub fn checkout(&self, rev: &str) {
+        self.git(&["checkout", "--quiet", rev]);
+    }
+
+    /// Creates branch `name` at `start` and checks it out.
+    pub fn checkout_new(&self, name: &str, start: &str) {
+        self.git(&["checkout", "--quiet", "-b", name, start]);
+    }
+
+    /// Creates and checks out branch `name` with no commits.
+    pub fn checkout_orphan(&self, name: &str) {
+        self.git(&["checkout", "--quiet", "--orphan", name]);
+    }
+
+    /// Merges `revs` into the current branch (an octopus merge if there are
+    /// several), always creating a merge commit, and returns its ID.
+    pub fn merge(&self, message: &str, revs: &[&str]) -> String {
+        let mut args = vec!["merge", "--quiet", "--no-ff", "--allow-unrelated-histories"];
+        args.extend(["--message", message]);
+        args.extend(revs);
+        self.git(&args);
+        self.rev_parse("HEAD")
+    }
+
+    /// Feeds `stream` to `git fast-import`, which builds large histories far
+    /// faster than committing one by one. Its commits carry their own dates.
+    pub fn fast_import(&self, stream: &[u8]) {
+        let mut git = self
+            .command("git")
+            .args(["fast-import", "--quiet"])
+            .stdin(Stdio::piped())
+            .spawn()
+            .expect("failed to run git fast-import");
+        git.stdin.take().unwrap().write_all(stream).expect("failed to write to git fast-import");
+        let status = git.wait().expect("failed to wait for git fast-import");
+        assert!(status.success(), "git fast-import failed: {status}");
+    }
+
+    /// Creates the lightweight tag `name` at `rev`.
+    pub fn tag(&self, name: &str, rev: &str) {
+        self.git(&["tag", name, rev]);
+    }
+
+    /// Clones this repository over `file://`, passing `args` to `git clone`
+    /// (e.g. `--depth=1`). The clone's remote is `origin`.
+    #[must_use]
+    pub fn clone_with(&self, args: &[&str]) -> Self {
+        let clone = Self::empty();
+        clone.clock.set(self.clock.get());
+        // Windows paths (`C:\...`) need an extra slash and forward slashes.
+        let path = self.path.to_str().expect("non-UTF-8 temporary directory").replace('\\', "/");
+        let url = if path.starts_with('/') {
+            format!("file://{path}")
+        } else {
+            format!("file:///{path}")
+        };
+        let mut clone_args = vec!["clone", "--quiet"];
+        clone_args.extend(args);
+        clone_args.extend([url.as_str(), "."]);
+        clone.git(&clone_args);
+        clone
+    }
+
+    /// Returns the subject of commit `id`.
+    #[must_use]
+    pub fn subject(&self, id: &str) -> String {
+        self.git(&["show", "--no-patch", "--format=%s", id])
+    }
+}
+
+impl Default for Repo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Repo {
+    fn drop(&mut self) {
+        drop(fs::remove_dir_all(&self.path));
+    }
+}
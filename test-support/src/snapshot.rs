@@ -0,0 +1,55 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Snapshot testing: output is compared with a file checked into the
+//! repository, so changes to it show up in review.
+//!
+//! When a snapshot does not match, the new output is written next to it with
+//! a `.new` suffix for inspection. Running the tests with
+//! `GITXL_UPDATE_SNAPSHOTS=1` overwrites the snapshots instead.
+
+use std::env::var_os;
+use std::fs;
+use std::path::Path;
+
+/// Checks that `actual` matches the snapshot at `path`, or updates the
+/// snapshot if `GITXL_UPDATE_SNAPSHOTS` is set.
+pub fn check(path: &Path, actual: &str) {
+    let mut new = path.as_os_str().to_owned();
+    new.push(".new");
+    if var_os("GITXL_UPDATE_SNAPSHOTS").is_some_and(|value| !value.is_empty()) {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).expect("failed to create the snapshot directory");
+        }
+        fs::write(path, actual).expect("failed to write the snapshot");
+        drop(fs::remove_file(new));
+        return;
+    }
+    let expected = fs::read_to_string(path).unwrap_or_default();
+    if expected == actual {
+        drop(fs::remove_file(new));
+        return;
+    }
+    fs::write(&new, actual).expect("failed to write the new snapshot");
+    let first_difference =
+        expected.lines().zip(actual.lines()).take_while(|(before, after)| before == after).count();
+    assert!(
+        expected == actual,
+        "{} does not match (first difference at line {}); the new output is in {}. Rerun \
+         with GITXL_UPDATE_SNAPSHOTS=1 to accept it.",
+        path.display(),
+        first_difference.saturating_add(1),
+        Path::new(&new).display()
+    );
+}
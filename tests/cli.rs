@@ -0,0 +1,420 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! End-to-end tests of git-tree's command-line options, run in temporary
+//! repositories.
+
+#![allow(clippy::tests_outside_test_module, reason = "this is an integration test")]
+
+use core::time::Duration;
+use git_tree_test_support::Repo;
+#[cfg(target_os = "linux")]
+use std::fs;
+
+/// Runs git-tree in `repo` with `args` and returns its stdout.
+fn git_tree(repo: &Repo, args: &[&str]) -> String {
+    repo.run(env!("CARGO_BIN_EXE_git-tree"), args)
+}
+
+#[test]
+fn watch_rerenders_when_a_ref_moves() {
+    use std::io::Read as _;
+    use std::process::Stdio;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Instant;
+
+    let repo = Repo::new();
+    repo.commit("base");
+    let mut child = repo
+        .command(env!("CARGO_BIN_EXE_git-tree"))
+        .args(["--watch", "--graph", "--format=%s"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to run git-tree");
+    let mut stdout = child.stdout.take().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut chunk = [0; 1024];
+        while let Ok(len @ 1..) = stdout.read(&mut chunk) {
+            if sender.send(chunk.get(..len).unwrap_or_default().to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+    let mut output = vec![];
+    let mut wait_for = |expected: &str| {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !String::from_utf8_lossy(&output).ends_with(expected) {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let chunk = receiver.recv_timeout(left);
+            let shown = String::from_utf8_lossy(&output);
+            assert!(chunk.is_ok(), "timed out waiting for {expected:?}, got {shown:?}");
+            output.extend(chunk.unwrap());
+        }
+    };
+    // Each render clears the screen first.
+    wait_for("\x1b[H\x1b[2J* base\n");
+    repo.commit("second");
+    wait_for("\x1b[H\x1b[2J* second\n");
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn collapse_replaces_long_linear_runs() {
+    let repo = Repo::new();
+    let base = repo.commit("base");
+    repo.checkout_new("feature", "main");
+    repo.commits("f", 2);
+    repo.checkout_new("side", "main");
+    repo.commit("s1");
+    repo.checkout("main");
+    repo.commits("m", 3);
+    let merge = repo.merge("merge", &["side"]);
+    repo.git(&["branch", "--delete", "--quiet", "side"]);
+    repo.commits("n", 4);
+    // The runs m1..m3 and n1..n3 are longer than 2; f1 is not, and the tips,
+    // the merge, and the merge base are kept.
+    assert_eq!(
+        git_tree(&repo, &["--render=native", "--ascii", "--format=%s", "--collapse=2"]),
+        "* n4\n\
+         : ... 3 commits ...\n\
+         * merge\n\
+         +-.\n\
+         | * s1\n\
+         : | ... 3 commits ...\n\
+         `-+\n\
+         * | f2\n\
+         * | f1\n\
+         `-+\n  \
+           * base\n"
+    );
+    // Each placeholder is a node named after the run's first commit, with the
+    // run's length, linked to the run's child and parent.
+    let json = git_tree(&repo, &["--format=json-edges", "--collapse=2"]);
+    let (n3, m3) = (repo.rev_parse("main~"), repo.rev_parse("main~4^"));
+    let placeholder = format!(
+        r#"{{"id":"collapsed-{n3}","refs":[],"author":"","time":1700001500,"subject":"","collapsed":3}}"#
+    );
+    assert!(json.contains(&placeholder), "{json}");
+    for (source, target) in
+        [(&merge, format!("collapsed-{m3}")), (&format!("collapsed-{m3}"), base)]
+    {
+        assert!(
+            json.contains(&format!(r#"{{"source":"{source}","target":"{target}"}}"#)),
+            "{json}"
+        );
+    }
+    for hidden in ["m1", "m3", "n1", "n3"] {
+        assert!(!json.contains(&format!(r#""subject":"{hidden}""#)), "{json}");
+    }
+}
+
+#[test]
+fn html_report_draws_the_graph() {
+    let repo = Repo::new();
+    let base = repo.commit("base");
+    repo.checkout_new("feature", "main");
+    let feature = repo.commit(r#"f1 <b>&"'"#);
+    repo.checkout("main");
+    repo.commit("m1");
+    let html = git_tree(&repo, &["--format=html"]);
+    assert!(
+        html.starts_with(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>git-tree</title>\n"
+        ),
+        "{html}"
+    );
+    assert!(html.ends_with("</svg>\n</body></html>\n"), "{html}");
+    assert_eq!(html.matches("<svg xmlns=\"http://www.w3.org/2000/svg\"").count(), 1, "{html}");
+    // A node and a tooltip for each commit, and an edge from each tip to the
+    // merge base.
+    assert_eq!(html.matches("<g class=\"commit\">").count(), 3, "{html}");
+    assert_eq!(html.matches("<circle ").count(), 3, "{html}");
+    assert_eq!(html.matches("<title>").count(), 4, "{html}");
+    assert_eq!(html.matches("<path ").count(), 2, "{html}");
+    assert!(!html.contains("<a "), "{html}");
+    // The labels and tooltips are escaped.
+    let short = repo.git(&["rev-parse", "--short", &feature]);
+    let subject = "f1 &lt;b&gt;&amp;&quot;&#39;";
+    assert!(
+        html.contains(&format!(
+            "<tspan class=\"id\">{short}</tspan> <tspan class=\"branch\">feature</tspan> \
+             {subject}</text>"
+        )),
+        "{html}"
+    );
+    assert!(
+        html.contains("<tspan class=\"head\">HEAD</tspan> <tspan class=\"branch\">main</tspan> m1"),
+        "{html}"
+    );
+    assert!(html.contains(&format!("<title>{feature}\nTest &lt;test@example.com&gt;\n")), "{html}");
+    assert!(html.contains(&format!("\n\n{subject}</title>")), "{html}");
+    assert!(html.contains(&format!("<title>{base}\n")), "{html}");
+}
+
+#[test]
+fn svg_export_draws_the_layers() {
+    let repo = Repo::new();
+    let base = repo.commit("base");
+    repo.checkout_new("feature", "main");
+    let feature = repo.commit("f1 <b>");
+    repo.checkout("main");
+    let m1 = repo.commit("m1");
+    let m2 = repo.commit("m2");
+    let svg = git_tree(&repo, &["--format=svg"]);
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" "), "{svg}");
+    assert!(svg.ends_with("</g>\n</svg>\n"), "{svg}");
+    // The tips are in the top layer, each commit a layer below its lowest
+    // child, and the merge base is highlighted.
+    for node in [
+        format!("<title>{m2} m2</title><circle cx=\"24\" cy=\"24\" r=\"6\"/>"),
+        format!("<title>{feature} f1 &lt;b&gt;</title><circle cx=\"144\" cy=\"24\" r=\"6\"/>"),
+        format!("<title>{m1} m1</title><circle cx=\"24\" cy=\"72\" r=\"6\"/>"),
+        format!(
+            "<title>{base} (merge base) base</title>\
+             <circle class=\"base\" cx=\"24\" cy=\"120\" r=\"6\"/>"
+        ),
+    ] {
+        assert!(svg.contains(&node), "{node}\n{svg}");
+    }
+    // The edge from the feature branch to the merge base passes through a
+    // dummy node in the layer it crosses.
+    for edge in [
+        "<path d=\"M24 24 C24 48 24 48 24 72\"/>",
+        "<path d=\"M24 72 C24 96 24 96 24 120\"/>",
+        "<path d=\"M144 24 C144 48 144 48 144 72 C144 96 24 96 24 120\"/>",
+    ] {
+        assert!(svg.contains(edge), "{edge}\n{svg}");
+    }
+    assert_eq!(svg.matches("<path ").count(), 3, "{svg}");
+    let head = "<tspan class=\"label head\">HEAD</tspan> <tspan class=\"label\">main</tspan>";
+    assert!(svg.contains(head), "{svg}");
+    assert!(svg.contains("<tspan class=\"label\">feature</tspan>"), "{svg}");
+}
+
+#[test]
+fn format_conditionals_are_evaluated_for_each_commit() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    repo.commits("f", 2);
+    repo.checkout("main");
+    repo.commit("m1");
+    let format = "--format=%s\
+                  %(if:tip) tip%(if:head) head%(else) other%(end)%(end)\
+                  %(if:base) base%(else)%(if:tip)%(else) plain%(end)%(end)";
+    assert_eq!(
+        git_tree(&repo, &["--render=native", "--ascii", format]),
+        "* m1 tip head\n\
+         | * f2 tip other\n\
+         | * f1 plain\n\
+         +-'\n\
+         * base base\n"
+    );
+    // An escaped percent sign starts no placeholder.
+    let escaped = ["--render=native", "--ascii", "--format=%s %%(end)"];
+    assert!(git_tree(&repo, &escaped).starts_with("* m1 %(end)\n"));
+}
+
+#[test]
+fn malformed_format_conditionals_are_rejected() {
+    /// git-tree's status for invalid arguments.
+    const USAGE: i32 = 2;
+    let repo = Repo::new();
+    repo.commit("base");
+    for (format, error) in [
+        ("%(if:tip)tip", "%(if:...) without %(end)"),
+        ("%(if:tip", "unterminated %(if:"),
+        ("%(if:merge)merge%(end)", "unknown condition %(if:merge)"),
+        ("end%(end)", "%(end) without %(if:...)"),
+        ("else%(else)", "%(else) without %(if:...)"),
+        ("%(if:tip)a%(else)b%(else)c%(end)", "duplicate %(else)"),
+        ("%(if:tip)%(if:head)nested%(end)", "%(if:...) without %(end)"),
+    ] {
+        let output = repo
+            .command(env!("CARGO_BIN_EXE_git-tree"))
+            .args(["--render=native", &format!("--format={format}")])
+            .output()
+            .expect("failed to run git-tree");
+        assert_eq!(output.status.code(), Some(USAGE), "{format}");
+        assert_eq!(String::from_utf8_lossy(&output.stderr), format!("git-tree: {error}\n"));
+        assert!(output.stdout.is_empty(), "{format}");
+    }
+}
+
+#[test]
+fn no_color_turns_off_the_escape_sequences() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    repo.commit("f1");
+    repo.checkout("main");
+    repo.commit("m1");
+    repo.git(&["config", "color.ui", "always"]);
+    let native = |color: &str, no_color: &str| {
+        let output = repo
+            .command(env!("CARGO_BIN_EXE_git-tree"))
+            .args(["--render=native", "--format=%h %s", color])
+            .env("NO_COLOR", no_color)
+            .output()
+            .expect("failed to run git-tree");
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8(output.stdout).expect("non-UTF-8 output")
+    };
+    // color.ui colors the output without a terminal, unless NO_COLOR is set
+    // to something.
+    assert!(native("--color=auto", "").contains('\x1b'));
+    let plain = native("--color=auto", "1");
+    assert!(!plain.contains('\x1b'), "{plain:?}");
+    assert!(plain.contains(" f1\n"), "{plain}");
+    // An explicit --color wins over NO_COLOR and color.ui.
+    assert!(native("--color=always", "1").contains('\x1b'));
+    assert!(!native("--no-color", "").contains('\x1b'));
+}
+
+#[test]
+fn status_command_shows_the_status_of_each_tip() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    let feature = repo.commit("f1");
+    repo.checkout_new("wip", "main");
+    let wip = repo.commit("w1");
+    repo.checkout("main");
+    repo.commit("m1");
+    // The command is run in the shell, with the tip as its argument.
+    let command = format!(
+        "--status-command=status() {{ case $1 in {feature}) echo 'Success!';; {wip}) echo running \
+         late;; *) echo FAILED: 3 tests;; esac; }}; status"
+    );
+    let native = ["--render=native", "--ascii", "--no-color", "--format=%s"];
+    assert_eq!(
+        git_tree(&repo, &[&native[..], &[&command]].concat()),
+        "* m1 [CI fail]\n\
+         | * w1 [CI pending]\n\
+         +-'\n\
+         | * f1 [CI pass]\n\
+         +-'\n\
+         * base\n"
+    );
+    let json = git_tree(&repo, &["--format=json-edges", &command]);
+    for (subject, status) in [("m1", r#""fail""#), ("f1", r#""pass""#), ("base", "null")] {
+        let node = format!(r#""subject":"{subject}","collapsed":0,"ciStatus":{status}"#);
+        assert!(json.contains(&node), "{json}");
+    }
+    // A failing command reports no status, and git-tree carries on.
+    let output = repo
+        .command(env!("CARGO_BIN_EXE_git-tree"))
+        .args(native)
+        .arg("--status-command=echo >&2 no CI here; false")
+        .output()
+        .expect("failed to run git-tree");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "* m1\n\
+         | * w1\n\
+         +-'\n\
+         | * f1\n\
+         +-'\n\
+         * base\n"
+    );
+}
+
+#[test]
+#[allow(clippy::literal_string_with_formatting_args, reason = "they are placeholders")]
+fn link_template_links_the_commits() {
+    let repo = Repo::new();
+    let base = repo.commit("base");
+    repo.checkout_new("feature", "main");
+    let feature = repo.commit("f1");
+    let short = repo.git(&["rev-parse", "--short", &feature]);
+    let url = format!("https://review.example.com/{feature}?short={short}&id={short}");
+    // The placeholders are expanded, and the abbreviation the native graph
+    // shows is a terminal hyperlink.
+    let template = "--link-template=https://review.example.com/{hash}?short={short}&id=%h";
+    let native =
+        git_tree(&repo, &["--render=native", "--color=always", "--format=%h %s", template]);
+    assert!(
+        native.contains(&format!("\x1b]8;;{url}\x1b\\{short}\x1b]8;;\x1b\\ f1\n")),
+        "{native:?}"
+    );
+    let plain = git_tree(&repo, &["--render=native", "--no-color", "--format=%h %s", template]);
+    assert!(!plain.contains('\x1b'), "{plain:?}");
+    // In HTML and SVG, the links are escaped hrefs.
+    let href = format!("<a href=\"{}\"", url.replace('&', "&amp;"));
+    assert!(git_tree(&repo, &["--format=html", template]).contains(&href));
+    assert!(git_tree(&repo, &["--format=svg", template]).contains(&href));
+    let report = git_tree(&repo, &["report", template]);
+    assert!(report.contains(&format!("[`{short}`]({url}) f1\n")), "{report}");
+    // Markdown gets the characters that would end the link percent-encoded,
+    // and the terminal no control characters.
+    let unsafe_template = "--link-template=https://example.com/a b(c)\x07\x1b]{hash}";
+    let unsafe_report = git_tree(&repo, &["report", unsafe_template]);
+    let encoded = format!("(https://example.com/a%20b%28c%29%07%1B]{feature}) f1\n");
+    assert!(unsafe_report.contains(&encoded), "{unsafe_report}");
+    let native_args = ["--render=native", "--color=always", "--format=%h %s", unsafe_template];
+    let unsafe_native = git_tree(&repo, &native_args);
+    let stripped = format!("\x1b]8;;https://example.com/a b(c)]{feature}\x1b\\{short}");
+    assert!(unsafe_native.contains(&stripped), "{unsafe_native:?}");
+    // gitxl.linkTemplate applies when there is no --link-template.
+    repo.git(&["config", "gitxl.linkTemplate", r#"https://example.com/"{hash}"<>"#]);
+    let html = git_tree(&repo, &["--format=html"]);
+    assert!(
+        html.contains(&format!(r#"<a href="https://example.com/&quot;{base}&quot;&lt;&gt;""#)),
+        "{html}"
+    );
+}
+
+/// Runs `git tree --pick` in `repo` on a terminal (made by util-linux's
+/// `script`) typing `keys`, and returns the commit IDs it prints.
+#[cfg(target_os = "linux")]
+fn pick(repo: &Repo, keys: &str) -> Vec<String> {
+    use std::io::Write as _;
+    use std::process::Stdio;
+
+    let command = format!("{} --pick >picked", env!("CARGO_BIN_EXE_git-tree"));
+    let mut child = repo
+        .command("script")
+        .args(["--quiet", "--return", "--command", &command, "/dev/null"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .expect("failed to run script");
+    child.stdin.take().unwrap().write_all(keys.as_bytes()).unwrap();
+    assert!(child.wait().unwrap().success(), "git-tree --pick failed");
+    let picked = fs::read_to_string(repo.path().join("picked")).unwrap();
+    picked.lines().map(str::to_owned).collect()
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn pick_prints_the_picked_commits() {
+    let repo = Repo::new();
+    let base = repo.commit("base");
+    repo.checkout_new("feature", "main");
+    let feature = repo.commit("fix the parser");
+    repo.checkout("main");
+    let main = repo.commit("main work");
+    // The query filters the commits by subsequence, and Enter picks the one
+    // under the cursor.
+    assert_eq!(pick(&repo, "fxprs\r"), [feature]);
+    assert_eq!(pick(&repo, "ma wo\r"), [main.as_str()]);
+    assert!(pick(&repo, "nothing\r").is_empty());
+    // Tab marks commits (Ctrl-N moves down), and Enter picks the marked ones
+    // in the graph's order.
+    assert_eq!(pick(&repo, "\x0e\x0e\t\x10\x10\t\r"), [main, base]);
+}
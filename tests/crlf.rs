@@ -0,0 +1,62 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks that git-tree handles git output with `\r\n` line endings, as some
+//! Windows builds of git write, by running it with a `git` wrapper that
+//! converts git's output. The wrapper is a shell script, so this only runs on
+//! Unix; on Windows, the rest of the tests run against the real thing.
+
+#![cfg(unix)]
+#![allow(clippy::tests_outside_test_module, reason = "this is an integration test")]
+
+use core::iter;
+use git_tree_test_support::fixtures;
+use std::env::{join_paths, split_paths, var_os};
+use std::fs;
+use std::os::unix::fs::PermissionsExt as _;
+
+#[test]
+fn crlf_git_output_is_handled() {
+    for (name, repo) in fixtures::all() {
+        let git = repo.run("sh", &["-c", "command -v git"]);
+        let bin = repo.path().join(".git/crlf-bin");
+        fs::create_dir_all(&bin).expect("failed to create the wrapper directory");
+        let wrapper = bin.join("git");
+        fs::write(
+            &wrapper,
+            format!(
+                "#!/bin/sh\nout=$(mktemp)\n'{}' \"$@\" >\"$out\"\nstatus=$?\n\
+                 sed 's/$/\\r/' \"$out\"\nrm -f \"$out\"\nexit $status\n",
+                git.trim_end()
+            ),
+        )
+        .expect("failed to write the wrapper");
+        fs::set_permissions(&wrapper, fs::Permissions::from_mode(0o755))
+            .expect("failed to make the wrapper executable");
+        let path =
+            join_paths(iter::once(bin).chain(split_paths(&var_os("PATH").unwrap_or_default())))
+                .expect("invalid PATH");
+
+        let expected = repo.run(env!("CARGO_BIN_EXE_git-tree"), &["--porcelain"]);
+        drop(fs::remove_dir_all(repo.path().join(".git/gitxl")));
+        let output = repo
+            .command(env!("CARGO_BIN_EXE_git-tree"))
+            .arg("--porcelain")
+            .env("PATH", path)
+            .output()
+            .expect("failed to run git-tree");
+        assert!(output.status.success(), "{name}: {}", String::from_utf8_lossy(&output.stderr));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), expected, "{name}");
+    }
+}
@@ -0,0 +1,97 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Differential tests: git-tree finds the displayed commits by walking the
+//! commit-graph when the repository has one and by parsing `git rev-list`
+//! otherwise, and caches the result. These tests compute the displayed commits
+//! for every fixture along each path, and independently with `git rev-list
+//! --ancestry-path`, and fail if any of them disagree.
+
+#![allow(clippy::tests_outside_test_module, reason = "this is an integration test")]
+
+use alloc::collections::BTreeSet;
+use git_tree_test_support::{fixtures, Repo};
+use std::fs;
+
+extern crate alloc;
+
+/// What git-tree's porcelain output says about a repository.
+struct Porcelain {
+    tips: Vec<String>,
+    bases: BTreeSet<String>,
+    displayed: BTreeSet<String>,
+}
+
+/// Runs `git tree --porcelain` in `repo`, after deleting the cache unless
+/// `cached` is set.
+fn porcelain(repo: &Repo, cached: bool) -> Porcelain {
+    if !cached {
+        drop(fs::remove_dir_all(repo.path().join(".git/gitxl")));
+    }
+    let output = repo.run(env!("CARGO_BIN_EXE_git-tree"), &["--porcelain"]);
+    let mut porcelain =
+        Porcelain { tips: vec![], bases: BTreeSet::new(), displayed: BTreeSet::new() };
+    for line in output.lines() {
+        let mut fields = line.split(' ');
+        match (fields.next(), fields.next()) {
+            (Some("head" | "branch"), Some(id)) => porcelain.tips.push(id.to_owned()),
+            (Some("base"), Some(id)) => drop(porcelain.bases.insert(id.to_owned())),
+            (Some("commit"), Some(id)) => drop(porcelain.displayed.insert(id.to_owned())),
+            _ => {}
+        }
+    }
+    porcelain
+}
+
+/// Returns the commits that should be displayed, computed with plain git: the
+/// tips if they have no merge base, and otherwise every commit that is both a
+/// descendant of a merge base and an ancestor of a tip, and the merge bases.
+fn oracle(repo: &Repo, tips: &[String]) -> (BTreeSet<String>, BTreeSet<String>) {
+    let mut args = vec!["merge-base", "--all", "--octopus"];
+    args.extend(tips.iter().map(String::as_str));
+    let output = repo.command("git").args(&args).output().expect("failed to run git");
+    let bases: BTreeSet<String> =
+        String::from_utf8_lossy(&output.stdout).lines().map(str::to_owned).collect();
+    if bases.is_empty() {
+        return (bases, tips.iter().cloned().collect());
+    }
+    let mut displayed = bases.clone();
+    for base in &bases {
+        let mut rev_list = vec!["rev-list", "--ancestry-path"];
+        rev_list.extend(tips.iter().map(String::as_str));
+        let exclude = format!("^{base}");
+        rev_list.push(&exclude);
+        displayed.extend(repo.git(&rev_list).lines().map(str::to_owned));
+    }
+    (bases, displayed)
+}
+
+#[test]
+fn traversals_agree_with_each_other_and_with_git() {
+    for (name, repo) in fixtures::all() {
+        drop(fs::remove_file(repo.path().join(".git/objects/info/commit-graph")));
+        drop(fs::remove_dir_all(repo.path().join(".git/objects/info/commit-graphs")));
+        let rev_list = porcelain(&repo, false);
+        let (bases, displayed) = oracle(&repo, &rev_list.tips);
+        assert_eq!(rev_list.bases, bases, "{name}: merge bases differ from git's");
+        assert_eq!(rev_list.displayed, displayed, "{name}: rev-list traversal differs from git");
+
+        repo.git(&["commit-graph", "write", "--reachable"]);
+        let commit_graph = porcelain(&repo, false);
+        assert_eq!(commit_graph.displayed, displayed, "{name}: commit-graph traversal differs");
+
+        let cached = porcelain(&repo, true);
+        assert_eq!(cached.displayed, displayed, "{name}: cached view differs");
+    }
+}
@@ -0,0 +1,44 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Snapshot tests of every output format on the fixture repositories. The
+//! snapshots live in `tests/snapshots/<fixture>.<format>`; see
+//! `git_tree_test_support::snapshot` for how to update them.
+
+#![allow(clippy::tests_outside_test_module, reason = "this is an integration test")]
+
+use git_tree_test_support::fixtures;
+use git_tree_test_support::snapshot;
+use std::path::Path;
+
+/// The outputs snapshotted for each fixture: (file extension, arguments).
+const FORMATS: [(&str, &[&str]); 6] = [
+    ("native", &["--render=native", "--no-color", "--no-pager"]),
+    ("ascii", &["--render=native", "--ascii", "--no-color", "--no-pager", "--format=%h %s"]),
+    ("json", &["--format=json-edges"]),
+    ("graphml", &["--format=graphml"]),
+    ("porcelain", &["--porcelain"]),
+    ("report.md", &["report", "--no-pager"]),
+];
+
+#[test]
+fn outputs_match_snapshots() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots");
+    for (name, repo) in fixtures::all() {
+        for (extension, args) in FORMATS {
+            let output = repo.run(env!("CARGO_BIN_EXE_git-tree"), args);
+            snapshot::check(&dir.join(format!("{name}.{extension}")), &output);
+        }
+    }
+}
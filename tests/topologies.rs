@@ -0,0 +1,2409 @@
+// Copyright 2025 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! End-to-end tests that build repositories with representative topologies and
+//! check which commits git-tree displays and how it draws them.
+
+#![allow(clippy::tests_outside_test_module, reason = "this is an integration test")]
+
+use core::time::Duration;
+use git_tree_test_support::Repo;
+use std::fs;
+use std::time::SystemTime;
+
+/// Runs git-tree in `repo` with `args` and returns its stdout.
+fn git_tree(repo: &Repo, args: &[&str]) -> String {
+    repo.run(env!("CARGO_BIN_EXE_git-tree"), args)
+}
+
+/// Returns the subjects of the displayed commits, sorted.
+fn displayed(repo: &Repo) -> Vec<String> {
+    let porcelain = git_tree(repo, &["--porcelain"]);
+    let mut subjects: Vec<_> = porcelain
+        .lines()
+        .filter_map(|line| line.strip_prefix("commit "))
+        .filter_map(|rest| rest.split(' ').next())
+        .map(|id| repo.subject(id))
+        .collect();
+    subjects.sort_unstable();
+    subjects
+}
+
+/// Returns the native graph with each commit's subject.
+fn graph(repo: &Repo) -> String {
+    git_tree(repo, &["--render=native", "--ascii", "--no-color", "--no-pager", "--format=%s"])
+}
+
+#[test]
+fn linear_history_shows_only_the_tip() {
+    let repo = Repo::new();
+    repo.commits("c", 3);
+    assert_eq!(displayed(&repo), ["c3"]);
+}
+
+#[test]
+fn diverged_branches_show_both_sides_and_the_base() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.branch("feature", "main");
+    repo.commits("m", 2);
+    repo.checkout("feature");
+    repo.commits("f", 2);
+    assert_eq!(displayed(&repo), ["base", "f1", "f2", "m1", "m2"]);
+    assert_eq!(graph(&repo), "* f2\n* f1\n| * m2\n| * m1\n+-'\n* base\n");
+}
+
+#[test]
+fn native_format_may_contain_nuls_and_newlines() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.branch("feature", "main");
+    repo.commit("m1");
+    repo.checkout("feature");
+    repo.commit("f1");
+    let native = ["--render=native", "--ascii", "--no-color", "--no-pager"];
+    assert_eq!(
+        git_tree(&repo, &[&native[..], &["--format=%s%x00%n"]].concat()),
+        "* f1\0\n|\n| * m1\0\n| |\n+-'\n* base\0\n \n"
+    );
+    // Text that looks like the header of another commit's record is still
+    // part of this commit's text.
+    assert_eq!(
+        git_tree(&repo, &[&native[..], &["--format=%s%ncommit %s"]].concat()),
+        "* f1\n| commit f1\n| * m1\n| | commit m1\n+-'\n* base\n  commit base\n"
+    );
+}
+
+#[test]
+fn stacked_branches_show_the_whole_stack() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("s1", "main");
+    repo.commit("s1a");
+    repo.checkout_new("s2", "s1");
+    repo.commits("s2-", 2);
+    repo.checkout_new("s3", "s2");
+    repo.commit("s3a");
+    assert_eq!(displayed(&repo), ["base", "s1a", "s2-1", "s2-2", "s3a"]);
+    assert_eq!(graph(&repo), "* s3a\n* s2-2\n* s2-1\n* s1a\n* base\n");
+}
+
+#[test]
+fn merged_branch_shows_the_merge_and_the_branch_tip() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    repo.commit("f1");
+    repo.checkout("main");
+    repo.commit("m1");
+    repo.merge("merge feature", &["feature"]);
+    assert_eq!(displayed(&repo), ["f1", "merge feature"]);
+}
+
+#[test]
+fn criss_cross_merges_have_two_merge_bases() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("x", "main");
+    repo.commit("x1");
+    repo.checkout_new("y", "main");
+    repo.commit("y1");
+    repo.checkout("x");
+    repo.merge("x2", &["y~0"]);
+    repo.checkout("y");
+    repo.merge("y2", &["x~1"]);
+    repo.git(&["branch", "--quiet", "--delete", "--force", "main"]);
+    let porcelain = git_tree(&repo, &["--porcelain"]);
+    let mut bases: Vec<_> = porcelain
+        .lines()
+        .filter_map(|line| line.strip_prefix("base "))
+        .map(|id| repo.subject(id))
+        .collect();
+    bases.sort_unstable();
+    assert_eq!(bases, ["x1", "y1"]);
+    assert_eq!(displayed(&repo), ["x1", "x2", "y1", "y2"]);
+}
+
+#[test]
+fn criss_cross_below_the_merge_base_is_hidden() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("x", "main");
+    repo.commit("x1");
+    repo.checkout_new("z", "x");
+    repo.commit("z1");
+    repo.checkout_new("y", "main");
+    repo.commit("y1");
+    repo.checkout("x");
+    repo.merge("x2", &["y~0"]);
+    repo.checkout("y");
+    repo.merge("y2", &["x~1"]);
+    repo.git(&["branch", "--quiet", "--delete", "--force", "main"]);
+    // The merge base of all three tips is x1, so y1 is not displayed even
+    // though y2 reaches it without going through x1.
+    assert_eq!(displayed(&repo), ["x1", "x2", "y2", "z1"]);
+    let mut logged: Vec<_> =
+        git_tree(&repo, &["--no-pager", "--format=%s"]).lines().map(str::to_owned).collect();
+    logged.sort_unstable();
+    assert_eq!(logged, displayed(&repo));
+}
+
+#[test]
+fn octopus_merge_shows_every_arm() {
+    let repo = Repo::new();
+    repo.commit("base");
+    for arm in ["a", "b", "c"] {
+        repo.checkout_new(arm, "main");
+        repo.commit(&format!("{arm}1"));
+    }
+    repo.checkout("main");
+    repo.merge("octopus", &["a", "b", "c"]);
+    assert_eq!(displayed(&repo), ["a1", "b1", "base", "c1", "octopus"]);
+    assert_eq!(
+        graph(&repo),
+        "* octopus\n+-.-.-.\n| | | * c1\n+-+-+-'\n| | * b1\n+-+-'\n| * a1\n+-'\n* base\n"
+    );
+}
+
+#[test]
+fn octopus_merge_is_visible_through_any_parent() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.branch("side1", "main");
+    repo.branch("side2", "main");
+    repo.commit("m1");
+    repo.checkout_new("arm", "main");
+    repo.commit("a1");
+    for side in ["side1", "side2"] {
+        repo.checkout(side);
+        repo.commit(side);
+    }
+    repo.checkout_new("octopus", "side1");
+    repo.merge("octopus", &["side2", "arm"]);
+    repo.git(&["branch", "--quiet", "--delete", "--force", "side1", "side2", "arm"]);
+    // Only the octopus merge's last parent reaches the merge base, m1; the
+    // other arms forked below it.
+    assert_eq!(displayed(&repo), ["a1", "m1", "octopus"]);
+    assert_eq!(graph(&repo), "* octopus\n* a1\n* m1\n");
+}
+
+#[test]
+fn first_parent_shows_the_first_parent_chains() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    repo.commits("f", 2);
+    repo.checkout("main");
+    repo.commit("m1");
+    repo.merge("merge feature", &["feature"]);
+    repo.checkout("feature");
+    repo.commit("f3");
+    // Without --first-parent, the merge base is f2, and m1 is hidden.
+    assert_eq!(displayed(&repo), ["f2", "f3", "merge feature"]);
+    assert_eq!(
+        git_tree(&repo, &["--no-pager", "--first-parent", "--format=%s"]),
+        "f3\nmerge feature\nm1\nf2\nf1\nbase\n"
+    );
+    assert_eq!(
+        git_tree(&repo, &["--first-parent", "--render=native", "--ascii", "--format=%s"]),
+        "* f3\n| * merge feature\n* | f2\n* | f1\n| * m1\n+-'\n* base\n"
+    );
+}
+
+#[test]
+fn priority_date_orders_the_commits() {
+    let repo = Repo::new();
+    repo.commit("base");
+    // Each branch's commit, with its author and committer dates.
+    for (branch, author, committer) in
+        [("a", "100", "300"), ("b", "200", "200"), ("c", "400", "100")]
+    {
+        repo.checkout_new(branch, "main");
+        let status = repo
+            .command("git")
+            .args(["commit", "--quiet", "--allow-empty", "--message", branch])
+            .env("GIT_AUTHOR_DATE", format!("@2000000{author} +0000"))
+            .env("GIT_COMMITTER_DATE", format!("@2000000{committer} +0000"))
+            .status()
+            .expect("failed to run git");
+        assert!(status.success());
+    }
+    let order = |args: &[&str]| {
+        let mut all = vec!["--no-pager", "--format=%s"];
+        all.extend(args);
+        git_tree(&repo, &all)
+    };
+    assert_eq!(order(&["--priority-date=author"]), "c\nb\na\nbase\n");
+    assert_eq!(order(&["--priority-date=committer"]), "a\nb\nc\nbase\n");
+    let native = ["--render=native", "--ascii", "--priority-date=max"];
+    assert_eq!(order(&native), "* c\n| * a\n+-'\n| * b\n+-'\n* base\n");
+}
+
+#[test]
+fn context_shows_first_parents_below_the_merge_base() {
+    let repo = Repo::new();
+    repo.commit("old");
+    repo.checkout_new("side", "main");
+    repo.commit("side");
+    repo.checkout("main");
+    repo.commit("m0");
+    repo.merge("merge side", &["side"]);
+    repo.git(&["branch", "--delete", "side"]);
+    repo.commits("m", 2);
+    repo.checkout_new("feature", "main");
+    repo.commit("feature");
+    repo.checkout("main");
+    repo.commit("m3");
+    let context = |lines: &str| {
+        git_tree(&repo, &["--no-pager", "--format=%s", "--topo-order", "--context", lines])
+    };
+    assert_eq!(displayed(&repo), ["feature", "m2", "m3"]);
+    assert_eq!(context("0"), "m3\nfeature\nm2\n");
+    // The side branch merged into the context forked below it, so it is not
+    // shown.
+    assert_eq!(context("3"), "m3\nfeature\nm2\nm1\nmerge side\nm0\n");
+    // Context stops at the root, and shows the side branch forked within it.
+    assert_eq!(context("10"), "m3\nfeature\nm2\nm1\nmerge side\nside\nm0\nold\n");
+}
+
+#[test]
+fn mine_only_hides_the_upstream_commits() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("upstream", "main");
+    repo.commit("theirs");
+    repo.checkout("main");
+    repo.commit("mine");
+    repo.git(&["update-ref", "refs/remotes/origin/main", "upstream"]);
+    repo.git(&["branch", "--delete", "--force", "upstream"]);
+    repo.git(&["config", "remote.origin.url", "."]);
+    repo.git(&["config", "remote.origin.fetch", "+refs/heads/*:refs/remotes/origin/*"]);
+    repo.git(&["branch", "--set-upstream-to=origin/main", "main"]);
+    assert_eq!(displayed(&repo), ["base", "mine", "theirs"]);
+    assert_eq!(git_tree(&repo, &["--no-pager", "--format=%s", "--mine-only"]), "mine\n");
+}
+
+#[test]
+fn status_lists_the_local_branches() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("upstream", "main");
+    repo.commit("theirs");
+    repo.git(&["update-ref", "refs/remotes/origin/main", "upstream"]);
+    repo.git(&["update-ref", "refs/remotes/origin/old", "upstream"]);
+    repo.checkout("main");
+    repo.git(&["branch", "--delete", "--force", "upstream"]);
+    repo.commits("mine", 2);
+    repo.branch("feature", "main~2");
+    repo.branch("old", "main~2");
+    repo.git(&["config", "remote.origin.url", "."]);
+    repo.git(&["config", "remote.origin.fetch", "+refs/heads/*:refs/remotes/origin/*"]);
+    repo.git(&["branch", "--set-upstream-to=origin/main", "main"]);
+    repo.git(&["branch", "--set-upstream-to=origin/old", "old"]);
+    repo.git(&["update-ref", "-d", "refs/remotes/origin/old"]);
+    let output = repo
+        .command(env!("CARGO_BIN_EXE_git-tree"))
+        .arg("status")
+        .env("GIT_TEST_DATE_NOW", "1700259200")
+        .output()
+        .expect("failed to run git-tree");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "  feature  -                   3 days ago  base\n\
+         * main     origin/main  +2 -1  3 days ago  mine2\n\
+         \x20 old      origin/old   gone   3 days ago  base\n"
+    );
+}
+
+#[test]
+fn check_fails_when_a_branch_diverges_too_far() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("release/1", "main");
+    repo.commit("fix");
+    repo.checkout("main");
+    repo.commits("m", 3);
+    repo.git(&["update-ref", "refs/remotes/origin/release/2", "main~1"]);
+    repo.branch("feature", "main");
+    let check = |args: &[&str]| {
+        let output = repo
+            .command(env!("CARGO_BIN_EXE_git-tree"))
+            .args(["check", "--trunk", "main", "--branches", "release/*"])
+            .args(args)
+            .output()
+            .expect("failed to run git-tree");
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        (output.status.success(), String::from_utf8_lossy(&output.stdout).into_owned(), stderr)
+    };
+    let (passed, stdout, stderr) = check(&["--max-behind", "3"]);
+    assert!(passed, "{stderr}");
+    assert_eq!(
+        stdout,
+        "{\"trunk\":\"main\",\"maxAhead\":null,\"maxBehind\":3,\"ok\":true,\"branches\":[\
+         {\"name\":\"release/1\",\"ahead\":1,\"behind\":3,\"ok\":true},\
+         {\"name\":\"origin/release/2\",\"ahead\":0,\"behind\":1,\"ok\":true}]}\n"
+    );
+    let (strict_passed, strict_stdout, strict_stderr) = check(&["--max-behind=2", "--max-ahead=0"]);
+    assert!(!strict_passed);
+    assert!(strict_stdout.contains("\"ok\":false,\"branches\""), "{strict_stdout}");
+    assert_eq!(strict_stderr, "git-tree: release/1 is 1 ahead of and 3 behind main\n");
+}
+
+#[test]
+fn bundle_contains_the_displayed_commits() {
+    let repo = Repo::new();
+    let root = repo.commit("root");
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    let feature = repo.commit("f1");
+    repo.checkout("main");
+    let main = repo.commit("m1");
+    assert_eq!(repo.run(env!("CARGO_BIN_EXE_git-tree"), &["bundle", "-o", "work.bundle"]), "");
+    assert_eq!(
+        repo.git(&["bundle", "list-heads", "work.bundle"]),
+        format!("{feature} refs/heads/feature\n{main} refs/heads/main\n{main} HEAD")
+    );
+    // Only the commits below the merge base are left out.
+    let verify = repo.git(&["bundle", "verify", "work.bundle"]);
+    assert!(verify.contains(&format!("requires this ref:\n{root}")), "{verify}");
+}
+
+#[test]
+fn format_patch_starts_at_the_fork_point() {
+    let repo = Repo::new();
+    let base = repo.commit("base");
+    repo.checkout_new("feature", "main");
+    repo.commit_file("a", "a\n", "add a");
+    repo.commit_file("b", "b\n", "add b");
+    repo.checkout("main");
+    repo.commit("m1");
+    let patches = repo.run(
+        env!("CARGO_BIN_EXE_git-tree"),
+        &["format-patch", "--trunk", "main", "feature", "-o", "patches"],
+    );
+    assert_eq!(patches, "patches/0001-add-a.patch\npatches/0002-add-b.patch\n");
+    let read = |name: &str| {
+        fs::read_to_string(repo.path().join("patches").join(name)).expect("failed to read patch")
+    };
+    let first = read("0001-add-a.patch");
+    assert!(first.contains("Subject: [PATCH 1/2] add a"), "{first}");
+    assert!(first.contains(&format!("base-commit: {base}")), "{first}");
+    assert!(read("0002-add-b.patch").contains("Subject: [PATCH 2/2] add b"));
+}
+
+#[test]
+fn conflicts_lists_the_conflicting_files() {
+    let repo = Repo::new();
+    repo.commit_file("a", "a\n", "base");
+    repo.checkout_new("clean", "main");
+    repo.commit_file("b", "b\n", "add b");
+    repo.checkout_new("conflicting", "main");
+    repo.commit_file("a", "theirs\n", "change a");
+    repo.branch("merged", "main");
+    repo.checkout("main");
+    repo.commit_file("a", "ours\n", "change a too");
+    assert_eq!(
+        repo.run(env!("CARGO_BIN_EXE_git-tree"), &["conflicts", "--with", "main"]),
+        "clean: merges cleanly with main\nconflicting: conflicts with main in a\n"
+    );
+}
+
+#[test]
+fn cherry_mark_marks_equivalent_commits() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    let picked = repo.commit_file("a", "a\n", "add a");
+    repo.commit_file("b", "b\n", "add b");
+    repo.checkout("main");
+    repo.git(&["cherry-pick", &picked]);
+    let copy = repo.rev_parse("HEAD");
+    let (picked_short, copy_short) = (picked.get(..7).unwrap(), copy.get(..7).unwrap());
+    assert_eq!(
+        git_tree(&repo, &["--render=native", "--ascii", "--format=%s", "--cherry-mark"]),
+        format!(
+            "* add a [same change as {picked_short}]\n\
+             | * add b\n\
+             | * add a [same change as {copy_short}]\n\
+             +-'\n\
+             * base\n"
+        )
+    );
+    let json = git_tree(&repo, &["--format=json-edges", "--cherry-mark"]);
+    assert!(json.contains(&format!("\"equivalentTo\":[\"{picked}\"]")), "{json}");
+    let label = |args: &[&str]| {
+        let mut all = vec!["--render=native", "--ascii", "--format=%s", "--cherry-mark"];
+        all.extend(args);
+        git_tree(&repo, &all).lines().next().unwrap_or_default().to_owned()
+    };
+    assert_eq!(label(&["--full-hashes"]), format!("* add a [same change as {picked}]"));
+    repo.git(&["config", "core.abbrev", "10"]);
+    assert_eq!(label(&[]), format!("* add a [same change as {}]", picked.get(..10).unwrap()));
+}
+
+#[test]
+fn files_lists_the_most_touched_paths() {
+    let repo = Repo::new();
+    repo.commit_file("a", "0\n", "base");
+    repo.checkout_new("feature", "main");
+    repo.commit_file("a", "1\n", "f1");
+    repo.commit_file("b", "1\n", "f2");
+    repo.checkout("main");
+    repo.commit_file("a", "2\n", "m1");
+    repo.commit_file("a", "3\n", "m2");
+    repo.commit_file("c", "3\n", "m3");
+    assert_eq!(
+        repo.run(env!("CARGO_BIN_EXE_git-tree"), &["files", "--limit=2"]),
+        "All branches:\n\
+         \x20 3  a (feature, main)\n\
+         \x20 1  b (feature)\n\
+         \n\
+         feature:\n\
+         \x20 1  a\n\
+         \x20 1  b\n\
+         \n\
+         main:\n\
+         \x20 2  a\n\
+         \x20 1  c\n"
+    );
+}
+
+#[test]
+fn authors_groups_the_branches_by_author() {
+    let repo = Repo::new();
+    repo.commit("base");
+    let commit_as = |author: &str, message: &str| {
+        repo.git(&["commit", "--quiet", "--allow-empty", "--author", author, "-m", message]);
+    };
+    repo.checkout_new("feature", "main");
+    commit_as("alice <alice@example.com>", "f1");
+    commit_as("bob <bob@example.com>", "f2");
+    commit_as("alice <alice@example.com>", "f3");
+    repo.checkout_new("fix", "feature");
+    commit_as("alice <alice@example.com>", "x1");
+    repo.checkout("main");
+    repo.commit("m1");
+    assert_eq!(
+        repo.run(env!("CARGO_BIN_EXE_git-tree"), &["authors"]),
+        "Test: main (1)\n\
+         alice: feature (2), fix (1)\n\
+         bob: feature (1)\n"
+    );
+}
+
+#[test]
+fn profile_selects_the_interesting_branches() {
+    /// git-tree's status for invalid arguments.
+    const USAGE: i32 = 2;
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("upstream", "main");
+    repo.commit("theirs");
+    repo.git(&["update-ref", "refs/remotes/origin/main", "upstream"]);
+    repo.checkout_new("other", "main");
+    repo.commit("other");
+    repo.git(&["update-ref", "refs/remotes/origin/other", "other"]);
+    repo.checkout("main");
+    repo.git(&["branch", "--delete", "--force", "upstream", "other"]);
+    repo.commit("mine");
+    repo.git(&["config", "remote.origin.url", "."]);
+    repo.git(&["config", "remote.origin.fetch", "+refs/heads/*:refs/remotes/origin/*"]);
+    repo.git(&["branch", "--set-upstream-to=origin/main", "main"]);
+    assert_eq!(displayed(&repo), ["base", "mine", "theirs"]);
+    repo.git(&["config", "gitxl.profile.local.remotes", "none"]);
+    repo.git(&["config", "gitxl.profile.local.logArgs", "--format=%s"]);
+    assert_eq!(git_tree(&repo, &["--no-pager", "--profile", "local"]), "mine\n");
+    repo.git(&["config", "gitxl.profile.remote.remotes", "all"]);
+    repo.git(&["config", "gitxl.profile.remote.ref", "refs/remotes/origin/*"]);
+    assert_eq!(
+        git_tree(&repo, &["--no-pager", "--format=%s", "--profile=remote"]),
+        "mine\nother\ntheirs\nbase\n"
+    );
+    let output = repo
+        .command(env!("CARGO_BIN_EXE_git-tree"))
+        .args(["--no-pager", "--profile=missing"])
+        .output()
+        .expect("failed to run git-tree");
+    assert_eq!(output.status.code(), Some(USAGE));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "git-tree: unknown profile missing (no gitxl.profile.missing.* settings)\n"
+    );
+}
+
+#[test]
+fn pins_override_the_heuristic() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("integration", "main");
+    repo.commit("i1");
+    repo.git(&["update-ref", "refs/remotes/origin/integration", "integration"]);
+    repo.checkout_new("wip", "main");
+    repo.commit("w1");
+    repo.checkout("main");
+    repo.git(&["branch", "--delete", "--force", "integration"]);
+    repo.commit("m1");
+    assert_eq!(displayed(&repo), ["base", "m1", "w1"]);
+    assert_eq!(git_tree(&repo, &["pin", "origin/integration"]), "");
+    assert_eq!(git_tree(&repo, &["pin", "--never", "wip"]), "");
+    assert_eq!(displayed(&repo), ["base", "i1", "m1"]);
+    assert_eq!(
+        git_tree(&repo, &["pin"]),
+        "never refs/heads/wip\nalways refs/remotes/origin/integration\n"
+    );
+    assert_eq!(git_tree(&repo, &["unpin", "wip"]), "");
+    assert_eq!(displayed(&repo), ["base", "i1", "m1", "w1"]);
+}
+
+#[test]
+fn worktrees_share_the_pins_and_the_cache() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.branch("wip", "main");
+    let worktree = repo.path().join("linked");
+    repo.git(&["worktree", "add", "--quiet", "linked", "wip"]);
+    let in_worktree = |args: &[&str]| {
+        let output = repo
+            .command(env!("CARGO_BIN_EXE_git-tree"))
+            .args(args)
+            .current_dir(&worktree)
+            .output()
+            .expect("failed to run git-tree");
+        assert!(output.status.success());
+    };
+    in_worktree(&["pin", "--never", "main"]);
+    in_worktree(&["--no-pager"]);
+    assert_eq!(git_tree(&repo, &["pin"]), "never refs/heads/main\n");
+    assert!(repo.path().join(".git/gitxl/cache").exists());
+    assert!(!repo.path().join(".git/worktrees/linked/gitxl").exists());
+}
+
+#[test]
+fn concurrent_pins_are_not_lost() {
+    let repo = Repo::new();
+    repo.commit("base");
+    let names = ["b0", "b1", "b2", "b3", "b4", "b5", "b6", "b7"];
+    for name in names {
+        repo.branch(name, "main");
+    }
+    let children: Vec<_> = names
+        .iter()
+        .map(|name| {
+            repo.command(env!("CARGO_BIN_EXE_git-tree"))
+                .args(["pin", name])
+                .spawn()
+                .expect("failed to run git-tree")
+        })
+        .collect();
+    for mut child in children {
+        assert!(child.wait().expect("failed to wait for git-tree").success());
+    }
+    let pins = git_tree(&repo, &["pin"]);
+    assert_eq!(
+        pins.lines().collect::<Vec<_>>(),
+        names.map(|name| format!("always refs/heads/{name}"))
+    );
+}
+
+#[test]
+fn abandoned_locks_are_broken() {
+    let repo = Repo::new();
+    repo.commit("base");
+    let lock = repo.path().join(".git/gitxl/pins.lock");
+    fs::create_dir_all(lock.parent().unwrap()).unwrap();
+    let file = fs::File::create(&lock).unwrap();
+    let pin = || {
+        let output = repo
+            .command(env!("CARGO_BIN_EXE_git-tree"))
+            .args(["pin", "main"])
+            .output()
+            .expect("failed to run git-tree");
+        (output.status.success(), String::from_utf8_lossy(&output.stderr).into_owned())
+    };
+    let (success, stderr) = pin();
+    assert!(!success);
+    assert!(stderr.contains("is locked by another git-tree"), "{stderr}");
+    file.set_modified(SystemTime::now() - Duration::from_mins(1)).unwrap();
+    assert_eq!(pin(), (true, String::new()));
+    assert!(!lock.exists());
+    assert_eq!(git_tree(&repo, &["pin"]), "always refs/heads/main\n");
+}
+
+#[test]
+fn prompt_summarizes_the_current_branch() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("upstream", "main");
+    repo.commit("theirs");
+    repo.git(&["update-ref", "refs/remotes/origin/main", "upstream"]);
+    repo.git(&["symbolic-ref", "refs/remotes/origin/HEAD", "refs/remotes/origin/main"]);
+    repo.checkout("main");
+    repo.git(&["branch", "--delete", "--force", "upstream"]);
+    repo.commits("mine", 2);
+    repo.branch("merged", "main~2");
+    repo.checkout_new("feature", "main");
+    repo.commit("f1");
+    repo.checkout("main");
+    repo.git(&["config", "remote.origin.url", "."]);
+    repo.git(&["config", "remote.origin.fetch", "+refs/heads/*:refs/remotes/origin/*"]);
+    repo.git(&["branch", "--set-upstream-to=origin/main", "main"]);
+    assert_eq!(git_tree(&repo, &["prompt", "--budget=0"]), "main +2 -1, 1 unmerged\n");
+    // The second time, the summary comes from the cache.
+    assert!(repo.path().join(".git/gitxl/prompt").exists());
+    assert_eq!(git_tree(&repo, &["prompt"]), "main +2 -1, 1 unmerged\n");
+    repo.checkout("feature");
+    assert_eq!(git_tree(&repo, &["prompt", "--budget=0"]), "feature, 1 unmerged\n");
+}
+
+#[test]
+fn bench_reports_each_stage() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    repo.commit("f1");
+    let output = git_tree(&repo, &["bench", "--iterations=3"]);
+    let mut lines = output.lines();
+    assert_eq!(lines.next(), Some("3 iterations:"));
+    let stages: Vec<_> = lines.filter_map(|line| line.get(2..14)).map(str::trim_end).collect();
+    let expected = ["stage", "refs", "merge bases", "traversal", "load", "layout", "total"];
+    assert_eq!(stages, expected);
+    // Nothing is cached.
+    assert!(!repo.path().join(".git/gitxl/cache").exists());
+}
+
+#[test]
+fn branches_of_the_same_name_are_sorted_by_ref_name() {
+    let repo = Repo::new();
+    repo.commit("base");
+    let remote = repo.commit("remote");
+    repo.git(&["update-ref", "refs/remotes/origin/topic", &remote]);
+    repo.checkout_new("topic", "main~1");
+    repo.checkout_new("origin/topic", "main~1");
+    let local = repo.commit("local");
+    repo.checkout("main");
+    let porcelain = git_tree(&repo, &["--porcelain"]);
+    let branches: Vec<_> =
+        porcelain.lines().filter_map(|line| line.strip_prefix("branch ")).collect();
+    let base = repo.rev_parse("main~1");
+    assert_eq!(
+        branches,
+        [
+            format!("{remote} main"),
+            format!("{local} origin/topic"),
+            format!("{remote} origin/topic"),
+            format!("{base} topic"),
+        ]
+    );
+}
+
+#[test]
+fn remote_chooses_the_remotes() {
+    let repo = Repo::new();
+    repo.commit("base");
+    for (remote_branch, subject) in
+        [("origin/main", "ours"), ("upstream/main", "theirs"), ("upstream/release/1", "rel")]
+    {
+        repo.checkout_new("side", "main");
+        repo.commit(subject);
+        repo.git(&["update-ref", &format!("refs/remotes/{remote_branch}"), "side"]);
+        repo.checkout("main");
+        repo.git(&["branch", "--delete", "--force", "side"]);
+    }
+    repo.commit("mine");
+    repo.git(&["config", "remote.origin.url", "."]);
+    repo.git(&["config", "remote.origin.fetch", "+refs/heads/*:refs/remotes/origin/*"]);
+    repo.git(&["branch", "--set-upstream-to=origin/main", "main"]);
+    let subjects = |args: &[&str]| {
+        let mut all = vec!["--no-pager", "--format=%s"];
+        all.extend(args);
+        let mut logged: Vec<_> = git_tree(&repo, &all).lines().map(str::to_owned).collect();
+        logged.sort_unstable();
+        logged
+    };
+    assert_eq!(subjects(&[]), ["base", "mine", "ours", "theirs"]);
+    assert_eq!(subjects(&["--remote", "origin"]), ["base", "mine", "ours"]);
+    repo.git(&["config", "gitxl.remotes", "upstream/release/*"]);
+    assert_eq!(subjects(&[]), ["base", "mine", "rel"]);
+    assert_eq!(
+        subjects(&["--remote=upstream", "--remote=origin"]),
+        ["base", "mine", "ours", "theirs"]
+    );
+}
+
+#[test]
+fn warn_behind_lists_the_stale_branches() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.branch("feature", "main");
+    repo.commits("t", 3);
+    repo.git(&["update-ref", "refs/remotes/origin/main", "main"]);
+    repo.git(&["symbolic-ref", "refs/remotes/origin/HEAD", "refs/remotes/origin/main"]);
+    repo.checkout("feature");
+    repo.commit("f1");
+    let run = |args: &[&str]| {
+        let output = repo
+            .command(env!("CARGO_BIN_EXE_git-tree"))
+            .args(["--no-pager", "--format=%s"])
+            .args(args)
+            .output()
+            .expect("failed to run git-tree");
+        (output.status.success(), String::from_utf8_lossy(&output.stderr).into_owned())
+    };
+    let warning = "git-tree: warning: 1 branch is more than 2 commits behind origin/HEAD; \
+                   consider rebasing:\n  feature (3 behind)\n";
+    assert_eq!(run(&["--warn-behind=2"]), (false, warning.to_owned()));
+    assert_eq!(run(&["--warn-behind=3"]), (true, String::new()));
+    repo.git(&["config", "gitxl.warnBehind", "2"]);
+    assert_eq!(run(&[]), (true, warning.to_owned()));
+}
+
+#[test]
+fn dates_follow_log_date_and_the_date_option() {
+    let repo = Repo::new();
+    repo.commit("base");
+    let status = |args: &[&str]| {
+        let output = repo
+            .command(env!("CARGO_BIN_EXE_git-tree"))
+            .arg("status")
+            .args(args)
+            .output()
+            .expect("failed to run git-tree");
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    repo.git(&["config", "log.date", "short"]);
+    assert_eq!(status(&[]), "* main  -    2023-11-14  base\n");
+    assert_eq!(status(&["--date=iso-strict"]), "* main  -    2023-11-14T22:14:20Z  base\n");
+    // Formats git-tree does not know fall back to relative.
+    assert!(status(&["--date=human"]).ends_with(" ago  base\n"));
+    // The native renderer's placeholders follow them too.
+    let log = |args: &[&str]| {
+        git_tree(&repo, &[&["--no-pager", "--ascii", "--format=%ad"], args].concat())
+    };
+    assert!(log(&[]).contains("2023-11-14\n"));
+    assert!(log(&["--date=unix"]).contains("1700000060\n"));
+}
+
+#[test]
+fn tables_align_wide_characters() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("\u{6a5f}\u{80fd}", "main");
+    repo.commit("\u{1f389} party time");
+    repo.checkout("main");
+    repo.git(&["config", "core.quotePath", "false"]);
+    let status = |columns: &str| {
+        let output = repo
+            .command(env!("CARGO_BIN_EXE_git-tree"))
+            .arg("status")
+            .env("COLUMNS", columns)
+            .env("GIT_TEST_DATE_NOW", "1700259200")
+            .output()
+            .expect("failed to run git-tree");
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    // The branch name is four columns wide, like main.
+    assert_eq!(
+        status("80"),
+        "* main  -    3 days ago  base\n\
+         \x20 \u{6a5f}\u{80fd}  -    3 days ago  \u{1f389} party time\n"
+    );
+    // The emoji is two columns wide, so it is kept whole or not at all.
+    assert_eq!(
+        status("28").lines().nth(1),
+        Some("  \u{6a5f}\u{80fd}  -    3 days ago  \u{1f389}\u{2026}")
+    );
+    assert_eq!(status("27").lines().nth(1), Some("  \u{6a5f}\u{80fd}  -    3 days ago  \u{2026}"));
+}
+
+#[test]
+fn native_renderer_fits_the_terminal() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    repo.commit("a rather long subject");
+    repo.checkout("main");
+    repo.commit("short");
+    let output = repo
+        .command(env!("CARGO_BIN_EXE_git-tree"))
+        .args([
+            "--render=native",
+            "--ascii",
+            "--color=always",
+            "--no-pager",
+            "--format=%C(red)%s%C(reset)",
+        ])
+        .env("COLUMNS", "12")
+        .output()
+        .expect("failed to run git-tree");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // The escape sequences are kept, so the color still ends with the text.
+    let plain: String = stdout
+        .split('\x1b')
+        .enumerate()
+        .map(
+            |(i, part)| {
+                if i == 0 {
+                    part
+                } else {
+                    part.split_once('m').map_or("", |(_, rest)| rest)
+                }
+            },
+        )
+        .collect();
+    assert_eq!(plain, "* short\n| * a rathe\u{2026}\n+-'\n* base\n");
+    assert!(stdout.contains("* \x1b[31ma rathe\u{2026}\x1b[m\n"), "{stdout}");
+}
+
+#[cfg(unix)]
+#[test]
+fn branches_whose_names_are_not_utf8_are_ignored() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt as _;
+
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    repo.commit("f1");
+    let status = repo
+        .command("git")
+        .arg("update-ref")
+        .arg(OsStr::from_bytes(b"refs/heads/caf\xe9"))
+        .arg("main")
+        .status()
+        .expect("failed to run git");
+    assert!(status.success());
+    let output = repo
+        .command(env!("CARGO_BIN_EXE_git-tree"))
+        .arg("--porcelain")
+        .output()
+        .expect("failed to run git-tree");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "git-tree: warning: ignoring \"refs/heads/caf\\351\", whose name is not UTF-8\n"
+    );
+    assert_eq!(displayed(&repo), ["base", "f1"]);
+}
+
+#[test]
+fn git_failures_are_reported_as_errors() {
+    const FAILURE: i32 = 1;
+    let repo = Repo::new();
+    repo.commit("base");
+    for format in ["--render=native", "--format=json-edges"] {
+        let output = repo
+            .command(env!("CARGO_BIN_EXE_git-tree"))
+            .args(["--no-pager", "--find=\\(", format])
+            .output()
+            .expect("failed to run git-tree");
+        assert_eq!(output.status.code(), Some(FAILURE), "{format}");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.starts_with("fatal: "), "{stderr}");
+        assert!(
+            stderr.ends_with("\ngit-tree: git rev-list failed: exit status: 128\n"),
+            "{stderr}"
+        );
+    }
+}
+
+#[test]
+fn messages_follow_the_locale() {
+    let repo = Repo::new();
+    let stderr = |vars: &[(&str, &str)]| {
+        let output = repo
+            .command(env!("CARGO_BIN_EXE_git-tree"))
+            .arg("--no-pager")
+            .envs(vars.iter().copied())
+            .output()
+            .expect("failed to run git-tree");
+        assert!(output.status.success());
+        String::from_utf8(output.stderr).unwrap()
+    };
+    assert_eq!(
+        stderr(&[("LANG", "de_DE.UTF-8")]),
+        "git-tree: das Repository hat noch keine Commits\n"
+    );
+    assert_eq!(
+        stderr(&[("LANG", "de_DE.UTF-8"), ("LC_ALL", "C")]),
+        "git-tree: repository has no commits yet\n"
+    );
+    assert_eq!(stderr(&[("LC_MESSAGES", "fr_FR")]), "git-tree: repository has no commits yet\n");
+}
+
+#[test]
+fn translations_keep_the_placeholders() {
+    // The `{$name}` placeholders in `message`, sorted.
+    fn placeholders(message: &str) -> Vec<&str> {
+        let mut names: Vec<_> = message
+            .split("{$")
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+    let (mut msgid, mut msgstr, mut in_msgstr) = (String::new(), String::new(), false);
+    let mut entries = vec![];
+    for line in include_str!("../locales/de.po").lines().chain(["msgid \"\""]) {
+        if let Some(string) = line.strip_prefix("msgid ") {
+            entries.push((msgid.clone(), msgstr.clone()));
+            (msgid, msgstr, in_msgstr) =
+                (string.trim_matches('"').to_owned(), String::new(), false);
+        } else if let Some(string) = line.strip_prefix("msgstr ") {
+            (msgstr, in_msgstr) = (string.trim_matches('"').to_owned(), true);
+        } else if line.starts_with('"') {
+            let string = line.trim_matches('"');
+            if in_msgstr {
+                msgstr.push_str(string);
+            } else {
+                msgid.push_str(string);
+            }
+        }
+    }
+    entries.retain(|(id, _)| !id.is_empty());
+    assert!(!entries.is_empty());
+    for (id, translation) in &entries {
+        assert!(!translation.is_empty(), "{id} is not translated");
+        assert_eq!(placeholders(id), placeholders(translation), "{id}");
+    }
+}
+
+#[test]
+fn help_shows_the_commands_and_topics() {
+    /// git-tree's status for invalid arguments.
+    const USAGE: i32 = 2;
+    let repo = Repo::new();
+    let overview = git_tree(&repo, &["help", "--no-pager"]);
+    assert!(overview.starts_with("git-tree: "), "{overview}");
+    for name in ["status", "restack", "heuristics", "formats", "config"] {
+        assert!(overview.contains(&format!("\n  {name} ")), "{name}");
+    }
+    assert_eq!(git_tree(&repo, &["--help", "--no-pager"]), overview);
+    assert!(
+        git_tree(&repo, &["help", "--no-pager", "matrix"]).starts_with("usage: git-tree matrix")
+    );
+    assert!(git_tree(&repo, &["help", "--no-pager", "config"]).contains("\ngitxl.refHook\n"));
+    let man = git_tree(&repo, &["--man"]);
+    assert!(man.starts_with(".TH GIT\\-TREE 1 "), "{man}");
+    for section in ["NAME", "SYNOPSIS", "DESCRIPTION", "OPTIONS", "COMMANDS", "CONFIGURATION"] {
+        assert!(man.contains(&format!("\n.SH {section}\n")), "{section}");
+    }
+    let output = repo
+        .command(env!("CARGO_BIN_EXE_git-tree"))
+        .args(["help", "nonsense"])
+        .output()
+        .expect("failed to run git-tree");
+    assert_eq!(output.status.code(), Some(USAGE));
+    assert_eq!(output.stderr, b"git-tree: unknown help topic nonsense\n");
+}
+
+#[test]
+fn stdin_refs_replace_the_interesting_branches() {
+    use std::io::Write as _;
+    use std::process::Stdio;
+
+    /// git-tree's status for invalid arguments.
+    const USAGE: i32 = 2;
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("a", "main");
+    repo.commit("a1");
+    repo.checkout_new("b", "main");
+    let b1 = repo.commit("b1");
+    repo.checkout("main");
+    repo.commit("m1");
+    repo.checkout_new("c", "main");
+    repo.commit("c1");
+    let run = |input: &[u8]| {
+        let mut child = repo
+            .command(env!("CARGO_BIN_EXE_git-tree"))
+            .args(["--stdin-refs", "--porcelain"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("failed to run git-tree");
+        child.stdin.take().unwrap().write_all(input).unwrap();
+        child.wait_with_output().unwrap()
+    };
+    // HEAD (c) is not one of them, so it is not shown.
+    let output = run(format!("a\0{b1}\0").as_bytes());
+    assert!(output.status.success());
+    let porcelain = String::from_utf8(output.stdout).unwrap();
+    assert!(porcelain.contains(&format!("\nbranch {b1} {b1}\n")), "{porcelain}");
+    assert!(!porcelain.contains("\nhead "), "{porcelain}");
+    let mut subjects: Vec<_> = porcelain
+        .lines()
+        .filter_map(|line| line.strip_prefix("commit "))
+        .filter_map(|rest| rest.split(' ').next())
+        .map(|id| repo.subject(id))
+        .collect();
+    subjects.sort_unstable();
+    assert_eq!(subjects, ["a1", "b1", "base"]);
+    let unknown = run(b"a\nnonsense\n");
+    assert_eq!(unknown.status.code(), Some(USAGE));
+    assert_eq!(unknown.stderr, b"git-tree: nonsense is not a commit\n");
+    assert_eq!(run(b"\n").status.code(), Some(USAGE));
+}
+
+#[test]
+fn revisions_replace_the_interesting_branches() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("a", "main");
+    repo.commit("a1");
+    repo.checkout_new("b", "main");
+    let b1 = repo.commit("b1");
+    repo.checkout("main");
+    repo.commit("m1");
+    let args = ["--no-pager", "--render=native", "--ascii", "--no-color", "--format=%s"];
+    // HEAD (main) is not one of them, so it is not shown.
+    let two = git_tree(&repo, &[&args[..], &["a", &b1]].concat());
+    assert_eq!(two, "* b1\n| * a1\n+-'\n* base\n");
+    // A bare word after an option's separate value is still a revision.
+    let grep = git_tree(&repo, &["--no-pager", "--format=%s", "--grep", "1", "b"]);
+    assert_eq!(grep, "b1\n");
+}
+
+#[test]
+fn revisions_named_like_commands_follow_an_option() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("status", "main");
+    repo.commit_file("file", "s1", "s1");
+    repo.checkout("main");
+    repo.commit("m1");
+    // Only the first argument can be a command.
+    let status = git_tree(&repo, &["status"]);
+    assert!(status.starts_with("* main "), "{status}");
+    let format = ["--no-pager", "--format=%s"];
+    assert_eq!(git_tree(&repo, &[&format[..], &["status"]].concat()), "s1\n");
+    // After --end-of-options, everything up to -- is a revision.
+    let args = [&format[..], &["--end-of-options", "status", "--", "file"]].concat();
+    assert_eq!(git_tree(&repo, &args), "s1\n");
+}
+
+#[test]
+fn merge_filters_keep_the_graph_connected() {
+    let repo = Repo::new();
+    let base = repo.commit("base");
+    repo.checkout_new("topic", "main");
+    repo.commit("t1");
+    repo.checkout("main");
+    repo.commit("m1");
+    repo.merge("merge", &["topic"]);
+    repo.commit("m2");
+    repo.git(&["branch", "-D", "topic"]);
+    repo.checkout_new("feature", &base);
+    repo.commit("f1");
+    let args = ["--render=native", "--ascii", "--no-color", "--no-pager", "--format=%s"];
+    let no_merges = git_tree(&repo, &[&args[..], &["--no-merges"]].concat());
+    // m2 now joins m1 and t1 directly.
+    assert_eq!(no_merges, "* f1\n| * m2\n| +-.\n| | * t1\n+-+-'\n| * m1\n+-'\n* base\n");
+    let merges_only = git_tree(&repo, &[&args[..], &["--merges-only"]].concat());
+    // The tips and the merge base stay, and both of merge's parents become base.
+    assert_eq!(merges_only, "* f1\n| * m2\n| * merge\n+-'\n* base\n");
+}
+
+#[test]
+fn decorations_only_shows_the_skeleton() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    repo.commits("f", 3);
+    repo.checkout("main");
+    repo.commits("m", 3);
+    repo.tag("v1", "main");
+    repo.commits("n", 4);
+    let args = ["--render=native", "--ascii", "--no-color", "--no-pager", "--format=%s"];
+    let skeleton = git_tree(&repo, &[&args[..], &["--decorations-only"]].concat());
+    // The tips, the tag v1 (m3), and the merge base, with what is between them
+    // counted.
+    let expected = "* n4\n: ... 3 commits ...\n* m3\n: ... 2 commits ...\n\
+                    | * f3\n| : ... 2 commits ...\n+-'\n* base\n";
+    assert_eq!(skeleton, expected);
+}
+
+#[test]
+fn fork_points_are_marked() {
+    let repo = Repo::new();
+    let base = repo.commit("base");
+    repo.commit("m1");
+    repo.checkout_new("feature", &base);
+    repo.commit("f1");
+    repo.git(&["branch", "--set-upstream-to=main"]);
+    repo.checkout_new("empty", &base);
+    let args = ["--render=native", "--ascii", "--no-color", "--no-pager", "--format=%s"];
+    let output = repo
+        .command(env!("CARGO_BIN_EXE_git-tree"))
+        .args(args)
+        .arg("--fork-points")
+        // 12 days after the commits.
+        .env("GIT_TEST_DATE_NOW", "1701036800")
+        .output()
+        .expect("failed to run git-tree");
+    assert!(output.status.success());
+    let graph = String::from_utf8(output.stdout).unwrap();
+    // empty has no commits of its own, and main neither an upstream nor a trunk.
+    assert_eq!(graph, "* f1\n| * m1\n+-'\n* base [feature forked from main 12 days ago]\n");
+    let json = git_tree(&repo, &["--format=json-edges", "--fork-points"]);
+    assert!(json.contains(r#""forkPoints":[{"branch":"feature","from":"main"}]"#), "{json}");
+}
+
+#[test]
+fn color_by_age_buckets_the_commits() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.git(&["config", "gitxl.ageColor.older", "blue"]);
+    let run = |args: &[&str], now: &str| {
+        let output = repo
+            .command(env!("CARGO_BIN_EXE_git-tree"))
+            .args(["--no-pager", "--color-by-age"])
+            .args(args)
+            .env("GIT_TEST_DATE_NOW", now)
+            .output()
+            .expect("failed to run git-tree");
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    // An hour, and 40 days, after the commit.
+    let (today, older) = ("1700003600", "1703456000");
+    assert!(run(&["--format=json-edges"], today).contains(r#""age":"today""#));
+    assert!(run(&["--format=json-edges"], older).contains(r#""age":"older""#));
+    let native = ["--render=native", "--ascii", "--color=always", "--format=%s"];
+    assert_eq!(run(&native, today), "* \x1b[1;32mbase\x1b[m\n");
+    assert_eq!(run(&native, older), "* \x1b[34mbase\x1b[m\n");
+}
+
+#[test]
+fn icons_mark_the_kinds_of_commits() {
+    let repo = Repo::new();
+    let base = repo.commit("base");
+    repo.tag("v1", "main");
+    repo.checkout_new("topic", "main");
+    repo.commit("t1");
+    repo.checkout("main");
+    repo.merge("merge", &["topic"]);
+    repo.git(&["branch", "-D", "topic"]);
+    repo.checkout_new("feature", &base);
+    repo.commit("f1");
+    let run = |args: &[&str], locale: &str| {
+        let output = repo
+            .command(env!("CARGO_BIN_EXE_git-tree"))
+            .args(["--no-pager", "--render=native", "--no-color", "--format=%s"])
+            .args(args)
+            .env("LC_ALL", locale)
+            .output()
+            .expect("failed to run git-tree");
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let ascii = "* @> f1\n| * >& merge\n+-+-.\n|   * t1\n+---'\n* # base\n";
+    assert_eq!(run(&["--icons", "--ascii"], "C.UTF-8"), ascii);
+    assert_eq!(run(&["--icons", "--ascii"], "C"), ascii);
+    assert_eq!(run(&["--no-icons", "--ascii"], "C.UTF-8"), ascii);
+    let glyphs = run(&["--icons"], "C.UTF-8");
+    assert!(glyphs.contains(" \u{f061}\u{e725} f1\n"), "{glyphs}");
+    assert!(run(&["--icons"], "C").contains(" @> f1\n"));
+}
+
+#[test]
+fn find_highlights_or_keeps_the_matches() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    repo.commit("fix the parser");
+    repo.commit("tidy up");
+    repo.commit("feature tip");
+    repo.checkout("main");
+    repo.commit("fix the lexer");
+    repo.commit("main tip");
+    let args = ["--render=native", "--ascii", "--no-pager", "--format=%s", "--find", "^fix"];
+    let highlighted = git_tree(&repo, &[&args[..], &["--no-color"]].concat());
+    assert_eq!(
+        highlighted,
+        "* main tip\n* fix the lexer [found]\n| * feature tip\n| * tidy up\n\
+         | * fix the parser [found]\n+-'\n* base\n"
+    );
+    let colored = git_tree(&repo, &[&args[..], &["--color=always"]].concat());
+    assert!(colored.contains("\x1b[7mfix the lexer\x1b[m\n"), "{colored}");
+    // The tips and the merge base stay, joined through the hidden commits.
+    let only = git_tree(&repo, &[&args[..], &["--no-color", "--find-only"]].concat());
+    assert_eq!(
+        only,
+        "* main tip\n* fix the lexer [found]\n| * feature tip\n| * fix the parser [found]\n\
+         +-'\n* base\n"
+    );
+}
+
+#[test]
+fn bisect_marks_are_overlaid() {
+    let repo = Repo::new();
+    let base = repo.commit("base");
+    repo.commits("c", 5);
+    repo.git(&["bisect", "start"]);
+    repo.git(&["bisect", "bad", "main"]);
+    repo.git(&["bisect", "good", &base]);
+    // Skips c2, leaving c3 checked out.
+    repo.git(&["bisect", "skip"]);
+    let args = ["--render=native", "--ascii", "--no-color", "--no-pager", "--format=%s"];
+    // The good commit is below HEAD, the merge base, unless --bisect adds it.
+    assert_eq!(
+        git_tree(&repo, &args),
+        "* c5 [bisect bad]\n* c4 [bisect candidate]\n* c3 [bisect candidate]\n"
+    );
+    assert_eq!(
+        git_tree(&repo, &[&args[..], &["--bisect"]].concat()),
+        "* c5 [bisect bad]\n* c4 [bisect candidate]\n* c3 [bisect candidate]\n\
+         * c2 [bisect skip]\n* c1 [bisect candidate]\n* base [bisect good]\n"
+    );
+    let json = git_tree(&repo, &["--format=json-edges", "--bisect"]);
+    assert!(json.contains(r#""subject":"base","collapsed":0,"bisect":"good""#), "{json}");
+    repo.git(&["bisect", "reset"]);
+    assert_eq!(git_tree(&repo, &args), "* c5\n");
+}
+
+#[test]
+fn rebase_todo_is_overlaid() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    repo.commits("f", 3);
+    repo.checkout("main");
+    repo.commit("m1");
+    repo.checkout("feature");
+    let editor = "sequence.editor=sed -i -e '2s/^pick/edit/' -e '3s/^pick/squash/'";
+    // Stops after applying f2.
+    repo.git(&["-c", editor, "rebase", "--interactive", "--keep-empty", "main"]);
+    let args = ["--render=native", "--ascii", "--no-color", "--no-pager", "--format=%s"];
+    assert_eq!(
+        git_tree(&repo, &args),
+        "* f2\n* f1\n* m1\n| * f3 [rebase: squash]\n| * f2 [rebase: edit, current]\n\
+         | * f1 [rebase: pick, applied]\n+-'\n* base\n"
+    );
+    let json = git_tree(&repo, &["--format=json-edges"]);
+    assert!(json.contains(r#""rebase":{"action":"squash","state":"pending"}"#), "{json}");
+    repo.git(&["rebase", "--abort"]);
+    assert!(!git_tree(&repo, &args).contains("rebase:"));
+}
+
+#[test]
+fn preview_merge_shows_the_merge_and_its_conflicts() {
+    let repo = Repo::new();
+    repo.commit_file("a.txt", "1\n", "base");
+    repo.checkout_new("clash", "main");
+    repo.commit_file("a.txt", "2\n", "clash1");
+    repo.checkout_new("clean", "main");
+    repo.commit_file("b.txt", "x\n", "clean1");
+    repo.checkout("main");
+    repo.commit_file("a.txt", "3\n", "m1");
+    repo.git(&["update-ref", "refs/remotes/origin/main", "main"]);
+    repo.git(&["symbolic-ref", "refs/remotes/origin/HEAD", "refs/remotes/origin/main"]);
+    let args = ["--render=native", "--ascii", "--no-color", "--no-pager", "--format=%s"];
+    let merges = ["--preview-merge", "clash", "--preview-merge", "clean"];
+    assert_eq!(
+        git_tree(&repo, &[&args[..], &merges].concat()),
+        "* Merge clash, clean into origin/HEAD [clash would conflict in a.txt]\n+-.-.\n\
+         * | | m1\n| | * clean1\n+-+-'\n| * clash1\n+-'\n* base\n"
+    );
+    let json = git_tree(&repo, &["--format=json-edges", "--preview-merge", "clean"]);
+    assert!(
+        json.contains(r#""subject":"Merge clean into origin/HEAD","collapsed":0,"conflicts":[]"#)
+    );
+    // Nothing was merged for real.
+    assert_eq!(repo.git(&["status", "--porcelain"]), "");
+    assert_eq!(repo.rev_parse("main"), repo.rev_parse("origin/main"));
+}
+
+#[test]
+fn diffstat_sizes_each_branch() {
+    let repo = Repo::new();
+    repo.commit_file("a.txt", "1\n2\n", "base");
+    repo.checkout_new("feature", "main");
+    repo.commit_file("a.txt", "1\n3\n4\n", "f1");
+    repo.commit_file("b.txt", "x\n", "f2");
+    repo.git(&["branch", "--set-upstream-to=main"]);
+    let json = git_tree(&repo, &["--format=json-edges", "--diffstat"]);
+    let stat = r#""graph":{"diffstats":[{"branch":"feature","from":"main","filesChanged":2,"insertions":3,"deletions":1}]}"#;
+    assert!(json.contains(stat), "{json}");
+}
+
+#[test]
+fn mailmap_canonicalizes_the_authors() {
+    let repo = Repo::new();
+    repo.commit_file(".mailmap", "Alice <alice@example.com> alice <old@example.com>\n", "base");
+    repo.checkout_new("feature", "main");
+    repo.git(&[
+        "commit",
+        "--quiet",
+        "--allow-empty",
+        "--author",
+        "alice <old@example.com>",
+        "-m",
+        "f1",
+    ]);
+    let git_tree_bin = env!("CARGO_BIN_EXE_git-tree");
+    assert_eq!(repo.run(git_tree_bin, &["authors"]), "Alice: feature (1)\n");
+    assert_eq!(repo.run(git_tree_bin, &["authors", "--no-mailmap"]), "alice: feature (1)\n");
+    let json = git_tree(&repo, &["--format=json-edges"]);
+    assert!(json.contains(r#""author":"Alice""#), "{json}");
+    let unmapped = git_tree(&repo, &["--format=json-edges", "--no-mailmap"]);
+    assert!(unmapped.contains(r#""author":"alice""#), "{unmapped}");
+    // git log's own formats map the authors too, unless told not to.
+    let log = git_tree(&repo, &["--no-pager", "-1", "feature"]);
+    assert!(log.contains("Author: Alice <alice@example.com>"), "{log}");
+    let unmapped_log = git_tree(&repo, &["--no-pager", "-1", "--no-mailmap", "feature"]);
+    assert!(unmapped_log.contains("Author: alice <old@example.com>"), "{unmapped_log}");
+}
+
+#[test]
+fn trailers_are_listed_and_shown() {
+    let repo = Repo::new();
+    repo.commit("base");
+    let trailers = "Bug: 12\nReviewed-by: Alice <alice@example.com>\nbug: 13";
+    repo.git(&["commit", "--quiet", "--allow-empty", "-m", "fix", "-m", trailers]);
+    let id = repo.rev_parse("HEAD");
+    let args = ["--render=native", "--ascii", "--no-color", "--no-pager", "--format=%s"];
+    assert_eq!(
+        git_tree(&repo, &[&args[..], &["--trailer", "bug", "--trailer", "Change-Id"]].concat()),
+        "* fix [Bug: 12, 13]\n"
+    );
+    assert!(!git_tree(&repo, &["--format=json-edges"]).contains("trailers"));
+    let json = git_tree(&repo, &["--format=json-edges", "--trailer=bug", "--trailer=reviewed-by"]);
+    let listed = r#""trailers":{"Bug":["12","13"],"Reviewed-by":["Alice <alice@example.com>"]}"#;
+    assert!(json.contains(listed), "{json}");
+    assert!(!git_tree(&repo, &["--porcelain"]).contains("trailer"));
+    assert!(git_tree(&repo, &["--porcelain", "--trailer=Bug"]).ends_with(&format!(
+        "commit {id} head,tip,base\ntrailer {id} Bug 12\ntrailer {id} bug 13\n"
+    )));
+    // Continuation lines are unfolded, so that each trailer is one record.
+    let folded = "See-also: the first line\n  and the second";
+    repo.git(&["commit", "--quiet", "--allow-empty", "-m", "more", "-m", folded]);
+    let more = repo.rev_parse("HEAD");
+    assert!(git_tree(&repo, &["--porcelain", "--trailer=see-also"])
+        .contains(&format!("trailer {more} See-also the first line and the second\n")));
+}
+
+#[test]
+fn issues_are_linked_and_grouped() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.git(&["config", "gitxl.issuePattern", "b/[0-9]+|#[0-9]+"]);
+    repo.git(&["config", "gitxl.issueUrl", "https://issues.example.com/{number}"]);
+    repo.checkout_new("feature", "main");
+    repo.commit("Work on #7 and b/12");
+    repo.checkout_new("topic", "main");
+    repo.commit("Refs #7");
+    repo.checkout("main");
+    repo.commit("Fix b/12 crash");
+    assert_eq!(
+        git_tree(&repo, &["--group-by-issue", "--no-color"]),
+        "b/12: feature, main\n#7: feature, topic\n"
+    );
+    let native = git_tree(&repo, &["--render=native", "--color=always", "--format=%s"]);
+    let link = "\x1b]8;;https://issues.example.com/12\x1b\\b/12\x1b]8;;\x1b\\";
+    assert!(native.contains(&format!("Fix {link} crash")), "{native:?}");
+    let html = git_tree(&repo, &["--format=html"]);
+    let anchor = r#"<a href="https://issues.example.com/7" target="_blank">"#;
+    assert!(
+        html.contains(&format!(r#"Refs {anchor}<tspan class="issue">#7</tspan></a>"#)),
+        "{html}"
+    );
+}
+
+#[test]
+fn notes_are_shown() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.commit("fix");
+    repo.git(&["notes", "--ref=review", "add", "-m", "LGTM\n\nShip it", "HEAD"]);
+    repo.git(&["notes", "add", "-m", "default", "HEAD~"]);
+    repo.branch("topic", "HEAD~");
+    let args = ["--render=native", "--ascii", "--no-color", "--no-pager", "--format=%s"];
+    assert_eq!(
+        git_tree(&repo, &[&args[..], &["--notes=review"]].concat()),
+        "* fix\n| Notes:\n|     LGTM\n|\n|     Ship it\n* base\n"
+    );
+    let json = git_tree(&repo, &["--format=json-edges", "--notes=review", "--notes"]);
+    assert!(json.contains(r#""subject":"fix""#) && json.contains(r#""notes":"LGTM\n\nShip it""#));
+    assert!(json.contains(r#""notes":"default""#), "{json}");
+    let log = git_tree(&repo, &["--no-pager", "--notes=review", "--format=%s%n%N"]);
+    assert!(log.contains("LGTM"), "{log}");
+    assert!(!git_tree(&repo, &["--format=json-edges"]).contains("notes"));
+}
+
+#[test]
+fn extra_refs_add_the_latest_refs() {
+    let repo = Repo::new();
+    repo.commit("base");
+    let patchset = repo.commit("patchset");
+    let cert = repo.commit("cert");
+    repo.git(&["reset", "--quiet", "--hard", "HEAD~2"]);
+    repo.commit("main");
+    // As fetched by --gerrit-fetch.
+    repo.git(&["update-ref", "refs/changes/45/12345/1", &patchset]);
+    repo.git(&["update-ref", "refs/certs/push", &cert]);
+    assert_eq!(displayed(&repo), ["main"]);
+    let porcelain = git_tree(&repo, &["--porcelain", "--extra-refs"]);
+    assert!(porcelain.contains(&format!("branch {patchset} changes/45/12345/1")), "{porcelain}");
+    assert!(!porcelain.contains(&cert), "{porcelain}");
+    repo.git(&["config", "--add", "gitxl.extraRefs", "refs/changes"]);
+    repo.git(&["config", "--add", "gitxl.extraRefs", "refs/certs/*"]);
+    let latest = git_tree(&repo, &["--porcelain", "--extra-refs=1"]);
+    assert!(latest.contains(&format!("branch {cert} certs/push")), "{latest}");
+    assert!(!latest.contains("changes/"), "{latest}");
+}
+
+#[cfg(unix)]
+#[test]
+fn github_pull_requests_belong_to_their_branches() {
+    use std::env;
+    use std::os::unix::fs::PermissionsExt as _;
+
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("fix", "main");
+    let fix = repo.commit("fix");
+    repo.checkout_new("feature", "main");
+    let below = repo.commit("f1");
+    let feature = repo.commit("f2");
+    let native =
+        ["--github", "--render=native", "--ascii", "--no-color", "--no-pager", "--format=%s"];
+    // Outside a GitHub repository, gh fails, so the fetched pull request heads
+    // are used. One whose head is not a tip belongs to no branch.
+    repo.git(&["update-ref", "refs/pull/7/head", &feature]);
+    repo.git(&["update-ref", "refs/remotes/origin/pull/8/head", &fix]);
+    repo.git(&["update-ref", "refs/pull/9/head", &below]);
+    assert_eq!(
+        git_tree(&repo, &native),
+        "* f2 [PR #7]\n\
+         * f1\n\
+         | * fix [PR #8]\n\
+         +-'\n\
+         * base\n"
+    );
+    // gh knows the head branches' names too, but a fork's branch of the same
+    // name as a local one is someone else's.
+    let pulls = format!(
+        r#"[{{"number":10,"headRefOid":"{fix}","headRefName":"fix","isCrossRepository":false,"isDraft":true,"reviewDecision":""}},
+{{"number":11,"headRefOid":"{below}","headRefName":"feature","isCrossRepository":false,"isDraft":false,"reviewDecision":"APPROVED"}},
+{{"number":12,"headRefOid":"{below}","headRefName":"fix","isCrossRepository":true,"isDraft":false,"reviewDecision":"CHANGES_REQUESTED"}}]"#
+    );
+    let bin = repo.path().join(".git/bin");
+    fs::create_dir_all(&bin).unwrap();
+    fs::write(bin.join("gh"), format!("#!/bin/sh\ncat <<'EOF'\n{pulls}\nEOF\n")).unwrap();
+    fs::set_permissions(bin.join("gh"), fs::Permissions::from_mode(0o755)).unwrap();
+    let path = env::var("PATH").unwrap_or_default();
+    let output = repo
+        .command(env!("CARGO_BIN_EXE_git-tree"))
+        .args(native)
+        .env("PATH", format!("{}:{path}", bin.display()))
+        .output()
+        .expect("failed to run git-tree");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "* f2 [PR #11 (open, approved)]\n\
+         * f1\n\
+         | * fix [PR #10 (draft)]\n\
+         +-'\n\
+         * base\n"
+    );
+}
+
+#[test]
+fn gerrit_groups_the_patchsets_of_a_change() {
+    let change_id = "I0123456789abcdef0123456789abcdef01234567";
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("topic", "main");
+    let first = repo.commit(&format!("fix\n\nChange-Id: {change_id}"));
+    repo.checkout_new("amended", "main");
+    let second = repo.commit(&format!("fix again\n\nChange-Id: {change_id}"));
+    repo.commit("no change");
+    // As fetched by --gerrit-fetch.
+    repo.git(&["update-ref", "refs/changes/45/12345/1", &first]);
+    repo.git(&["update-ref", "refs/changes/45/12345/2", &second]);
+    let native = git_tree(
+        &repo,
+        &["--gerrit", "--render=native", "--ascii", "--no-color", "--no-pager", "--format=%s"],
+    );
+    let short = |id: &str| id.get(..7).unwrap().to_owned();
+    assert_eq!(
+        native,
+        format!(
+            "* no change\n\
+             * fix again [I0123456 patchset 2 of 2, also {} (patchset 1)]\n\
+             | * fix [I0123456 patchset 1 of 2, also {} (patchset 2)]\n\
+             +-'\n\
+             * base\n",
+            short(&first),
+            short(&second)
+        )
+    );
+    let json = git_tree(&repo, &["--gerrit", "--format=json-edges"]);
+    let changes = format!(
+        r#""graph":{{"changes":[{{"changeId":"{change_id}","number":12345,"latestPatchset":2,"commits":[{{"id":"{second}","patchset":2}},{{"id":"{first}","patchset":1}}]}}]}}"#
+    );
+    assert!(json.contains(&changes), "{json}");
+    let fix = format!(r#""subject":"fix","collapsed":0,"changeId":"{change_id}""#);
+    assert!(json.contains(&fix), "{json}");
+    assert!(json.contains(r#""subject":"no change","collapsed":0,"changeId":null"#), "{json}");
+}
+
+/// Sends the `requests` to `git-tree serve` in `repo` over stdin, and returns
+/// the response lines it writes to stdout.
+fn serve(repo: &Repo, requests: &[&str]) -> Vec<String> {
+    use std::io::Write as _;
+    use std::process::Stdio;
+
+    let mut child = repo
+        .command(env!("CARGO_BIN_EXE_git-tree"))
+        .arg("serve")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to run git-tree");
+    let mut stdin = child.stdin.take().unwrap();
+    for request in requests {
+        writeln!(stdin, "{request}").unwrap();
+    }
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "git-tree serve failed");
+    String::from_utf8(output.stdout).unwrap().lines().map(str::to_owned).collect()
+}
+
+#[test]
+fn serve_answers_requests_over_stdio() {
+    let repo = Repo::new();
+    let base = repo.commit("base");
+    repo.branch("feature", "main");
+    let tip = repo.commits("m", 3);
+    repo.checkout("feature");
+    let feature = repo.commit("f1");
+    let responses = serve(
+        &repo,
+        &[
+            r#"{"jsonrpc":"2.0","id":1,"method":"listBranches"}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"getGraph"}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"getGraph","params":{"collapse":1}}"#,
+            r#"{"jsonrpc":"2.0","id":4,"method":"explainCommit","params":{"commit":"main~3"}}"#,
+            r#"{"jsonrpc":"2.0","id":5,"method":"explainCommit","params":{"commit":"main"}}"#,
+            // A notification gets no response.
+            r#"{"jsonrpc":"2.0","method":"listBranches"}"#,
+            "",
+        ],
+    );
+    let branches = format!(
+        r#""branches":[{{"name":"feature","id":"{feature}","time":1700000660}},{{"name":"main","id":"{tip}","time":1700000480}}]"#
+    );
+    let [list, graph, collapsed, explain_base, explain_tip] =
+        <[String; 5]>::try_from(responses).unwrap();
+    assert_eq!(
+        list,
+        format!(r#"{{"jsonrpc":"2.0","id":1,"result":{{"head":"{feature}",{branches}}}}}"#)
+    );
+    let start = format!(
+        r#"{{"jsonrpc":"2.0","id":2,"result":{{"head":"{feature}",{branches},"mergeBases":["{base}"],"commits":[{{"id":"{feature}","#
+    );
+    assert!(graph.starts_with(&start), "{graph}");
+    for subject in ["m3", "m2", "m1", "base"] {
+        assert!(graph.contains(&format!(r#""subject":"{subject}""#)), "{graph}");
+    }
+    // m1 and m2 are a run of two linear commits.
+    assert!(collapsed.contains(r#""collapsed":2"#), "{collapsed}");
+    assert!(!collapsed.contains(r#""subject":"m1""#), "{collapsed}");
+    assert!(explain_base.contains(r#""displayed":true,"roles":["mergeBase"]"#), "{explain_base}");
+    assert!(explain_tip.contains(r#""roles":["tip"]"#), "{explain_tip}");
+}
+
+#[test]
+fn serve_reports_malformed_requests() {
+    let repo = Repo::new();
+    repo.commit("base");
+    // Too deeply nested to parse without overflowing the stack.
+    let deep = "[".repeat(200_000);
+    let responses = serve(
+        &repo,
+        &[
+            "nonsense",
+            &deep,
+            r#"{"jsonrpc":"2.0","id":1}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"frob"}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"explainCommit"}"#,
+            r#"{"jsonrpc":"2.0","id":4,"method":"explainCommit","params":{"commit":"nope"}}"#,
+            r#"{"jsonrpc":"2.0","id":5,"method":"getGraph","params":{"collapse":"x"}}"#,
+            // Notifications of unknown methods get no response either.
+            r#"{"jsonrpc":"2.0","method":"frob"}"#,
+        ],
+    );
+    assert_eq!(
+        responses,
+        [
+            r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32700,"message":"parse error"}}"#,
+            r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32700,"message":"parse error"}}"#,
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32600,"message":"invalid request"}}"#,
+            r#"{"jsonrpc":"2.0","id":2,"error":{"code":-32601,"message":"method not found: frob"}}"#,
+            r#"{"jsonrpc":"2.0","id":3,"error":{"code":-32602,"message":"missing \"commit\" parameter"}}"#,
+            r#"{"jsonrpc":"2.0","id":4,"error":{"code":-32602,"message":"unknown commit: nope"}}"#,
+            r#"{"jsonrpc":"2.0","id":5,"error":{"code":-32602,"message":"\"collapse\" must be a non-negative integer"}}"#,
+        ]
+    );
+}
+
+#[test]
+fn serve_reports_git_failures_and_keeps_serving() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.branch("feature", "main");
+    let missing = repo.commit("m1");
+    repo.commit("m2");
+    repo.checkout("feature");
+    repo.commit("f1");
+    let (dir, file) = missing.split_at(2);
+    fs::remove_file(repo.path().join(".git/objects").join(dir).join(file)).unwrap();
+    let responses = serve(
+        &repo,
+        &[
+            r#"{"jsonrpc":"2.0","id":1,"method":"getGraph"}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"listBranches"}"#,
+        ],
+    );
+    let [graph, list] = <[String; 2]>::try_from(responses).unwrap();
+    assert!(graph.starts_with(r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32603,"#), "{graph}");
+    assert!(graph.contains(" failed: "), "{graph}");
+    assert!(list.starts_with(r#"{"jsonrpc":"2.0","id":2,"result":"#), "{list}");
+}
+
+#[cfg(unix)]
+#[test]
+fn serve_socket_replaces_only_a_stale_socket() {
+    use core::iter;
+    use std::io::{BufRead as _, BufReader, Write as _};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::process::Stdio;
+    use std::thread;
+
+    let repo = Repo::new();
+    repo.commit("base");
+    let socket = repo.path().join("serve.sock");
+    let serve = || {
+        repo.command(env!("CARGO_BIN_EXE_git-tree"))
+            .args(["serve", "--socket"])
+            .arg(&socket)
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("failed to run git-tree")
+    };
+
+    // A live socket is left alone.
+    let listener = UnixListener::bind(&socket).unwrap();
+    let output = serve().wait_with_output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("is in use by another git-tree serve"), "{stderr}");
+    drop(listener);
+
+    // Once nothing listens on it, it is replaced.
+    let mut child = serve();
+    let mut stream = iter::repeat_n((), 100)
+        .find_map(|()| {
+            thread::sleep(Duration::from_millis(50));
+            UnixStream::connect(&socket).ok()
+        })
+        .expect("git-tree serve did not listen on the socket");
+    writeln!(stream, r#"{{"jsonrpc":"2.0","id":1,"method":"listBranches"}}"#).unwrap();
+    let mut response = String::new();
+    BufReader::new(&stream).read_line(&mut response).unwrap();
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(response.starts_with(r#"{"jsonrpc":"2.0","id":1,"result":"#), "{response}");
+}
+
+#[test]
+fn orphan_branch_shows_only_the_tips() {
+    let repo = Repo::new();
+    repo.commits("m", 2);
+    repo.checkout_orphan("orphan");
+    repo.commits("o", 2);
+    assert_eq!(displayed(&repo), ["m2", "o2"]);
+}
+
+#[test]
+fn empty_repository_has_nothing_to_show() {
+    let repo = Repo::new();
+    let output = repo
+        .command(env!("CARGO_BIN_EXE_git-tree"))
+        .arg("--no-pager")
+        .output()
+        .expect("failed to run git-tree");
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert_eq!(output.stderr, b"git-tree: repository has no commits yet\n");
+}
+
+#[test]
+fn unborn_head_shows_the_existing_branches() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    repo.commit("f1");
+    repo.checkout("main");
+    repo.commit("m1");
+    repo.checkout_orphan("unborn");
+    assert_eq!(displayed(&repo), ["base", "f1", "m1"]);
+}
+
+#[test]
+fn detached_head_is_interesting() {
+    let repo = Repo::new();
+    let base = repo.commit("base");
+    repo.commit("m1");
+    repo.checkout(&base);
+    repo.commit("detached");
+    assert_eq!(displayed(&repo), ["base", "detached", "m1"]);
+}
+
+#[test]
+fn tracked_remote_branch_is_interesting() {
+    let upstream = Repo::new();
+    upstream.commits("u", 2);
+    let clone = upstream.clone_with(&[]);
+    clone.commit("local");
+    upstream.commit("u3");
+    clone.git(&["fetch", "--quiet"]);
+    assert_eq!(displayed(&clone), ["local", "u2", "u3"]);
+}
+
+#[test]
+fn upstream_with_a_different_name_is_interesting() {
+    let upstream = Repo::new();
+    upstream.commit("base");
+    upstream.checkout_new("topic", "main");
+    upstream.commit("t1");
+    upstream.checkout("main");
+    upstream.commit("m1");
+    let clone = upstream.clone_with(&[]);
+    clone.checkout_new("mine", "main");
+    clone.git(&["branch", "--quiet", "--set-upstream-to=origin/topic"]);
+    clone.commit("local");
+    assert_eq!(displayed(&clone), ["base", "local", "m1", "t1"]);
+}
+
+#[test]
+fn remote_branch_without_local_branch_is_not_interesting() {
+    let upstream = Repo::new();
+    upstream.commit("base");
+    upstream.checkout_new("other", "main");
+    upstream.commit("o1");
+    upstream.checkout("main");
+    upstream.commit("m1");
+    let clone = upstream.clone_with(&[]);
+    assert_eq!(displayed(&clone), ["m1"]);
+}
+
+#[test]
+fn shallow_clone_with_common_history() {
+    let upstream = Repo::new();
+    upstream.commits("c", 3);
+    upstream.checkout_new("feature", "main~1");
+    upstream.commit("f1");
+    upstream.checkout("main");
+    upstream.commits("d", 2);
+    let clone = upstream.clone_with(&["--depth=4", "--no-single-branch"]);
+    clone.branch("feature", "origin/feature");
+    assert_eq!(displayed(&clone), ["c2", "c3", "d1", "d2", "f1"]);
+}
+
+#[test]
+fn shallow_clone_without_common_history_shows_only_the_tips() {
+    let upstream = Repo::new();
+    upstream.commits("c", 3);
+    upstream.checkout_new("feature", "main~2");
+    upstream.commit("f1");
+    upstream.checkout("main");
+    let clone = upstream.clone_with(&["--depth=1", "--no-single-branch"]);
+    clone.branch("feature", "origin/feature");
+    assert_eq!(displayed(&clone), ["c3", "f1"]);
+}
+
+#[test]
+fn shallow_boundary_is_marked() {
+    let upstream = Repo::new();
+    upstream.commits("c", 3);
+    let clone = upstream.clone_with(&["--depth=2"]);
+    clone.checkout_new("feature", "main~1");
+    clone.commit("f1");
+    assert_eq!(graph(&clone), "* f1\n| * c3\n+-'\n* c2 [shallow boundary]\n");
+    assert!(!clone.path().join(".git/gitxl/cache").exists());
+}
+
+#[test]
+fn partial_clone_fetches_no_blobs() {
+    let upstream = Repo::new();
+    upstream.git(&["config", "uploadpack.allowFilter", "true"]);
+    upstream.commit_file("a", "a1", "a1");
+    upstream.branch("feature", "main");
+    upstream.commit_file("a", "a2", "a2");
+    upstream.checkout("feature");
+    upstream.commit_file("b", "b1", "b1");
+    let clone = upstream.clone_with(&["--filter=blob:none", "--no-checkout", "--no-single-branch"]);
+    clone.branch("main", "origin/main");
+    let missing =
+        || clone.git(&["rev-list", "--objects", "--all", "--missing=print"]).matches('?').count();
+    let before = missing();
+    assert_eq!(before, 3);
+    for args in [
+        &["--no-pager"][..],
+        &["--render=native", "--no-pager"],
+        &["--format=json-edges"],
+        &["--porcelain"],
+        &["report", "--no-pager"],
+    ] {
+        git_tree(&clone, args);
+        assert_eq!(missing(), before, "git-tree {args:?} fetched blobs");
+    }
+}
+
+#[test]
+fn replace_refs_rewrite_the_ancestry() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.branch("feature", "main");
+    let main = repo.commit("m1");
+    repo.checkout("feature");
+    let feature = repo.commit("f1");
+    // The commit-graph records the original parents, so it must not be used.
+    repo.git(&["commit-graph", "write", "--reachable"]);
+    repo.git(&["replace", "--graft", &feature, &main]);
+    assert_eq!(displayed(&repo), ["f1", "m1"]);
+    let porcelain = git_tree(&repo, &["--porcelain", "--no-replace-objects"]);
+    assert!(porcelain.contains(&format!("commit {main} tip\n")), "{porcelain}");
+    assert_eq!(porcelain.lines().filter(|line| line.starts_with("commit ")).count(), 3);
+}
+
+#[test]
+fn symbolic_branches_are_aliases_and_broken_ones_are_skipped() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    repo.commit("f1");
+    repo.git(&["symbolic-ref", "refs/heads/alias", "refs/heads/main"]);
+    repo.git(&["symbolic-ref", "refs/heads/dangling", "refs/heads/gone"]);
+    repo.git(&["symbolic-ref", "refs/heads/loop1", "refs/heads/loop2"]);
+    repo.git(&["symbolic-ref", "refs/heads/loop2", "refs/heads/loop1"]);
+    let output = repo
+        .command(env!("CARGO_BIN_EXE_git-tree"))
+        .arg("--porcelain")
+        .output()
+        .expect("failed to run git-tree");
+    assert!(output.status.success());
+    let branches: Vec<_> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.strip_prefix("branch "))
+        .filter_map(|rest| rest.split(' ').nth(1).map(String::from))
+        .collect();
+    assert_eq!(branches, ["feature", "main"]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "git-tree: warning: ignoring broken symbolic ref refs/heads/dangling\n\
+         git-tree: warning: ignoring broken symbolic ref refs/heads/loop1\n\
+         git-tree: warning: ignoring broken symbolic ref refs/heads/loop2\n"
+    );
+    assert_eq!(displayed(&repo), ["base", "f1"]);
+}
+
+#[test]
+fn namespace_branches_replace_the_repository_branches() {
+    let repo = Repo::new();
+    let base = repo.commit("base");
+    let x1 = repo.commit("x1");
+    repo.checkout(&base);
+    let y1 = repo.commit("y1");
+    repo.checkout("main");
+    repo.commit("m1");
+    repo.git(&["update-ref", "refs/namespaces/ns/refs/heads/x", &x1]);
+    repo.git(&["update-ref", "refs/namespaces/ns/refs/heads/y", &y1]);
+    let head = "refs/namespaces/ns/HEAD";
+    repo.git(&["symbolic-ref", head, "refs/namespaces/ns/refs/heads/y"]);
+    let expected = format!(
+        "version 1\nhead {y1}\nbranch {x1} x\nbranch {y1} y\nbase {base}\n\
+         commit {y1} head,tip\nedge {y1} {base}\ncommit {x1} tip\nedge {x1} {base}\n\
+         commit {base} base\n"
+    );
+    assert_eq!(git_tree(&repo, &["--porcelain", "--namespace=ns"]), expected);
+    let output = repo
+        .command(env!("CARGO_BIN_EXE_git-tree"))
+        .arg("--porcelain")
+        .env("GIT_NAMESPACE", "ns")
+        .output()
+        .expect("failed to run git-tree");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+}
+
+#[test]
+fn branches_on_one_commit_are_one_merge_base_argument() {
+    let repo = Repo::new();
+    let tip = repo.commit("c1");
+    for name in ["a", "b", "c"] {
+        repo.branch(name, "main");
+    }
+    let output = repo
+        .command(env!("CARGO_BIN_EXE_git-tree"))
+        .arg("--porcelain")
+        .env("GIT_TRACE", "1")
+        .output()
+        .expect("failed to run git-tree");
+    let trace = String::from_utf8_lossy(&output.stderr);
+    let merge_base = trace.lines().find_map(|line| line.split_once(" merge-base "));
+    assert_eq!(merge_base.map(|(_, args)| args), Some(format!("-a --octopus {tip}").as_str()));
+}
+
+#[test]
+fn timing_reports_each_stage_on_stderr() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    repo.commits("f", 2);
+    let output = repo
+        .command(env!("CARGO_BIN_EXE_git-tree"))
+        .args(["--porcelain", "--timing"])
+        .output()
+        .expect("failed to run git-tree");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), git_tree(&repo, &["--porcelain"]));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stages: Vec<_> = stderr.lines().skip(1).filter_map(|line| line.get(2..14)).collect();
+    assert_eq!(
+        stages,
+        [
+            "refs        ",
+            "cache miss  ",
+            "merge bases ",
+            "traversal   ",
+            "output      ",
+            "total       "
+        ]
+    );
+    assert!(stderr.contains("branches: 2"), "{stderr}");
+    assert!(stderr.contains("commits: 2"), "{stderr}");
+    #[cfg(unix)]
+    assert!(stderr.lines().skip(1).all(|line| line.contains(" MiB peak rss")), "{stderr}");
+}
+
+#[test]
+fn error_format_json_reports_failures_as_json() {
+    /// git-tree's status for invalid arguments.
+    const USAGE: i32 = 2;
+    /// git-tree's status when it fails.
+    const FAILURE: i32 = 1;
+    let repo = Repo::new();
+    repo.commit("base");
+    let run = |args: &[&str], git_dir: Option<&str>| {
+        let mut command = repo.command(env!("CARGO_BIN_EXE_git-tree"));
+        if let Some(git_dir) = git_dir {
+            command.env("GIT_DIR", git_dir);
+        }
+        let output = command.args(args).output().expect("failed to run git-tree");
+        (output.status.code(), String::from_utf8_lossy(&output.stderr).into_owned())
+    };
+    assert_eq!(
+        run(&["--error-format=json", "--render=bogus"], None),
+        (
+            Some(USAGE),
+            "{\"code\":\"usage\",\"message\":\"unknown renderer bogus\",\"stage\":\"arguments\"}\n"
+                .to_owned()
+        )
+    );
+    let (status, stderr) = run(&["status", "--error-format=json"], Some("missing"));
+    assert_eq!(status, Some(FAILURE));
+    assert!(stderr.starts_with("{\"code\":\"not-a-repository\",\"message\":\"not a git repository"));
+    assert!(stderr.ends_with("\"stage\":\"repository\"}\n"), "{stderr}");
+    // Without it, failures are reported as text.
+    assert_eq!(
+        run(&["--render=bogus"], None),
+        (Some(USAGE), "git-tree: unknown renderer bogus\n".to_owned())
+    );
+}
+
+#[test]
+fn verbose_logs_json_events_on_stderr() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    repo.commit("f");
+    let base = repo.rev_parse("main");
+    // Commit IDs are abbreviated like git's.
+    let short_base = base.get(..7).unwrap();
+    let output = repo
+        .command(env!("CARGO_BIN_EXE_git-tree"))
+        .args(["--porcelain", "-v"])
+        .env("GITXL_LOG_FORMAT", "json")
+        .output()
+        .expect("failed to run git-tree");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), git_tree(&repo, &["--porcelain"]));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let merge_base = format!(
+        r#"{{"level":"debug","target":"merge-base","message":"computed the merge bases","fields":{{"tips":2,"bases":["{short_base}"]}}}}"#
+    );
+    assert!(stderr.lines().any(|line| line == merge_base), "{stderr}");
+    // -v is debug level, which leaves out the commands git-tree runs.
+    assert!(!stderr.contains(r#""target":"process""#), "{stderr}");
+}
+
+#[test]
+fn exit_status_is_git_logs() {
+    /// git's status for fatal errors, such as an unknown option.
+    const GIT_FATAL: i32 = 128;
+    const SUCCESS: i32 = 0;
+    /// git-tree's status for invalid arguments.
+    const USAGE: i32 = 2;
+    let repo = Repo::new();
+    repo.commit("base");
+    let run = |args: &[&str]| {
+        let output = repo
+            .command(env!("CARGO_BIN_EXE_git-tree"))
+            .arg("--no-pager")
+            .args(args)
+            .output()
+            .expect("failed to run git-tree");
+        (output.status.code(), String::from_utf8_lossy(&output.stderr).into_owned())
+    };
+    let (status, stderr) = run(&["--no-such-option"]);
+    assert_eq!(status, Some(GIT_FATAL));
+    assert!(stderr.contains("--no-such-option"), "{stderr}");
+    assert_eq!(run(&["--oneline"]).0, Some(SUCCESS));
+    assert_eq!(run(&["--render=bogus"]).0, Some(USAGE));
+}
+
+#[test]
+fn quiet_silences_notices() {
+    let repo = Repo::new();
+    let stderr = |args: &[&str]| {
+        let output = repo
+            .command(env!("CARGO_BIN_EXE_git-tree"))
+            .arg("--no-pager")
+            .args(args)
+            .output()
+            .expect("failed to run git-tree");
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+    assert_eq!(stderr(&[]), "git-tree: repository has no commits yet\n");
+    assert_eq!(stderr(&["-q"]), "");
+}
+
+#[test]
+fn paths_after_double_dash_limit_the_log() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    repo.commit_file("a", "a", "touches a");
+    repo.commit_file("b", "b", "touches b");
+    assert_eq!(git_tree(&repo, &["--no-pager", "--format=%s", "--", "a"]), "touches a\n");
+    // An option's value is not mistaken for a revision.
+    assert_eq!(git_tree(&repo, &["--no-pager", "--format=%s", "-n", "1"]), "touches b\n");
+    let relative = git_tree(&repo, &["--no-pager", "--format=%ad", "--date", "relative", "-n1"]);
+    assert!(relative.ends_with(" ago\n"), "{relative}");
+    let args = ["--no-pager", "--format=%s", "--diff-filter", "A", "--stat-width", "40", "-n1"];
+    let stat = git_tree(&repo, &args);
+    assert!(stat.starts_with("touches b\n\n b | 1 +\n"), "{stat}");
+}
+
+#[test]
+fn revision_arguments_are_rejected() {
+    /// git-tree's status for invalid arguments.
+    const USAGE: i32 = 2;
+    let repo = Repo::new();
+    repo.commit("base");
+    for args in [&["--all"][..], &["--branches=f*"], &["nonsense"], &["main", "--stdin-refs"]] {
+        let output = repo
+            .command(env!("CARGO_BIN_EXE_git-tree"))
+            .arg("--no-pager")
+            .args(args)
+            .output()
+            .expect("failed to run git-tree");
+        assert_eq!(output.status.code(), Some(USAGE), "{args:?}");
+        assert!(output.stdout.is_empty(), "{args:?}");
+    }
+}
+
+#[test]
+fn decorate_interesting_ignores_decoration_settings() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    repo.commit("f");
+    repo.tag("v1", "feature");
+    repo.git(&["config", "log.excludeDecoration", "refs/heads/*"]);
+    repo.git(&["config", "--add", "log.excludeDecoration", "HEAD"]);
+    let args = ["--no-pager", "--decorate-interesting", "--format=%s%d"];
+    assert_eq!(git_tree(&repo, &args), "f (HEAD -> feature)\nbase (main)\n");
+    let native =
+        git_tree(&repo, &[&args[..], &["--render=native", "--ascii", "--no-color"]].concat());
+    assert_eq!(native, "* f (HEAD, feature)\n* base (main)\n");
+}
+
+#[test]
+fn matrix_counts_every_pair_of_branches() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("a", "main");
+    repo.commit("a1");
+    repo.checkout_new("b", "a");
+    repo.commit("b1");
+    repo.checkout_new("c", "main");
+    repo.commits("c", 2);
+    assert_eq!(
+        git_tree(&repo, &["matrix", "--list"]),
+        "a...b: ahead 0, behind 1, contained in b\n\
+         a...c: ahead 1, behind 2\n\
+         a...main: ahead 1, behind 0, contains main\n\
+         b...c: ahead 2, behind 2\n\
+         b...main: ahead 2, behind 0, contains main\n\
+         c...main: ahead 2, behind 0, contains main\n"
+    );
+    assert_eq!(
+        git_tree(&repo, &["matrix"]),
+        "          a      b      c   main\n\
+         a         -  +0 -1  +1 -2  +1 -0\n\
+         b     +1 -0      -  +2 -2  +2 -0\n\
+         c     +2 -1  +2 -2      -  +2 -0\n\
+         main  +0 -1  +0 -2  +0 -2      -\n"
+    );
+}
+
+#[test]
+fn branch_names_are_quoted_like_paths() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.branch("caf\u{e9}", "main");
+    repo.branch("say\"hi\"", "main");
+    assert_eq!(
+        git_tree(&repo, &["matrix", "--list"]),
+        "\"caf\\303\\251\"...main: ahead 0, behind 0, same commit\n\
+         \"caf\\303\\251\"...\"say\\\"hi\\\"\": ahead 0, behind 0, same commit\n\
+         main...\"say\\\"hi\\\"\": ahead 0, behind 0, same commit\n"
+    );
+    repo.git(&["config", "core.quotePath", "false"]);
+    assert_eq!(
+        git_tree(&repo, &["matrix", "--list"]).lines().next(),
+        Some("caf\u{e9}...main: ahead 0, behind 0, same commit")
+    );
+}
+
+#[test]
+fn restack_rebases_a_stack_onto_the_new_trunk() {
+    let repo = Repo::new();
+    let base = repo.commit("base");
+    repo.checkout_new("s1", "main");
+    let s1 = repo.commit_file("a", "a", "s1");
+    repo.checkout_new("s2", "s1");
+    repo.commit_file("b", "b", "s2");
+    repo.branch("s2-copy", "s2");
+    repo.checkout("main");
+    repo.commit_file("c", "c", "m2");
+    repo.checkout("s2");
+    assert_eq!(
+        git_tree(&repo, &["restack", "--onto", "main", "--dry-run"]),
+        format!(
+            "git rebase --onto main {base} s1\n\
+             git rebase --onto s1 {s1} s2\n\
+             git branch --force s2-copy s2\n\
+             git switch --quiet s2\n"
+        )
+    );
+    git_tree(&repo, &["restack", "--onto", "main", "--exec"]);
+    assert_eq!(repo.git(&["log", "--format=%s", "s2"]), "s2\ns1\nm2\nbase");
+    assert_eq!(repo.rev_parse("s2-copy"), repo.rev_parse("s2"));
+    assert_eq!(repo.git(&["symbolic-ref", "--short", "HEAD"]), "s2");
+    // Nothing is left to do.
+    assert_eq!(git_tree(&repo, &["restack", "--onto", "main"]), "");
+}
+
+#[test]
+fn since_last_shows_what_changed() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("feature", "main");
+    let f1 = repo.commit("f1");
+    let run = |args: &[&str]| {
+        let output = repo
+            .command(env!("CARGO_BIN_EXE_git-tree"))
+            .args(["--no-pager", "--since-last", "--format=%s"])
+            .args(args)
+            .output()
+            .expect("failed to run git-tree");
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        (stdout, String::from_utf8_lossy(&output.stderr).into_owned())
+    };
+    assert_eq!(
+        run(&[]),
+        (
+            "f1\nbase\n".to_owned(),
+            "git-tree: no previous --since-last run; showing everything\n".to_owned()
+        )
+    );
+    let f2 = repo.commit("f2");
+    repo.branch("topic", "main");
+    let (stdout, stderr) = run(&[]);
+    assert_eq!(stdout, "f2\n");
+    let (old, new) = (f1.get(..7).unwrap(), f2.get(..7).unwrap());
+    assert!(
+        stderr.contains(&format!("git-tree: feature: moved from {old} to {new}\n")),
+        "{stderr}"
+    );
+    assert!(stderr.contains("git-tree: topic: new at "), "{stderr}");
+    repo.commit("f3");
+    let (native, _) = run(&["--render=native", "--ascii"]);
+    assert_eq!(native, "* f3 [new]\n* f2\n* f1\n* base\n");
+}
+
+#[test]
+fn reflog_shows_orphaned_commits() {
+    let repo = Repo::new();
+    let base = repo.commit("base");
+    repo.commit("lost");
+    repo.git(&["reset", "--quiet", "--hard", &base]);
+    repo.commit("kept");
+    let log = |args: &[&str]| {
+        let mut all = vec!["--no-pager", "--format=%s"];
+        all.extend(args);
+        git_tree(&repo, &all)
+    };
+    assert_eq!(log(&[]), "kept\n");
+    assert_eq!(log(&["--reflog"]), "kept\nlost\nbase\n");
+    assert_eq!(log(&["--reflog=1"]), "kept\n");
+    assert_eq!(
+        log(&["--reflog", "--render=native", "--ascii"]),
+        "* kept\n| * lost [reflog]\n+-'\n* base\n"
+    );
+}
+
+#[test]
+fn default_args_come_before_the_command_line() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.git(&["config", "--add", "gitxl.logArgs", "--format=%s"]);
+    repo.git(&["config", "--add", "gitxl.logArgs", "--no-pager"]);
+    let run = |env: Option<&str>, args: &[&str]| {
+        let mut command = repo.command(env!("CARGO_BIN_EXE_git-tree"));
+        if let Some(default_args) = env {
+            command.env("GITXL_DEFAULT_ARGS", default_args);
+        }
+        let output = command.args(args).output().expect("failed to run git-tree");
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    assert_eq!(run(None, &[]), "base\n");
+    assert_eq!(run(Some("--format=env:%s"), &[]), "env:base\n");
+    assert_eq!(run(Some("--format=env:%s"), &["--format=cli:%s"]), "cli:base\n");
+    let id = repo.rev_parse("main");
+    assert_eq!(run(None, &["--no-pager", "--no-default-args", "--format=%H"]), format!("{id}\n"));
+}
+
+#[test]
+fn ref_hook_adjusts_the_interesting_branches() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("topic", "main");
+    repo.commit("t1");
+    repo.checkout_new("other", "main");
+    let o1 = repo.commit("o1");
+    repo.checkout("main");
+    repo.git(&["branch", "--quiet", "--delete", "--force", "other"]);
+    repo.git(&["update-ref", "refs/remotes/origin/other", &o1]);
+    let hook = "grep -v refs/heads/topic; echo refs/remotes/origin/other; echo refs/heads/nope";
+    repo.git(&["config", "gitxl.refHook", hook]);
+    let output = repo
+        .command(env!("CARGO_BIN_EXE_git-tree"))
+        .args(["--no-pager", "--format=%s"])
+        .output()
+        .expect("failed to run git-tree");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "o1\nbase\n");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "git-tree: warning: gitxl.refHook chose unknown branch refs/heads/nope\n"
+    );
+    repo.git(&["config", "gitxl.refHook", "exit 1"]);
+    assert_eq!(git_tree(&repo, &["--no-pager", "--format=%s"]), "t1\nbase\n");
+}
+
+#[test]
+fn remote_branches_are_peeled_to_commits() {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.checkout_new("tagged", "main");
+    let t1 = repo.commit("t1");
+    repo.checkout("main");
+    repo.git(&["tag", "--annotate", "--message=inner", "inner", &t1]);
+    repo.git(&["-c", "advice.nestedTag=false", "tag", "-a", "-m", "outer", "outer", "inner"]);
+    repo.git(&["update-ref", "refs/remotes/origin/tagged", "refs/tags/outer"]);
+    repo.git(&["update-ref", "refs/remotes/origin/main", "main^{tree}"]);
+    let output = repo
+        .command(env!("CARGO_BIN_EXE_git-tree"))
+        .args(["--porcelain"])
+        .output()
+        .expect("failed to run git-tree");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("branch {t1} origin/tagged\n")), "{stdout}");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "git-tree: warning: ignoring refs/remotes/origin/main, which does not point to a commit\n"
+    );
+}
+
+#[test]
+fn tags_are_not_interesting() {
+    let repo = Repo::new();
+    let base = repo.commit("base");
+    repo.commit("m1");
+    repo.checkout(&base);
+    let tagged = repo.commit("tagged");
+    repo.checkout("main");
+    repo.tag("v1", &tagged);
+    assert_eq!(displayed(&repo), ["m1"]);
+}
+
+#[test]
+fn porcelain_v1_format() {
+    let repo = Repo::new();
+    let base = repo.commit("base");
+    repo.branch("feature", "main");
+    let main = repo.commit("m1");
+    repo.checkout("feature");
+    let feature = repo.commit("f1");
+    assert_eq!(
+        git_tree(&repo, &["--porcelain=v1"]),
+        format!(
+            "version 1\nhead {feature}\nbranch {feature} feature\nbranch {main} main\n\
+             base {base}\ncommit {feature} head,tip\nedge {feature} {base}\n\
+             commit {main} tip\nedge {main} {base}\ncommit {base} base\n"
+        )
+    );
+    assert_eq!(
+        git_tree(&repo, &["--porcelain", "-z"]),
+        git_tree(&repo, &["--porcelain"]).replace('\n', "\0")
+    );
+}